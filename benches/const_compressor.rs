@@ -0,0 +1,61 @@
+//! Compare the per-frame cost of the **ConstCompressor** against the `Frame`-generic
+//! **Compressor** for 1, 2 and 4 channels.
+//!
+//! Run with `cargo bench --bench const_compressor`.
+
+extern crate compressor;
+extern crate envelope_detector;
+
+use compressor::{Compressor, ConstCompressor, Minimum, PeakCompressor};
+use envelope_detector::Frame;
+use std::time::{Duration, Instant};
+
+const SAMPLE_HZ: f64 = 44_100.0;
+const FRAMES: usize = 1 << 20;
+
+/// A signal that differs in level across the channels, so that the even gain has work to do.
+fn signal<const N: usize>() -> Vec<[f32; N]> {
+    (0..FRAMES).map(|i| {
+        let mut frame = [0.0; N];
+        for (ch, s) in frame.iter_mut().enumerate() {
+            *s = 0.9 * (i as f32 * 0.01 * (ch + 1) as f32).sin();
+        }
+        frame
+    }).collect()
+}
+
+/// Time compressing every frame of the signal via the given `next_frame`.
+fn time<const N: usize, T>(frames: &[[f32; N]], mut next_frame: T) -> Duration
+    where T: FnMut([f32; N]) -> [f32; N],
+{
+    let start = Instant::now();
+    let mut sum = 0.0;
+    for &frame in frames {
+        sum += next_frame(frame)[0];
+    }
+    let elapsed = start.elapsed();
+    // Keep the result observable so that the work isn't optimised away.
+    assert!(sum.is_finite());
+    elapsed
+}
+
+fn bench<const N: usize>()
+    where [f32; N]: Frame<Sample=f32, Float=[f32; N]>,
+{
+    let frames = signal::<N>();
+    let compressor: PeakCompressor<[f32; N], Minimum> =
+        Compressor::peak(1.0, 100.0, SAMPLE_HZ, 0.25, 4.0);
+    let mut generic = compressor.clone();
+    let mut constant = ConstCompressor::new(compressor);
+    let generic = time(&frames, |f| generic.next_frame(f));
+    let constant = time(&frames, |f| constant.next_frame(f));
+    let ns_per_frame = |d: Duration| d.as_secs_f64() * 1e9 / FRAMES as f64;
+    println!("{} channel(s): generic {:>6.2} ns/frame, const {:>6.2} ns/frame",
+             N, ns_per_frame(generic), ns_per_frame(constant));
+}
+
+fn main() {
+    bench::<1>();
+    bench::<2>();
+    bench::<4>();
+}
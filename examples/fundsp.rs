@@ -0,0 +1,30 @@
+//! Compose a stereo compressor into a `fundsp` chain and process a short burst of noise.
+//!
+//! Run with `cargo run --example fundsp --features fundsp`.
+
+extern crate compressor;
+extern crate fundsp;
+
+use compressor::PeakMinCompressor;
+use fundsp::hacker32::*;
+
+fn main() {
+
+    const SAMPLE_HZ: f64 = 44_100.0;
+    const ATTACK_MS: f64 = 5.0;
+    const RELEASE_MS: f64 = 100.0;
+    const THRESHOLD: f32 = 0.25;
+    const RATIO: f32 = 4.0;
+
+    let compressor: PeakMinCompressor<[f32; 2]> =
+        PeakMinCompressor::peak_min(ATTACK_MS, RELEASE_MS, SAMPLE_HZ, THRESHOLD, RATIO);
+
+    // Stereo noise, compressed, then attenuated a little.
+    let mut graph = (noise() | noise()) >> An(compressor) >> (mul(0.5) | mul(0.5));
+    graph.set_sample_rate(SAMPLE_HZ);
+
+    for _ in 0..8 {
+        let (l, r) = graph.get_stereo();
+        println!("{:>8.4} {:>8.4}", l, r);
+    }
+}
@@ -0,0 +1,200 @@
+//! Offline analysis of a **Compressor**'s behaviour, e.g. to assist with tuning.
+
+
+/// A description of the periodic modulation found in a history of gain reduction.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PumpingReport {
+    /// The estimated frequency of the modulation in Hz, or `0.0` if none was found.
+    pub frequency_hz: f32,
+    /// The estimated peak-to-peak depth of the modulation in decibels.
+    pub depth_db: f32,
+    /// How periodic the modulation is, from `0.0` (not at all) to `1.0` (perfectly periodic).
+    pub periodicity: f32,
+}
+
+/// The lowest modulation frequency considered by `detect_pumping`.
+pub const PUMPING_MIN_HZ: f64 = 0.5;
+/// The highest modulation frequency considered by `detect_pumping`.
+pub const PUMPING_MAX_HZ: f64 = 20.0;
+/// The least depth of modulation in decibels that `PumpingReport::is_pumping` considers audible.
+pub const PUMPING_MIN_DEPTH_DB: f32 = 1.0;
+/// The least periodicity that `PumpingReport::is_pumping` considers to be pumping rather than
+/// the gain reduction simply following the material.
+pub const PUMPING_MIN_PERIODICITY: f32 = 0.5;
+/// The rate in Hz to which the history is decimated before analysis, well above
+/// `PUMPING_MAX_HZ`.
+const ANALYSIS_HZ: f64 = 200.0;
+/// The peak level in decibels relative to full scale of the sine used by
+/// `Compressor::measure_thd`.
+pub const THD_TEST_LEVEL_DB: f32 = -6.0;
+/// The duration in milliseconds of the steady-state output analysed by `Compressor::measure_thd`.
+pub const THD_ANALYSIS_MS: f64 = 500.0;
+/// The highest harmonic measured by `harmonic_distortion`, counting the fundamental as the first.
+pub const THD_MAX_HARMONIC: usize = 10;
+
+
+impl PumpingReport {
+
+    /// Whether the modulation is periodic and deep enough to be heard as pumping or breathing.
+    pub fn is_pumping(&self) -> bool {
+        self.frequency_hz > 0.0
+            && self.depth_db >= PUMPING_MIN_DEPTH_DB
+            && self.periodicity >= PUMPING_MIN_PERIODICITY
+    }
+
+}
+
+
+/// Examine a history of gain reduction for low frequency periodic modulation, indicative of
+/// audible pumping or breathing.
+///
+/// `gr_history` holds the gain reduction in decibels for each consecutive frame at the given
+/// `sample_hz`, e.g. `-db::amp_to_db(gain)` for each gain yielded by `next_gain`. Modulation is
+/// searched for between `PUMPING_MIN_HZ` and `PUMPING_MAX_HZ` via the autocorrelation of the
+/// history, so the history should span at least a few periods of the slowest modulation of
+/// interest. Where pumping is found, a slower release is usually the remedy.
+pub fn detect_pumping(gr_history: &[f32], sample_hz: f64) -> PumpingReport {
+    let none = PumpingReport { frequency_hz: 0.0, depth_db: 0.0, periodicity: 0.0 };
+
+    // Decimate by averaging, as the modulation of interest is far below the sample rate.
+    let factor = (sample_hz / ANALYSIS_HZ) as usize;
+    let factor = if factor < 1 { 1 } else { factor };
+    let rate = sample_hz / factor as f64;
+    let mut x: Vec<f32> = gr_history.chunks(factor)
+        .filter(|chunk| chunk.len() == factor)
+        .map(|chunk| chunk.iter().fold(0.0, |sum, &gr| sum + gr) / factor as f32)
+        .collect();
+    if x.is_empty() {
+        return none;
+    }
+    let mean = x.iter().fold(0.0, |sum, &gr| sum + gr) / x.len() as f32;
+    for gr in &mut x {
+        *gr -= mean;
+    }
+    let energy = x.iter().fold(0.0, |sum, &gr| sum + gr * gr);
+    if energy <= 0.0 {
+        return none;
+    }
+    // The peak-to-peak depth of a sinusoid with the same RMS deviation.
+    let depth_db = 2.0 * ::std::f32::consts::SQRT_2 * (energy / x.len() as f32).sqrt();
+
+    let min_lag = (rate / PUMPING_MAX_HZ) as usize;
+    let min_lag = if min_lag < 1 { 1 } else { min_lag };
+    let max_lag = (rate / PUMPING_MIN_HZ) as usize;
+    let max_lag = if max_lag > x.len() - 1 { x.len() - 1 } else { max_lag };
+    let autocorrelation = |lag: usize| {
+        x.iter().zip(x[lag..].iter()).fold(0.0, |sum, (&a, &b)| sum + a * b) / energy
+    };
+
+    // Skip the central lobe, which is high for any slowly varying signal, by searching for the
+    // highest peak only once the autocorrelation has first fallen below zero.
+    let mut lag = 1;
+    while lag <= max_lag && autocorrelation(lag) >= 0.0 {
+        lag += 1;
+    }
+    let start = if lag > min_lag { lag } else { min_lag };
+    let mut best = None;
+    for lag in start..max_lag + 1 {
+        let r = autocorrelation(lag);
+        best = match best {
+            Some((_, best_r)) if best_r >= r => best,
+            _ => Some((lag, r)),
+        };
+    }
+    let (lag, r) = match best {
+        Some((lag, r)) if r > 0.0 => (lag, r),
+        _ => return PumpingReport { depth_db: depth_db, ..none },
+    };
+
+    // Refine the period by fitting a parabola through the peak and its neighbours.
+    let period = if lag > start && lag < max_lag {
+        let (before, after) = (autocorrelation(lag - 1), autocorrelation(lag + 1));
+        let curvature = before - 2.0 * r + after;
+        if curvature < 0.0 { lag as f32 + 0.5 * (before - after) / curvature } else { lag as f32 }
+    } else {
+        lag as f32
+    };
+
+    PumpingReport {
+        frequency_hz: (rate / period as f64) as f32,
+        depth_db: depth_db,
+        periodicity: if r > 1.0 { 1.0 } else { r },
+    }
+}
+
+/// The total harmonic distortion of the given `signal`, a sine of the given `fundamental_hz` at
+/// the given `sample_hz`, as the ratio of the combined amplitude of its harmonics to that of the
+/// fundamental, e.g. `0.01` for 1%.
+///
+/// The signal is Hann windowed and the amplitude of the fundamental and of each harmonic up to
+/// `THD_MAX_HARMONIC` is found via the discrete Fourier transform evaluated at exactly that
+/// frequency, so the fundamental needn't fall on an FFT bin. Harmonics at or above the Nyquist
+/// frequency are excluded. The signal should span many periods of the fundamental, so that its
+/// leakage doesn't reach the harmonics. Yields `0.0` if there is no fundamental.
+pub fn harmonic_distortion(signal: &[f32], fundamental_hz: f64, sample_hz: f64) -> f32 {
+    let len = signal.len();
+    let tau = 2.0 * ::std::f64::consts::PI;
+    let window = |i: usize| 0.5 - 0.5 * (tau * i as f64 / len as f64).cos();
+    let amplitude = |hz: f64| {
+        let w = tau * hz / sample_hz;
+        let (re, im) = signal.iter().enumerate().fold((0.0, 0.0), |(re, im), (i, &s)| {
+            let s = s as f64 * window(i);
+            (re + s * (w * i as f64).cos(), im - s * (w * i as f64).sin())
+        });
+        (re * re + im * im).sqrt()
+    };
+    let fundamental = amplitude(fundamental_hz);
+    if fundamental <= 0.0 {
+        return 0.0;
+    }
+    let harmonics_power = (2..THD_MAX_HARMONIC + 1)
+        .map(|h| h as f64 * fundamental_hz)
+        .take_while(|&hz| hz < sample_hz / 2.0)
+        .map(|hz| amplitude(hz).powi(2))
+        .fold(0.0, |sum, power| sum + power);
+    (harmonics_power.sqrt() / fundamental) as f32
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_HZ: f64 = 1_000.0;
+
+    #[test]
+    fn gain_reduction_oscillating_at_4hz_is_pumping() {
+        let history: Vec<f32> = (0..3_000)
+            .map(|i| {
+                let phase = i as f64 / SAMPLE_HZ * 4.0 * 2.0 * std::f64::consts::PI;
+                6.0 + 3.0 * phase.sin() as f32
+            })
+            .collect();
+        let report = detect_pumping(&history, SAMPLE_HZ);
+        assert!(report.is_pumping());
+        assert!((report.frequency_hz - 4.0).abs() < 0.1);
+        assert!((report.depth_db - 6.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn steady_gain_reduction_is_not_pumping() {
+        let report = detect_pumping(&[6.0; 3_000], SAMPLE_HZ);
+        assert!(!report.is_pumping());
+        assert!(!detect_pumping(&[], SAMPLE_HZ).is_pumping());
+    }
+
+    fn sine_with_third_harmonic(third: f64) -> Vec<f32> {
+        let w = 2.0 * ::std::f64::consts::PI * 100.0 / 44_100.0;
+        (0..22_050)
+            .map(|i| ((w * i as f64).sin() * 0.5 + (3.0 * w * i as f64).sin() * 0.5 * third) as f32)
+            .collect()
+    }
+
+    #[test]
+    fn harmonic_distortion_measures_the_harmonic_ratio() {
+        assert!(harmonic_distortion(&sine_with_third_harmonic(0.0), 100.0, 44_100.0) < 1e-4);
+        let thd = harmonic_distortion(&sine_with_third_harmonic(0.1), 100.0, 44_100.0);
+        assert!((thd - 0.1).abs() < 1e-3, "{}", thd);
+        assert_eq!(harmonic_distortion(&[0.0; 1_000], 100.0, 44_100.0), 0.0);
+    }
+}
@@ -0,0 +1,101 @@
+//! A slow output leveller that trims the compressed signal toward a target long-term level.
+
+use db;
+
+
+/// Follows the long-term power of the compressed signal and yields the trim gain that brings it
+/// to a target level.
+///
+/// The follower is a one-pole average of the signal's mean square, so that after its window the
+/// measured power has covered roughly 63.2% of the distance toward a new steady level. With a
+/// window of several seconds, the trim follows changes in program level without following
+/// individual notes, avoiding audible pumping.
+#[derive(Copy, Clone, Debug)]
+pub struct AutoGain {
+    /// The amount of the previous power retained each frame.
+    gain: f32,
+    /// The long-term mean square of the untrimmed signal.
+    power: f32,
+    /// The mean square of a signal at the target level.
+    target_power: f32,
+}
+
+/// The most that the trim will boost the signal in decibels, so that near silence isn't raised to
+/// the target level.
+pub const MAX_BOOST_DB: f32 = 24.0;
+
+
+fn calc_gain(n_frames: f32) -> f32 {
+    ::std::f32::consts::E.powf(-1.0 / n_frames)
+}
+
+
+impl AutoGain {
+
+    /// Construct a new **AutoGain** averaging power over the given number of frames and targeting
+    /// the given RMS amplitude.
+    pub fn new(window_frames: f32, target_rms: f32) -> Self {
+        let target_power = target_rms * target_rms;
+        AutoGain {
+            gain: calc_gain(window_frames),
+            power: target_power,
+            target_power: target_power,
+        }
+    }
+
+    /// Set the period over which power is averaged as a number of frames.
+    pub fn set_window_frames(&mut self, frames: f32) {
+        self.gain = calc_gain(frames);
+    }
+
+    /// Set the RMS amplitude toward which the signal is trimmed.
+    ///
+    /// The measured power is seeded at the target so that the trim begins at unity.
+    pub fn set_target(&mut self, target_rms: f32) {
+        self.target_power = target_rms * target_rms;
+        self.power = self.target_power;
+    }
+
+    /// Step the follower forward with the power of the next untrimmed frame.
+    #[inline]
+    pub fn next(&mut self, power: f32) {
+        self.power = power + (self.power - power) * self.gain;
+    }
+
+    /// The gain that brings the long-term power of the signal to the target.
+    pub fn trim(&self) -> f32 {
+        let max_trim = db::db_to_amp(MAX_BOOST_DB);
+        let max_power_ratio = max_trim * max_trim;
+        if self.power * max_power_ratio < self.target_power {
+            max_trim
+        } else {
+            (self.target_power / self.power).sqrt()
+        }
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trim_begins_at_unity_and_converges_to_the_target() {
+        let mut auto_gain = AutoGain::new(100.0, 0.5);
+        assert_eq!(auto_gain.trim(), 1.0);
+        for _ in 0..10_000 {
+            auto_gain.next(0.25 * 0.25);
+        }
+        assert!((auto_gain.trim() - 2.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn trim_boosts_near_silence_by_no_more_than_the_maximum() {
+        let mut auto_gain = AutoGain::new(100.0, 0.5);
+        for _ in 0..10_000 {
+            auto_gain.next(0.0);
+        }
+        assert_eq!(auto_gain.trim(), db::db_to_amp(MAX_BOOST_DB));
+    }
+}
@@ -0,0 +1,102 @@
+//! Sample-accurate scheduling of parameter changes within a block of frames.
+
+
+/// A change to one of the **Compressor**'s parameters.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Change {
+    /// Set the threshold to the given linear amplitude.
+    Threshold(f32),
+    /// Set the ratio, i.e. `4.0` for 4:1.
+    Ratio(f32),
+}
+
+/// A queue of parameter changes, each scheduled at a frame offset from the start of the next
+/// block.
+///
+/// Events are kept sorted by their offset, with events sharing an offset kept in the order they
+/// were scheduled.
+#[derive(Clone, Debug)]
+pub struct AutomationQueue {
+    events: Vec<(usize, Change)>,
+}
+
+
+impl AutomationQueue {
+
+    /// Construct an empty **AutomationQueue**.
+    pub fn new() -> Self {
+        AutomationQueue { events: Vec::new() }
+    }
+
+    /// Schedule the given change at the given frame offset.
+    pub fn schedule(&mut self, frame_offset: usize, change: Change) {
+        let index = self.events.iter()
+            .position(|&(offset, _)| offset > frame_offset)
+            .unwrap_or(self.events.len());
+        self.events.insert(index, (frame_offset, change));
+    }
+
+    /// Allocate room for at least `additional` more scheduled changes.
+    pub fn reserve(&mut self, additional: usize) {
+        self.events.reserve(additional);
+    }
+
+    /// Remove all scheduled changes.
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    /// Pop the next change scheduled at or before the given frame offset, if there is one.
+    #[inline]
+    pub fn pop_due(&mut self, frame_offset: usize) -> Option<Change> {
+        match self.events.first() {
+            Some(&(offset, _)) if offset <= frame_offset => Some(self.events.remove(0).1),
+            _ => None,
+        }
+    }
+
+    /// Move the remaining changes `n_frames` closer to the start of the next block.
+    ///
+    /// Called once a block of `n_frames` has been processed, so that changes scheduled beyond the
+    /// end of the block take effect at the correct frame of the following block.
+    pub fn advance(&mut self, n_frames: usize) {
+        for event in &mut self.events {
+            event.0 = event.0.saturating_sub(n_frames);
+        }
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes_pop_in_order_of_offset_then_scheduling() {
+        let mut queue = AutomationQueue::new();
+        queue.schedule(10, Change::Ratio(2.0));
+        queue.schedule(5, Change::Threshold(0.5));
+        queue.schedule(10, Change::Threshold(0.25));
+        assert_eq!(queue.pop_due(4), None);
+        assert_eq!(queue.pop_due(5), Some(Change::Threshold(0.5)));
+        assert_eq!(queue.pop_due(9), None);
+        assert_eq!(queue.pop_due(10), Some(Change::Ratio(2.0)));
+        assert_eq!(queue.pop_due(10), Some(Change::Threshold(0.25)));
+        assert_eq!(queue.pop_due(100), None);
+    }
+
+    #[test]
+    fn advancing_carries_changes_into_the_next_block() {
+        let mut queue = AutomationQueue::new();
+        queue.schedule(70, Change::Ratio(2.0));
+        queue.schedule(30, Change::Ratio(4.0));
+        queue.advance(64);
+        assert_eq!(queue.pop_due(0), Some(Change::Ratio(4.0)));
+        assert_eq!(queue.pop_due(5), None);
+        assert_eq!(queue.pop_due(6), Some(Change::Ratio(2.0)));
+        queue.schedule(1, Change::Ratio(8.0));
+        queue.clear();
+        assert_eq!(queue.pop_due(100), None);
+    }
+}
@@ -0,0 +1,298 @@
+//! Construction of **Compressor**s whose detector and **EvenGainFunction** are chosen by value.
+//!
+//! The primary type of interest is the [**CompressorBuilder**](./struct.CompressorBuilder).
+
+use {Compressor, DynamicCompressor};
+use detector::{DetectorKind, DynamicDetector};
+use envelope_detector::Frame;
+use error::BuilderError;
+use even_gain_fn::EvenGainKind;
+use params::CompressorParams;
+use time::Ms;
+
+
+/// The threshold used by a **CompressorBuilder** unless otherwise specified.
+pub const DEFAULT_THRESHOLD: f32 = 1.0;
+/// The ratio used by a **CompressorBuilder** unless otherwise specified.
+pub const DEFAULT_RATIO: f32 = 1.0;
+/// The attack in milliseconds used by a **CompressorBuilder** unless otherwise specified.
+pub const DEFAULT_ATTACK_MS: f64 = 10.0;
+/// The release in milliseconds used by a **CompressorBuilder** unless otherwise specified.
+pub const DEFAULT_RELEASE_MS: f64 = 100.0;
+/// The RMS window in milliseconds used by a **CompressorBuilder** unless otherwise specified.
+pub const DEFAULT_WINDOW_MS: f64 = 10.0;
+
+
+/// Builds a **Compressor** whose detector kind, **EvenGainFunction** and parameters are all
+/// chosen by value rather than via type parameters.
+///
+/// Every **Compressor** built is a **DynamicCompressor**, i.e. a
+/// `Compressor<F, DynamicDetector<F::Float>, EvenGainKind>`, so compressors configured
+/// differently share the same type.
+///
+/// Unless otherwise specified, the builder produces a **Peak** detector with the **Average**
+/// even gain, a threshold of `DEFAULT_THRESHOLD` and a ratio of `DEFAULT_RATIO` (i.e. no
+/// compression), along with the `DEFAULT_ATTACK_MS`, `DEFAULT_RELEASE_MS` and
+/// `DEFAULT_WINDOW_MS`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CompressorBuilder {
+    /// The kind of envelope detection.
+    pub detector: DetectorKind,
+    /// The function used to determine the gain applied evenly to all channels.
+    pub even_gain: EvenGainKind,
+    /// The tunable parameters of the **Compressor**.
+    pub params: CompressorParams,
+}
+
+
+impl CompressorBuilder {
+
+    /// Construct a new **CompressorBuilder** with the default configuration.
+    pub fn new() -> Self {
+        CompressorBuilder {
+            detector: DetectorKind::Peak,
+            even_gain: EvenGainKind::Average,
+            params: CompressorParams {
+                threshold: DEFAULT_THRESHOLD,
+                ratio: DEFAULT_RATIO,
+                attack_ms: DEFAULT_ATTACK_MS,
+                release_ms: DEFAULT_RELEASE_MS,
+                window_ms: None,
+                knee_db: 0.0,
+                makeup_gain: 1.0,
+                auto_makeup: false,
+            },
+        }
+    }
+
+    /// Build a **Compressor** using the given kind of envelope detection.
+    pub fn detector(self, detector: DetectorKind) -> Self {
+        CompressorBuilder { detector: detector, ..self }
+    }
+
+    /// Build a **Compressor** using the given function to determine the gain applied evenly to
+    /// all channels.
+    pub fn even_gain(self, even_gain: EvenGainKind) -> Self {
+        CompressorBuilder { even_gain: even_gain, ..self }
+    }
+
+    /// Build a **Compressor** with all of the given parameters.
+    ///
+    /// A `window_ms` of `None` leaves the current window duration untouched.
+    pub fn params(mut self, params: CompressorParams) -> Self {
+        let window_ms = params.window_ms.or(self.params.window_ms);
+        self.params = CompressorParams { window_ms: window_ms, ..params };
+        self
+    }
+
+    /// Build a **Compressor** with the given threshold.
+    pub fn threshold(mut self, threshold: f32) -> Self {
+        self.params.threshold = threshold;
+        self
+    }
+
+    /// Build a **Compressor** with the given ratio.
+    pub fn ratio(mut self, ratio: f32) -> Self {
+        self.params.ratio = ratio;
+        self
+    }
+
+    /// Build a **Compressor** with the given attack duration in milliseconds.
+    pub fn attack_ms(mut self, ms: f64) -> Self {
+        self.params.attack_ms = ms;
+        self
+    }
+
+    /// Build a **Compressor** with the given release duration in milliseconds.
+    pub fn release_ms(mut self, ms: f64) -> Self {
+        self.params.release_ms = ms;
+        self
+    }
+
+    /// Build a **Compressor** with the given RMS window duration in milliseconds.
+    ///
+    /// This is ignored by **Peak** detection, for which `try_build` reports an error.
+    /// Defaults to `DEFAULT_WINDOW_MS` for **Rms** detection.
+    pub fn window_ms(mut self, ms: f64) -> Self {
+        self.params.window_ms = Some(ms);
+        self
+    }
+
+    /// Check the builder's settings for conflicts and values out of range.
+    ///
+    /// The following rules are checked in order:
+    ///
+    /// - A `window_ms` may only be given for **Rms** detection, as **Peak** detection has no
+    ///   window to apply it to (`BuilderError::WindowWithoutRms`).
+    /// - The threshold must be zero or greater (`BuilderError::InvalidThreshold`).
+    /// - The ratio must be `1.0` or greater, where infinity is a limiter
+    ///   (`BuilderError::InvalidRatio`).
+    /// - The attack, release and window durations must be finite and zero or greater
+    ///   (`BuilderError::InvalidDuration`).
+    pub fn validate(&self) -> Result<(), BuilderError> {
+        let CompressorParams { threshold, ratio, attack_ms, release_ms, window_ms, .. } =
+            self.params;
+        if let (DetectorKind::Peak, Some(_)) = (self.detector, window_ms) {
+            return Err(BuilderError::WindowWithoutRms);
+        }
+        if !(threshold >= 0.0) {
+            return Err(BuilderError::InvalidThreshold(threshold));
+        }
+        if !(ratio >= 1.0) {
+            return Err(BuilderError::InvalidRatio(ratio));
+        }
+        let durations = [Some(attack_ms), Some(release_ms), window_ms];
+        for &ms in durations.iter().filter_map(|ms| ms.as_ref()) {
+            if !(ms >= 0.0 && ms.is_finite()) {
+                return Err(BuilderError::InvalidDuration(ms));
+            }
+        }
+        Ok(())
+    }
+
+    /// Build the **Compressor** for the given sample_hz, first checking that the sample_hz is
+    /// finite and greater than zero (`BuilderError::InvalidSampleRate`) and then the settings via
+    /// `validate`.
+    pub fn try_build<F>(&self, sample_hz: f64) -> Result<DynamicCompressor<F>, BuilderError>
+        where F: Frame,
+    {
+        if !(sample_hz > 0.0 && sample_hz.is_finite()) {
+            return Err(BuilderError::InvalidSampleRate(sample_hz));
+        }
+        self.validate().map(|()| self.build(sample_hz))
+    }
+
+    /// Build the **Compressor** for the given sample_hz.
+    ///
+    /// Conflicting settings are resolved silently, e.g. a `window_ms` is ignored by **Peak**
+    /// detection. See `try_build` to have them reported instead.
+    ///
+    /// **Panics** if the sample_hz is not finite and greater than zero.
+    pub fn build<F>(&self, sample_hz: f64) -> DynamicCompressor<F>
+        where F: Frame,
+    {
+        let CompressorParams { threshold, ratio, attack_ms, release_ms, window_ms, .. } =
+            self.params;
+        let window_ms = Ms(window_ms.unwrap_or(DEFAULT_WINDOW_MS));
+        let attack_ms = Ms(attack_ms);
+        let release_ms = Ms(release_ms);
+        let attack_frames = attack_ms.samples(sample_hz) as f32;
+        let release_frames = release_ms.samples(sample_hz) as f32;
+        let detector = DynamicDetector::new(self.detector,
+                                            window_ms,
+                                            attack_frames,
+                                            release_frames,
+                                            sample_hz);
+        let mut compressor = Compressor::new(detector, attack_ms, release_ms, sample_hz, threshold,
+                                             ratio, self.even_gain);
+        compressor.set_knee_db(self.params.knee_db);
+        compressor.set_makeup_gain(self.params.makeup_gain);
+        compressor.set_auto_makeup(self.params.auto_makeup);
+        compressor
+    }
+
+}
+
+impl Default for CompressorBuilder {
+    fn default() -> Self {
+        CompressorBuilder::new()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {PeakAvgCompressor, RmsMinCompressor, RmsWindow};
+
+    const SAMPLE_HZ: f64 = 44_100.0;
+
+    fn assert_same_output<A, B>(mut a: A, mut b: B)
+        where A: FnMut([f32; 2]) -> [f32; 2],
+              B: FnMut([f32; 2]) -> [f32; 2],
+    {
+        for i in 0..8_820 {
+            let s = (i as f32 * 0.02).sin() * if i < 4_410 { 1.0 } else { 0.2 };
+            let frame = [s, 0.5 * s];
+            assert_eq!(a(frame), b(frame), "frame {}", i);
+        }
+    }
+
+    #[test]
+    fn default_builder_does_not_compress() {
+        let mut compressor = CompressorBuilder::new().build::<[f32; 2]>(SAMPLE_HZ);
+        for _ in 0..1_000 {
+            assert_eq!(compressor.next_frame([0.9, -0.9]), [0.9, -0.9]);
+        }
+    }
+
+    #[test]
+    fn built_peak_compressor_matches_its_static_equivalent() {
+        let mut built = CompressorBuilder::new()
+            .threshold(0.25)
+            .ratio(4.0)
+            .attack_ms(1.0)
+            .release_ms(50.0)
+            .build::<[f32; 2]>(SAMPLE_HZ);
+        let mut typed: PeakAvgCompressor<[f32; 2]> =
+            Compressor::peak_avg(1.0, 50.0, SAMPLE_HZ, 0.25, 4.0);
+        assert_same_output(|f| built.next_frame(f), |f| typed.next_frame(f));
+    }
+
+    #[test]
+    fn built_rms_compressor_matches_its_static_equivalent() {
+        let params = CompressorParams {
+            threshold: 0.1,
+            ratio: 8.0,
+            attack_ms: 2.0,
+            release_ms: 80.0,
+            window_ms: Some(5.0),
+            knee_db: 6.0,
+            makeup_gain: 2.0,
+            auto_makeup: false,
+        };
+        let mut built = CompressorBuilder::new()
+            .detector(DetectorKind::Rms(RmsWindow::Rectangular))
+            .even_gain(EvenGainKind::Minimum)
+            .params(params)
+            .build::<[f32; 2]>(SAMPLE_HZ);
+        let mut typed: RmsMinCompressor<[f32; 2]> =
+            Compressor::rms_min(5.0, 2.0, 80.0, SAMPLE_HZ, 0.1, 8.0);
+        typed.set_knee_db(6.0);
+        typed.set_makeup_gain(2.0);
+        assert_same_output(|f| built.next_frame(f), |f| typed.next_frame(f));
+    }
+
+    #[test]
+    fn params_without_a_window_keep_the_current_window() {
+        let params = CompressorParams { window_ms: None, ..CompressorBuilder::new().params };
+        let builder = CompressorBuilder::new().window_ms(20.0).params(params);
+        assert_eq!(builder.params.window_ms, Some(20.0));
+    }
+
+    #[test]
+    fn conflicting_settings_are_reported() {
+        let builder = CompressorBuilder::new();
+        assert_eq!(builder.validate(), Ok(()));
+        assert_eq!(builder.window_ms(10.0).validate(), Err(BuilderError::WindowWithoutRms));
+        assert!(builder.detector(DetectorKind::Rms(RmsWindow::Hann)).window_ms(10.0)
+            .validate().is_ok());
+        assert_eq!(builder.threshold(-0.1).validate(), Err(BuilderError::InvalidThreshold(-0.1)));
+        assert_eq!(builder.ratio(0.5).validate(), Err(BuilderError::InvalidRatio(0.5)));
+        assert!(builder.ratio(std::f32::INFINITY).validate().is_ok());
+        assert_eq!(builder.attack_ms(-1.0).validate(), Err(BuilderError::InvalidDuration(-1.0)));
+        let infinite = builder.release_ms(std::f64::INFINITY).validate();
+        assert_eq!(infinite, Err(BuilderError::InvalidDuration(std::f64::INFINITY)));
+        assert!(builder.ratio(std::f32::NAN).validate().is_err());
+    }
+
+    #[test]
+    fn try_build_checks_the_sample_rate_first() {
+        let builder = CompressorBuilder::new().window_ms(10.0);
+        let zero = builder.try_build::<[f32; 2]>(0.0).unwrap_err();
+        assert_eq!(zero, BuilderError::InvalidSampleRate(0.0));
+        let conflict = builder.try_build::<[f32; 2]>(SAMPLE_HZ).unwrap_err();
+        assert_eq!(conflict, BuilderError::WindowWithoutRms);
+        assert!(CompressorBuilder::new().try_build::<[f32; 2]>(SAMPLE_HZ).is_ok());
+    }
+}
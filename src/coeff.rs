@@ -0,0 +1,62 @@
+//! Pure functions converting attack and release durations to the coefficients used by the
+//! envelope detector.
+//!
+//! These are the exact conversions the **Compressor** uses internally, allowing coefficients to
+//! be calculated independently, e.g. to configure an external detector to match.
+//!
+//! A duration is first converted to a whole number of frames at the given sample rate, truncating
+//! any fraction of a frame. The coefficient is then `e^(-1 / frames)`: the amount of the
+//! previous envelope retained each frame. After `frames` frames the envelope has covered `1 -
+//! 1/e` (roughly 63.2%) of the distance toward a steady target. A duration shorter than a single
+//! frame yields a coefficient of `0.0`, i.e. the envelope follows its target instantaneously.
+
+use envelope;
+use time::Ms;
+
+
+/// The coefficient for a duration lasting the given number of frames.
+pub fn coeff_for_frames(frames: f32) -> f32 {
+    envelope::calc_gain(frames)
+}
+
+/// The coefficient for a duration in milliseconds at the given sample_hz.
+pub fn coeff_for_ms<M: Into<Ms>>(ms: M, sample_hz: f64) -> f32 {
+    let ms: Ms = ms.into();
+    coeff_for_frames(ms.samples(sample_hz) as f32)
+}
+
+/// The coefficient by which the envelope retains its previous value each frame while rising, for
+/// an attack of the given duration in milliseconds at the given sample_hz.
+pub fn attack_coeff_for_ms<M: Into<Ms>>(attack_ms: M, sample_hz: f64) -> f32 {
+    coeff_for_ms(attack_ms, sample_hz)
+}
+
+/// The coefficient by which the envelope retains its previous value each frame while falling,
+/// for a release of the given duration in milliseconds at the given sample_hz.
+pub fn release_coeff_for_ms<M: Into<Ms>>(release_ms: M, sample_hz: f64) -> f32 {
+    coeff_for_ms(release_ms, sample_hz)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {Compressor, PeakAvgCompressor};
+
+    #[test]
+    fn durations_truncate_to_whole_frames() {
+        assert_eq!(coeff_for_ms(10.0, 44_100.0), coeff_for_frames(441.0));
+        assert_eq!(coeff_for_ms(0.03, 44_100.0), coeff_for_frames(1.0));
+        assert_eq!(coeff_for_ms(0.01, 44_100.0), 0.0);
+        assert!((coeff_for_frames(1.0) - (-1.0f32).exp()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn coefficients_match_those_of_the_compressor() {
+        let compressor: PeakAvgCompressor<[f32; 2]> =
+            Compressor::peak_avg(3.0, 250.0, 48_000.0, 0.5, 4.0);
+        let coefficients = compressor.coefficients();
+        assert_eq!(coefficients.attack_gain, attack_coeff_for_ms(3.0, 48_000.0));
+        assert_eq!(coefficients.release_gain, release_coeff_for_ms(250.0, 48_000.0));
+    }
+}
@@ -0,0 +1,220 @@
+//! A **Compressor** for `[f32; N]` frames whose channel count is known at compile time.
+//!
+//! The primary type of interest is the [**ConstCompressor**](./struct.ConstCompressor).
+
+use {Compressor, Detector, EvenGainFunction};
+use even_gain_fn::{Average, AverageDb, EvenGainKind, Minimum};
+use envelope_detector::Frame;
+use std;
+
+
+/// An **EvenGainFunction** that may reduce an array of `N` channel gains, with `N` known at
+/// compile time, to the gain applied evenly across all channels.
+///
+/// Each implementation yields exactly the same gain as the **EvenGainFunction** does for the
+/// `Frame` of the same channel count.
+pub trait ConstEvenGainFunction: EvenGainFunction {
+    /// Reduce the given channel gains to a single even gain.
+    fn const_even_gain<const N: usize>(&self, gains: [f32; N]) -> f32;
+}
+
+impl ConstEvenGainFunction for Average {
+    #[inline]
+    fn const_even_gain<const N: usize>(&self, gains: [f32; N]) -> f32 {
+        let mut sum = 0.0;
+        for &gain in &gains {
+            sum += gain;
+        }
+        sum / N as f32
+    }
+}
+
+impl ConstEvenGainFunction for Minimum {
+    #[inline]
+    fn const_even_gain<const N: usize>(&self, gains: [f32; N]) -> f32 {
+        let mut min = 1.0;
+        for &gain in &gains {
+            if gain < min {
+                min = gain;
+            }
+        }
+        min
+    }
+}
+
+impl ConstEvenGainFunction for AverageDb {
+    #[inline]
+    fn const_even_gain<const N: usize>(&self, gains: [f32; N]) -> f32 {
+        let mut product = 1.0f32;
+        for &gain in &gains {
+            product *= gain;
+        }
+        (product as f64).powf(1.0 / N as f64) as f32
+    }
+}
+
+impl ConstEvenGainFunction for EvenGainKind {
+    #[inline]
+    fn const_even_gain<const N: usize>(&self, gains: [f32; N]) -> f32 {
+        match *self {
+            EvenGainKind::Average => Average.const_even_gain(gains),
+            EvenGainKind::AverageDb => AverageDb.const_even_gain(gains),
+            EvenGainKind::Minimum => Minimum.const_even_gain(gains),
+        }
+    }
+}
+
+
+/// A **Compressor** over `[f32; N]` frames that reduces the `N` channel gains to the even gain
+/// with the channel count known at compile time, so that the per-channel loops may be fully
+/// unrolled.
+///
+/// The output is exactly that of the wrapped **Compressor**. Every other stage is shared with
+/// it, so it may be configured as usual via `compressor_mut`.
+#[derive(Clone)]
+pub struct ConstCompressor<const N: usize, D, EGF = Average>
+    where [f32; N]: Frame,
+{
+    compressor: Compressor<[f32; N], D, EGF>,
+}
+
+impl<const N: usize, D, EGF> std::fmt::Debug for ConstCompressor<N, D, EGF>
+    where [f32; N]: Frame,
+          Compressor<[f32; N], D, EGF>: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "ConstCompressor {{ compressor: {:?} }}", &self.compressor)
+    }
+}
+
+
+impl<const N: usize, D, EGF> ConstCompressor<N, D, EGF>
+    where [f32; N]: Frame<Sample=f32, Float=[f32; N]>,
+          D: Detector<[f32; N]>,
+          EGF: ConstEvenGainFunction,
+{
+
+    /// Construct a new **ConstCompressor** from the given **Compressor**.
+    pub fn new(compressor: Compressor<[f32; N], D, EGF>) -> Self {
+        ConstCompressor {
+            compressor: compressor,
+        }
+    }
+
+    /// Borrow the wrapped **Compressor**.
+    pub fn compressor(&self) -> &Compressor<[f32; N], D, EGF> {
+        &self.compressor
+    }
+
+    /// Mutably borrow the wrapped **Compressor**.
+    pub fn compressor_mut(&mut self) -> &mut Compressor<[f32; N], D, EGF> {
+        &mut self.compressor
+    }
+
+    /// Consume the **ConstCompressor**, yielding the wrapped **Compressor**.
+    pub fn into_compressor(self) -> Compressor<[f32; N], D, EGF> {
+        self.compressor
+    }
+
+    /// Produce the gain to be applied evenly across all channels for the next frame.
+    ///
+    /// See `Compressor::next_gain`.
+    #[inline]
+    pub fn next_gain(&mut self, next_frame: [f32; N]) -> f32 {
+        self.compressor.next_gain_by(next_frame, const_even_gain)
+    }
+
+    /// Steps forward the **ConstCompressor** by the given frame and returns the compressed
+    /// result.
+    ///
+    /// See `Compressor::next_frame`.
+    #[inline]
+    pub fn next_frame(&mut self, next_frame: [f32; N]) -> [f32; N] {
+        self.compressor.next_frame_and_gain_by(next_frame, next_frame, const_even_gain).0
+    }
+}
+
+/// Steps the detectors of the given **Compressor** and reduces its channel gains via the
+/// **ConstEvenGainFunction**.
+#[inline]
+fn const_even_gain<const N: usize, D, EGF>(compressor: &mut Compressor<[f32; N], D, EGF>,
+                                           frame: [f32; N]) -> f32
+    where [f32; N]: Frame<Sample=f32, Float=[f32; N]>,
+          D: Detector<[f32; N]>,
+          EGF: ConstEvenGainFunction,
+{
+    let gains = compressor.next_gain_per_channel(frame);
+    compressor.even_gain_fn.const_even_gain(gains)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {PeakCompressor, RmsCompressor};
+
+    const SAMPLE_HZ: f64 = 44_100.0;
+
+    /// Check that the **ConstCompressor** yields exactly the frames and gains of its generic
+    /// twin for a signal that differs in level across the channels.
+    fn assert_matches_generic<const N: usize, D, EGF>(compressor: Compressor<[f32; N], D, EGF>)
+        where [f32; N]: Frame<Sample=f32, Float=[f32; N]>,
+              D: Detector<[f32; N]> + Clone,
+              EGF: ConstEvenGainFunction + Clone,
+    {
+        let mut generic = compressor.clone();
+        let mut constant = ConstCompressor::new(compressor);
+        for i in 0..4_410 {
+            let mut frame = [0.0; N];
+            for (ch, s) in frame.iter_mut().enumerate() {
+                *s = 0.9 * (i as f32 * 0.01 * (ch + 1) as f32).sin();
+            }
+            if i % 2 == 0 {
+                assert_eq!(constant.next_frame(frame), generic.next_frame(frame));
+            } else {
+                assert_eq!(constant.next_gain(frame), generic.next_gain(frame));
+            }
+        }
+    }
+
+    fn peak<const N: usize, EGF>() -> PeakCompressor<[f32; N], EGF>
+        where [f32; N]: Frame<Sample=f32, Float=[f32; N]>,
+              EGF: EvenGainFunction + Default,
+    {
+        Compressor::peak(1.0, 100.0, SAMPLE_HZ, 0.25, 4.0)
+    }
+
+    #[test]
+    fn outputs_match_the_generic_compressor() {
+        assert_matches_generic(peak::<1, Average>());
+        assert_matches_generic(peak::<2, Average>());
+        assert_matches_generic(peak::<4, Average>());
+        assert_matches_generic(peak::<1, Minimum>());
+        assert_matches_generic(peak::<2, Minimum>());
+        assert_matches_generic(peak::<4, Minimum>());
+        assert_matches_generic(peak::<1, AverageDb>());
+        assert_matches_generic(peak::<2, AverageDb>());
+        assert_matches_generic(peak::<4, AverageDb>());
+        let rms: RmsCompressor<[f32; 4], Average> =
+            Compressor::rms(10.0, 1.0, 100.0, SAMPLE_HZ, 0.25, 4.0);
+        assert_matches_generic(rms);
+    }
+
+    #[test]
+    fn each_kind_matches_the_generic_compressor() {
+        for &kind in &[EvenGainKind::Average, EvenGainKind::AverageDb, EvenGainKind::Minimum] {
+            let mut compressor: PeakCompressor<[f32; 4], EvenGainKind> = peak();
+            compressor.set_even_gain(kind);
+            assert_matches_generic(compressor);
+        }
+    }
+
+    #[test]
+    fn shared_stages_affect_both_paths_alike() {
+        let mut compressor: PeakCompressor<[f32; 2], Average> = peak();
+        compressor.set_punch_ms(1.0, SAMPLE_HZ);
+        compressor.set_auto_gain(Some(-12.0));
+        compressor.set_sidechain_delay_frames(64);
+        assert_matches_generic(compressor);
+    }
+}
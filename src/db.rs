@@ -0,0 +1,39 @@
+//! Conversions between linear amplitude and decibels.
+//!
+//! Decibels are relative to an amplitude of `1.0`, i.e. `0.0` dB is full scale.
+
+
+/// Convert a linear amplitude to decibels.
+///
+/// An amplitude of `0.0` yields negative infinity.
+#[inline]
+pub fn amp_to_db(amp: f32) -> f32 {
+    20.0 * amp.log10()
+}
+
+/// Convert decibels to a linear amplitude.
+#[inline]
+pub fn db_to_amp(db: f32) -> f32 {
+    10.0f32.powf(db / 20.0)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_scale_is_zero_db() {
+        assert_eq!(amp_to_db(1.0), 0.0);
+        assert_eq!(db_to_amp(0.0), 1.0);
+        assert_eq!(amp_to_db(0.0), ::std::f32::NEG_INFINITY);
+    }
+
+    #[test]
+    fn conversions_round_trip() {
+        for &db in &[-60.0, -20.0, -6.0, 0.0, 6.0] {
+            assert!((amp_to_db(db_to_amp(db)) - db).abs() < 1e-4);
+        }
+        assert!((amp_to_db(0.5) + 6.0206).abs() < 1e-3);
+    }
+}
@@ -1,9 +1,23 @@
-use envelope_detector::{Frame, Mode, EnvelopeDetector, Peak, Rms};
+use builder::DEFAULT_WINDOW_MS;
+use envelope::EnvelopeDetector;
+use envelope_detector::{Frame, Mode, Peak, Sample};
+use rms::{Rms, RmsWindow};
 use std;
 use time::Ms;
 
 /// A peak `Detector` type.
-pub use envelope_detector::PeakEnvelopeDetector;
+pub use envelope::PeakEnvelopeDetector;
+
+
+/// The number of frames spanned by an RMS window of the given duration at the given sample_hz.
+///
+/// The result is clamped to a minimum of one frame, so that a window shorter than a single frame
+/// behaves as a one frame window (which follows the rectified signal much like a peak detector)
+/// rather than an empty window that detects nothing.
+pub fn rms_window_frames(window_ms: Ms, sample_hz: f64) -> usize {
+    let frames = window_ms.samples(sample_hz);
+    if frames < 1 { 1 } else { frames as usize }
+}
 
 
 /// Envelope **Detector** types that may be used by the **Compressor**.
@@ -14,10 +28,112 @@ pub trait Detector<F>
     type Mode: Mode<F>;
     /// Mutably borrow the **MultiChannelEnvelopeDetector**.
     fn detector(&mut self) -> &mut EnvelopeDetector<F, Self::Mode>;
+
+    /// Borrow the **MultiChannelEnvelopeDetector**.
+    fn detector_ref(&self) -> &EnvelopeDetector<F, Self::Mode>;
+
+    /// The duration of the detector's window, if it has one.
+    fn window_ms(&self) -> Option<Ms> {
+        None
+    }
+
+    /// The length of the detector's window as a number of frames, or `0` if it has none.
+    fn window_frames(&self) -> usize {
+        0
+    }
+
+    /// Set the duration of the detector's window without updating it to any sample rate.
+    ///
+    /// This has no effect on detectors that have no window.
+    fn set_window_ms(&mut self, _ms: Ms) {}
+
+    /// Update any of the detector's own duration-based state (i.e. its window) in accordance with
+    /// the given sample_hz.
+    ///
+    /// Attack and release are updated separately via the **EnvelopeDetector**.
+    fn update_to_sample_hz(&mut self, _sample_hz: f64) {}
+
+    /// Allocate room for a window of up to `max_window_frames`, then restore the window to its
+    /// duration at the given sample_hz.
+    ///
+    /// This has no effect on detectors that have no window.
+    fn reserve_window_frames(&mut self, _max_window_frames: usize, _sample_hz: f64) {}
+
+    /// Advance the detector as though it had been fed `n_frames` of silence.
+    ///
+    /// By default only the **EnvelopeDetector**'s envelope is released. Detectors with a window
+    /// should also advance it so that it holds the silence.
+    fn advance_silence(&mut self, n_frames: usize) {
+        self.detector().decay(n_frames);
+    }
+
+    /// The number of frames combined into each element of the detector's window, if it has one.
+    fn decimation(&self) -> usize {
+        1
+    }
+
+    /// Set the number of frames combined into each element of the detector's window.
+    ///
+    /// This has no effect on detectors that have no window.
+    fn set_decimation(&mut self, _factor: usize) {}
+
+    /// Whether the detector's window accumulates its sums of squares in `f64`, if it has one.
+    fn high_precision(&self) -> bool {
+        false
+    }
+
+    /// Enable or disable accumulating the sums of squares of the detector's window in `f64`.
+    ///
+    /// This has no effect on detectors that have no window.
+    fn set_high_precision(&mut self, _enabled: bool) {}
+
+    /// Detect the envelope of the given frame.
+    ///
+    /// By default the **EnvelopeDetector** is stepped and its envelope yielded. Detectors that
+    /// combine several envelopes should override this along with `last_envelope`.
+    fn next_envelope(&mut self, frame: F) -> F {
+        self.detector().next(frame)
+    }
+
+    /// The most recently detected envelope.
+    ///
+    /// By default this is the **EnvelopeDetector**'s envelope.
+    fn last_envelope(&self) -> F {
+        self.detector_ref().envelope()
+    }
+
+    /// Seed the detector as though it had settled on the given envelope.
+    ///
+    /// By default only the **EnvelopeDetector**'s envelope is seeded. Detectors with a window
+    /// should also fill it so that the window yields the same envelope.
+    fn set_envelope(&mut self, envelope: F) {
+        self.detector().set_envelope(envelope);
+    }
+
+    /// Clear all detection state, as though the detector had only ever been fed silence.
+    ///
+    /// By default only the **EnvelopeDetector** is reset. Detectors with a window should also
+    /// clear it.
+    fn reset(&mut self) {
+        self.detector().reset();
+    }
+
+    /// The rough cost of detecting each frame, relative to **Peak** detection at `1.0`.
+    fn relative_cost(&self) -> f32 {
+        1.0
+    }
+
+    /// A short human-readable description of the kind of detection, e.g. for logging.
+    fn description(&self) -> String {
+        "peak".to_string()
+    }
 }
 
 
 /// An RMS envelope detector with a window adjustable in milliseconds.
+///
+/// Cloning the detector allocates a fresh RMS window holding a copy of the original's contents,
+/// so the clone and the original may be fed different signals without affecting one another.
 #[derive(Clone)]
 pub struct RmsEnvelopeDetector<F>
     where F: Frame,
@@ -45,6 +161,9 @@ impl<F> Detector<F> for PeakEnvelopeDetector<F>
     fn detector(&mut self) -> &mut Self {
         self
     }
+    fn detector_ref(&self) -> &Self {
+        self
+    }
 }
 
 impl<F> Detector<F> for RmsEnvelopeDetector<F>
@@ -54,5 +173,874 @@ impl<F> Detector<F> for RmsEnvelopeDetector<F>
     fn detector(&mut self) -> &mut EnvelopeDetector<F, Self::Mode> {
         &mut self.rms
     }
+
+    fn detector_ref(&self) -> &EnvelopeDetector<F, Self::Mode> {
+        &self.rms
+    }
+
+    fn window_ms(&self) -> Option<Ms> {
+        Some(self.window_ms)
+    }
+
+    fn window_frames(&self) -> usize {
+        self.rms.mode().window_frames()
+    }
+
+    fn set_window_ms(&mut self, ms: Ms) {
+        self.window_ms = ms;
+    }
+
+    fn update_to_sample_hz(&mut self, sample_hz: f64) {
+        let frames = rms_window_frames(self.window_ms, sample_hz);
+        self.rms.set_window_frames(frames);
+    }
+
+    fn reserve_window_frames(&mut self, max_window_frames: usize, sample_hz: f64) {
+        // Growing the window pads its front with silence, which is the first to be popped when
+        // the window is shrunk back down, leaving the window's contents and its capacity intact.
+        self.rms.set_window_frames(max_window_frames);
+        self.update_to_sample_hz(sample_hz);
+    }
+
+    fn advance_silence(&mut self, n_frames: usize) {
+        self.rms.mode_mut().advance_silence(n_frames);
+        self.rms.decay(n_frames);
+    }
+
+    fn set_envelope(&mut self, envelope: F) {
+        self.rms.mode_mut().fill(envelope.to_float_frame());
+        self.rms.set_envelope(envelope);
+    }
+
+    fn reset(&mut self) {
+        self.rms.mode_mut().reset();
+        self.rms.reset();
+    }
+
+    fn decimation(&self) -> usize {
+        self.rms.mode().decimation()
+    }
+
+    fn set_decimation(&mut self, factor: usize) {
+        self.rms.mode_mut().set_decimation(factor);
+    }
+
+    fn high_precision(&self) -> bool {
+        self.rms.mode().high_precision()
+    }
+
+    fn set_high_precision(&mut self, enabled: bool) {
+        self.rms.mode_mut().set_high_precision(enabled);
+    }
+
+    fn relative_cost(&self) -> f32 {
+        self.rms.mode().relative_cost()
+    }
+
+    fn description(&self) -> String {
+        format!("RMS ({:?} window)", self.rms.mode().shape())
+    }
+}
+
+
+/// The mix between peak and RMS detection used unless otherwise specified.
+pub const DEFAULT_DETECTION_MIX: f32 = 0.5;
+
+
+/// A detection **Mode** that runs both full wave **Peak** and **Rms** detection and blends
+/// between them.
+#[derive(Clone)]
+pub struct PeakRms<F>
+    where F: Frame,
+{
+    /// The **Peak** detection.
+    peak: Peak,
+    /// The **Rms** detection.
+    rms: Rms<F>,
+    /// The blend between the two, where `0.0` is pure **Rms** and `1.0` is pure **Peak**.
+    mix: f32,
+}
+
+/// An envelope detector blending continuously between peak and RMS detection, with an RMS window
+/// adjustable in milliseconds.
+#[derive(Clone)]
+pub struct BlendEnvelopeDetector<F>
+    where F: Frame,
+{
+    /// The multi-channel blended envelope detector.
+    pub envelope: EnvelopeDetector<F, PeakRms<F>>,
+    /// The duration of the RMS window used by the detector.
+    pub window_ms: Ms,
+}
+
+impl<F> std::fmt::Debug for PeakRms<F>
+    where F: Frame,
+          F::Float: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "PeakRms {{ peak: {:?}, rms: {:?}, mix: {:?} }}",
+               &self.peak, &self.rms, &self.mix)
+    }
+}
+
+impl<F> std::fmt::Debug for BlendEnvelopeDetector<F>
+    where F: Frame + std::fmt::Debug,
+          F::Float: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "BlendEnvelopeDetector {{ envelope: {:?}, window_ms: {:?} }}",
+               &self.envelope, &self.window_ms)
+    }
+}
+
+impl<F> PeakRms<F>
+    where F: Frame,
+{
+
+    /// Construct a new **PeakRms** with an RMS window of the given shape and length.
+    pub fn new(window_frames: usize, shape: RmsWindow, mix: f32) -> Self {
+        PeakRms {
+            peak: Peak::full_wave(),
+            rms: Rms::with_shape(window_frames, shape),
+            mix: mix,
+        }
+    }
+
+    /// The blend between the two, where `0.0` is pure **Rms** and `1.0` is pure **Peak**.
+    pub fn mix(&self) -> f32 {
+        self.mix
+    }
+
+    /// Set the blend between the two, clamped between `0.0` (pure **Rms**) and `1.0` (pure
+    /// **Peak**).
+    pub fn set_mix(&mut self, mix: f32) {
+        self.mix = if mix < 0.0 { 0.0 } else if mix > 1.0 { 1.0 } else { mix };
+    }
+
+}
+
+impl<F> Mode<F> for PeakRms<F>
+    where F: Frame,
+{
+    fn next_frame(&mut self, frame: F) -> F {
+        let peak = self.peak.next_frame(frame);
+        let rms = self.rms.next_frame(frame);
+        let mix: <F::Sample as Sample>::Float = self.mix.to_sample();
+        peak.zip_map(rms, |p, r| {
+            let (p, r) = (p.to_float_sample(), r.to_float_sample());
+            (r + (p - r) * mix).to_sample()
+        })
+    }
+}
+
+impl<F> BlendEnvelopeDetector<F>
+    where F: Frame,
+{
+
+    /// Construct a new **BlendEnvelopeDetector** with the given blend between **Peak** (`1.0`)
+    /// and **Rms** (`0.0`) detection.
+    pub fn new(shape: RmsWindow,
+               window_ms: Ms,
+               mix: f32,
+               attack_frames: f32,
+               release_frames: f32,
+               sample_hz: f64) -> Self
+    {
+        let window_frames = rms_window_frames(window_ms, sample_hz);
+        let mut mode = PeakRms::new(window_frames, shape, DEFAULT_DETECTION_MIX);
+        mode.set_mix(mix);
+        BlendEnvelopeDetector {
+            envelope: EnvelopeDetector::new(mode, attack_frames, release_frames),
+            window_ms: window_ms,
+        }
+    }
+
+}
+
+impl<F> Detector<F> for BlendEnvelopeDetector<F>
+    where F: Frame,
+{
+    type Mode = PeakRms<F>;
+    fn detector(&mut self) -> &mut EnvelopeDetector<F, Self::Mode> {
+        &mut self.envelope
+    }
+
+    fn detector_ref(&self) -> &EnvelopeDetector<F, Self::Mode> {
+        &self.envelope
+    }
+
+    fn window_ms(&self) -> Option<Ms> {
+        Some(self.window_ms)
+    }
+
+    fn window_frames(&self) -> usize {
+        self.envelope.mode().rms.window_frames()
+    }
+
+    fn set_window_ms(&mut self, ms: Ms) {
+        self.window_ms = ms;
+    }
+
+    fn update_to_sample_hz(&mut self, sample_hz: f64) {
+        let frames = rms_window_frames(self.window_ms, sample_hz);
+        self.envelope.mode_mut().rms.set_window_frames(frames);
+    }
+
+    fn reserve_window_frames(&mut self, max_window_frames: usize, sample_hz: f64) {
+        self.envelope.mode_mut().rms.set_window_frames(max_window_frames);
+        self.update_to_sample_hz(sample_hz);
+    }
+
+    fn advance_silence(&mut self, n_frames: usize) {
+        self.envelope.mode_mut().rms.advance_silence(n_frames);
+        self.envelope.decay(n_frames);
+    }
+
+    fn set_envelope(&mut self, envelope: F) {
+        self.envelope.mode_mut().rms.fill(envelope.to_float_frame());
+        self.envelope.set_envelope(envelope);
+    }
+
+    fn reset(&mut self) {
+        self.envelope.mode_mut().rms.reset();
+        self.envelope.reset();
+    }
+
+    fn decimation(&self) -> usize {
+        self.envelope.mode().rms.decimation()
+    }
+
+    fn set_decimation(&mut self, factor: usize) {
+        self.envelope.mode_mut().rms.set_decimation(factor);
+    }
+
+    fn high_precision(&self) -> bool {
+        self.envelope.mode().rms.high_precision()
+    }
+
+    fn set_high_precision(&mut self, enabled: bool) {
+        self.envelope.mode_mut().rms.set_high_precision(enabled);
+    }
+
+    fn relative_cost(&self) -> f32 {
+        1.0 + self.envelope.mode().rms.relative_cost()
+    }
+
+    fn description(&self) -> String {
+        let mode = self.envelope.mode();
+        format!("peak/RMS blend ({:?} window, mix {})", mode.rms.shape(), mode.mix)
+    }
+}
+
+
+/// The attack of a **DualEnvelopeDetector**'s fast follower used unless otherwise specified.
+pub const DEFAULT_FAST_ATTACK_MS: f64 = 0.5;
+/// The release of a **DualEnvelopeDetector**'s fast follower used unless otherwise specified.
+pub const DEFAULT_FAST_RELEASE_MS: f64 = 20.0;
+
+
+/// An envelope detector running a fast and a slow **Peak** follower side by side.
+///
+/// While the fast follower is above the slow one, i.e. while the signal is rising, the envelope
+/// is pulled from the slow follower toward the fast one by the `fast_weight`, so that transients
+/// are caught at the fast follower's attack. Once the fast follower falls back below the slow one
+/// the envelope is the slow follower alone, so the release always follows the slow follower's
+/// longer time constant. This gives quick transient capture without the pumping of a fast
+/// release. Should the signal fall before the slow follower has caught up, the envelope first
+/// falls back to the slow follower at the fast follower's release.
+///
+/// The slow follower's attack and release are those of the **Compressor**.
+#[derive(Clone)]
+pub struct DualEnvelopeDetector<F>
+    where F: Frame,
+{
+    /// The slow follower.
+    pub slow: PeakEnvelopeDetector<F>,
+    /// The fast follower.
+    pub fast: PeakEnvelopeDetector<F>,
+    /// The attack duration of the fast follower.
+    pub fast_attack_ms: Ms,
+    /// The release duration of the fast follower.
+    pub fast_release_ms: Ms,
+    /// How far the envelope is pulled toward the fast follower while it leads the slow one.
+    fast_weight: f32,
+    /// The most recently detected envelope.
+    last_env_frame: F,
+}
+
+impl<F> std::fmt::Debug for DualEnvelopeDetector<F>
+    where F: Frame + std::fmt::Debug,
+          F::Float: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "DualEnvelopeDetector {{ slow: {:?}, fast: {:?}, fast_attack_ms: {:?}, \
+                   fast_release_ms: {:?}, fast_weight: {:?} }}",
+               &self.slow, &self.fast, &self.fast_attack_ms, &self.fast_release_ms,
+               &self.fast_weight)
+    }
+}
+
+impl<F> DualEnvelopeDetector<F>
+    where F: Frame,
+{
+
+    /// Construct a new **DualEnvelopeDetector** whose slow follower has the given attack and
+    /// release, and whose fast follower uses the `DEFAULT_FAST_ATTACK_MS` and
+    /// `DEFAULT_FAST_RELEASE_MS` with a `fast_weight` of `1.0`.
+    pub fn new(attack_frames: f32, release_frames: f32, sample_hz: f64) -> Self {
+        let fast_attack_ms = Ms(DEFAULT_FAST_ATTACK_MS);
+        let fast_release_ms = Ms(DEFAULT_FAST_RELEASE_MS);
+        let fast_attack_frames = fast_attack_ms.samples(sample_hz) as f32;
+        let fast_release_frames = fast_release_ms.samples(sample_hz) as f32;
+        DualEnvelopeDetector {
+            slow: EnvelopeDetector::peak(attack_frames, release_frames),
+            fast: EnvelopeDetector::peak(fast_attack_frames, fast_release_frames),
+            fast_attack_ms: fast_attack_ms,
+            fast_release_ms: fast_release_ms,
+            fast_weight: 1.0,
+            last_env_frame: F::equilibrium(),
+        }
+    }
+
+    /// How far the envelope is pulled toward the fast follower while it leads the slow one, from
+    /// `0.0` (the slow follower alone) to `1.0` (the greater of the two).
+    pub fn fast_weight(&self) -> f32 {
+        self.fast_weight
+    }
+
+    /// Set how far the envelope is pulled toward the fast follower while it leads the slow one,
+    /// clamped between `0.0` (the slow follower alone) and `1.0` (the greater of the two).
+    pub fn set_fast_weight(&mut self, fast_weight: f32) {
+        self.fast_weight = if fast_weight < 0.0 {
+            0.0
+        } else if fast_weight > 1.0 {
+            1.0
+        } else {
+            fast_weight
+        };
+    }
+
+    /// Combine the envelopes of the two followers.
+    fn blend(&self, slow: F, fast: F) -> F {
+        let weight: <F::Sample as Sample>::Float = self.fast_weight.to_sample();
+        slow.zip_map(fast, |s, f| {
+            if f > s {
+                let (s, f) = (s.to_float_sample(), f.to_float_sample());
+                (s + (f - s) * weight).to_sample()
+            } else {
+                s
+            }
+        })
+    }
+
+}
+
+impl<F> Detector<F> for DualEnvelopeDetector<F>
+    where F: Frame,
+{
+    type Mode = Peak;
+    fn detector(&mut self) -> &mut EnvelopeDetector<F, Self::Mode> {
+        &mut self.slow
+    }
+
+    fn detector_ref(&self) -> &EnvelopeDetector<F, Self::Mode> {
+        &self.slow
+    }
+
+    fn update_to_sample_hz(&mut self, sample_hz: f64) {
+        self.fast.set_attack_frames(self.fast_attack_ms.samples(sample_hz) as f32);
+        self.fast.set_release_frames(self.fast_release_ms.samples(sample_hz) as f32);
+    }
+
+    fn advance_silence(&mut self, n_frames: usize) {
+        self.slow.decay(n_frames);
+        self.fast.decay(n_frames);
+        self.last_env_frame = self.blend(self.slow.envelope(), self.fast.envelope());
+    }
+
+    fn next_envelope(&mut self, frame: F) -> F {
+        let slow = self.slow.next(frame);
+        let fast = self.fast.next(frame);
+        self.last_env_frame = self.blend(slow, fast);
+        self.last_env_frame
+    }
+
+    fn last_envelope(&self) -> F {
+        self.last_env_frame
+    }
+
+    fn set_envelope(&mut self, envelope: F) {
+        self.slow.set_envelope(envelope);
+        self.fast.set_envelope(envelope);
+        self.last_env_frame = envelope;
+    }
+
+    fn reset(&mut self) {
+        self.slow.reset();
+        self.fast.reset();
+        self.last_env_frame = F::equilibrium();
+    }
+
+    fn relative_cost(&self) -> f32 {
+        2.0
+    }
+
+    fn description(&self) -> String {
+        format!("dual peak (fast {} ms / {} ms, weight {})",
+                self.fast_attack_ms.ms(), self.fast_release_ms.ms(), self.fast_weight)
+    }
+}
+
+
+/// The kind of envelope detection performed by a **DynamicDetector**.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DetectorKind {
+    /// Full wave **Peak** detection.
+    Peak,
+    /// **Rms** detection over a window of the given shape.
+    Rms(RmsWindow),
 }
 
+/// The detection **Mode** of a **DynamicDetector**, chosen at runtime.
+#[derive(Clone)]
+pub enum DynamicMode<F>
+    where F: Frame,
+{
+    /// Full wave **Peak** detection.
+    Peak(Peak),
+    /// **Rms** detection.
+    Rms(Rms<F>),
+}
+
+/// An envelope **Detector** whose kind is chosen at runtime rather than via the **Compressor**'s
+/// type.
+///
+/// This is the detector used by **Compressor**s produced by the **CompressorBuilder**.
+#[derive(Clone)]
+pub struct DynamicDetector<F>
+    where F: Frame,
+{
+    /// The multi-channel envelope detector.
+    pub envelope: EnvelopeDetector<F, DynamicMode<F>>,
+    /// The duration of the RMS window, used only by **Rms** detection.
+    pub window_ms: Ms,
+}
+
+impl<F> std::fmt::Debug for DynamicMode<F>
+    where F: Frame,
+          F::Float: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match *self {
+            DynamicMode::Peak(ref peak) => write!(f, "Peak({:?})", peak),
+            DynamicMode::Rms(ref rms) => write!(f, "Rms({:?})", rms),
+        }
+    }
+}
+
+impl<F> std::fmt::Debug for DynamicDetector<F>
+    where F: Frame + std::fmt::Debug,
+          F::Float: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "DynamicDetector {{ envelope: {:?}, window_ms: {:?} }}",
+               &self.envelope, &self.window_ms)
+    }
+}
+
+impl<F> Mode<F> for DynamicMode<F>
+    where F: Frame,
+{
+    fn next_frame(&mut self, frame: F) -> F {
+        match *self {
+            DynamicMode::Peak(ref mut peak) => peak.next_frame(frame),
+            DynamicMode::Rms(ref mut rms) => rms.next_frame(frame),
+        }
+    }
+}
+
+impl<F> DynamicDetector<F>
+    where F: Frame,
+{
+
+    /// Construct a new **DynamicDetector** of the given kind.
+    ///
+    /// The `window_ms` is only used by **Rms** detection.
+    pub fn new(kind: DetectorKind,
+               window_ms: Ms,
+               attack_frames: f32,
+               release_frames: f32,
+               sample_hz: f64) -> Self
+    {
+        let mode = match kind {
+            DetectorKind::Peak => DynamicMode::Peak(Peak::full_wave()),
+            DetectorKind::Rms(shape) => {
+                let window_frames = rms_window_frames(window_ms, sample_hz);
+                DynamicMode::Rms(Rms::with_shape(window_frames, shape))
+            },
+        };
+        DynamicDetector {
+            envelope: EnvelopeDetector::new(mode, attack_frames, release_frames),
+            window_ms: window_ms,
+        }
+    }
+
+    /// The kind of envelope detection performed.
+    pub fn kind(&self) -> DetectorKind {
+        match *self.envelope.mode() {
+            DynamicMode::Peak(_) => DetectorKind::Peak,
+            DynamicMode::Rms(ref rms) => DetectorKind::Rms(rms.shape()),
+        }
+    }
+
+    /// Switch to the given kind of envelope detection, retaining the attack, release and current
+    /// envelope.
+    ///
+    /// Switching to **Rms** detection fills the new window so that it yields the current
+    /// envelope, while the contents of any previous window are discarded. Switching between
+    /// **Rms** window shapes retains the decimation and precision. This has no effect if
+    /// the detector already performs the given kind of detection.
+    pub fn set_kind(&mut self, kind: DetectorKind, sample_hz: f64) {
+        if kind == self.kind() {
+            return;
+        }
+        let envelope = self.envelope.envelope();
+        let decimation = self.decimation();
+        let high_precision = self.high_precision();
+        *self.envelope.mode_mut() = match kind {
+            DetectorKind::Peak => DynamicMode::Peak(Peak::full_wave()),
+            DetectorKind::Rms(shape) => {
+                let window_frames = rms_window_frames(self.window_ms, sample_hz);
+                DynamicMode::Rms(Rms::with_shape(window_frames, shape))
+            },
+        };
+        self.set_decimation(decimation);
+        self.set_high_precision(high_precision);
+        Detector::set_envelope(self, envelope);
+    }
+
+    /// Convert into a **PeakEnvelopeDetector**, retaining all state.
+    ///
+    /// Returns `None` if it performs **Rms** detection, in which case the detector is dropped.
+    /// Check the `kind` first to keep the detector either way.
+    pub fn into_peak(self) -> Option<PeakEnvelopeDetector<F>> {
+        if let DynamicMode::Rms(_) = *self.envelope.mode() {
+            return None;
+        }
+        Some(self.envelope.map_mode(|mode| match mode {
+            DynamicMode::Peak(peak) => peak,
+            DynamicMode::Rms(_) => unreachable!(),
+        }))
+    }
+
+    /// Convert into an **RmsEnvelopeDetector**, retaining all state including the RMS window.
+    ///
+    /// Returns `None` if it performs **Peak** detection, in which case the detector is dropped.
+    /// Check the `kind` first to keep the detector either way.
+    pub fn into_rms(self) -> Option<RmsEnvelopeDetector<F>> {
+        if let DynamicMode::Peak(_) = *self.envelope.mode() {
+            return None;
+        }
+        let DynamicDetector { envelope, window_ms } = self;
+        let rms = envelope.map_mode(|mode| match mode {
+            DynamicMode::Rms(rms) => rms,
+            DynamicMode::Peak(_) => unreachable!(),
+        });
+        Some(RmsEnvelopeDetector { rms: rms, window_ms: window_ms })
+    }
+
+}
+
+impl<F> Detector<F> for DynamicDetector<F>
+    where F: Frame,
+{
+    type Mode = DynamicMode<F>;
+    fn detector(&mut self) -> &mut EnvelopeDetector<F, Self::Mode> {
+        &mut self.envelope
+    }
+
+    fn detector_ref(&self) -> &EnvelopeDetector<F, Self::Mode> {
+        &self.envelope
+    }
+
+    fn window_ms(&self) -> Option<Ms> {
+        match *self.envelope.mode() {
+            DynamicMode::Peak(_) => None,
+            DynamicMode::Rms(_) => Some(self.window_ms),
+        }
+    }
+
+    fn window_frames(&self) -> usize {
+        match *self.envelope.mode() {
+            DynamicMode::Peak(_) => 0,
+            DynamicMode::Rms(ref rms) => rms.window_frames(),
+        }
+    }
+
+    fn set_window_ms(&mut self, ms: Ms) {
+        self.window_ms = ms;
+    }
+
+    fn update_to_sample_hz(&mut self, sample_hz: f64) {
+        let frames = rms_window_frames(self.window_ms, sample_hz);
+        if let DynamicMode::Rms(ref mut rms) = *self.envelope.mode_mut() {
+            rms.set_window_frames(frames);
+        }
+    }
+
+    fn reserve_window_frames(&mut self, max_window_frames: usize, sample_hz: f64) {
+        if let DynamicMode::Rms(ref mut rms) = *self.envelope.mode_mut() {
+            rms.set_window_frames(max_window_frames);
+        }
+        self.update_to_sample_hz(sample_hz);
+    }
+
+    fn advance_silence(&mut self, n_frames: usize) {
+        if let DynamicMode::Rms(ref mut rms) = *self.envelope.mode_mut() {
+            rms.advance_silence(n_frames);
+        }
+        self.envelope.decay(n_frames);
+    }
+
+    fn set_envelope(&mut self, envelope: F) {
+        if let DynamicMode::Rms(ref mut rms) = *self.envelope.mode_mut() {
+            rms.fill(envelope.to_float_frame());
+        }
+        self.envelope.set_envelope(envelope);
+    }
+
+    fn reset(&mut self) {
+        if let DynamicMode::Rms(ref mut rms) = *self.envelope.mode_mut() {
+            rms.reset();
+        }
+        self.envelope.reset();
+    }
+
+    fn decimation(&self) -> usize {
+        match *self.envelope.mode() {
+            DynamicMode::Peak(_) => 1,
+            DynamicMode::Rms(ref rms) => rms.decimation(),
+        }
+    }
+
+    fn set_decimation(&mut self, factor: usize) {
+        if let DynamicMode::Rms(ref mut rms) = *self.envelope.mode_mut() {
+            rms.set_decimation(factor);
+        }
+    }
+
+    fn high_precision(&self) -> bool {
+        match *self.envelope.mode() {
+            DynamicMode::Peak(_) => false,
+            DynamicMode::Rms(ref rms) => rms.high_precision(),
+        }
+    }
+
+    fn set_high_precision(&mut self, enabled: bool) {
+        if let DynamicMode::Rms(ref mut rms) = *self.envelope.mode_mut() {
+            rms.set_high_precision(enabled);
+        }
+    }
+
+    fn relative_cost(&self) -> f32 {
+        match *self.envelope.mode() {
+            DynamicMode::Peak(_) => 1.0,
+            DynamicMode::Rms(ref rms) => rms.relative_cost(),
+        }
+    }
+
+    fn description(&self) -> String {
+        match *self.envelope.mode() {
+            DynamicMode::Peak(_) => "peak".to_string(),
+            DynamicMode::Rms(ref rms) => format!("RMS ({:?} window)", rms.shape()),
+        }
+    }
+}
+
+impl<F> From<PeakEnvelopeDetector<F>> for DynamicDetector<F>
+    where F: Frame,
+{
+    /// The window duration, unused by **Peak** detection, is set to `DEFAULT_WINDOW_MS`.
+    fn from(peak: PeakEnvelopeDetector<F>) -> Self {
+        DynamicDetector {
+            envelope: peak.map_mode(DynamicMode::Peak),
+            window_ms: Ms(DEFAULT_WINDOW_MS),
+        }
+    }
+}
+
+impl<F> From<RmsEnvelopeDetector<F>> for DynamicDetector<F>
+    where F: Frame,
+{
+    fn from(rms: RmsEnvelopeDetector<F>) -> Self {
+        DynamicDetector {
+            envelope: rms.rms.map_mode(DynamicMode::Rms),
+            window_ms: rms.window_ms,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_HZ: f64 = 44_100.0;
+
+    /// Feed a clone of a warmed-up detector a different signal from the original, checking that
+    /// the two diverge and that the original matches a twin that was never cloned.
+    fn assert_clones_diverge<D, M>(make: M)
+        where D: Detector<[f32; 2]> + Clone,
+              M: Fn() -> D,
+    {
+        let mut original = make();
+        let mut twin = make();
+        for _ in 0..512 {
+            original.next_envelope([0.5, -0.5]);
+            twin.next_envelope([0.5, -0.5]);
+        }
+        let mut clone = original.clone();
+        assert_eq!(clone.last_envelope(), original.last_envelope());
+        for _ in 0..512 {
+            original.next_envelope([1.0, -1.0]);
+            twin.next_envelope([1.0, -1.0]);
+            clone.next_envelope([0.0, 0.0]);
+        }
+        assert_eq!(original.last_envelope(), twin.last_envelope());
+        assert!(original.last_envelope()[0] > 0.5);
+        assert!(clone.last_envelope()[0] < 0.5);
+    }
+
+    fn rms(shape: RmsWindow) -> RmsEnvelopeDetector<[f32; 2]> {
+        let window_ms = Ms(5.0);
+        let window_frames = rms_window_frames(window_ms, SAMPLE_HZ);
+        let rms = EnvelopeDetector::new(Rms::with_shape(window_frames, shape), 10.0, 100.0);
+        RmsEnvelopeDetector { rms, window_ms }
+    }
+
+    #[test]
+    fn cloned_peak_detector_diverges() {
+        assert_clones_diverge(|| PeakEnvelopeDetector::<[f32; 2]>::peak(10.0, 100.0));
+    }
+
+    #[test]
+    fn cloned_rms_detector_diverges() {
+        assert_clones_diverge(|| rms(RmsWindow::Rectangular));
+    }
+
+    #[test]
+    fn rms_window_spans_at_least_one_frame() {
+        assert_eq!(rms_window_frames(Ms(0.0), SAMPLE_HZ), 1);
+        assert_eq!(rms_window_frames(Ms(0.01), SAMPLE_HZ), 1);
+        assert_eq!(rms_window_frames(Ms(10.0), SAMPLE_HZ), 441);
+    }
+
+    #[test]
+    fn cloned_hann_rms_detector_diverges() {
+        assert_clones_diverge(|| rms(RmsWindow::Hann));
+    }
+
+    #[test]
+    fn cloned_dynamic_detector_diverges() {
+        assert_clones_diverge(|| {
+            DynamicDetector::new(DetectorKind::Peak, Ms(5.0), 10.0, 100.0, SAMPLE_HZ)
+        });
+        assert_clones_diverge(|| {
+            DynamicDetector::new(DetectorKind::Rms(RmsWindow::Hann), Ms(5.0), 10.0, 100.0,
+                                 SAMPLE_HZ)
+        });
+    }
+
+    #[test]
+    fn dynamic_detector_converts_only_into_its_own_kind() {
+        let mut peak = PeakEnvelopeDetector::<[f32; 2]>::peak(10.0, 100.0);
+        peak.detector().next([0.5, -0.5]);
+        let dynamic = DynamicDetector::from(peak);
+        assert_eq!(dynamic.kind(), DetectorKind::Peak);
+        assert!(dynamic.clone().into_rms().is_none());
+        let mut converted = dynamic.into_peak().unwrap();
+        assert_eq!(converted.detector().next([0.5, -0.5]), peak.detector().next([0.5, -0.5]));
+
+        let rms = rms(RmsWindow::Hann);
+        let dynamic = DynamicDetector::from(rms);
+        assert_eq!(dynamic.kind(), DetectorKind::Rms(RmsWindow::Hann));
+        assert!(dynamic.clone().into_peak().is_none());
+        assert_eq!(dynamic.into_rms().unwrap().window_ms, Ms(5.0));
+    }
+
+    #[test]
+    fn blended_level_lies_between_peak_and_rms() {
+        let mut peak = Peak::full_wave();
+        let mut rms = Rms::new(64);
+        let mut blend = PeakRms::new(64, RmsWindow::Rectangular, 0.25);
+        for i in 0..1024 {
+            let s = (i as f32 * 0.05).sin() * if (i / 256) % 2 == 0 { 1.0 } else { 0.1 };
+            let frame = [s, -0.5 * s];
+            let p: [f32; 2] = peak.next_frame(frame);
+            let r: [f32; 2] = rms.next_frame(frame);
+            let b = blend.next_frame(frame);
+            for ((p, r), b) in p.iter().zip(&r).zip(&b) {
+                assert!((b - (r + (p - r) * 0.25)).abs() < 1e-6);
+                assert!(*b >= p.min(*r) - 1e-6 && *b <= p.max(*r) + 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn detection_mix_is_clamped() {
+        let mut blend = PeakRms::<[f32; 2]>::new(64, RmsWindow::Rectangular, 0.5);
+        blend.set_mix(-1.0);
+        assert_eq!(blend.mix(), 0.0);
+        blend.set_mix(2.0);
+        assert_eq!(blend.mix(), 1.0);
+    }
+
+    #[test]
+    fn dual_detector_attacks_fast_and_releases_slow() {
+        let mut dual = DualEnvelopeDetector::<[f32; 1]>::new(441.0, 4_410.0, SAMPLE_HZ);
+        let mut slow = PeakEnvelopeDetector::<[f32; 1]>::peak(441.0, 4_410.0);
+        assert_eq!(dual.fast_weight(), 1.0);
+        // 2ms into a burst the fast follower has caught the transient while the slow has not.
+        for _ in 0..88 {
+            dual.next_envelope([1.0]);
+            slow.next_envelope([1.0]);
+        }
+        assert!(dual.last_envelope()[0] > 0.95);
+        assert!(slow.last_envelope()[0] < 0.2);
+        for _ in 0..4_322 {
+            dual.next_envelope([1.0]);
+            slow.next_envelope([1.0]);
+        }
+        // Once the burst ends the envelope releases with the slow follower alone.
+        for _ in 0..4_410 {
+            let (d, s) = (dual.next_envelope([0.0])[0], slow.next_envelope([0.0])[0]);
+            assert!((d - s).abs() < 1e-3);
+        }
+        assert!(dual.last_envelope()[0] > 0.3);
+    }
+
+    #[test]
+    fn zero_fast_weight_is_the_slow_follower_alone() {
+        let mut dual = DualEnvelopeDetector::<[f32; 1]>::new(441.0, 4_410.0, SAMPLE_HZ);
+        dual.set_fast_weight(-1.0);
+        assert_eq!(dual.fast_weight(), 0.0);
+        let mut slow = PeakEnvelopeDetector::<[f32; 1]>::peak(441.0, 4_410.0);
+        for i in 0..8_820 {
+            let s = if i < 4_410 { 1.0 } else { 0.0 };
+            assert_eq!(dual.next_envelope([s]), slow.next_envelope([s]));
+        }
+    }
+
+    #[test]
+    fn cloned_blend_detector_diverges() {
+        assert_clones_diverge(|| {
+            BlendEnvelopeDetector::new(RmsWindow::Rectangular, Ms(5.0), 0.5, 10.0, 100.0,
+                                       SAMPLE_HZ)
+        });
+    }
+
+    #[test]
+    fn cloned_dual_detector_diverges() {
+        assert_clones_diverge(|| DualEnvelopeDetector::new(10.0, 100.0, SAMPLE_HZ));
+    }
+}
@@ -0,0 +1,462 @@
+//! The attack and release ballistics used to follow the envelope of a signal.
+//!
+//! The primary type of interest is the [**EnvelopeDetector**](./struct.EnvelopeDetector), generic
+//! over any detection **Mode**.
+
+use db;
+use envelope_detector::{Frame, Mode, Peak, Sample};
+use rms::Rms;
+use sample::FloatSample;
+
+
+/// The most channels a `Frame` may have, as `sample` implements **Frame** for arrays of up to 32
+/// samples.
+const MAX_CHANNELS: usize = 32;
+
+/// The algorithm by which the envelope approaches the detected level while rising (attack) or
+/// falling (release).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SmoothingMode {
+    /// The envelope approaches the level exponentially, covering roughly 63.2% of the distance
+    /// over the duration. This is the default for both attack and release.
+    Exponential,
+    /// The envelope moves toward the level at a constant rate, covering the full scale (an
+    /// amplitude of `1.0`) over the duration.
+    Linear,
+    /// The envelope holds its value for the duration, then steps directly to the level.
+    ///
+    /// With a duration shorter than a frame, the envelope follows the level instantaneously,
+    /// i.e. a `Hold` attack catches every peak.
+    Hold,
+}
+
+
+/// Iteratively extracts the amplitude envelope from a signal based on an attack time, a release
+/// time and a detection **Mode** (i.e. **Peak** or **Rms**).
+///
+/// Unlike the `envelope_detector` crate's type of the same name, the attack and release
+/// coefficients and the current envelope are exposed, allowing the **Compressor** to match,
+/// inspect and seed its detector.
+#[derive(Copy, Clone, Debug)]
+pub struct EnvelopeDetector<F, M>
+    where F: Frame,
+          M: Mode<F>,
+{
+    attack_gain: f32,
+    release_gain: f32,
+    /// The attack duration in frames, used by the **Linear** and **Hold** smoothing modes.
+    attack_frames: f32,
+    /// The release duration in frames, used by the **Linear** and **Hold** smoothing modes.
+    release_frames: f32,
+    attack_mode: SmoothingMode,
+    release_mode: SmoothingMode,
+    /// Whether the attack duration adapts to the size of each rise in level.
+    auto_attack: bool,
+    last_env_frame: F,
+    /// The number of frames for which each channel's envelope has been held by a **Hold** mode.
+    held_frames: F::Float,
+    mode: M,
+}
+
+/// An **EnvelopeDetector** that tracks the full wave **Peak** envelope of a signal.
+pub type PeakEnvelopeDetector<F> = EnvelopeDetector<F, Peak>;
+
+
+/// The rise in decibels of the detected level above the envelope at which an auto attack lasts
+/// exactly the nominal attack duration.
+pub const AUTO_ATTACK_REFERENCE_DB: f32 = 6.0;
+/// The shortest auto attack as a fraction of the nominal attack duration.
+pub const AUTO_ATTACK_MIN_SCALE: f32 = 0.1;
+/// The longest auto attack as a multiple of the nominal attack duration.
+pub const AUTO_ATTACK_MAX_SCALE: f32 = 2.0;
+
+
+/// The coefficient by which the envelope retains its previous value each frame, for an attack or
+/// release lasting the given number of frames.
+pub fn calc_gain(n_frames: f32) -> f32 {
+    ::std::f32::consts::E.powf(-1.0 / n_frames)
+}
+
+/// The attack duration in frames for a rise from the envelope `l` to the detected level `m`,
+/// given the nominal attack duration in frames.
+///
+/// The duration is scaled by `AUTO_ATTACK_REFERENCE_DB` divided by the rise in decibels, clamped
+/// between `AUTO_ATTACK_MIN_SCALE` and `AUTO_ATTACK_MAX_SCALE`.
+fn auto_attack_frames(l: f32, m: f32, frames: f32) -> f32 {
+    let rise_db = db::amp_to_db(m / l);
+    let scale = AUTO_ATTACK_REFERENCE_DB / rise_db;
+    let scale = if scale < AUTO_ATTACK_MIN_SCALE {
+        AUTO_ATTACK_MIN_SCALE
+    } else if scale > AUTO_ATTACK_MAX_SCALE {
+        AUTO_ATTACK_MAX_SCALE
+    } else {
+        scale
+    };
+    frames * scale
+}
+
+/// The number of frames for which an attack or release with the given coefficient lasts.
+///
+/// The inverse of `calc_gain`.
+fn calc_frames(gain: f32) -> f32 {
+    -1.0 / gain.ln()
+}
+
+
+impl<F> EnvelopeDetector<F, Rms<F>>
+    where F: Frame,
+{
+
+    /// Construct a new **Rms** **EnvelopeDetector**.
+    pub fn rms(rms_window_frames: usize, attack_frames: f32, release_frames: f32) -> Self {
+        let rms = Rms::new(rms_window_frames);
+        Self::new(rms, attack_frames, release_frames)
+    }
+
+    /// Set the duration of the **Rms** window in frames.
+    pub fn set_window_frames(&mut self, n_window_frames: usize) {
+        self.mode.set_window_frames(n_window_frames);
+    }
+
+}
+
+impl<F> EnvelopeDetector<F, Peak>
+    where F: Frame,
+{
+
+    /// Construct a new full wave **Peak** **EnvelopeDetector**.
+    pub fn peak(attack_frames: f32, release_frames: f32) -> Self {
+        let peak = Peak::full_wave();
+        Self::new(peak, attack_frames, release_frames)
+    }
+
+}
+
+impl<F, M> EnvelopeDetector<F, M>
+    where F: Frame,
+          M: Mode<F>,
+{
+
+    /// Construct a new **EnvelopeDetector** from the given detection **Mode**.
+    pub fn new(mode: M, attack_frames: f32, release_frames: f32) -> Self {
+        EnvelopeDetector {
+            mode: mode,
+            last_env_frame: F::equilibrium(),
+            held_frames: Frame::equilibrium(),
+            attack_gain: calc_gain(attack_frames),
+            release_gain: calc_gain(release_frames),
+            attack_frames: attack_frames,
+            release_frames: release_frames,
+            attack_mode: SmoothingMode::Exponential,
+            release_mode: SmoothingMode::Exponential,
+            auto_attack: false,
+        }
+    }
+
+    /// Set the **EnvelopeDetector**'s attack time as a number of frames.
+    pub fn set_attack_frames(&mut self, frames: f32) {
+        self.attack_gain = calc_gain(frames);
+        self.attack_frames = frames;
+    }
+
+    /// Set the **EnvelopeDetector**'s release time as a number of frames.
+    pub fn set_release_frames(&mut self, frames: f32) {
+        self.release_gain = calc_gain(frames);
+        self.release_frames = frames;
+    }
+
+    /// The algorithm by which the envelope rises.
+    pub fn attack_mode(&self) -> SmoothingMode {
+        self.attack_mode
+    }
+
+    /// The algorithm by which the envelope falls.
+    pub fn release_mode(&self) -> SmoothingMode {
+        self.release_mode
+    }
+
+    /// Set the algorithm by which the envelope rises.
+    pub fn set_attack_mode(&mut self, mode: SmoothingMode) {
+        self.attack_mode = mode;
+    }
+
+    /// Set the algorithm by which the envelope falls.
+    pub fn set_release_mode(&mut self, mode: SmoothingMode) {
+        self.release_mode = mode;
+    }
+
+    /// Whether the attack duration adapts to the size of each rise in level.
+    pub fn auto_attack(&self) -> bool {
+        self.auto_attack
+    }
+
+    /// Enable or disable the adaptation of the attack duration to the size of each rise in level.
+    pub fn set_auto_attack(&mut self, enabled: bool) {
+        self.auto_attack = enabled;
+    }
+
+    /// The coefficient by which the envelope retains its previous value each frame while rising.
+    pub fn attack_gain(&self) -> f32 {
+        self.attack_gain
+    }
+
+    /// The coefficient by which the envelope retains its previous value each frame while falling.
+    pub fn release_gain(&self) -> f32 {
+        self.release_gain
+    }
+
+    /// Directly set the attack coefficient, bypassing any conversion from a number of frames.
+    pub fn set_attack_gain(&mut self, gain: f32) {
+        self.attack_gain = gain;
+        self.attack_frames = calc_frames(gain);
+    }
+
+    /// Directly set the release coefficient, bypassing any conversion from a number of frames.
+    pub fn set_release_gain(&mut self, gain: f32) {
+        self.release_gain = gain;
+        self.release_frames = calc_frames(gain);
+    }
+
+    /// The most recently detected envelope.
+    pub fn envelope(&self) -> F {
+        self.last_env_frame
+    }
+
+    /// Seed the envelope from which the next frame's envelope is detected.
+    ///
+    /// The state of the detection **Mode** is left untouched.
+    pub fn set_envelope(&mut self, envelope: F) {
+        self.last_env_frame = envelope;
+    }
+
+    /// Clear the envelope and any held release, as though only silence had been detected.
+    ///
+    /// The state of the detection **Mode** is left untouched.
+    pub fn reset(&mut self) {
+        self.last_env_frame = F::equilibrium();
+        self.held_frames = Frame::equilibrium();
+    }
+
+    /// Release the envelope as though it had been fed `n_frames` of silence, without stepping the
+    /// detection **Mode**.
+    ///
+    /// The envelope decays exponentially via the release coefficient regardless of the release
+    /// mode. This is only exact for the **Exponential** release, however the difference is
+    /// negligible once the envelope is near silence.
+    pub fn decay(&mut self, n_frames: usize) {
+        let decay = self.release_gain.powf(n_frames as f32);
+        self.last_env_frame = self.last_env_frame.map(|l| l.mul_amp(decay.to_sample()));
+        self.held_frames = Frame::equilibrium();
+    }
+
+    /// Convert the **EnvelopeDetector** to one using the detection **Mode** produced from the
+    /// current **Mode** by `map`.
+    ///
+    /// The ballistics and the current envelope are retained.
+    pub fn map_mode<N, G>(self, map: G) -> EnvelopeDetector<F, N>
+        where N: Mode<F>,
+              G: FnOnce(M) -> N,
+    {
+        EnvelopeDetector {
+            attack_gain: self.attack_gain,
+            release_gain: self.release_gain,
+            attack_frames: self.attack_frames,
+            release_frames: self.release_frames,
+            attack_mode: self.attack_mode,
+            release_mode: self.release_mode,
+            auto_attack: self.auto_attack,
+            last_env_frame: self.last_env_frame,
+            held_frames: self.held_frames,
+            mode: map(self.mode),
+        }
+    }
+
+    /// Borrow the detection **Mode**.
+    pub fn mode(&self) -> &M {
+        &self.mode
+    }
+
+    /// Mutably borrow the detection **Mode**.
+    pub fn mode_mut(&mut self) -> &mut M {
+        &mut self.mode
+    }
+
+    /// Given the next input signal frame, detect and return the next envelope frame.
+    pub fn next(&mut self, frame: F) -> F {
+        let exponential = SmoothingMode::Exponential;
+        if self.attack_mode != exponential || self.release_mode != exponential || self.auto_attack {
+            let mode_frame = self.mode.next_frame(frame);
+            return self.next_smoothed(mode_frame);
+        }
+
+        let EnvelopeDetector {
+            attack_gain, release_gain, ref mut mode, ref mut last_env_frame, ..
+        } = *self;
+
+        let mode_frame = mode.next_frame(frame);
+        let new_env_frame = last_env_frame.zip_map(mode_frame, |l, m| {
+            let gain = if l < m { attack_gain } else { release_gain };
+            let diff = l.add_amp(-m.to_signed_sample());
+            m.add_amp(diff.mul_amp(gain.to_sample()).to_sample())
+        });
+        *last_env_frame = new_env_frame;
+        new_env_frame
+    }
+
+    /// Step the envelope toward the given detected frame using the attack and release modes.
+    fn next_smoothed(&mut self, mode_frame: F) -> F {
+        let last_env_frame = self.last_env_frame;
+        let held_frames = self.held_frames;
+        // Each channel is smoothed once, stashing its hold count while the envelope is built.
+        let mut new_held = [<F::Sample as Sample>::Float::equilibrium(); MAX_CHANNELS];
+        let new_env_frame = F::from_fn(|i| {
+            let l = *last_env_frame.channel(i).unwrap();
+            let m = *mode_frame.channel(i).unwrap();
+            let held = *held_frames.channel(i).unwrap();
+            let (env, held) = self.smooth_channel(l, m, held);
+            new_held[i] = held;
+            env
+        });
+        let new_held_frames = F::Float::from_fn(|i| new_held[i]);
+        self.last_env_frame = new_env_frame;
+        self.held_frames = new_held_frames;
+        new_env_frame
+    }
+
+    /// Step a single channel's envelope `l` toward the detected level `m`, yielding the new
+    /// envelope along with the number of frames for which it has now been held.
+    fn smooth_channel(&self,
+                      l: F::Sample,
+                      m: F::Sample,
+                      held: <F::Sample as Sample>::Float)
+                      -> (F::Sample, <F::Sample as Sample>::Float)
+    {
+        let zero = <F::Sample as Sample>::Float::equilibrium();
+        let (smoothing_mode, gain, frames) = if l < m && self.auto_attack {
+            let l_f: f32 = l.to_float_sample().to_sample();
+            let m_f: f32 = m.to_float_sample().to_sample();
+            let frames = auto_attack_frames(l_f, m_f, self.attack_frames);
+            (self.attack_mode, calc_gain(frames), frames)
+        } else if l < m {
+            (self.attack_mode, self.attack_gain, self.attack_frames)
+        } else {
+            (self.release_mode, self.release_gain, self.release_frames)
+        };
+        match smoothing_mode {
+            SmoothingMode::Exponential => {
+                let diff = l.add_amp(-m.to_signed_sample());
+                (m.add_amp(diff.mul_amp(gain.to_sample()).to_sample()), zero)
+            },
+            SmoothingMode::Linear => {
+                let (l_f, m_f) = (l.to_float_sample(), m.to_float_sample());
+                let step: <F::Sample as Sample>::Float = (1.0 / frames).to_sample();
+                let new_f = if l_f < m_f {
+                    let stepped = l_f + step;
+                    if stepped > m_f { m_f } else { stepped }
+                } else {
+                    let stepped = l_f - step;
+                    if stepped < m_f { m_f } else { stepped }
+                };
+                (new_f.to_sample(), zero)
+            },
+            SmoothingMode::Hold => {
+                let one = <<F::Sample as Sample>::Float as FloatSample>::identity();
+                let held = held + one;
+                if l == m || held.to_sample::<f32>() >= frames {
+                    (m, zero)
+                } else {
+                    (l, held)
+                }
+            },
+        }
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coefficients_set_directly_are_reported_back() {
+        let mut detector = PeakEnvelopeDetector::<[f32; 1]>::peak(10.0, 100.0);
+        assert_eq!(detector.attack_gain(), calc_gain(10.0));
+        assert_eq!(detector.release_gain(), calc_gain(100.0));
+        detector.set_attack_gain(calc_gain(20.0));
+        detector.set_release_gain(calc_gain(200.0));
+        assert_eq!(detector.attack_gain(), calc_gain(20.0));
+        assert_eq!(detector.release_gain(), calc_gain(200.0));
+    }
+
+    #[test]
+    fn exponential_attack_covers_most_of_a_step_over_its_duration() {
+        let mut detector = PeakEnvelopeDetector::<[f32; 1]>::peak(100.0, 1_000.0);
+        let mut envelope = [0.0];
+        for _ in 0..100 {
+            envelope = detector.next([1.0]);
+        }
+        let expected = 1.0 - (-1.0f32).exp();
+        assert!((envelope[0] - expected).abs() < 0.01, "{}", envelope[0]);
+    }
+
+    #[test]
+    fn linear_attack_covers_full_scale_over_its_duration() {
+        let mut detector = PeakEnvelopeDetector::<[f32; 1]>::peak(100.0, 1_000.0);
+        detector.set_attack_mode(SmoothingMode::Linear);
+        for i in 1..101 {
+            let envelope = detector.next([1.0])[0];
+            assert!((envelope - i as f32 / 100.0).abs() < 1e-4, "{}: {}", i, envelope);
+        }
+        assert_eq!(detector.next([1.0]), [1.0]);
+        assert_eq!(detector.next([0.5]), [0.5 + (-1.0f32 / 1_000.0).exp() * 0.5]);
+    }
+
+    #[test]
+    fn hold_release_steps_down_after_its_duration() {
+        let mut detector = PeakEnvelopeDetector::<[f32; 1]>::peak(0.5, 10.0);
+        detector.set_attack_mode(SmoothingMode::Hold);
+        detector.set_release_mode(SmoothingMode::Hold);
+        assert_eq!(detector.next([1.0]), [1.0]);
+        for _ in 0..9 {
+            assert_eq!(detector.next([0.0]), [1.0]);
+        }
+        assert_eq!(detector.next([0.0]), [0.0]);
+    }
+
+    #[test]
+    fn smoothing_modes_track_each_channel_independently() {
+        let mut detector = PeakEnvelopeDetector::<[f32; 2]>::peak(0.5, 4.0);
+        detector.set_attack_mode(SmoothingMode::Hold);
+        detector.set_release_mode(SmoothingMode::Hold);
+        detector.next([1.0, 1.0]);
+        detector.next([0.0, 1.0]);
+        detector.next([0.0, 1.0]);
+        assert_eq!(detector.next([0.0, 0.0]), [1.0, 1.0]);
+        assert_eq!(detector.next([0.0, 0.0]), [0.0, 1.0]);
+        assert_eq!(detector.next([0.0, 0.0]), [0.0, 1.0]);
+        assert_eq!(detector.next([0.0, 0.0]), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn auto_attack_scales_inversely_with_the_rise() {
+        let six_db = db::db_to_amp(AUTO_ATTACK_REFERENCE_DB);
+        assert!((auto_attack_frames(0.25, 0.25 * six_db, 100.0) - 100.0).abs() < 1e-3);
+        let twelve_db = six_db * six_db;
+        assert!((auto_attack_frames(0.25, 0.25 * twelve_db, 100.0) - 50.0).abs() < 1e-2);
+        assert_eq!(auto_attack_frames(0.5, 0.51, 100.0), 100.0 * AUTO_ATTACK_MAX_SCALE);
+        assert_eq!(auto_attack_frames(0.0, 1.0, 100.0), 100.0 * AUTO_ATTACK_MIN_SCALE);
+    }
+
+    #[test]
+    fn auto_attack_catches_large_rises_sooner() {
+        let rise = |auto_attack: bool, from: f32| {
+            let mut detector = PeakEnvelopeDetector::<[f32; 1]>::peak(100.0, 1_000.0);
+            detector.set_auto_attack(auto_attack);
+            detector.set_envelope([from]);
+            (0..50).fold([0.0], |_, _| detector.next([1.0]))[0]
+        };
+        assert!(rise(true, 0.01) > rise(false, 0.01) + 0.1);
+        assert!(rise(true, 0.9) < rise(false, 0.9));
+    }
+}
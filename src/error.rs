@@ -0,0 +1,97 @@
+//! Errors reported by the **Compressor**'s runtime-checked methods and by the
+//! **CompressorBuilder**.
+
+use std;
+
+
+/// An error reported when the buffers or settings given to a **Compressor** are invalid.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CompressorError {
+    /// The number of channels in some buffer differs from that of the **Compressor**'s `Frame`.
+    ChannelMismatch {
+        /// The number of channels of the **Compressor**'s `Frame`.
+        expected: usize,
+        /// The number of channels given.
+        found: usize,
+    },
+    /// Some buffer differs in length from the first input buffer.
+    LengthMismatch {
+        /// The length of the first input buffer.
+        expected: usize,
+        /// The length given.
+        found: usize,
+    },
+    /// The sample rate is zero, negative, infinite or NaN, from which no meaningful coefficients
+    /// may be derived.
+    InvalidSampleRate(f64),
+}
+
+
+/// An error reported by `CompressorBuilder::try_build` when the requested settings conflict or
+/// are out of range.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BuilderError {
+    /// A `window_ms` was given for **Peak** detection, which has no window.
+    WindowWithoutRms,
+    /// The threshold is negative or NaN.
+    InvalidThreshold(f32),
+    /// The ratio is less than `1.0` or NaN.
+    InvalidRatio(f32),
+    /// The attack, release or window duration in milliseconds is negative, infinite or NaN.
+    InvalidDuration(f64),
+    /// The sample rate is zero, negative, infinite or NaN.
+    InvalidSampleRate(f64),
+}
+
+
+impl std::fmt::Display for CompressorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match *self {
+            CompressorError::ChannelMismatch { expected, found } =>
+                write!(f, "expected {} channels but found {}", expected, found),
+            CompressorError::LengthMismatch { expected, found } =>
+                write!(f, "expected buffers of {} frames but found {}", expected, found),
+            CompressorError::InvalidSampleRate(hz) =>
+                write!(f, "the sample rate {}Hz is not finite and greater than zero", hz),
+        }
+    }
+}
+
+impl std::fmt::Display for BuilderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match *self {
+            BuilderError::WindowWithoutRms =>
+                write!(f, "a window was given for peak detection, which has no window"),
+            BuilderError::InvalidThreshold(threshold) =>
+                write!(f, "the threshold {} is negative or NaN", threshold),
+            BuilderError::InvalidRatio(ratio) =>
+                write!(f, "the ratio {} is less than 1 or NaN", ratio),
+            BuilderError::InvalidDuration(ms) =>
+                write!(f, "the duration {}ms is negative or not finite", ms),
+            BuilderError::InvalidSampleRate(hz) =>
+                write!(f, "the sample rate {}Hz is not finite and greater than zero", hz),
+        }
+    }
+}
+
+impl std::error::Error for CompressorError {
+    fn description(&self) -> &str {
+        match *self {
+            CompressorError::ChannelMismatch { .. } => "channel count mismatch",
+            CompressorError::LengthMismatch { .. } => "buffer length mismatch",
+            CompressorError::InvalidSampleRate(_) => "invalid sample rate",
+        }
+    }
+}
+
+impl std::error::Error for BuilderError {
+    fn description(&self) -> &str {
+        match *self {
+            BuilderError::WindowWithoutRms => "window given without rms detection",
+            BuilderError::InvalidThreshold(_) => "invalid threshold",
+            BuilderError::InvalidRatio(_) => "invalid ratio",
+            BuilderError::InvalidDuration(_) => "invalid duration",
+            BuilderError::InvalidSampleRate(_) => "invalid sample rate",
+        }
+    }
+}
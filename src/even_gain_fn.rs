@@ -1,20 +1,22 @@
 
 use {Compressor, Detector};
 use envelope_detector::{Sample, Frame};
+use sample::FloatSample;
+use std::convert::TryFrom;
 
 /// Some function that yields a gain to be applied evenly across all channels in a single frame.
 pub trait EvenGainFunction: Sized {
     /// Yield the gain to be applied to each channel for the given frame of samples.
     fn next_gain<F, D>(&mut Compressor<F, D, Self>, frame: F) -> <F::Sample as Sample>::Float
         where F: Frame,
-              D: Detector<F>;
+              D: Detector<F::Float>;
 }
 
 
 /// An [**EvenGainFunction**](./trait.EvenGainFunction) that yields the *average* between each of
 /// the produced channel gains.
-#[derive(Copy, Clone, Debug)]
-pub enum Average {}
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Average;
 
 impl EvenGainFunction for Average {
     /// The next compressor gain for the `Frame`.
@@ -23,20 +25,17 @@ impl EvenGainFunction for Average {
     #[inline]
     fn next_gain<F, D>(compressor: &mut Compressor<F, D, Self>, frame: F) -> <F::Sample as Sample>::Float
         where F: Frame,
-              D: Detector<F>,
+              D: Detector<F::Float>,
     {
-        let next_frame = compressor.next_gain_per_channel(frame);
-        let sum: <F::Sample as Sample>::Float =
-            next_frame.channels().fold(Sample::equilibrium(), |s, ch_gain| s + ch_gain);
-        sum / (F::n_channels() as f32).to_sample()
+        average(compressor.next_gain_per_channel(frame))
     }
 }
 
 
 /// An [**EvenGainFunction**](./trait.EvenGainFunction) that yields the *minimum* between each of
 /// the produced channel gains.
-#[derive(Copy, Clone, Debug)]
-pub enum Minimum {}
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Minimum;
 
 impl EvenGainFunction for Minimum {
     /// The next compressor gain for the `Frame`.
@@ -45,10 +44,222 @@ impl EvenGainFunction for Minimum {
     #[inline]
     fn next_gain<F, D>(compressor: &mut Compressor<F, D, Self>, frame: F) -> <F::Sample as Sample>::Float
         where F: Frame,
-              D: Detector<F>,
+              D: Detector<F::Float>,
     {
-        let next_frame = compressor.next_gain_per_channel(frame);
-        let one = <F::Sample as Sample>::identity();
-        next_frame.channels().fold(one, |min, ch_gain| if ch_gain < min { ch_gain } else { min })
+        minimum(compressor.next_gain_per_channel(frame))
+    }
+}
+
+
+/// An [**EvenGainFunction**](./trait.EvenGainFunction) that yields the average between each of
+/// the produced channel gains in the *decibel* domain.
+///
+/// Averaging in decibels (i.e. the geometric mean of the linear gains) matches the way engineers
+/// usually reason about stereo linking. When the channel gains differ greatly it yields less gain
+/// than the linear [**Average**](./struct.Average): averaging -20dB and 0dB of gain reduction
+/// yields -10dB, whereas the linear average of 0.1 and 1.0 is 0.55 or roughly -5.2dB.
+///
+/// There is no decibel-domain equivalent of [**Minimum**](./struct.Minimum), as the lowest linear
+/// gain is also the lowest gain in decibels.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct AverageDb;
+
+impl EvenGainFunction for AverageDb {
+    /// The next compressor gain for the `Frame`.
+    ///
+    /// The returned gain is the *geometric mean* between each of the channel gains.
+    #[inline]
+    fn next_gain<F, D>(compressor: &mut Compressor<F, D, Self>, frame: F) -> <F::Sample as Sample>::Float
+        where F: Frame,
+              D: Detector<F::Float>,
+    {
+        average_db(compressor.next_gain_per_channel(frame))
+    }
+}
+
+
+/// An [**EvenGainFunction**](./trait.EvenGainFunction) chosen at runtime.
+///
+/// Where the other **EvenGainFunction**s are selected via the **Compressor**'s type, a
+/// **Compressor** using **EvenGainKind** stores the kind by value, allowing it to be chosen (and
+/// changed) at runtime. Each variant yields exactly the same gain as its namesake.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EvenGainKind {
+    /// The behaviour of [**Average**](./struct.Average).
+    Average,
+    /// The behaviour of [**AverageDb**](./struct.AverageDb).
+    AverageDb,
+    /// The behaviour of [**Minimum**](./struct.Minimum).
+    Minimum,
+}
+
+// Deriving `Default` for an enum requires the `#[default]` attribute of Rust 1.62.
+#[allow(clippy::derivable_impls)]
+impl Default for EvenGainKind {
+    fn default() -> Self {
+        EvenGainKind::Average
+    }
+}
+
+impl From<Average> for EvenGainKind {
+    fn from(_: Average) -> Self {
+        EvenGainKind::Average
+    }
+}
+
+impl From<AverageDb> for EvenGainKind {
+    fn from(_: AverageDb) -> Self {
+        EvenGainKind::AverageDb
+    }
+}
+
+impl From<Minimum> for EvenGainKind {
+    fn from(_: Minimum) -> Self {
+        EvenGainKind::Minimum
+    }
+}
+
+impl TryFrom<EvenGainKind> for Average {
+    type Error = EvenGainKind;
+    fn try_from(kind: EvenGainKind) -> Result<Self, Self::Error> {
+        match kind {
+            EvenGainKind::Average => Ok(Average),
+            kind => Err(kind),
+        }
+    }
+}
+
+impl TryFrom<EvenGainKind> for AverageDb {
+    type Error = EvenGainKind;
+    fn try_from(kind: EvenGainKind) -> Result<Self, Self::Error> {
+        match kind {
+            EvenGainKind::AverageDb => Ok(AverageDb),
+            kind => Err(kind),
+        }
+    }
+}
+
+impl TryFrom<EvenGainKind> for Minimum {
+    type Error = EvenGainKind;
+    fn try_from(kind: EvenGainKind) -> Result<Self, Self::Error> {
+        match kind {
+            EvenGainKind::Minimum => Ok(Minimum),
+            kind => Err(kind),
+        }
+    }
+}
+
+impl EvenGainFunction for EvenGainKind {
+    /// The next compressor gain for the `Frame`, as yielded by the **Compressor**'s current kind.
+    #[inline]
+    fn next_gain<F, D>(compressor: &mut Compressor<F, D, Self>, frame: F) -> <F::Sample as Sample>::Float
+        where F: Frame,
+              D: Detector<F::Float>,
+    {
+        let kind = compressor.even_gain_fn;
+        let gains = compressor.next_gain_per_channel(frame);
+        match kind {
+            EvenGainKind::Average => average(gains),
+            EvenGainKind::AverageDb => average_db(gains),
+            EvenGainKind::Minimum => minimum(gains),
+        }
+    }
+}
+
+
+/// The *average* between each of the given channel gains.
+#[inline]
+fn average<G>(gains: G) -> G::Sample
+    where G: Frame,
+          G::Sample: FloatSample,
+{
+    let sum = gains.channels().fold(<G::Sample as Sample>::equilibrium(), |s, ch_gain| s + ch_gain);
+    sum / (G::n_channels() as f32).to_sample()
+}
+
+/// The *lowest* between each of the given channel gains.
+#[inline]
+fn minimum<G>(gains: G) -> G::Sample
+    where G: Frame,
+          G::Sample: FloatSample,
+{
+    let one = <G::Sample as FloatSample>::identity();
+    gains.channels().fold(one, |min, ch_gain| if ch_gain < min { ch_gain } else { min })
+}
+
+/// The *geometric mean* between each of the given channel gains.
+#[inline]
+fn average_db<G>(gains: G) -> G::Sample
+    where G: Frame,
+          G::Sample: FloatSample,
+{
+    let one = <G::Sample as FloatSample>::identity();
+    let product = gains.channels().fold(one, |product, ch_gain| product * ch_gain);
+    let product: f64 = product.to_sample();
+    product.powf(1.0 / G::n_channels() as f64).to_sample()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {CompressorBuilder, PeakCompressor};
+
+    #[test]
+    fn average_db_halves_the_reduction_in_decibels() {
+        let mut compressor: PeakCompressor<[f32; 2], AverageDb> =
+            Compressor::peak(1.0, 100.0, 44_100.0, 0.25, 4.0);
+        let mut twin: PeakCompressor<[f32; 2], Minimum> =
+            Compressor::peak(1.0, 100.0, 44_100.0, 0.25, 4.0);
+        let (mut gain, mut loud_channel_gain) = (0.0, 0.0);
+        for _ in 0..4_410 {
+            gain = compressor.next_gain([1.0, 0.0]);
+            loud_channel_gain = twin.next_gain([1.0, 0.0]);
+        }
+        assert!((gain - loud_channel_gain.sqrt()).abs() < 1e-5);
+    }
+
+    fn assert_kind_matches<G>(kind: EvenGainKind, mut namesake: G)
+        where G: FnMut([f32; 2]) -> f32,
+    {
+        let mut by_kind = CompressorBuilder::new()
+            .threshold(0.25)
+            .ratio(4.0)
+            .attack_ms(1.0)
+            .even_gain(kind)
+            .build::<[f32; 2]>(44_100.0);
+        for i in 0..4_410 {
+            let frame = [(i as f32 * 0.02).sin(), 0.3];
+            assert_eq!(by_kind.next_gain(frame), namesake(frame), "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn each_kind_yields_the_gain_of_its_namesake() {
+        let mut average: PeakCompressor<[f32; 2], Average> =
+            Compressor::peak(1.0, 100.0, 44_100.0, 0.25, 4.0);
+        let mut average_db: PeakCompressor<[f32; 2], AverageDb> =
+            Compressor::peak(1.0, 100.0, 44_100.0, 0.25, 4.0);
+        let mut minimum: PeakCompressor<[f32; 2], Minimum> =
+            Compressor::peak(1.0, 100.0, 44_100.0, 0.25, 4.0);
+        assert_kind_matches(EvenGainKind::Average, |f| average.next_gain(f));
+        assert_kind_matches(EvenGainKind::AverageDb, |f| average_db.next_gain(f));
+        assert_kind_matches(EvenGainKind::Minimum, |f| minimum.next_gain(f));
+    }
+
+    #[test]
+    fn channel_gains_combine_as_documented() {
+        let gains = [0.1f32, 1.0];
+        assert!((average(gains) - 0.55).abs() < 1e-6);
+        assert_eq!(minimum(gains), 0.1);
+        assert!((average_db(gains) - 0.1f32.sqrt()).abs() < 1e-6);
+        assert!((average_db([0.5f32, 0.5, 0.5]) - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn kinds_convert_to_and_from_their_namesakes() {
+        assert_eq!(EvenGainKind::from(Minimum), EvenGainKind::Minimum);
+        assert!(AverageDb::try_from(EvenGainKind::from(AverageDb)).is_ok());
+        assert_eq!(Average::try_from(EvenGainKind::Minimum).err(), Some(EvenGainKind::Minimum));
     }
 }
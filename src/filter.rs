@@ -0,0 +1,352 @@
+//! Biquad filters used to shape the signal seen by the envelope detector.
+//!
+//! Coefficients follow Robert Bristow-Johnson's "Audio EQ Cookbook" and are calculated in `f64`
+//! before being converted to the sample format of the filtered frames.
+
+use envelope_detector::{Frame, Sample};
+use sample::FloatSample;
+use std;
+
+
+/// The `q` of a second-order Butterworth response.
+pub const BUTTERWORTH_Q: f64 = std::f64::consts::FRAC_1_SQRT_2;
+
+/// The centre frequency of the K-weighting pre-filter's high shelf.
+const K_SHELF_HZ: f64 = 1681.974450955533;
+/// The gain in decibels of the K-weighting pre-filter's high shelf.
+const K_SHELF_GAIN_DB: f64 = 3.999843853973347;
+/// The `q` of the K-weighting pre-filter's high shelf.
+const K_SHELF_Q: f64 = 0.7071752369554196;
+/// The cutoff of the K-weighting RLB high-pass filter.
+const K_HIGHPASS_HZ: f64 = 38.13547087602444;
+/// The `q` of the K-weighting RLB high-pass filter.
+const K_HIGHPASS_Q: f64 = 0.5003270373238773;
+
+
+/// The coefficients of a biquad filter, normalised so that `a0 == 1.0`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BiquadCoefficients {
+    /// The gain of the current input.
+    pub b0: f64,
+    /// The gain of the previous input.
+    pub b1: f64,
+    /// The gain of the input before the previous input.
+    pub b2: f64,
+    /// The gain of the previous output.
+    pub a1: f64,
+    /// The gain of the output before the previous output.
+    pub a2: f64,
+}
+
+/// A biquad filter applied to every channel of a `Frame` of floating point samples.
+///
+/// Each channel has its own state and may have its own coefficients.
+#[derive(Copy, Clone, Debug)]
+pub struct Biquad<F> {
+    b0: F,
+    b1: F,
+    b2: F,
+    a1: F,
+    a2: F,
+    /// The first state of the transposed direct form II structure.
+    s1: F,
+    /// The second state of the transposed direct form II structure.
+    s2: F,
+}
+
+
+impl BiquadCoefficients {
+
+    /// Coefficients that pass the signal through unchanged.
+    pub fn identity() -> Self {
+        BiquadCoefficients { b0: 1.0, b1: 0.0, b2: 0.0, a1: 0.0, a2: 0.0 }
+    }
+
+    /// Normalise the given coefficients by `a0`.
+    fn normalised(b0: f64, b1: f64, b2: f64, a0: f64, a1: f64, a2: f64) -> Self {
+        BiquadCoefficients {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+        }
+    }
+
+    /// A second-order high-pass filter.
+    pub fn highpass(cutoff_hz: f64, q: f64, sample_hz: f64) -> Self {
+        let w0 = 2.0 * std::f64::consts::PI * cutoff_hz / sample_hz;
+        let (sin_w0, cos_w0) = (w0.sin(), w0.cos());
+        let alpha = sin_w0 / (2.0 * q);
+        Self::normalised((1.0 + cos_w0) / 2.0,
+                         -(1.0 + cos_w0),
+                         (1.0 + cos_w0) / 2.0,
+                         1.0 + alpha,
+                         -2.0 * cos_w0,
+                         1.0 - alpha)
+    }
+
+    /// A second-order low-pass filter.
+    pub fn lowpass(cutoff_hz: f64, q: f64, sample_hz: f64) -> Self {
+        let w0 = 2.0 * std::f64::consts::PI * cutoff_hz / sample_hz;
+        let (sin_w0, cos_w0) = (w0.sin(), w0.cos());
+        let alpha = sin_w0 / (2.0 * q);
+        Self::normalised((1.0 - cos_w0) / 2.0,
+                         1.0 - cos_w0,
+                         (1.0 - cos_w0) / 2.0,
+                         1.0 + alpha,
+                         -2.0 * cos_w0,
+                         1.0 - alpha)
+    }
+
+    /// A second-order all-pass filter, shifting the phase around the cutoff while leaving the
+    /// magnitude of every frequency unchanged.
+    pub fn allpass(cutoff_hz: f64, q: f64, sample_hz: f64) -> Self {
+        let w0 = 2.0 * std::f64::consts::PI * cutoff_hz / sample_hz;
+        let (sin_w0, cos_w0) = (w0.sin(), w0.cos());
+        let alpha = sin_w0 / (2.0 * q);
+        Self::normalised(1.0 - alpha,
+                         -2.0 * cos_w0,
+                         1.0 + alpha,
+                         1.0 + alpha,
+                         -2.0 * cos_w0,
+                         1.0 - alpha)
+    }
+
+    /// A second-order peaking filter, boosting (or cutting) by `gain_db` around the centre
+    /// frequency and leaving frequencies far from it unchanged.
+    pub fn peaking(centre_hz: f64, q: f64, gain_db: f64, sample_hz: f64) -> Self {
+        let a = 10.0f64.powf(gain_db / 40.0);
+        let w0 = 2.0 * std::f64::consts::PI * centre_hz / sample_hz;
+        let (sin_w0, cos_w0) = (w0.sin(), w0.cos());
+        let alpha = sin_w0 / (2.0 * q);
+        Self::normalised(1.0 + alpha * a,
+                         -2.0 * cos_w0,
+                         1.0 - alpha * a,
+                         1.0 + alpha / a,
+                         -2.0 * cos_w0,
+                         1.0 - alpha / a)
+    }
+
+    /// A second-order peaking filter spanning the band between `low_hz` and `high_hz`.
+    ///
+    /// The filter is centred on the geometric mean of the band edges, with a `q` of the centre
+    /// frequency divided by the bandwidth.
+    pub fn peaking_band(low_hz: f64, high_hz: f64, gain_db: f64, sample_hz: f64) -> Self {
+        let centre_hz = (low_hz * high_hz).sqrt();
+        let q = centre_hz / (high_hz - low_hz).abs();
+        Self::peaking(centre_hz, q, gain_db, sample_hz)
+    }
+
+    /// The first stage of the ITU-R BS.1770 K-weighting filter: a high shelf of roughly +4dB
+    /// modelling the acoustic effect of the head.
+    ///
+    /// At 48kHz this yields the coefficients given by the recommendation:
+    ///
+    /// - `b0 = 1.53512485958697`, `b1 = -2.69169618940638`, `b2 = 1.19839281085285`
+    /// - `a1 = -1.69065929318241`, `a2 = 0.73248077421585`
+    ///
+    /// The filter is re-derived via the bilinear transform for other sample rates.
+    pub fn k_weighting_shelf(sample_hz: f64) -> Self {
+        let k = (std::f64::consts::PI * K_SHELF_HZ / sample_hz).tan();
+        let vh = 10.0f64.powf(K_SHELF_GAIN_DB / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let k_q = k / K_SHELF_Q;
+        let k2 = k * k;
+        Self::normalised(vh + vb * k_q + k2,
+                         2.0 * (k2 - vh),
+                         vh - vb * k_q + k2,
+                         1.0 + k_q + k2,
+                         2.0 * (k2 - 1.0),
+                         1.0 - k_q + k2)
+    }
+
+    /// The second stage of the ITU-R BS.1770 K-weighting filter: the "RLB" high-pass filter.
+    ///
+    /// At 48kHz this yields the coefficients given by the recommendation:
+    ///
+    /// - `b0 = 1.0`, `b1 = -2.0`, `b2 = 1.0`
+    /// - `a1 = -1.99004745483398`, `a2 = 0.99007225036621`
+    ///
+    /// The filter is re-derived via the bilinear transform for other sample rates.
+    pub fn k_weighting_highpass(sample_hz: f64) -> Self {
+        let k = (std::f64::consts::PI * K_HIGHPASS_HZ / sample_hz).tan();
+        let k_q = k / K_HIGHPASS_Q;
+        let k2 = k * k;
+        let a0 = 1.0 + k_q + k2;
+        BiquadCoefficients {
+            b0: 1.0,
+            b1: -2.0,
+            b2: 1.0,
+            a1: 2.0 * (k2 - 1.0) / a0,
+            a2: (1.0 - k_q + k2) / a0,
+        }
+    }
+
+}
+
+impl<F> Biquad<F>
+    where F: Frame,
+          F::Sample: FloatSample,
+{
+
+    /// Construct a new **Biquad** using the given coefficients for every channel.
+    pub fn new(coefficients: BiquadCoefficients) -> Self {
+        let zero = F::equilibrium();
+        let mut biquad = Biquad {
+            b0: zero, b1: zero, b2: zero, a1: zero, a2: zero, s1: zero, s2: zero,
+        };
+        biquad.set_coefficients(coefficients);
+        biquad
+    }
+
+    /// Set the coefficients used by every channel.
+    pub fn set_coefficients(&mut self, c: BiquadCoefficients) {
+        self.b0 = F::from_fn(|_| c.b0.to_sample());
+        self.b1 = F::from_fn(|_| c.b1.to_sample());
+        self.b2 = F::from_fn(|_| c.b2.to_sample());
+        self.a1 = F::from_fn(|_| c.a1.to_sample());
+        self.a2 = F::from_fn(|_| c.a2.to_sample());
+    }
+
+    /// Set the coefficients used by the channel at the given index, leaving those of the other
+    /// channels unchanged.
+    pub fn set_channel_coefficients(&mut self, channel: usize, c: BiquadCoefficients) {
+        let set = |frame: F, coefficient: f64| F::from_fn(|i| match i == channel {
+            true => coefficient.to_sample(),
+            false => *frame.channel(i).unwrap(),
+        });
+        self.b0 = set(self.b0, c.b0);
+        self.b1 = set(self.b1, c.b1);
+        self.b2 = set(self.b2, c.b2);
+        self.a1 = set(self.a1, c.a1);
+        self.a2 = set(self.a2, c.a2);
+    }
+
+    /// Zero the filter's state, as though it had only ever been fed silence.
+    pub fn reset(&mut self) {
+        self.s1 = F::equilibrium();
+        self.s2 = F::equilibrium();
+    }
+
+    /// Filter the next frame.
+    #[inline]
+    pub fn next(&mut self, x: F) -> F {
+        let y = mul_add(x, self.b0, self.s1);
+        let s1 = mul_add(x, self.b1, self.s2).zip_map(mul(y, self.a1), |s, ay| s - ay);
+        let s2 = mul(x, self.b2).zip_map(mul(y, self.a2), |s, ay| s - ay);
+        self.s1 = s1;
+        self.s2 = s2;
+        y
+    }
+
+}
+
+
+/// Multiply each channel of `a` with that of `b`.
+#[inline]
+fn mul<F>(a: F, b: F) -> F
+    where F: Frame,
+          F::Sample: FloatSample,
+{
+    a.zip_map(b, |a, b| a * b)
+}
+
+/// Multiply each channel of `a` with that of `b` and add that of `c`.
+#[inline]
+fn mul_add<F>(a: F, b: F, c: F) -> F
+    where F: Frame,
+          F::Sample: FloatSample,
+{
+    mul(a, b).zip_map(c, |ab, c| ab + c)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_HZ: f64 = 48_000.0;
+
+    /// The steady-state peak amplitude of a unit sine at `hz` once filtered by `coefficients`.
+    fn gain_at(coefficients: BiquadCoefficients, hz: f64) -> f64 {
+        let mut biquad = Biquad::<[f64; 1]>::new(coefficients);
+        let n_frames = SAMPLE_HZ as usize;
+        (0..n_frames).fold(0.0, |peak, i| {
+            let phase = 2.0 * std::f64::consts::PI * hz * i as f64 / SAMPLE_HZ;
+            let y = biquad.next([phase.sin()])[0].abs();
+            if i > n_frames / 2 && y > peak { y } else { peak }
+        })
+    }
+
+    #[test]
+    fn identity_passes_the_signal_unchanged() {
+        let mut biquad = Biquad::<[f32; 2]>::new(BiquadCoefficients::identity());
+        for i in 0..100 {
+            let frame = [i as f32 * 0.01, -(i as f32) * 0.02];
+            assert_eq!(biquad.next(frame), frame);
+        }
+    }
+
+    #[test]
+    fn highpass_attenuates_below_its_cutoff() {
+        let highpass = BiquadCoefficients::highpass(1_000.0, BUTTERWORTH_Q, SAMPLE_HZ);
+        assert!(gain_at(highpass, 50.0) < 0.01);
+        assert!((gain_at(highpass, 1_000.0) - std::f64::consts::FRAC_1_SQRT_2).abs() < 0.01);
+        assert!((gain_at(highpass, 10_000.0) - 1.0).abs() < 0.02);
+    }
+
+    #[test]
+    fn lowpass_attenuates_above_its_cutoff() {
+        let lowpass = BiquadCoefficients::lowpass(1_000.0, BUTTERWORTH_Q, SAMPLE_HZ);
+        assert!((gain_at(lowpass, 50.0) - 1.0).abs() < 0.01);
+        assert!((gain_at(lowpass, 1_000.0) - std::f64::consts::FRAC_1_SQRT_2).abs() < 0.01);
+        assert!(gain_at(lowpass, 20_000.0) < 0.01);
+    }
+
+    #[test]
+    fn k_weighting_matches_bs_1770() {
+        let shelf = BiquadCoefficients::k_weighting_shelf(SAMPLE_HZ);
+        let highpass = BiquadCoefficients::k_weighting_highpass(SAMPLE_HZ);
+        let gain_db = |hz: f64| 20.0 * (gain_at(shelf, hz) * gain_at(highpass, hz)).log10();
+        // The -0.691 dB offset of BS.1770 cancels the K-weighting's gain at 997 Hz.
+        assert!((gain_db(997.0) - 0.691).abs() < 0.05, "{}", gain_db(997.0));
+        assert!((gain_db(10_000.0) - 4.0).abs() < 0.2, "{}", gain_db(10_000.0));
+        assert!(gain_db(20.0) < -10.0, "{}", gain_db(20.0));
+    }
+
+    #[test]
+    fn k_weighting_coefficients_at_48_khz_match_the_published_values() {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-6;
+        let shelf = BiquadCoefficients::k_weighting_shelf(48_000.0);
+        assert!(close(shelf.b0, 1.53512485958697) && close(shelf.b1, -2.69169618940638));
+        assert!(close(shelf.b2, 1.19839281085285));
+        assert!(close(shelf.a1, -1.69065929318241) && close(shelf.a2, 0.73248077421585));
+        let highpass = BiquadCoefficients::k_weighting_highpass(48_000.0);
+        assert!(close(highpass.b0, 1.0) && close(highpass.b1, -2.0) && close(highpass.b2, 1.0));
+        assert!(close(highpass.a1, -1.99004745483398) && close(highpass.a2, 0.99007225036621));
+    }
+
+    #[test]
+    fn peaking_band_boosts_its_centre_alone() {
+        let peaking = BiquadCoefficients::peaking_band(5_000.0, 8_000.0, 12.0, SAMPLE_HZ);
+        let centre_hz = (5_000.0f64 * 8_000.0).sqrt();
+        assert!((20.0 * gain_at(peaking, centre_hz).log10() - 12.0).abs() < 0.1);
+        assert!((gain_at(peaking, 100.0) - 1.0).abs() < 0.01);
+        let cut = BiquadCoefficients::peaking(1_000.0, 1.0, -6.0, SAMPLE_HZ);
+        assert!((20.0 * gain_at(cut, 1_000.0).log10() + 6.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn channel_coefficients_apply_to_their_channel_alone() {
+        let mut biquad = Biquad::<[f64; 2]>::new(BiquadCoefficients::identity());
+        let highpass = BiquadCoefficients::highpass(1_000.0, BUTTERWORTH_Q, SAMPLE_HZ);
+        biquad.set_channel_coefficients(1, highpass);
+        let mut y = [0.0; 2];
+        for _ in 0..48_000 {
+            y = biquad.next([1.0, 1.0]);
+        }
+        assert_eq!(y[0], 1.0);
+        assert!(y[1].abs() < 1e-6);
+    }
+}
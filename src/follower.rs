@@ -0,0 +1,313 @@
+//! Extraction of a **Compressor**'s detected envelope or gain as a signal in its own right, e.g.
+//! for use as a modulation source.
+//!
+//! The primary type of interest is the [**EnvelopeFollowerNode**](./struct.EnvelopeFollowerNode).
+//! The [**LevelFollower**](./struct.LevelFollower) follows the level of a signal without any
+//! **Compressor** at all.
+
+use {Compressor, Detector, EvenGainFunction};
+use coeff;
+use db;
+use detector::{self, RmsEnvelopeDetector};
+use envelope::EnvelopeDetector;
+use envelope_detector::{Frame, Sample};
+use rms::Rms;
+use std;
+use time::Ms;
+
+
+/// The signal produced by an **EnvelopeFollowerNode**.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FollowerOutput {
+    /// Each channel carries the envelope detected for that channel, in the same terms as the
+    /// detector, i.e. the peak amplitude for peak detection or the RMS for RMS detection. For
+    /// input within full scale this lies between `0.0` and `1.0`.
+    Envelope,
+    /// Every channel carries the gain that the **Compressor** would apply to the frame, from
+    /// `1.0` (no gain reduction) down toward `0.0` (complete gain reduction).
+    Gain,
+}
+
+/// Steps a **Compressor**'s detectors with the incoming signal and outputs the detected envelope
+/// or the resulting gain in place of the compressed signal.
+///
+/// The wrapped **Compressor** may be configured as usual via `compressor_mut`, so that e.g. the
+/// threshold and ratio shape the `Gain` output.
+#[derive(Clone)]
+pub struct EnvelopeFollowerNode<F, D, EGF>
+    where F: Frame,
+{
+    compressor: Compressor<F, D, EGF>,
+    output: FollowerOutput,
+}
+
+/// Follows the RMS level of each channel of a signal, with an attack and release in
+/// milliseconds, e.g. for metering or as a modulation source.
+///
+/// This is the same detection used by an RMS **Compressor**, without any threshold or ratio.
+#[derive(Clone)]
+pub struct LevelFollower<F>
+    where F: Frame,
+{
+    detector: RmsEnvelopeDetector<F::Float>,
+    attack_ms: Ms,
+    release_ms: Ms,
+}
+
+impl<F, D, EGF> std::fmt::Debug for EnvelopeFollowerNode<F, D, EGF>
+    where F: Frame,
+          Compressor<F, D, EGF>: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "EnvelopeFollowerNode {{ compressor: {:?}, output: {:?} }}",
+               &self.compressor, &self.output)
+    }
+}
+
+
+impl<F, D, EGF> EnvelopeFollowerNode<F, D, EGF>
+    where F: Frame,
+          D: Detector<F::Float>,
+          EGF: EvenGainFunction,
+{
+
+    /// Construct a new **EnvelopeFollowerNode** from the given **Compressor**.
+    pub fn new(compressor: Compressor<F, D, EGF>, output: FollowerOutput) -> Self {
+        EnvelopeFollowerNode {
+            compressor: compressor,
+            output: output,
+        }
+    }
+
+    /// The signal produced by the node.
+    pub fn output(&self) -> FollowerOutput {
+        self.output
+    }
+
+    /// Set the signal produced by the node.
+    pub fn set_output(&mut self, output: FollowerOutput) {
+        self.output = output;
+    }
+
+    /// Borrow the wrapped **Compressor**.
+    pub fn compressor(&self) -> &Compressor<F, D, EGF> {
+        &self.compressor
+    }
+
+    /// Mutably borrow the wrapped **Compressor**.
+    pub fn compressor_mut(&mut self) -> &mut Compressor<F, D, EGF> {
+        &mut self.compressor
+    }
+
+    /// Consume the node, yielding the wrapped **Compressor**.
+    pub fn into_compressor(self) -> Compressor<F, D, EGF> {
+        self.compressor
+    }
+
+    /// Steps forward the detectors by the given frame and yields the envelope or gain.
+    #[inline]
+    pub fn next_frame(&mut self, frame: F) -> F {
+        let gain = self.compressor.next_gain(frame);
+        match self.output {
+            FollowerOutput::Envelope => self.compressor.envelope().map(|s| s.to_sample()),
+            FollowerOutput::Gain => F::from_fn(|_| gain.to_sample()),
+        }
+    }
+
+    /// Replaces each of the given `frames` in place with the envelope or gain.
+    #[inline]
+    pub fn process_slice(&mut self, frames: &mut [F]) {
+        for frame in frames.iter_mut() {
+            *frame = self.next_frame(*frame);
+        }
+    }
+
+}
+
+impl<F> std::fmt::Debug for LevelFollower<F>
+    where F: Frame,
+          RmsEnvelopeDetector<F::Float>: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "LevelFollower {{ detector: {:?}, attack_ms: {:?}, release_ms: {:?} }}",
+               &self.detector, &self.attack_ms, &self.release_ms)
+    }
+}
+
+
+impl<F> LevelFollower<F>
+    where F: Frame,
+{
+
+    /// Construct a new **LevelFollower** with the given RMS window, attack and release.
+    pub fn new<W, A, R>(window_ms: W, attack_ms: A, release_ms: R, sample_hz: f64) -> Self
+        where W: Into<Ms>,
+              A: Into<Ms>,
+              R: Into<Ms>,
+    {
+        let window_ms: Ms = window_ms.into();
+        let window_frames = detector::rms_window_frames(window_ms, sample_hz);
+        let rms = EnvelopeDetector::new(Rms::new(window_frames), 0.0, 0.0);
+        let mut follower = LevelFollower {
+            detector: RmsEnvelopeDetector { rms: rms, window_ms: window_ms },
+            attack_ms: attack_ms.into(),
+            release_ms: release_ms.into(),
+        };
+        follower.update_to_sample_hz(sample_hz);
+        follower
+    }
+
+    /// The duration of the attack in milliseconds.
+    pub fn attack_ms(&self) -> Ms {
+        self.attack_ms
+    }
+
+    /// The duration of the release in milliseconds.
+    pub fn release_ms(&self) -> Ms {
+        self.release_ms
+    }
+
+    /// The duration of the RMS window in milliseconds.
+    pub fn window_ms(&self) -> Ms {
+        self.detector.window_ms
+    }
+
+    /// Set the duration of the attack in milliseconds.
+    pub fn set_attack_ms<M: Into<Ms>>(&mut self, ms: M, sample_hz: f64) {
+        self.attack_ms = ms.into();
+        let gain = coeff::attack_coeff_for_ms(self.attack_ms, sample_hz);
+        self.detector.rms.set_attack_gain(gain);
+    }
+
+    /// Set the duration of the release in milliseconds.
+    pub fn set_release_ms<M: Into<Ms>>(&mut self, ms: M, sample_hz: f64) {
+        self.release_ms = ms.into();
+        let gain = coeff::release_coeff_for_ms(self.release_ms, sample_hz);
+        self.detector.rms.set_release_gain(gain);
+    }
+
+    /// Set the duration of the RMS window in milliseconds.
+    ///
+    /// The window always spans at least one frame, however short the given duration.
+    pub fn set_window_ms<M: Into<Ms>>(&mut self, ms: M, sample_hz: f64) {
+        self.detector.set_window_ms(ms.into());
+        self.detector.update_to_sample_hz(sample_hz);
+    }
+
+    /// Updates the attack, release and window in accordance with the given sample_hz.
+    pub fn update_to_sample_hz(&mut self, sample_hz: f64) {
+        let (attack_ms, release_ms) = (self.attack_ms, self.release_ms);
+        self.set_attack_ms(attack_ms, sample_hz);
+        self.set_release_ms(release_ms, sample_hz);
+        self.detector.update_to_sample_hz(sample_hz);
+    }
+
+    /// Steps forward the follower by the given frame and yields the level of each channel.
+    #[inline]
+    pub fn next(&mut self, frame: F) -> F::Float {
+        self.detector.next_envelope(frame.to_float_frame())
+    }
+
+    /// The most recently detected level of each channel.
+    pub fn level(&self) -> F::Float {
+        self.detector.last_envelope()
+    }
+
+    /// The most recently detected level of each channel in decibels, relative to full scale.
+    ///
+    /// A silent channel yields negative infinity.
+    pub fn level_db(&self) -> F::Float {
+        self.level().map(|s| db::amp_to_db(s.to_sample()).to_sample())
+    }
+
+    /// The most recently detected level of the loudest channel in decibels.
+    pub fn peak_level_db(&self) -> f32 {
+        let loudest = self.level().channels().fold(0.0, |max, sample| {
+            let s: f32 = sample.to_sample();
+            if s > max { s } else { max }
+        });
+        db::amp_to_db(loudest)
+    }
+
+    /// Restart the follower from silence.
+    pub fn reset(&mut self) {
+        self.detector.set_envelope(F::Float::equilibrium());
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use PeakAvgCompressor;
+
+    const SAMPLE_HZ: f64 = 44_100.0;
+
+    fn compressor() -> PeakAvgCompressor<[f32; 2]> {
+        Compressor::peak_avg(1.0, 100.0, SAMPLE_HZ, 0.25, 4.0)
+    }
+
+    #[test]
+    fn envelope_output_follows_each_channel() {
+        let mut node = EnvelopeFollowerNode::new(compressor(), FollowerOutput::Envelope);
+        let mut frames = [[0.8, -0.2]; 4_410];
+        node.process_slice(&mut frames);
+        assert!((frames[4_409][0] - 0.8).abs() < 1e-3);
+        assert!((frames[4_409][1] - 0.2).abs() < 1e-3);
+    }
+
+    #[test]
+    fn gain_output_matches_the_compressor_gain() {
+        let mut node = EnvelopeFollowerNode::new(compressor(), FollowerOutput::Gain);
+        let mut twin = compressor();
+        for i in 0..4_410 {
+            let s = (i as f32 * 0.02).sin();
+            let gain = twin.next_gain([s, 0.1]);
+            assert_eq!(node.next_frame([s, 0.1]), [gain, gain]);
+        }
+        node.set_output(FollowerOutput::Envelope);
+        assert_eq!(node.output(), FollowerOutput::Envelope);
+    }
+
+    /// The number of frames of `frame` until the follower's left level crosses `level`.
+    fn frames_to_cross(follower: &mut LevelFollower<[f32; 2]>, frame: [f32; 2], level: f32)
+        -> usize
+    {
+        let start = follower.level()[0];
+        (1..SAMPLE_HZ as usize)
+            .find(|_| (follower.next(frame)[0] - level) * (start - level) <= 0.0)
+            .expect("the level never crossed")
+    }
+
+    #[test]
+    fn level_follower_tracks_a_step_up_and_down() {
+        let mut follower = LevelFollower::new(1.0, 10.0, 100.0, SAMPLE_HZ);
+        assert_eq!(follower.peak_level_db(), std::f32::NEG_INFINITY);
+        let attack = frames_to_cross(&mut follower, [0.5, 0.25], 0.25);
+        for _ in 0..SAMPLE_HZ as usize {
+            follower.next([0.5, 0.25]);
+        }
+        assert!((follower.level()[0] - 0.5).abs() < 1e-3);
+        assert!((follower.level_db()[1] - db::amp_to_db(0.25)).abs() < 0.05);
+        assert!((follower.peak_level_db() - db::amp_to_db(0.5)).abs() < 0.05);
+        let release = frames_to_cross(&mut follower, [0.0; 2], 0.25);
+        // Each half-way crossing takes a fraction of its duration, the release ten times longer.
+        assert!(attack > 10 && attack < Ms(10.0).samples(SAMPLE_HZ) as usize);
+        assert!(release > 5 * attack && release < Ms(100.0).samples(SAMPLE_HZ) as usize);
+        follower.reset();
+        assert_eq!(follower.level(), [0.0; 2]);
+    }
+
+    #[test]
+    fn level_follower_settings_survive_a_change_of_rate() {
+        let mut follower = LevelFollower::<[f32; 1]>::new(20.0, 5.0, 50.0, SAMPLE_HZ);
+        follower.set_attack_ms(2.0, SAMPLE_HZ);
+        follower.set_release_ms(200.0, SAMPLE_HZ);
+        follower.set_window_ms(10.0, SAMPLE_HZ);
+        follower.update_to_sample_hz(96_000.0);
+        assert_eq!(follower.attack_ms(), Ms(2.0));
+        assert_eq!(follower.release_ms(), Ms(200.0));
+        assert_eq!(follower.window_ms(), Ms(10.0));
+    }
+}
@@ -0,0 +1,89 @@
+//! Implementations of `fundsp`'s **AudioNode** for mono and stereo **Compressor**s, allowing them
+//! to be composed into `fundsp` graphs.
+//!
+//! The channel arity of each node is fixed by the **Compressor**'s `Frame` type: a
+//! `Compressor<[f32; 1], _, _>` is a node with one input and one output, while a
+//! `Compressor<[f32; 2], _, _>` is a node with two inputs and two outputs. The attack, release,
+//! threshold and ratio are configured on the **Compressor** before it is added to the graph.
+
+extern crate fundsp;
+
+use {Compressor, Detector, EvenGainFunction};
+use self::fundsp::prelude::{AudioNode, Frame, U1, U2};
+
+
+impl<D, EGF> AudioNode for Compressor<[f32; 1], D, EGF>
+    where D: Detector<[f32; 1]> + Clone + Send + Sync,
+          EGF: EvenGainFunction + Clone + Send + Sync,
+{
+    const ID: u64 = 0x636f_6d70_0001;
+    type Inputs = U1;
+    type Outputs = U1;
+
+    fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.update_to_sample_hz(sample_rate);
+    }
+
+    #[inline]
+    fn tick(&mut self, input: &Frame<f32, U1>) -> Frame<f32, U1> {
+        self.next_frame([input[0]]).into()
+    }
+}
+
+impl<D, EGF> AudioNode for Compressor<[f32; 2], D, EGF>
+    where D: Detector<[f32; 2]> + Clone + Send + Sync,
+          EGF: EvenGainFunction + Clone + Send + Sync,
+{
+    const ID: u64 = 0x636f_6d70_0002;
+    type Inputs = U2;
+    type Outputs = U2;
+
+    fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.update_to_sample_hz(sample_rate);
+    }
+
+    #[inline]
+    fn tick(&mut self, input: &Frame<f32, U2>) -> Frame<f32, U2> {
+        self.next_frame([input[0], input[1]]).into()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {PeakAvgCompressor, PeakMinCompressor};
+
+    #[test]
+    fn mono_tick_matches_next_frame() {
+        let mut node: PeakAvgCompressor<[f32; 1]> =
+            Compressor::peak_avg(1.0, 100.0, 44_100.0, 0.25, 4.0);
+        let mut compressor = node.clone();
+        for i in 0..4_410 {
+            let s = (i as f32 * 0.02).sin();
+            assert_eq!(node.tick(&[s].into())[0], compressor.next_frame([s])[0]);
+        }
+    }
+
+    #[test]
+    fn stereo_tick_matches_next_frame() {
+        let mut node: PeakMinCompressor<[f32; 2]> =
+            Compressor::peak_min(1.0, 100.0, 44_100.0, 0.25, 4.0);
+        let mut compressor = node.clone();
+        for i in 0..4_410 {
+            let s = (i as f32 * 0.02).sin();
+            let output = node.tick(&[s, 0.5].into());
+            assert_eq!([output[0], output[1]], compressor.next_frame([s, 0.5]));
+        }
+    }
+
+    #[test]
+    fn set_sample_rate_updates_the_coefficients() {
+        let mut node: PeakAvgCompressor<[f32; 2]> =
+            Compressor::peak_avg(1.0, 100.0, 44_100.0, 0.25, 4.0);
+        let expected: PeakAvgCompressor<[f32; 2]> =
+            Compressor::peak_avg(1.0, 100.0, 96_000.0, 0.25, 4.0);
+        node.set_sample_rate(96_000.0);
+        assert_eq!(node.coefficients(), expected.coefficients());
+    }
+}
@@ -3,30 +3,131 @@
 //! The main type of interest is the [**Compressor**](./struct.Compressor).
 //!
 //! You may also find the [**EvenGainFunction**](./even_gain_fn/trait.EvenGainFunction) trait
-//! (implemented for both [**Average**](./even_gain_fn/enum.Average) and
-//! [**Minimum**](./even_gain_fn/enum.Minimum)) and the [**Detector**](./detector/trait.Detector)
+//! (implemented for both [**Average**](./even_gain_fn/struct.Average) and
+//! [**Minimum**](./even_gain_fn/struct.Minimum)) and the [**Detector**](./detector/trait.Detector)
 //! trait (implemented for [**PeakEnvelopeDetector**](./detector/type.PeakEnvelopeDetector) and
 //! [**RmsEnvelopeDetector**](./detector/struct.RmsEnvelopeDetector).
+//!
+//! ## Features
+//!
+//! The core **Compressor**, i.e. detection, the gain computer, lookahead, auto gain and the gain
+//! reduction and output peak readouts, is always available and depends only on
+//! `envelope_detector`, `sample` and `time_calc`. Everything else is optional:
+//!
+//! - `dsp-chain` (default): implements `dsp::Node` for the **Compressor** and the
+//!   **EnvelopeFollowerNode**.
+//! - `analysis` (default): the `analysis` module and `Compressor::measure_thd`.
+//! - `automation` (default): sample-accurate parameter changes via `schedule_threshold` and
+//!   `schedule_ratio`.
+//! - `metering` (default): the range and loudness reduction meters, `meter_snapshot`,
+//!   `loudness_matched_bypass` and `makeup_for_target_loudness`.
+//! - `multiband` (default): the **MultibandCompressor**.
+//! - `preset` (default): the **PresetBank**.
+//! - `sidechain-filter` (default): the sidechain high-pass, emphasis and K-weighting filters,
+//!   and `RmsAvgCompressor::bus_glue`.
+//! - `dasp`: implements `dasp_signal`'s **Signal** for the **CompressedSignal**, so that `dasp`
+//!   signals may be compressed via `Compressor::compress_dasp_signal`.
+//! - `fundsp`: implements `fundsp`'s **AudioNode** for mono and stereo **Compressor**s.
+//! - `serde`: derives `Serialize` and `Deserialize` for **CompressorParams** and the
+//!   **PresetBank**, e.g. to save and load presets.
+//! - `full`: enables all of the above.
+//!
+//! Building with `default-features = false` yields the minimal compressor, e.g. for embedded
+//! targets, and each of the above may then be enabled individually.
 
 #[deny(missing_copy_implementations)]
 #[deny(missing_docs)]
 
 extern crate envelope_detector;
+extern crate sample;
 extern crate time_calc as time;
+#[cfg(feature = "dasp")]
+extern crate dasp_frame;
+#[cfg(feature = "dasp")]
+extern crate dasp_signal;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
 
-use envelope_detector::{EnvelopeDetector, Frame, Sample};
+use auto_gain::AutoGain;
+#[cfg(feature = "automation")]
+use automation::{AutomationQueue, Change};
+use envelope::EnvelopeDetector;
+use envelope_detector::{Frame, Sample};
+#[cfg(feature = "metering")]
+use meter::{LoudnessMatcher, LoudnessReductionMeter, RangeFollower};
+use meter::PowerFollower;
+use quantize::Quantizer;
+use rms::Rms;
+use sidechain::Sidechain;
+use sample::FloatSample;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
 use std::marker::PhantomData;
 use time::Ms;
 
+#[cfg(feature = "analysis")]
+pub mod analysis;
+mod auto_gain;
+#[cfg(feature = "automation")]
+mod automation;
+pub mod builder;
+pub mod coeff;
+pub mod const_compressor;
+pub mod db;
 pub mod detector;
+pub mod envelope;
+pub mod error;
 pub mod even_gain_fn;
+#[cfg(any(feature = "metering", feature = "multiband", feature = "sidechain-filter"))]
+pub mod filter;
+pub mod follower;
+pub mod limiter;
+mod meter;
+#[cfg(feature = "multiband")]
+pub mod multiband;
+pub mod parallel;
+pub mod param_map;
+pub mod params;
+#[cfg(feature = "preset")]
+pub mod preset;
+pub mod ratio;
+pub mod rms;
+mod sidechain;
+pub mod signal;
+mod quantize;
 
 #[cfg(feature = "dsp-chain")]
 pub mod dsp_node;
+#[cfg(feature = "fundsp")]
+pub mod fundsp_node;
 
 
-pub use detector::{Detector, PeakEnvelopeDetector, RmsEnvelopeDetector};
-pub use even_gain_fn::{EvenGainFunction, Average, Minimum};
+#[cfg(feature = "analysis")]
+pub use analysis::{detect_pumping, harmonic_distortion, PumpingReport};
+pub use builder::CompressorBuilder;
+pub use const_compressor::{ConstCompressor, ConstEvenGainFunction};
+pub use detector::{BlendEnvelopeDetector, Detector, DetectorKind, DualEnvelopeDetector,
+                   DynamicDetector, PeakEnvelopeDetector, RmsEnvelopeDetector};
+pub use envelope::SmoothingMode;
+pub use error::{BuilderError, CompressorError};
+pub use even_gain_fn::{EvenGainFunction, EvenGainKind, Average, AverageDb, Minimum};
+pub use follower::{EnvelopeFollowerNode, FollowerOutput, LevelFollower};
+pub use limiter::StereoLimiter;
+#[cfg(feature = "metering")]
+pub use meter::{LOUDNESS_ABSOLUTE_GATE_LUFS, LOUDNESS_RELATIVE_GATE_LU,
+                LOUDNESS_REDUCTION_WINDOW_MS, MeterSnapshot};
+#[cfg(feature = "multiband")]
+pub use multiband::{BandParams, MultibandCompressor};
+pub use parallel::PeakRmsParallel;
+pub use params::{Coefficients, CompressorParams, CostEstimate, Direction, LatencyBreakdown,
+                 StageFlags, ThresholdDomain};
+#[cfg(feature = "preset")]
+pub use preset::PresetBank;
+pub use quantize::DitherKind;
+pub use rms::RmsWindow;
+pub use sidechain::DetectionMode;
+pub use signal::{CompressedSignal, SharedParams};
 
 
 /// A dynamics processing unit designed to compress some given audio signal that exceeds the
@@ -35,15 +136,45 @@ pub use even_gain_fn::{EvenGainFunction, Average, Minimum};
 /// The **Compressor** is generic over its envelope [**Detector**](./detector/trait.Detector) and
 /// the [**EvenGainFunction**](./even_gain_fn/trait.EvenGainFunction) (used to determine the gain
 /// that will be applied evenly to all channels for a single frame).
+///
+/// Envelope detection always takes place on the floating point representation of each frame, so
+/// the **Compressor** may be used with integer sample formats such as `[i16; 2]` as well as
+/// floating point ones. When compressing integer frames, the compressed result is rounded to the
+/// nearest value representable by the sample format.
+///
+/// Each frame is scaled by a single gain produced by the **EvenGainFunction**. The exact same
+/// floating point gain multiplies every channel, so compression never shifts the relative level or
+/// phase of the channels and the stereo image is preserved however heavily the signal is
+/// compressed. The only per-channel difference is the independent dither (if any) added to integer
+/// formats when rounding.
+///
+/// Cloning a **Compressor** copies all of its state, including the envelope detector's RMS window
+/// and the signal delay line, into freshly allocated buffers. This allows for the "clone per
+/// channel" multi-mono pattern, where each clone then tracks its own signal independently.
 #[derive(Clone, Debug)]
-pub struct Compressor<F, D, EGF> {
+pub struct Compressor<F, D, EGF>
+    where F: Frame,
+{
     /// The **EnvelopeDetector** used to create a "loudness" envelope.
     envelope_detector: D,
     /// The envelope attack duration in milliseconds.
     attack_ms: Ms,
     /// The envelope release duration in milliseconds.
     release_ms: Ms,
+    /// The fast and slow release durations and the crossover gain reduction in decibels of a
+    /// two-stage release, if enabled.
+    release_stages: Option<(Ms, Ms, f32)>,
+    /// The gain below which the fast release stage is used.
+    release_crossover_gain: f32,
+    /// The release coefficient of the fast release stage.
+    fast_release_gain: f32,
+    /// The release coefficient of the slow release stage.
+    slow_release_gain: f32,
+    /// Whether the detector is currently using the fast release stage.
+    fast_release: bool,
     /// When the detected envelope exceeds this threshold, the signal is compressed via the `ratio`.
+    ///
+    /// The threshold is given in the terms of the `threshold_domain`.
     pub threshold: f32,
     /// The slope of the `ratio`, used to calculate the compressor_gain.
     ///
@@ -53,35 +184,315 @@ pub struct Compressor<F, D, EGF> {
     /// *ratio of 4.0 == 4:1 == compress by every 4 parts of the exceeding envelope to 1 == slope
     /// of 0.75.*
     slope: f32,
+    /// The terms in which the `threshold` is given.
+    threshold_domain: ThresholdDomain,
+    /// The width in decibels of the soft knee centred on the threshold, or `0.0` for a hard knee.
+    knee_db: f32,
+    /// Which side of the threshold is attenuated.
+    direction: Direction,
+    /// The linear amplitude corresponding to `0.0` dB for all decibel conversions.
+    db_reference: f32,
+    /// The period over which changes to the `threshold` and `slope` are smoothed.
+    parameter_smoothing_ms: Ms,
+    /// The amount of the previous smoothed parameter values retained each frame.
+    parameter_smoothing_gain: f32,
+    /// The `threshold` currently in effect, following the `threshold` as it changes.
+    smoothed_threshold: f32,
+    /// The `slope` currently in effect, following the `slope` as it changes.
+    smoothed_slope: f32,
+    /// The amount by which each channel's envelope is drawn toward the loudest channel's.
+    detector_link: f32,
+    /// The period after the envelope first exceeds the threshold during which the gain is held
+    /// at unity.
+    punch_ms: Ms,
+    /// The `punch_ms` as a number of frames.
+    punch_frames: usize,
+    /// The number of consecutive frames for which the envelope has exceeded the threshold.
+    over_threshold_frames: usize,
+    /// The number of frames processed since construction.
+    frames_processed: u64,
+    /// Whether detection is skipped for blocks of silence.
+    silence_optimization: bool,
+    /// Whether the gain is frozen at the `last_gain`.
+    frozen: bool,
+    /// Whether the signal passes through without any change in gain.
+    bypass: bool,
+    /// The most gain reduction in decibels that may be applied, if limited.
+    max_reduction_db: Option<f32>,
+    /// The width in decibels of the knee over which the gain reduction approaches the
+    /// `max_reduction_db`.
+    range_knee_db: f32,
+    /// The least even gain that may be applied.
+    min_gain: f32,
+    /// The peak output amplitude beyond which each frame's gain is clamped, bridging the gap
+    /// until the detector reacts. Infinite when disabled.
+    overshoot_ceiling: f32,
+    /// The most recently produced even gain.
+    last_gain: f32,
+    /// The period over which the gain reported to the meters is smoothed.
+    meter_smoothing_ms: Ms,
+    /// The amount of the previous `meter_gain` retained each frame.
+    meter_smoothing_gain: f32,
+    /// The `last_gain` smoothed for display, as reported by the gain reduction meters.
+    meter_gain: f32,
+    /// The period over which the applied gain's range is followed for the range meter.
+    #[cfg(feature = "metering")]
+    range_window_ms: Ms,
+    /// Follows the highest and lowest gain applied over the `range_window_ms`.
+    #[cfg(feature = "metering")]
+    range: RangeFollower,
+    /// The period over which the loudness of the dry and compressed signals are matched.
+    #[cfg(feature = "metering")]
+    loudness_match_ms: Ms,
+    /// Matches the loudness of the dry signal to the compressed signal for fair comparison.
+    #[cfg(feature = "metering")]
+    loudness_match: LoudnessMatcher,
+    /// Measures the loudness removed by the **Compressor**, if enabled.
+    #[cfg(feature = "metering")]
+    loudness_reduction: Option<LoudnessReductionMeter<F::Float>>,
+    /// The peak absolute sample of the most recently produced frame.
+    last_output_peak: f32,
+    /// The linear amplitude at or beyond which an input sample is counted as clipped.
+    input_clip_level: f32,
+    /// The number of input frames with a clipped sample since the meters were last reset.
+    input_clip_count: u64,
+    /// The linear gain applied to each input frame before detection and compression.
+    input_gain: f32,
+    /// The linear gain applied to the compressed signal, unless `auto_makeup` is enabled.
+    makeup_gain: f32,
+    /// Whether the makeup gain is derived from the threshold and ratio.
+    auto_makeup: bool,
+    /// The long-term output level in decibels targeted by the auto gain, if enabled.
+    auto_gain_db: Option<f32>,
+    /// The period over which the auto gain measures the long-term output level.
+    auto_gain_ms: Ms,
+    /// Trims the compressed signal toward the `auto_gain_db`.
+    auto_gain: AutoGain,
+    /// The offset in decibels below the long-term input level at which the threshold is held,
+    /// if the adaptive threshold is enabled.
+    adaptive_threshold_db: Option<f32>,
+    /// The period over which the adaptive threshold follows the long-term input level.
+    adaptive_threshold_ms: Ms,
+    /// Follows the long-term input level for the adaptive threshold.
+    adaptive_level: PowerFollower,
+    /// Parameter changes scheduled at frame offsets within the next block.
+    #[cfg(feature = "automation")]
+    automation: AutomationQueue,
+    /// The target slope, the change in slope per frame and the number of frames remaining for a
+    /// ratio ramp in progress.
+    ratio_ramp: Option<(f32, f32, u64)>,
+    /// Processes the signal on its way to the envelope detector.
+    sidechain: Sidechain<F::Float>,
+    /// Delays the signal relative to the detector so that the gain lines up with a delayed
+    /// detection path.
+    signal_delay: VecDeque<F>,
+    /// Applies the gain to each frame of the signal.
+    quantizer: Quantizer<F::Sample>,
     /// Some function that yields a gain to be applied evenly across all channels in a single
     /// frame.
-    even_gain_fn: PhantomData<EGF>,
+    even_gain_fn: EGF,
     frame: PhantomData<F>,
 }
 
 /// A **Compressor** that uses a **Peak** envelope detector.
-pub type PeakCompressor<F, EGF> = Compressor<F, PeakEnvelopeDetector<F>, EGF>;
+pub type PeakCompressor<F, EGF> = Compressor<F, PeakEnvelopeDetector<<F as Frame>::Float>, EGF>;
 /// A **Compressor** that uses the average across channels yielded by a **Peak** envelope detector.
 pub type PeakAvgCompressor<F> = PeakCompressor<F, Average>;
 /// A **Compressor** that uses the minimum across channels yielded by a **Peak** envelope detector.
 pub type PeakMinCompressor<F> = PeakCompressor<F, Minimum>;
 
 /// A **Compressor** that uses an **Rms** envelope detector.
-pub type RmsCompressor<F, EGF> = Compressor<F, RmsEnvelopeDetector<F>, EGF>;
+pub type RmsCompressor<F, EGF> = Compressor<F, RmsEnvelopeDetector<<F as Frame>::Float>, EGF>;
 /// A **Compressor** that uses the average across channels yielded by a **Rms** envelope detector.
 pub type RmsAvgCompressor<F> = RmsCompressor<F, Average>;
 /// A **Compressor** that uses the minimum across channels yielded by a **Rms** envelope detector.
 pub type RmsMinCompressor<F> = RmsCompressor<F, Minimum>;
 
+/// A **Compressor** whose detector blends continuously between **Peak** and **Rms** detection.
+pub type BlendCompressor<F, EGF> =
+    Compressor<F, BlendEnvelopeDetector<<F as Frame>::Float>, EGF>;
+
+/// A **Compressor** whose detector runs a fast and a slow **Peak** follower, attacking with the
+/// fast one and releasing with the slow one.
+pub type DualCompressor<F, EGF> =
+    Compressor<F, DualEnvelopeDetector<<F as Frame>::Float>, EGF>;
+
+/// A **Compressor** whose detector kind and **EvenGainFunction** are chosen at runtime, as
+/// produced by the [**CompressorBuilder**](./builder/struct.CompressorBuilder).
+pub type DynamicCompressor<F> =
+    Compressor<F, DynamicDetector<<F as Frame>::Float>, EvenGainKind>;
+
+
+/// An iterator yielding each frame compressed by a **Compressor** along with the linear gain
+/// applied to it.
+///
+/// Created by `Compressor::compress_with_gain_iter`.
+#[derive(Clone)]
+pub struct CompressWithGain<I, F, D, EGF>
+    where F: Frame,
+{
+    frames: I,
+    compressor: Compressor<F, D, EGF>,
+}
+
+
+/// The default period over which the dynamic range reduction is measured.
+#[cfg(feature = "metering")]
+pub const DEFAULT_RANGE_WINDOW_MS: f64 = 3_000.0;
+/// The default period over which `loudness_matched_bypass` matches loudness, roughly that of a
+/// momentary loudness measurement.
+#[cfg(feature = "metering")]
+pub const DEFAULT_LOUDNESS_MATCH_MS: f64 = 400.0;
+/// The default period over which the auto gain measures the long-term output level.
+pub const DEFAULT_AUTO_GAIN_MS: f64 = 5_000.0;
+/// The default period over which the adaptive threshold follows the long-term input level.
+pub const DEFAULT_ADAPTIVE_THRESHOLD_MS: f64 = 3_000.0;
+/// The cutoff of the sidechain high-pass filter used by `RmsAvgCompressor::bus_glue`.
+#[cfg(feature = "sidechain-filter")]
+pub const BUS_GLUE_SIDECHAIN_HPF_HZ: f64 = 100.0;
+
+
+/// The peak amplitude below which a block is considered silent by the silence optimization.
+pub const SILENCE_AMPLITUDE: f32 = 1.0e-5;
+
+/// The fraction of a parameter change that must have taken effect for `settling_frames` to
+/// consider it settled.
+pub const SETTLED_FRACTION: f32 = 0.99;
+
+/// The gain reduction in decibels within which `frames_until_release_complete` considers the
+/// gain to have recovered to unity.
+pub const RELEASE_COMPLETE_DB: f32 = 0.1;
+
+
+/// The default linear amplitude corresponding to `0.0` dB, i.e. dB values are dBFS.
+pub const DEFAULT_DB_REFERENCE: f32 = 1.0;
+
+/// The quietest input level in decibels sampled by `sample_transfer_curve`.
+pub const TRANSFER_CURVE_MIN_DB: f32 = -60.0;
+/// The loudest input level in decibels sampled by `sample_transfer_curve`.
+pub const TRANSFER_CURVE_MAX_DB: f32 = 0.0;
+
+
+/// The gain to apply to a channel whose detected envelope is at the given level.
+#[inline]
+fn calc_gain<S>(envelope: S, threshold: S, slope: S) -> S
+    where S: FloatSample,
+{
+    let identity = <S as FloatSample>::identity();
+    let envelope = if envelope > identity { identity } else { envelope }; // Clamp between 0.0...1.0.
+    if envelope > threshold { identity - (envelope - threshold) * slope } else { identity }
+}
+
+/// The gain to apply to a channel whose detected envelope is at the given level, easing into the
+/// compression across a soft knee spanning from `threshold / knee` to `threshold * knee`.
+///
+/// Within the knee the gain follows a quadratic Bézier curve from unity at the lower edge to the
+/// hard knee's line at the upper edge, with its control point at the threshold where the two
+/// tangents meet, so that both the gain and its first derivative are continuous at either edge.
+/// A `knee` of `1.0` or less is exactly the hard knee of `calc_gain`.
+#[inline]
+fn calc_knee_gain<S>(envelope: S, threshold: S, slope: S, knee: S) -> S
+    where S: FloatSample,
+{
+    let identity = <S as FloatSample>::identity();
+    if knee <= identity {
+        return calc_gain(envelope, threshold, slope);
+    }
+    let envelope = if envelope > identity { identity } else { envelope }; // Clamp between 0.0...1.0.
+    let (lower, upper) = (threshold / knee, threshold * knee);
+    if envelope <= lower {
+        return identity;
+    }
+    if envelope >= upper {
+        return identity - (envelope - threshold) * slope;
+    }
+    // Solve for the Bézier parameter at which the curve reaches the envelope.
+    let a = lower + upper - threshold - threshold;
+    let b = threshold - lower;
+    let t = ((b * b + a * (envelope - lower)).sample_sqrt() - b) / a;
+    identity - t * t * (upper - threshold) * slope
+}
+
+/// The gain to apply to a channel whose detected envelope is at the given level when expanding
+/// downward below the threshold.
+///
+/// The ratio is applied in decibels, i.e. each decibel that the envelope falls below the threshold
+/// lowers the output by `ratio` decibels, so that a `slope` of `1.0` (an infinite ratio) silences
+/// everything below the threshold.
+#[inline]
+fn calc_expand_gain<S>(envelope: S, threshold: S, slope: S) -> S
+    where S: FloatSample,
+{
+    let identity = <S as FloatSample>::identity();
+    if !(envelope < threshold) {
+        return identity;
+    }
+    if slope >= identity {
+        return S::equilibrium();
+    }
+    let (envelope, threshold, slope): (f32, f32, f32) =
+        (envelope.to_sample(), threshold.to_sample(), slope.to_sample());
+    let ratio = ratio::ratio_from_slope(slope);
+    (envelope / threshold).powf(ratio - 1.0).to_sample()
+}
+
+/// The gain that the static transfer curve applies to an envelope at the given linear amplitude,
+/// with a soft knee `knee_db` wide centred on the `threshold`.
+///
+/// The `slope` is that of the ratio, e.g. as given by `ratio::slope_from_ratio`. Below the knee
+/// the gain is unity and above it the gain falls linearly with the envelope, exactly as with a
+/// hard knee. Across the knee, from `knee_db / 2` below the threshold to `knee_db / 2` above it,
+/// the gain follows a quadratic curve tangent to both, so that both the gain and its first
+/// derivative with respect to the envelope are continuous at either edge: the derivative is
+/// `0.0` at the lower edge and `-slope` at the upper edge. As the gain falls linearly with the
+/// envelope rather than in decibels, the edges aren't equidistant from the threshold in
+/// amplitude, and the knee's curvature varies with the threshold accordingly. A `knee_db` of
+/// `0.0` or less yields the hard knee exactly.
+///
+/// This is the curve used by the **Compressor** for a `knee_db` set via `set_knee_db`.
+pub fn knee_gain<S>(envelope: S, threshold: S, slope: S, knee_db: f32) -> S
+    where S: FloatSample,
+{
+    let knee = db::db_to_amp(knee_db / 2.0).to_sample();
+    calc_knee_gain(envelope, threshold, slope, knee)
+}
+
+/// The number of frames until a one-pole follower retaining the given `gain` each frame has
+/// covered `SETTLED_FRACTION` of a step, or `0` for a follower that doesn't smooth.
+fn frames_to_settle(gain: f32) -> usize {
+    if gain <= 0.0 {
+        return 0;
+    }
+    let remaining: f32 = 1.0 - SETTLED_FRACTION;
+    (remaining.ln() / gain.ln()).ceil() as usize
+}
+
+/// Check that the given sample_hz is finite and greater than zero, as required to derive any
+/// duration-based coefficients from it.
+fn validate_sample_hz(sample_hz: f64) -> Result<(), CompressorError> {
+    if sample_hz > 0.0 && sample_hz.is_finite() {
+        Ok(())
+    } else {
+        Err(CompressorError::InvalidSampleRate(sample_hz))
+    }
+}
+
+/// Panic with the given `context` unless the given sample_hz is valid.
+fn assert_valid_sample_hz(sample_hz: f64, context: &str) {
+    if let Err(err) = validate_sample_hz(sample_hz) {
+        panic!("{}: {}", context, err);
+    }
+}
 
-fn calc_slope(ratio: f32) -> f32 {
-    1.0 - (1.0 / ratio)
+/// The duration of the given number of frames at the given sample_hz.
+fn ms_for_frames(frames: f32, sample_hz: f64) -> Ms {
+    Ms(frames as f64 * 1_000.0 / sample_hz)
 }
 
 
 impl<F, D, EGF> Compressor<F, D, EGF>
     where F: Frame,
-          D: Detector<F>,
+          D: Detector<F::Float>,
           EGF: EvenGainFunction,
 {
 
@@ -89,19 +500,211 @@ impl<F, D, EGF> Compressor<F, D, EGF>
     ///
     /// This is a private constructor wrapped by the more specific `rms` and `peak` public
     /// constructors.
-    fn new(detector: D, attack_ms: Ms, release_ms: Ms, threshold: f32, ratio: f32) -> Self {
-        let slope = calc_slope(ratio);
+    fn new(detector: D,
+           attack_ms: Ms,
+           release_ms: Ms,
+           sample_hz: f64,
+           threshold: f32,
+           ratio: f32,
+           even_gain_fn: EGF) -> Self
+    {
+        assert_valid_sample_hz(sample_hz, "constructing a Compressor");
+        let slope = ratio::slope_from_ratio(ratio);
+        #[cfg(feature = "metering")]
+        let range_window_ms = Ms(DEFAULT_RANGE_WINDOW_MS);
+        #[cfg(feature = "metering")]
+        let range = RangeFollower::new(range_window_ms.samples(sample_hz) as f32);
+        #[cfg(feature = "metering")]
+        let loudness_match_ms = Ms(DEFAULT_LOUDNESS_MATCH_MS);
+        #[cfg(feature = "metering")]
+        let loudness_match = LoudnessMatcher::new(loudness_match_ms.samples(sample_hz) as f32);
+        let auto_gain_ms = Ms(DEFAULT_AUTO_GAIN_MS);
+        let auto_gain = AutoGain::new(auto_gain_ms.samples(sample_hz) as f32, DEFAULT_DB_REFERENCE);
+        let adaptive_threshold_ms = Ms(DEFAULT_ADAPTIVE_THRESHOLD_MS);
+        let adaptive_level = PowerFollower::new(adaptive_threshold_ms.samples(sample_hz) as f32);
         Compressor {
             envelope_detector: detector,
             attack_ms: attack_ms,
             release_ms: release_ms,
+            release_stages: None,
+            release_crossover_gain: 1.0,
+            fast_release_gain: 0.0,
+            slow_release_gain: 0.0,
+            fast_release: false,
             threshold: threshold,
             slope: slope,
-            even_gain_fn: std::marker::PhantomData,
+            threshold_domain: ThresholdDomain::Amplitude,
+            knee_db: 0.0,
+            direction: Direction::Compress,
+            db_reference: DEFAULT_DB_REFERENCE,
+            parameter_smoothing_ms: Ms(0.0),
+            parameter_smoothing_gain: 0.0,
+            smoothed_threshold: threshold,
+            smoothed_slope: slope,
+            detector_link: 0.0,
+            punch_ms: Ms(0.0),
+            punch_frames: 0,
+            over_threshold_frames: 0,
+            frames_processed: 0,
+            silence_optimization: false,
+            frozen: false,
+            bypass: false,
+            max_reduction_db: None,
+            range_knee_db: 0.0,
+            min_gain: 0.0,
+            overshoot_ceiling: std::f32::INFINITY,
+            last_gain: 1.0,
+            meter_smoothing_ms: Ms(0.0),
+            meter_smoothing_gain: 0.0,
+            meter_gain: 1.0,
+            #[cfg(feature = "metering")]
+            range_window_ms: range_window_ms,
+            #[cfg(feature = "metering")]
+            range: range,
+            #[cfg(feature = "metering")]
+            loudness_match_ms: loudness_match_ms,
+            #[cfg(feature = "metering")]
+            loudness_match: loudness_match,
+            #[cfg(feature = "metering")]
+            loudness_reduction: None,
+            last_output_peak: 0.0,
+            input_clip_level: Self::full_scale(),
+            input_clip_count: 0,
+            input_gain: 1.0,
+            makeup_gain: 1.0,
+            auto_makeup: false,
+            auto_gain_db: None,
+            auto_gain_ms: auto_gain_ms,
+            auto_gain: auto_gain,
+            adaptive_threshold_db: None,
+            adaptive_threshold_ms: adaptive_threshold_ms,
+            adaptive_level: adaptive_level,
+            #[cfg(feature = "automation")]
+            automation: AutomationQueue::new(),
+            ratio_ramp: None,
+            sidechain: Sidechain::new(),
+            signal_delay: VecDeque::new(),
+            quantizer: Quantizer::new(),
+            even_gain_fn: even_gain_fn,
             frame: std::marker::PhantomData,
         }
     }
 
+    /// Rebuild the **Compressor** around the detector and **EvenGainFunction** produced from the
+    /// current ones by `map`, retaining all other state.
+    fn map_parts<D2, EGF2, G>(self, map: G) -> Compressor<F, D2, EGF2>
+        where G: FnOnce(D, EGF) -> (D2, EGF2),
+    {
+        let (detector, even_gain_fn) = map(self.envelope_detector, self.even_gain_fn);
+        Compressor {
+            envelope_detector: detector,
+            attack_ms: self.attack_ms,
+            release_ms: self.release_ms,
+            release_stages: self.release_stages,
+            release_crossover_gain: self.release_crossover_gain,
+            fast_release_gain: self.fast_release_gain,
+            slow_release_gain: self.slow_release_gain,
+            fast_release: self.fast_release,
+            threshold: self.threshold,
+            slope: self.slope,
+            threshold_domain: self.threshold_domain,
+            knee_db: self.knee_db,
+            direction: self.direction,
+            db_reference: self.db_reference,
+            parameter_smoothing_ms: self.parameter_smoothing_ms,
+            parameter_smoothing_gain: self.parameter_smoothing_gain,
+            smoothed_threshold: self.smoothed_threshold,
+            smoothed_slope: self.smoothed_slope,
+            detector_link: self.detector_link,
+            punch_ms: self.punch_ms,
+            punch_frames: self.punch_frames,
+            over_threshold_frames: self.over_threshold_frames,
+            frames_processed: self.frames_processed,
+            silence_optimization: self.silence_optimization,
+            frozen: self.frozen,
+            bypass: self.bypass,
+            max_reduction_db: self.max_reduction_db,
+            range_knee_db: self.range_knee_db,
+            min_gain: self.min_gain,
+            overshoot_ceiling: self.overshoot_ceiling,
+            last_gain: self.last_gain,
+            meter_smoothing_ms: self.meter_smoothing_ms,
+            meter_smoothing_gain: self.meter_smoothing_gain,
+            meter_gain: self.meter_gain,
+            #[cfg(feature = "metering")]
+            range_window_ms: self.range_window_ms,
+            #[cfg(feature = "metering")]
+            range: self.range,
+            #[cfg(feature = "metering")]
+            loudness_match_ms: self.loudness_match_ms,
+            #[cfg(feature = "metering")]
+            loudness_match: self.loudness_match,
+            #[cfg(feature = "metering")]
+            loudness_reduction: self.loudness_reduction,
+            last_output_peak: self.last_output_peak,
+            input_clip_level: self.input_clip_level,
+            input_clip_count: self.input_clip_count,
+            input_gain: self.input_gain,
+            makeup_gain: self.makeup_gain,
+            auto_makeup: self.auto_makeup,
+            auto_gain_db: self.auto_gain_db,
+            auto_gain_ms: self.auto_gain_ms,
+            auto_gain: self.auto_gain,
+            adaptive_threshold_db: self.adaptive_threshold_db,
+            adaptive_threshold_ms: self.adaptive_threshold_ms,
+            adaptive_level: self.adaptive_level,
+            #[cfg(feature = "automation")]
+            automation: self.automation,
+            ratio_ramp: self.ratio_ramp,
+            sidechain: self.sidechain,
+            signal_delay: self.signal_delay,
+            quantizer: self.quantizer,
+            even_gain_fn: even_gain_fn,
+            frame: PhantomData,
+        }
+    }
+
+    /// Detect the level from the mean square of every `factor` frames rather than of every
+    /// frame, reducing the memory and CPU used by very long RMS windows, e.g. seconds long for
+    /// slow levelling. A `factor` of `1` (the default) disables decimation.
+    ///
+    /// The RMS window holds `1 / factor` as many elements, each the mean square of `factor`
+    /// consecutive frames, so the level of a steady signal is unchanged. The cost is temporal
+    /// resolution: the level is only recalculated once every `factor` frames and held in between,
+    /// so changes in level reach the detector up to `factor` frames late and in steps of `factor`
+    /// frames. Keep the `factor` well below the window length and the attack, e.g. a factor of
+    /// `64` for a window of several seconds.
+    ///
+    /// This only affects detectors with an RMS window and has no effect on **Peak** detection.
+    pub fn set_detection_decimation(&mut self, factor: usize) {
+        self.envelope_detector.set_decimation(factor);
+    }
+
+    /// The number of frames combined into each element of the detector's RMS window.
+    pub fn detection_decimation(&self) -> usize {
+        self.envelope_detector.decimation()
+    }
+
+    /// Enable or disable accumulating the detector's RMS sums of squares in `f64`, regardless of
+    /// the `Frame`'s sample type, while the rest of the signal path keeps its own precision.
+    ///
+    /// In `f32`, the running sum of a long RMS window retains a rounding error proportional to
+    /// the loudest material that has passed through it, so a quiet passage following a loud one
+    /// may read well above (or below) its true level until the window is reset. Accumulating in
+    /// `f64` reduces that error by roughly nine orders of magnitude, at the cost of maintaining a
+    /// second sum for each channel. Worthwhile for windows of many thousands of frames, e.g. slow
+    /// levelling, and negligible for short windows. Defaults to `false`.
+    ///
+    /// This only affects detectors with an RMS window and has no effect on **Peak** detection.
+    pub fn set_high_precision_rms(&mut self, enabled: bool) {
+        self.envelope_detector.set_high_precision(enabled);
+    }
+
+    /// Whether the detector's RMS sums of squares are accumulated in `f64`.
+    pub fn high_precision_rms(&self) -> bool {
+        self.envelope_detector.high_precision()
+    }
+
     /// Set the duration of the envelope's attack in milliseconds.
     pub fn set_attack_ms<M: Into<Ms>>(&mut self, ms: M, sample_hz: f64) {
         let ms: Ms = ms.into();
@@ -116,191 +719,5383 @@ impl<F, D, EGF> Compressor<F, D, EGF>
         self.update_release_to_sample_hz(sample_hz);
     }
 
-    /// Updates the **Compressor**'s `attack` gain in accordance with the current sample_hz.
-    pub fn update_attack_to_sample_hz(&mut self, sample_hz: f64) {
-        let frames = self.attack_ms.samples(sample_hz) as f32;
-        self.envelope_detector.detector().set_attack_frames(frames);
+    /// Use a two-stage release: a fast initial recovery while the gain reduction is large,
+    /// followed by a slower tail, as in the "auto" release of classic bus compressors.
+    ///
+    /// While the gain reduction applied to the previous frame exceeds `crossover_db`, the
+    /// envelope is released over `fast_ms`. Once the gain reduction has recovered to within
+    /// `crossover_db` of unity, the release switches to `slow_ms` for the remainder of the
+    /// recovery. Short bursts of gain reduction therefore recover quickly, while the final few
+    /// decibels ease back smoothly. The `release_ms` is ignored until the stages are cleared via
+    /// `clear_release_stages`.
+    pub fn set_release_stages<A, B>(&mut self,
+                                    fast_ms: A,
+                                    slow_ms: B,
+                                    crossover_db: f32,
+                                    sample_hz: f64)
+        where A: Into<Ms>,
+              B: Into<Ms>,
+    {
+        self.release_stages = Some((fast_ms.into(), slow_ms.into(), crossover_db));
+        self.release_crossover_gain = db::db_to_amp(-crossover_db);
+        self.update_release_to_sample_hz(sample_hz);
     }
 
-    /// Updates the **Compressor**'s `release` gain in accordance with the current sample_hz.
-    pub fn update_release_to_sample_hz(&mut self, sample_hz: f64) {
-        let frames = self.release_ms.samples(sample_hz) as f32;
-        self.envelope_detector.detector().set_release_frames(frames);
+    /// Return to a single release stage lasting `release_ms`.
+    pub fn clear_release_stages(&mut self, sample_hz: f64) {
+        self.release_stages = None;
+        self.update_release_to_sample_hz(sample_hz);
     }
 
-    /// Steps forward the detectors using the given frame and determines the gain per-channel,
-    /// yielding the result as a `Frame`.
-    pub fn next_gain_per_channel(&mut self, next_frame: F) -> F::Float {
-        let threshold = self.threshold.to_sample();
-        let slope = self.slope.to_sample();
-        let identity = <F::Sample as Sample>::identity();
-        let env_frame = self.envelope_detector.detector().next(next_frame).to_float_frame();
-        env_frame.map(|s| {
-            let s = if s > identity { identity } else { s }; // Clamp `s` between 0.0...1.0.
-            if s > threshold { identity - (s - threshold) * slope } else { identity }
-        })
+    /// The fast and slow release durations and the crossover gain reduction in decibels of the
+    /// two-stage release, if enabled.
+    pub fn release_stages(&self) -> Option<(Ms, Ms, f32)> {
+        self.release_stages
     }
 
-    /// Produce the gain to be applied evenly across all channels for the next frame.
-    #[inline]
-    pub fn next_gain(&mut self, next_frame: F) -> <F::Sample as Sample>::Float {
-        EGF::next_gain(self, next_frame)
+    /// Set the algorithm by which the detected envelope rises toward the signal level over the
+    /// attack duration.
+    ///
+    /// Attack and release may use different algorithms, e.g. a **Hold** release over an
+    /// **Exponential** attack, modelling hardware whose attack and release behave qualitatively
+    /// differently. Defaults to `SmoothingMode::Exponential`.
+    pub fn set_attack_mode(&mut self, mode: SmoothingMode) {
+        self.envelope_detector.detector().set_attack_mode(mode);
     }
 
-    /// Steps forward the `Compressor` by the given frame and returns the compressed result.
-    #[inline]
-    pub fn next_frame(&mut self, next_frame: F) -> F {
-        let gain = self.next_gain(next_frame);
-        next_frame.scale_amp(gain)
+    /// Set the algorithm by which the detected envelope falls toward the signal level over the
+    /// release duration.
+    ///
+    /// Defaults to `SmoothingMode::Exponential`.
+    pub fn set_release_mode(&mut self, mode: SmoothingMode) {
+        self.envelope_detector.detector().set_release_mode(mode);
     }
 
-}
+    /// Enable or disable the "auto attack", which adapts the attack duration to the size of each
+    /// rise in level.
+    ///
+    /// The heuristic compares the level presented to the detector with the current envelope
+    /// every frame. The attack duration is scaled by `envelope::AUTO_ATTACK_REFERENCE_DB` divided
+    /// by the rise in decibels, between `envelope::AUTO_ATTACK_MIN_SCALE` and
+    /// `envelope::AUTO_ATTACK_MAX_SCALE` times the set attack. A sharp transient that leaps far
+    /// above the envelope is therefore caught with a much shorter attack, while a gradual swell,
+    /// which the envelope follows closely, is met with up to twice the set attack and isn't dulled.
+    /// Defaults to `false`.
+    pub fn set_auto_attack(&mut self, enabled: bool) {
+        self.envelope_detector.detector().set_auto_attack(enabled);
+    }
 
-impl<F, EGF> PeakCompressor<F, EGF>
-    where F: Frame,
-          EGF: EvenGainFunction,
-{
+    /// Whether the attack duration adapts to the size of each rise in level.
+    pub fn auto_attack(&self) -> bool {
+        self.envelope_detector.detector_ref().auto_attack()
+    }
 
-    /// Construct a **Compressor** that uses a **Peak** **EnvelopeDetector**.
-    pub fn peak<A, R>(attack_ms: A,
-                      release_ms: R,
-                      sample_hz: f64,
-                      threshold: f32,
-                      ratio: f32) -> Self
-        where A: Into<Ms>,
-              R: Into<Ms>,
-    {
-        let attack_ms: Ms = attack_ms.into();
-        let release_ms: Ms = release_ms.into();
-        let attack_frames = attack_ms.samples(sample_hz) as f32;
-        let release_frames = release_ms.samples(sample_hz) as f32;
-        let envelope_detector = EnvelopeDetector::peak(attack_frames, release_frames);
-        Compressor::new(envelope_detector, attack_ms, release_ms, threshold, ratio)
+    /// The algorithm by which the detected envelope rises.
+    pub fn attack_mode(&self) -> SmoothingMode {
+        self.envelope_detector.detector_ref().attack_mode()
     }
 
-}
+    /// The algorithm by which the detected envelope falls.
+    pub fn release_mode(&self) -> SmoothingMode {
+        self.envelope_detector.detector_ref().release_mode()
+    }
 
-impl<F> PeakAvgCompressor<F>
-    where F: Frame,
-{
+    /// Set the compression slope as a percentage, where `0.0` applies no compression and `100.0`
+    /// limits the signal at the threshold (an infinite ratio).
+    ///
+    /// The percentage maps linearly to the slope, i.e. `50.0` is equivalent to a ratio of 2:1 and
+    /// `75.0` to 4:1. Values outside of `0.0` to `100.0` are clamped.
+    pub fn set_slope_percent(&mut self, percent: f32) {
+        let percent = if percent < 0.0 { 0.0 } else if percent > 100.0 { 100.0 } else { percent };
+        self.slope = percent / 100.0;
+    }
 
-    /// Construct a **Compressor** that uses the **Average** across all channels yielded by a
-    /// **Peak** **EnvelopeDetector**
-    pub fn peak_avg<A, R>(attack_ms: A,
-                          release_ms: R,
-                          sample_hz: f64,
-                          threshold: f32,
-                          ratio: f32) -> Self
-        where A: Into<Ms>,
-              R: Into<Ms>,
-    {
-        Self::peak(attack_ms, release_ms, sample_hz, threshold, ratio)
+    /// The compression slope as a percentage, where `0.0` applies no compression and `100.0`
+    /// limits the signal at the threshold.
+    pub fn slope_percent(&self) -> f32 {
+        self.slope * 100.0
     }
 
-}
+    /// Set the linear amplitude that corresponds to `0.0` dB for all of the **Compressor**'s
+    /// decibel levels, i.e. `db::db_to_amp(-18.0)` for a workflow in which -18 dBFS is 0 VU.
+    ///
+    /// This shifts the absolute levels accepted and reported by `set_threshold_db`,
+    /// `threshold_db`, `output_db_for_input_db` and `sample_transfer_curve`. Relative amounts,
+    /// such as `nudge_threshold_db` and `dynamic_range_reduction_db`, are unaffected. Defaults to
+    /// `DEFAULT_DB_REFERENCE`.
+    pub fn set_db_reference(&mut self, ref_amplitude: f32) {
+        self.db_reference = ref_amplitude;
+    }
 
-impl<F> PeakMinCompressor<F>
-    where F: Frame,
-{
+    /// The linear amplitude that corresponds to `0.0` dB.
+    pub fn db_reference(&self) -> f32 {
+        self.db_reference
+    }
 
-    /// Construct a **Compressor** that uses the **Minimum** across all channels yielded by a
-    /// **Peak** **EnvelopeDetector**
-    pub fn peak_min<A, R>(attack_ms: A,
-                          release_ms: R,
-                          sample_hz: f64,
-                          threshold: f32,
-                          ratio: f32) -> Self
-        where A: Into<Ms>,
-              R: Into<Ms>,
-    {
-        Self::peak(attack_ms, release_ms, sample_hz, threshold, ratio)
+    /// Set the threshold in decibels relative to the `db_reference`.
+    ///
+    /// The decibels describe the equivalent amplitude in either `ThresholdDomain`.
+    pub fn set_threshold_db(&mut self, threshold_db: f32) {
+        let amp = self.db_to_amp(threshold_db);
+        self.threshold = self.threshold_domain.from_amplitude(amp);
     }
 
-}
+    /// Set the threshold `db_below` decibels below the peak of the given `frames`, e.g. as a
+    /// starting point of "a few decibels below the loudest part".
+    ///
+    /// This is a one-shot analysis of the given `frames` only: the threshold is set once via
+    /// `set_threshold_db` and doesn't follow the material afterward. See `set_adaptive_threshold`
+    /// for a threshold that does. The peak is the largest absolute sample across all channels,
+    /// in decibels relative to the `db_reference`. The threshold is left untouched if the
+    /// `frames` are silent. The detectors are not stepped.
+    pub fn auto_set_threshold_below_peak(&mut self, frames: &[F], db_below: f32) {
+        let peak = frames.iter().fold(0.0, |max, &frame| {
+            let peak = meter::peak(frame);
+            if peak > max { peak } else { max }
+        });
+        if peak > 0.0 {
+            let peak_db = self.amp_to_db(peak);
+            self.set_threshold_db(peak_db - db_below);
+        }
+    }
 
-impl<F, EGF> RmsCompressor<F, EGF>
-    where F: Frame,
-          EGF: EvenGainFunction,
-{
+    /// The threshold in decibels relative to the `db_reference`.
+    pub fn threshold_db(&self) -> f32 {
+        self.amp_to_db(self.threshold_domain.to_amplitude(self.threshold))
+    }
 
-    /// Construct a **Compressor** that uses an **Rms** **EnvelopeDetector**.
-    pub fn rms<W, A, R>(window_ms: W,
-                        attack_ms: A,
-                        release_ms: R,
-                        sample_hz: f64,
-                        threshold: f32,
-                        ratio: f32) -> Self
-        where W: Into<Ms>,
-              A: Into<Ms>,
-              R: Into<Ms>,
-    {
-        let window_ms: Ms = window_ms.into();
-        let attack_ms: Ms = attack_ms.into();
-        let release_ms: Ms = release_ms.into();
-        let window_frames = window_ms.samples(sample_hz) as usize;
-        let attack_frames = attack_ms.samples(sample_hz) as f32;
-        let release_frames = release_ms.samples(sample_hz) as f32;
-        let envelope_detector = EnvelopeDetector::rms(window_frames, attack_frames, release_frames);
-        let rms_envelope_detector = RmsEnvelopeDetector {
-            rms: envelope_detector,
-            window_ms: window_ms,
-        };
-        Compressor::new(rms_envelope_detector, attack_ms, release_ms, threshold, ratio)
+    /// Set which side of the threshold is attenuated.
+    ///
+    /// `Direction::Compress` (the default) attenuates the envelope above the threshold as usual.
+    /// `Direction::Expand` turns the **Compressor** into a downward expander, e.g. to clean up
+    /// bleed on a drum microphone: the signal above the threshold passes untouched, while each
+    /// decibel that the envelope falls below the threshold lowers the output by `ratio` decibels,
+    /// so that an infinite ratio is a hard gate. The detector, attack, release, even gain, range
+    /// and every other stage work as they do for compression, e.g. `set_max_reduction_db` or
+    /// `set_min_gain` limit how far a gate closes. Unlike compression, the expansion ratio is
+    /// applied in decibels, and the soft knee and silence optimization have no effect.
+    pub fn set_direction(&mut self, direction: Direction) {
+        self.direction = direction;
     }
 
-    /// Set the duration of the envelope's RMS window in milliseconds.
-    pub fn set_window_ms<M: Into<Ms>>(&mut self, ms: M, sample_hz: f64) {
-        let ms: Ms = ms.into();
-        self.envelope_detector.window_ms = ms;
-        self.update_window_to_sample_hz(sample_hz);
+    /// Which side of the threshold is attenuated.
+    pub fn direction(&self) -> Direction {
+        self.direction
     }
 
-    /// Updates the **Compressor**'s window size in frames via the given sample_hz.
-    pub fn update_window_to_sample_hz(&mut self, sample_hz: f64) {
-        let frames = self.envelope_detector.window_ms.samples(sample_hz) as usize;
-        self.envelope_detector.rms.set_window_frames(frames);
+    /// Set the width in decibels of a soft knee centred on the threshold.
+    ///
+    /// Rather than the full ratio taking effect the moment the envelope crosses the threshold,
+    /// the compression eases in from `knee_db / 2` below the threshold and reaches the full ratio
+    /// `knee_db / 2` above it, without a corner in the gain curve. This avoids an audible snap on
+    /// material hovering around the threshold. Above the knee the gain is that of the hard knee.
+    /// Negative values are treated as `0.0`. Defaults to `0.0`, a hard knee.
+    pub fn set_knee_db(&mut self, knee_db: f32) {
+        self.knee_db = if knee_db < 0.0 { 0.0 } else { knee_db };
     }
 
-}
+    /// The width in decibels of the soft knee centred on the threshold.
+    pub fn knee_db(&self) -> f32 {
+        self.knee_db
+    }
 
-impl<F> RmsAvgCompressor<F>
-    where F: Frame,
-{
+    /// Set the terms in which the `threshold` is given.
+    ///
+    /// In the `Amplitude` domain the threshold is compared directly against the detected
+    /// envelope, while in the `Power` domain it is a mean-square power, i.e. the square of the
+    /// equivalent amplitude. The two differ only in how the `threshold` field is read, so the
+    /// `threshold` is left as is and takes on the new meaning. Defaults to
+    /// `ThresholdDomain::Amplitude`.
+    pub fn set_threshold_domain(&mut self, domain: ThresholdDomain) {
+        self.threshold_domain = domain;
+    }
 
-    /// Construct a **Compressor** that uses the **Average** across all channels yielded by a
-    /// **Rms** **EnvelopeDetector**
-    pub fn rms_avg<W, A, R>(window_ms: W,
-                            attack_ms: A,
-                            release_ms: R,
-                            sample_hz: f64,
-                            threshold: f32,
-                            ratio: f32) -> Self
-        where W: Into<Ms>,
-              A: Into<Ms>,
-              R: Into<Ms>,
+    /// The terms in which the `threshold` is given.
+    pub fn threshold_domain(&self) -> ThresholdDomain {
+        self.threshold_domain
+    }
+
+    /// Apply all of the given parameters at once.
+    ///
+    /// Unlike calling each setter in turn, the detector's coefficients are only recomputed once
+    /// after every parameter has been applied, avoiding redundant work and any transient
+    /// behaviour from a partially applied preset.
+    ///
+    /// The `window_ms` is ignored by detectors that have no window.
+    pub fn set_all(&mut self, params: &CompressorParams, sample_hz: f64) {
+        self.threshold = params.threshold;
+        self.slope = ratio::slope_from_ratio(params.ratio);
+        self.attack_ms = Ms(params.attack_ms);
+        self.release_ms = Ms(params.release_ms);
+        if let Some(window_ms) = params.window_ms {
+            self.envelope_detector.set_window_ms(Ms(window_ms));
+        }
+        self.set_knee_db(params.knee_db);
+        self.set_makeup_gain(params.makeup_gain);
+        self.set_auto_makeup(params.auto_makeup);
+        self.update_to_sample_hz(sample_hz);
+    }
+
+    /// Apply the given `profile` via `set_all`, offsetting its threshold to suit material that
+    /// peaks at `reference_peak_db`, so that one profile may be used across sources of different
+    /// levels.
+    ///
+    /// A profile's threshold is taken to have been set for material peaking at `0.0` dB relative
+    /// to the `db_reference`. The applied threshold is moved by `reference_peak_db`, keeping the
+    /// same distance in decibels below the material's peak: a profile with a threshold of -12 dB
+    /// applied to material peaking at -20 dB yields a threshold of -32 dB. The `reference_peak_db`
+    /// may be measured beforehand, e.g. the loudest `last_output_peak` of a bypassed pass, or
+    /// simply be known. The offset applies to the equivalent amplitude in either
+    /// `ThresholdDomain`. The ballistics are given in milliseconds and so suit any sample_hz.
+    ///
+    /// The ratio is applied unchanged. As the gain falls linearly with the envelope above the
+    /// threshold rather than in decibels, the material exceeds the threshold by the same number
+    /// of decibels at any level, but the resulting gain reduction is smaller for quieter material.
+    pub fn apply_profile_scaled(&mut self,
+                                profile: &CompressorParams,
+                                sample_hz: f64,
+                                reference_peak_db: f32)
     {
-        Self::rms(window_ms, attack_ms, release_ms, sample_hz, threshold, ratio)
+        let domain = self.threshold_domain;
+        let threshold = domain.to_amplitude(profile.threshold) * db::db_to_amp(reference_peak_db);
+        let params = CompressorParams { threshold: domain.from_amplitude(threshold), ..*profile };
+        self.set_all(&params, sample_hz);
     }
 
-}
+    /// The current tunable parameters.
+    ///
+    /// The `window_ms` is `None` for detectors that have no window.
+    pub fn params(&self) -> CompressorParams {
+        CompressorParams {
+            threshold: self.threshold,
+            ratio: ratio::ratio_from_slope(self.slope),
+            attack_ms: self.attack_ms.ms(),
+            release_ms: self.release_ms.ms(),
+            window_ms: self.envelope_detector.window_ms().map(|ms| ms.ms()),
+            knee_db: self.knee_db,
+            makeup_gain: self.makeup_gain,
+            auto_makeup: self.auto_makeup,
+        }
+    }
 
-impl<F> RmsMinCompressor<F>
-    where F: Frame,
-{
+    /// Interpolate all parameters between their current values (at an `amount` of `0.0`) and
+    /// `other_params` (at an `amount` of `1.0`) and apply the result, e.g. for an A/B blend knob.
+    ///
+    /// See **CompressorParams::interpolate** for how each parameter is interpolated. To morph
+    /// between two fixed presets, interpolate between them directly and apply the result via
+    /// `set_all`, as repeated calls to `morph` each begin from the result of the last.
+    pub fn morph(&mut self, other_params: &CompressorParams, amount: f32, sample_hz: f64) {
+        let params = self.params().interpolate(other_params, amount);
+        self.set_all(&params, sample_hz);
+    }
 
-    /// Construct a **Compressor** that uses the **Minimum** across all channels yielded by a
-    /// **Rms** **EnvelopeDetector**
-    pub fn rms_min<W, A, R>(window_ms: W,
-                            attack_ms: A,
-                            release_ms: R,
-                            sample_hz: f64,
-                            threshold: f32,
-                            ratio: f32) -> Self
-        where W: Into<Ms>,
-              A: Into<Ms>,
-              R: Into<Ms>,
-    {
-        Self::rms(window_ms, attack_ms, release_ms, sample_hz, threshold, ratio)
+    /// The raw detector coefficients and slope currently in effect.
+    pub fn coefficients(&self) -> Coefficients {
+        let detector = self.envelope_detector.detector_ref();
+        Coefficients {
+            attack_gain: detector.attack_gain(),
+            release_gain: detector.release_gain(),
+            slope: self.slope,
+        }
     }
 
+    /// Directly set the raw detector coefficients and slope.
+    ///
+    /// Copying the **Coefficients** of one **Compressor** to another makes both produce identical
+    /// gain for the same input, regardless of how their durations rounded to frames.
+    ///
+    /// Note that the attack and release coefficients are recomputed from `attack_ms` and
+    /// `release_ms` whenever the **Compressor** is updated to a sample rate.
+    pub fn set_coefficients(&mut self, coefficients: Coefficients) {
+        {
+            let detector = self.envelope_detector.detector();
+            detector.set_attack_gain(coefficients.attack_gain);
+            detector.set_release_gain(coefficients.release_gain);
+        }
+        self.slope = coefficients.slope;
+    }
+
+    /// Updates all of the **Compressor**'s duration-based state in accordance with the given
+    /// sample_hz.
+    ///
+    /// Every method taking a sample_hz requires it to be finite and greater than zero, as a zero,
+    /// infinite or NaN rate would yield meaningless coefficients. The constructors and the
+    /// `update_*_to_sample_hz` methods (and so the setters that call them) panic otherwise. See
+    /// `try_update_to_sample_hz` to have an invalid sample_hz reported instead.
+    ///
+    /// **Panics** if the sample_hz is not finite and greater than zero.
+    pub fn update_to_sample_hz(&mut self, sample_hz: f64) {
+        if let Err(err) = self.try_update_to_sample_hz(sample_hz) {
+            panic!("update_to_sample_hz: {}", err);
+        }
+    }
+
+    /// The same as `update_to_sample_hz`, but returns a **CompressorError** rather than panicking
+    /// if the sample_hz is not finite and greater than zero.
+    ///
+    /// The sample_hz is checked before any state is updated, so on error the **Compressor** is
+    /// left untouched.
+    pub fn try_update_to_sample_hz(&mut self, sample_hz: f64) -> Result<(), CompressorError> {
+        validate_sample_hz(sample_hz)?;
+        self.update_attack_to_sample_hz(sample_hz);
+        self.update_release_to_sample_hz(sample_hz);
+        self.update_parameter_smoothing_to_sample_hz(sample_hz);
+        self.update_meter_smoothing_to_sample_hz(sample_hz);
+        self.update_punch_to_sample_hz(sample_hz);
+        #[cfg(feature = "metering")]
+        self.update_range_window_to_sample_hz(sample_hz);
+        #[cfg(feature = "metering")]
+        self.update_loudness_match_to_sample_hz(sample_hz);
+        self.update_auto_gain_to_sample_hz(sample_hz);
+        self.update_adaptive_threshold_to_sample_hz(sample_hz);
+        #[cfg(feature = "metering")]
+        {
+            if let Some(ref mut meter) = self.loudness_reduction {
+                meter.update_to_sample_hz(sample_hz);
+            }
+        }
+        #[cfg(feature = "sidechain-filter")]
+        self.sidechain.update_to_sample_hz(sample_hz);
+        self.envelope_detector.update_to_sample_hz(sample_hz);
+        Ok(())
+    }
+
+    /// Updates the **Compressor**'s `attack` gain in accordance with the current sample_hz.
+    pub fn update_attack_to_sample_hz(&mut self, sample_hz: f64) {
+        assert_valid_sample_hz(sample_hz, "update_attack_to_sample_hz");
+        let gain = coeff::attack_coeff_for_ms(self.attack_ms, sample_hz);
+        self.envelope_detector.detector().set_attack_gain(gain);
+    }
+
+    /// Updates the **Compressor**'s `release` gain in accordance with the current sample_hz.
+    pub fn update_release_to_sample_hz(&mut self, sample_hz: f64) {
+        assert_valid_sample_hz(sample_hz, "update_release_to_sample_hz");
+        let gain = match self.release_stages {
+            None => coeff::release_coeff_for_ms(self.release_ms, sample_hz),
+            Some((fast_ms, slow_ms, _)) => {
+                self.fast_release_gain = coeff::release_coeff_for_ms(fast_ms, sample_hz);
+                self.slow_release_gain = coeff::release_coeff_for_ms(slow_ms, sample_hz);
+                if self.fast_release { self.fast_release_gain } else { self.slow_release_gain }
+            },
+        };
+        self.envelope_detector.detector().set_release_gain(gain);
+    }
+
+    /// Set the period over which changes to the threshold and ratio are smoothed.
+    ///
+    /// Rather than jumping to a new value, the threshold and ratio in effect approach it
+    /// exponentially, retaining roughly a third of the remaining difference after the given
+    /// period. This avoids clicks when the parameters are automated or dragged. The static
+    /// transfer curve always describes the target parameters. Defaults to `0.0`, i.e. changes
+    /// take effect immediately.
+    pub fn set_parameter_smoothing_ms<M: Into<Ms>>(&mut self, ms: M, sample_hz: f64) {
+        let ms: Ms = ms.into();
+        self.parameter_smoothing_ms = ms;
+        self.update_parameter_smoothing_to_sample_hz(sample_hz);
+    }
+
+    /// Updates the **Compressor**'s parameter smoothing in accordance with the current sample_hz.
+    pub fn update_parameter_smoothing_to_sample_hz(&mut self, sample_hz: f64) {
+        assert_valid_sample_hz(sample_hz, "update_parameter_smoothing_to_sample_hz");
+        self.parameter_smoothing_gain = coeff::coeff_for_ms(self.parameter_smoothing_ms, sample_hz);
+    }
+
+    /// The number of frames the parameter smoothing takes to apply a change to the threshold or
+    /// ratio, defined as the number of frames until `SETTLED_FRACTION` (99%) of the change has
+    /// taken effect.
+    ///
+    /// Hosts may use this to space parameter changes far enough apart, or to crossfade over,
+    /// so that each change has settled before the next. The gain follows the threshold and ratio
+    /// in effect without any further delay, as the attack and release smooth the detected
+    /// envelope rather than the gain. Without parameter smoothing this is `0`.
+    pub fn settling_frames(&self) -> usize {
+        frames_to_settle(self.parameter_smoothing_gain)
+    }
+
+    /// An estimate of the number of frames until the current gain reduction recovers to within
+    /// `RELEASE_COMPLETE_DB` of unity, should the input fall silent, e.g. to decide how long a
+    /// tail of silence to render after the end of a stream.
+    ///
+    /// The estimate follows the loudest channel's envelope as it releases exponentially via the
+    /// current release coefficient, until it falls to the level at which the static transfer
+    /// curve yields less than `RELEASE_COMPLETE_DB` of reduction. Yields `0` once the gain has
+    /// recovered. As with the silence optimization, the **Linear** and **Hold** release modes are
+    /// approximated by the exponential release, and any lookahead delay must be flushed in
+    /// addition.
+    pub fn frames_until_release_complete(&self) -> usize {
+        let slope = self.slope;
+        if slope <= 0.0 {
+            return 0;
+        }
+        let threshold = self.threshold_domain.to_amplitude(self.target_threshold());
+        let recovered = threshold + (1.0 - db::db_to_amp(-RELEASE_COMPLETE_DB)) / slope;
+        let envelope = self.loudest_envelope();
+        if envelope <= recovered {
+            return 0;
+        }
+        let release_gain = self.envelope_detector.detector_ref().release_gain();
+        if release_gain <= 0.0 {
+            return 1;
+        }
+        ((recovered / envelope).ln() / release_gain.ln()).ceil() as usize
+    }
+
+    /// Nudge the threshold by the given number of decibels.
+    ///
+    /// Like any other change to the threshold, the nudge is smoothed over the period set via
+    /// `set_parameter_smoothing_ms`, so that a series of small nudges (e.g. from dragging a knob)
+    /// doesn't click. The nudge is relative and so is unaffected by the `db_reference`.
+    pub fn nudge_threshold_db(&mut self, delta_db: f32) {
+        let domain = self.threshold_domain;
+        let amp = domain.to_amplitude(self.threshold) * db::db_to_amp(delta_db);
+        self.threshold = domain.from_amplitude(amp);
+    }
+
+    /// Nudge the ratio by the given amount, i.e. `0.5` turns 4:1 into 4.5:1.
+    ///
+    /// The ratio will not be nudged below 1:1. Like any other change to the ratio, the nudge is
+    /// smoothed over the period set via `set_parameter_smoothing_ms`.
+    pub fn nudge_ratio(&mut self, delta: f32) {
+        let ratio = ratio::ratio_from_slope(self.slope) + delta;
+        self.slope = ratio::slope_from_ratio(if ratio < 1.0 { 1.0 } else { ratio });
+    }
+
+    /// Schedule the threshold to be set to the given linear amplitude at the given frame offset
+    /// within the next block passed to `compress_slice`.
+    ///
+    /// Scheduled changes take effect exactly at their frame, before that frame is compressed,
+    /// giving sample-accurate automation rather than a change per block. A change scheduled beyond
+    /// the end of the next block carries over to the corresponding frame of the block after it.
+    /// Like any other change to the threshold, the change is smoothed over the period set via
+    /// `set_parameter_smoothing_ms`.
+    #[cfg(feature = "automation")]
+    pub fn schedule_threshold(&mut self, frame_offset: usize, threshold: f32) {
+        self.automation.schedule(frame_offset, Change::Threshold(threshold));
+    }
+
+    /// Schedule the ratio to be set to the given value at the given frame offset within the next
+    /// block passed to `compress_slice`.
+    ///
+    /// See `schedule_threshold` for details.
+    #[cfg(feature = "automation")]
+    pub fn schedule_ratio(&mut self, frame_offset: usize, ratio: f32) {
+        self.automation.schedule(frame_offset, Change::Ratio(ratio));
+    }
+
+    /// Ramp the ratio to the given target over the given number of beats at the given tempo,
+    /// e.g. to tighten the compression across a build.
+    ///
+    /// The ramp is linear in the slope of the ratio (i.e. `0.75` for 4:1) rather than in the ratio
+    /// itself, so that ramping toward an infinite ratio moves evenly and remains finite
+    /// throughout. The ramp advances with each processed frame and ends with the slope exactly at
+    /// the target. Ramping over zero beats sets the ratio immediately. Any ramp already in
+    /// progress is replaced.
+    ///
+    /// **Panics** if `beats` is negative or non-finite, or if `bpm` or `sample_hz` is not a
+    /// positive, finite number.
+    pub fn ramp_ratio_over_beats(&mut self,
+                                 target_ratio: f32,
+                                 beats: f64,
+                                 bpm: f64,
+                                 sample_hz: f64)
+    {
+        assert!(beats >= 0.0 && beats.is_finite(),
+                "ramp length must be a non-negative, finite number of beats (got {})", beats);
+        assert!(bpm > 0.0 && bpm.is_finite(),
+                "tempo must be a positive, finite bpm (got {})", bpm);
+        assert_valid_sample_hz(sample_hz, "ramping the ratio");
+        let target_slope = ratio::slope_from_ratio(target_ratio);
+        let ms = Ms(beats * 60_000.0 / bpm);
+        let frames = ms.samples(sample_hz);
+        if frames < 1 {
+            self.slope = target_slope;
+            self.ratio_ramp = None;
+        } else {
+            let step = (target_slope - self.slope) / frames as f32;
+            self.ratio_ramp = Some((target_slope, step, frames as u64));
+        }
+    }
+
+    /// Stop any ratio ramp in progress, leaving the ratio wherever the ramp has reached.
+    pub fn cancel_ratio_ramp(&mut self) {
+        self.ratio_ramp = None;
+    }
+
+    /// Whether a ratio ramp started via `ramp_ratio_over_beats` is in progress.
+    pub fn is_ramping_ratio(&self) -> bool {
+        self.ratio_ramp.is_some()
+    }
+
+    /// Allocate room for up to `max_events` parameter changes scheduled via `schedule_threshold`
+    /// and `schedule_ratio`, so that scheduling them never allocates.
+    #[cfg(feature = "automation")]
+    pub fn reserve_scheduled(&mut self, max_events: usize) {
+        self.automation.reserve(max_events);
+    }
+
+    /// Discard all parameter changes scheduled via `schedule_threshold` and `schedule_ratio`.
+    #[cfg(feature = "automation")]
+    pub fn clear_scheduled(&mut self) {
+        self.automation.clear();
+    }
+
+    /// Set the period for which the gain is held at unity after the envelope first exceeds the
+    /// threshold, letting the initial transient through uncompressed.
+    ///
+    /// Unlike the attack, which smooths the onset of gain reduction, the punch delays it
+    /// entirely. Once the envelope falls back below the threshold the punch is re-armed. Defaults
+    /// to `0.0`.
+    pub fn set_punch_ms<M: Into<Ms>>(&mut self, ms: M, sample_hz: f64) {
+        let ms: Ms = ms.into();
+        self.punch_ms = ms;
+        self.update_punch_to_sample_hz(sample_hz);
+    }
+
+    /// Updates the **Compressor**'s punch period in accordance with the current sample_hz.
+    pub fn update_punch_to_sample_hz(&mut self, sample_hz: f64) {
+        assert_valid_sample_hz(sample_hz, "update_punch_to_sample_hz");
+        self.punch_frames = self.punch_ms.samples(sample_hz) as usize;
+    }
+
+    /// Freeze or unfreeze the gain.
+    ///
+    /// While frozen, the most recently produced gain is applied to every frame and the detectors
+    /// are not stepped, so the gain stays constant regardless of the input. Once unfrozen,
+    /// detection resumes from the state it was frozen in and follows the current signal from
+    /// there via the attack and release.
+    pub fn set_freeze(&mut self, freeze: bool) {
+        self.frozen = freeze;
+    }
+
+    /// The number of frames processed since the **Compressor** was constructed.
+    ///
+    /// Every frame for which a gain is produced counts, whichever method produced it. All of the
+    /// **Compressor**'s time-dependent behaviour, such as the punch and the ballistics, is driven
+    /// by the number of frames processed rather than by any clock, so tests may rely on this to
+    /// assert exactly when a transition occurs.
+    pub fn frames_processed(&self) -> u64 {
+        self.frames_processed
+    }
+
+    /// Whether the gain is frozen.
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    /// Enable or disable bypassing the **Compressor**.
+    ///
+    /// While bypassed, each frame passes through the signal delay line but is otherwise returned
+    /// untouched: no gain, auto gain or quantization is applied. The detectors continue to follow
+    /// the signal, so that the gain is already appropriate to the material when the bypass is
+    /// disabled, and the latency is unchanged so that switching doesn't shift the signal in time.
+    pub fn set_bypass(&mut self, bypass: bool) {
+        self.bypass = bypass;
+    }
+
+    /// Whether the **Compressor** is bypassed.
+    pub fn is_bypassed(&self) -> bool {
+        self.bypass
+    }
+
+    /// Limit the gain reduction to at most the given number of decibels, or `None` for no limit.
+    ///
+    /// The gain reduction follows the ratio until it nears this floor, after which it is held at
+    /// the floor however far the envelope exceeds the threshold, e.g. so that a ducker or heavy
+    /// compression attenuates the signal without removing it. By default the floor is reached
+    /// with a sharp corner; see `set_range_knee_db` to approach it smoothly. Negative values are
+    /// treated as `0.0`.
+    pub fn set_max_reduction_db(&mut self, max_reduction_db: Option<f32>) {
+        self.max_reduction_db = max_reduction_db.map(|db| if db < 0.0 { 0.0 } else { db });
+    }
+
+    /// The most gain reduction in decibels that may be applied, if limited.
+    pub fn max_reduction_db(&self) -> Option<f32> {
+        self.max_reduction_db
+    }
+
+    /// Set the width in decibels of the knee over which the gain reduction approaches the floor
+    /// set via `set_max_reduction_db`.
+    ///
+    /// Rather than following the ratio right up to the floor and then stopping abruptly, the gain
+    /// reduction begins to level off `knee_db / 2` before the floor and meets it `knee_db / 2`
+    /// beyond, along a quadratic curve with no sharp corner. This makes gating and ducking less
+    /// audibly steppy as the signal crosses the floor. Has no effect unless a maximum reduction is
+    /// set. Negative values are treated as `0.0`. Defaults to `0.0`, a hard clamp.
+    pub fn set_range_knee_db(&mut self, knee_db: f32) {
+        self.range_knee_db = if knee_db < 0.0 { 0.0 } else { knee_db };
+    }
+
+    /// The width in decibels of the knee over which the gain reduction approaches the floor.
+    pub fn range_knee_db(&self) -> f32 {
+        self.range_knee_db
+    }
+
+    /// Set the least linear gain that may be applied, so that however hard the signal is
+    /// compressed it is attenuated but never silenced.
+    ///
+    /// This is a direct clamp on the even gain, applied after every other stage of the gain
+    /// computation, i.e. the transfer curve, the range and the punch. Where `set_max_reduction_db`
+    /// also limits the gain reduction, whichever floor is higher takes effect: the range eases
+    /// into its floor across the `range_knee_db`, while the minimum gain is a hard corner. The
+    /// input, makeup and auto gain are applied afterward and may move the output above or below
+    /// the floor. Values are clamped between `0.0` and `1.0`. Defaults to `0.0`, i.e. no floor.
+    pub fn set_min_gain(&mut self, gain: f32) {
+        self.min_gain = if gain < 0.0 { 0.0 } else if gain > 1.0 { 1.0 } else { gain };
+    }
+
+    /// The least linear gain that may be applied.
+    pub fn min_gain(&self) -> f32 {
+        self.min_gain
+    }
+
+    /// Set the linear peak amplitude that no compressed sample may exceed before the detector
+    /// has had a chance to react.
+    ///
+    /// The envelope lags the signal by the attack and, with `set_detection_decimation`, by up to a
+    /// whole detection period, so a fast transient may pass the threshold largely untouched. While
+    /// a ceiling is set, any frame whose peak would exceed it after the gain is applied is instead
+    /// scaled so that its peak sits exactly at the ceiling. Frames within the ceiling are
+    /// untouched, so this only engages during the detector's latency. Unlike a limiter there is no
+    /// attack or release of its own. Pass infinity (the default) to disable.
+    pub fn set_overshoot_ceiling(&mut self, ceiling: f32) {
+        self.overshoot_ceiling = ceiling;
+    }
+
+    /// The peak amplitude beyond which frames are clamped, or infinity if disabled.
+    pub fn overshoot_ceiling(&self) -> f32 {
+        self.overshoot_ceiling
+    }
+
+    /// Process the given `input` with the **Compressor** bypassed and yield the peak absolute
+    /// difference between the output and the input, which should be exactly `0.0`.
+    ///
+    /// This verifies that the bypass leaks no change in gain, whatever else is configured. The
+    /// output is compared against the input delayed by `latency_frames`, so the first
+    /// `latency_frames` of the output, which hold signal from before `input`, aren't compared.
+    /// The detectors are stepped as during any bypass, and the bypass is restored to its
+    /// previous state afterward.
+    pub fn null_test(&mut self, input: &[F]) -> f32 {
+        let bypass = self.bypass;
+        self.bypass = true;
+        let latency = self.latency_frames();
+        let mut peak = 0.0;
+        for (i, &frame) in input.iter().enumerate() {
+            let output = self.next_frame(frame);
+            if i < latency {
+                continue;
+            }
+            let dry = input[i - latency];
+            for (out, dry) in output.channels().zip(dry.channels()) {
+                let out: f32 = out.to_float_sample().to_sample();
+                let dry: f32 = dry.to_float_sample().to_sample();
+                let difference = (out - dry).abs();
+                if difference > peak {
+                    peak = difference;
+                }
+            }
+        }
+        self.bypass = bypass;
+        peak
+    }
+
+    /// Enable or disable skipping detection for blocks of silence passed to `compress_slice`.
+    ///
+    /// When enabled, a block is skipped if every sample and the detected envelope of every
+    /// channel lie below `SILENCE_AMPLITUDE` (-100 dBFS) and the threshold lies above it, as the
+    /// gain is then already at unity. Rather than stepping the detectors frame by frame, the
+    /// envelope is released analytically over the block and any detection window is advanced
+    /// with silence, so that the detectors are in the same state as if they had processed the
+    /// block and resuming from silence is glitch-free. The block still passes through the signal
+    /// delay line, so the output is unaffected. Defaults to `false`.
+    pub fn set_silence_optimization(&mut self, enabled: bool) {
+        self.silence_optimization = enabled;
+    }
+
+    /// Whether detection is skipped for blocks of silence.
+    pub fn silence_optimization(&self) -> bool {
+        self.silence_optimization
+    }
+
+    /// Set the period over which the gain reduction reported by `current_gain_reduction_db` and
+    /// `meter_snapshot` is smoothed.
+    ///
+    /// The raw gain reduction may jitter from frame to frame, making a meter look noisy. The
+    /// reported value follows it exponentially, retaining roughly a third of the remaining
+    /// difference after the given period, for a steadier reading. Only the meters are smoothed:
+    /// the gain applied by `next_frame` and the like is unaffected. Defaults to `0.0`, i.e. the
+    /// meters report the gain of the most recent frame.
+    pub fn set_meter_smoothing_ms<M: Into<Ms>>(&mut self, ms: M, sample_hz: f64) {
+        let ms: Ms = ms.into();
+        self.meter_smoothing_ms = ms;
+        self.update_meter_smoothing_to_sample_hz(sample_hz);
+    }
+
+    /// Updates the **Compressor**'s meter smoothing in accordance with the current sample_hz.
+    pub fn update_meter_smoothing_to_sample_hz(&mut self, sample_hz: f64) {
+        assert_valid_sample_hz(sample_hz, "update_meter_smoothing_to_sample_hz");
+        self.meter_smoothing_gain = coeff::coeff_for_ms(self.meter_smoothing_ms, sample_hz);
+    }
+
+    /// Set the period over which the range of applied gain is measured for
+    /// `dynamic_range_reduction_db`.
+    ///
+    /// Defaults to `DEFAULT_RANGE_WINDOW_MS`.
+    #[cfg(feature = "metering")]
+    pub fn set_range_window_ms<M: Into<Ms>>(&mut self, ms: M, sample_hz: f64) {
+        let ms: Ms = ms.into();
+        self.range_window_ms = ms;
+        self.update_range_window_to_sample_hz(sample_hz);
+    }
+
+    /// Updates the period of the **Compressor**'s range meter in accordance with the current
+    /// sample_hz.
+    #[cfg(feature = "metering")]
+    pub fn update_range_window_to_sample_hz(&mut self, sample_hz: f64) {
+        assert_valid_sample_hz(sample_hz, "update_range_window_to_sample_hz");
+        let frames = self.range_window_ms.samples(sample_hz) as f32;
+        self.range.set_window_frames(frames);
+    }
+
+    /// Set the period over which `loudness_matched_bypass` matches the loudness of the dry signal
+    /// to that of the compressed signal.
+    ///
+    /// Defaults to `DEFAULT_LOUDNESS_MATCH_MS`.
+    #[cfg(feature = "metering")]
+    pub fn set_loudness_match_ms<M: Into<Ms>>(&mut self, ms: M, sample_hz: f64) {
+        let ms: Ms = ms.into();
+        self.loudness_match_ms = ms;
+        self.update_loudness_match_to_sample_hz(sample_hz);
+    }
+
+    /// Updates the period of the **Compressor**'s loudness matching in accordance with the
+    /// current sample_hz.
+    #[cfg(feature = "metering")]
+    pub fn update_loudness_match_to_sample_hz(&mut self, sample_hz: f64) {
+        assert_valid_sample_hz(sample_hz, "update_loudness_match_to_sample_hz");
+        let frames = self.loudness_match_ms.samples(sample_hz) as f32;
+        self.loudness_match.set_window_frames(frames);
+    }
+
+    /// Set the input gain (or "drive") in decibels, applied to each frame before it reaches both
+    /// the detectors and the output path.
+    ///
+    /// Raising the input gain drives more of the signal over the threshold without changing the
+    /// threshold itself, like the input control of a hardware unit. Unlike the auto gain, which
+    /// trims the signal *after* compression, the input gain changes how much gain reduction is
+    /// applied. The signal flows as follows:
+    ///
+    /// input gain → detection and compression → makeup gain → auto gain → output
+    ///
+    /// The input gain is folded into the gain applied to each frame, so integer frames are only
+    /// rounded once. It is not applied while bypassed. Defaults to `0.0`.
+    pub fn set_input_gain_db(&mut self, gain_db: f32) {
+        self.input_gain = db::db_to_amp(gain_db);
+    }
+
+    /// The input gain in decibels.
+    pub fn input_gain_db(&self) -> f32 {
+        db::amp_to_db(self.input_gain)
+    }
+
+    /// Set the linear makeup gain applied to the compressed signal, restoring the level lost to
+    /// compression without a separate gain stage.
+    ///
+    /// The makeup gain scales every frame after the even gain is determined, alongside the input
+    /// gain, and is ignored while `auto_makeup` is enabled. It is not applied while bypassed.
+    /// Defaults to `1.0`, i.e. no makeup.
+    pub fn set_makeup_gain(&mut self, gain: f32) {
+        self.makeup_gain = gain;
+    }
+
+    /// The linear makeup gain set via `set_makeup_gain`.
+    ///
+    /// See `effective_makeup_gain` for the makeup gain actually applied.
+    pub fn makeup_gain(&self) -> f32 {
+        self.makeup_gain
+    }
+
+    /// Derive the makeup gain from the threshold and ratio rather than using the `makeup_gain`.
+    ///
+    /// The derived makeup gain restores a full-scale envelope to full scale, i.e. it is the
+    /// inverse of the static transfer curve's gain at an envelope of `1.0`. A steady signal near
+    /// full scale therefore leaves the **Compressor** at roughly its original level, while quieter
+    /// signals above the threshold are raised further by the compression of their peaks. As the
+    /// derivation follows the current threshold, ratio and knee, so does the makeup. Defaults to
+    /// `false`.
+    pub fn set_auto_makeup(&mut self, enabled: bool) {
+        self.auto_makeup = enabled;
+    }
+
+    /// Whether the makeup gain is derived from the threshold and ratio.
+    pub fn auto_makeup(&self) -> bool {
+        self.auto_makeup
+    }
+
+    /// The linear makeup gain currently applied, i.e. the derived makeup gain while `auto_makeup`
+    /// is enabled, or the `makeup_gain` otherwise.
+    ///
+    /// The derived makeup gain is `1.0` where the transfer curve removes the signal entirely,
+    /// i.e. for a threshold of zero with an infinite ratio.
+    pub fn effective_makeup_gain(&self) -> f32 {
+        if !self.auto_makeup {
+            return self.makeup_gain;
+        }
+        let full_scale_gain = self.static_gain(1.0);
+        if full_scale_gain > 0.0 { 1.0 / full_scale_gain } else { 1.0 }
+    }
+
+    /// Enable a slow automatic trim of the output toward the given long-term RMS level in
+    /// decibels relative to the `db_reference`, or `None` to disable it.
+    ///
+    /// Unlike a static makeup gain, the auto gain measures the long-term level of the compressed
+    /// signal and adjusts a trim gain to bring it to the target, keeping the output level steady
+    /// across a varied program regardless of how much gain reduction each passage receives. The
+    /// level is measured as a one-pole average of the output power over `auto_gain_ms`, which
+    /// defaults to `DEFAULT_AUTO_GAIN_MS`. The trim never boosts by more than 24 dB, so that
+    /// near silence isn't raised to the target.
+    ///
+    /// The measurement restarts at the target level whenever the target is changed, so the trim
+    /// begins at unity.
+    pub fn set_auto_gain(&mut self, target_db: Option<f32>) {
+        if let Some(db) = target_db {
+            let target_rms = self.db_to_amp(db);
+            self.auto_gain.set_target(target_rms);
+        }
+        self.auto_gain_db = target_db;
+    }
+
+    /// The long-term output level in decibels targeted by the auto gain, if enabled.
+    pub fn auto_gain_db(&self) -> Option<f32> {
+        self.auto_gain_db
+    }
+
+    /// Set the period over which the auto gain measures the long-term output level.
+    ///
+    /// Longer periods level more gently. Periods shorter than a few seconds begin to follow the
+    /// dynamics of the material itself and may pump. Defaults to `DEFAULT_AUTO_GAIN_MS`.
+    pub fn set_auto_gain_ms<M: Into<Ms>>(&mut self, ms: M, sample_hz: f64) {
+        let ms: Ms = ms.into();
+        self.auto_gain_ms = ms;
+        self.update_auto_gain_to_sample_hz(sample_hz);
+    }
+
+    /// Updates the period of the **Compressor**'s auto gain in accordance with the current
+    /// sample_hz.
+    pub fn update_auto_gain_to_sample_hz(&mut self, sample_hz: f64) {
+        assert_valid_sample_hz(sample_hz, "update_auto_gain_to_sample_hz");
+        let frames = self.auto_gain_ms.samples(sample_hz) as f32;
+        self.auto_gain.set_window_frames(frames);
+    }
+
+    /// Hold the threshold the given number of decibels below the long-term RMS level of the
+    /// input, so that the threshold follows the level of the source.
+    ///
+    /// This is useful for levelling varied sources, as quiet and loud passages are both
+    /// compressed rather than only the loud ones. Note that the gain falls linearly with the
+    /// envelope above the threshold, so louder sources still receive somewhat more gain reduction
+    /// in decibels than quieter ones at the same offset. The input level is measured as a one-pole
+    /// average of the input power over `adaptive_threshold_ms`, which defaults to
+    /// `DEFAULT_ADAPTIVE_THRESHOLD_MS`, so that after that period the threshold has covered
+    /// roughly 63.2% of the distance toward a new steady level. The `threshold` itself is ignored
+    /// while the adaptive threshold is enabled.
+    ///
+    /// The measurement restarts from the current threshold, so the effective threshold begins
+    /// where it was.
+    pub fn set_adaptive_threshold(&mut self, offset_db: f32) {
+        let current = match self.adaptive_threshold_db {
+            Some(_) => self.adaptive_threshold(),
+            None => self.threshold_domain.to_amplitude(self.threshold),
+        };
+        self.adaptive_level.set_rms(current * db::db_to_amp(offset_db));
+        self.adaptive_threshold_db = Some(offset_db);
+    }
+
+    /// Disable the adaptive threshold, returning to the fixed `threshold`.
+    pub fn clear_adaptive_threshold(&mut self) {
+        self.adaptive_threshold_db = None;
+    }
+
+    /// The offset in decibels below the long-term input level at which the threshold is held, if
+    /// the adaptive threshold is enabled.
+    pub fn adaptive_threshold_db(&self) -> Option<f32> {
+        self.adaptive_threshold_db
+    }
+
+    /// Set the period over which the adaptive threshold follows the long-term input level.
+    ///
+    /// Defaults to `DEFAULT_ADAPTIVE_THRESHOLD_MS`.
+    pub fn set_adaptive_threshold_ms<M: Into<Ms>>(&mut self, ms: M, sample_hz: f64) {
+        let ms: Ms = ms.into();
+        self.adaptive_threshold_ms = ms;
+        self.update_adaptive_threshold_to_sample_hz(sample_hz);
+    }
+
+    /// Updates the period of the **Compressor**'s adaptive threshold in accordance with the
+    /// current sample_hz.
+    pub fn update_adaptive_threshold_to_sample_hz(&mut self, sample_hz: f64) {
+        assert_valid_sample_hz(sample_hz, "update_adaptive_threshold_to_sample_hz");
+        let frames = self.adaptive_threshold_ms.samples(sample_hz) as f32;
+        self.adaptive_level.set_window_frames(frames);
+    }
+
+    /// The threshold held below the long-term input level by the adaptive threshold, as a linear
+    /// amplitude.
+    fn adaptive_threshold(&self) -> f32 {
+        let offset_db = self.adaptive_threshold_db.unwrap_or(0.0);
+        self.adaptive_level.rms() * db::db_to_amp(-offset_db)
+    }
+
+    /// The threshold toward which the smoothed threshold moves.
+    #[inline]
+    fn target_threshold(&self) -> f32 {
+        match self.adaptive_threshold_db {
+            Some(_) => self.threshold_domain.from_amplitude(self.adaptive_threshold()),
+            None => self.threshold,
+        }
+    }
+
+    /// The gain reduction applied to the most recent frame in decibels, i.e. `20 * log10` of the
+    /// most recent even gain, for a gain reduction meter.
+    ///
+    /// This is `0.0` while no compression is occurring and negative otherwise. It is updated by
+    /// every frame through `next_gain`, and so by `next_frame`, `compress_slice` and the like,
+    /// and is read without any further computation beyond the logarithm, so it may be polled on
+    /// the audio thread and published via an atomic. The gain is that of the detection, range
+    /// and punch, excluding the input, makeup and auto gain. See `meter_snapshot` for the same
+    /// reading as a positive amount alongside the other meters.
+    ///
+    /// The reading is smoothed over the period set via `set_meter_smoothing_ms`, if any.
+    pub fn current_gain_reduction_db(&self) -> f32 {
+        db::amp_to_db(self.meter_gain)
+    }
+
+    /// The difference in decibels between the most and the least gain reduction applied over
+    /// roughly the last `range_window_ms`.
+    ///
+    /// Where the instantaneous gain reduction shows how hard the **Compressor** is working right
+    /// now, this shows how much it is flattening the dynamics of the material: a steady signal
+    /// reads `0.0` no matter how heavily it is compressed, while material whose level varies
+    /// across the threshold reads the spread of gain reduction applied to it.
+    ///
+    /// The range is a relative amount and so is unaffected by the `db_reference`.
+    #[cfg(feature = "metering")]
+    pub fn dynamic_range_reduction_db(&self) -> f32 {
+        self.range.range_db()
+    }
+
+    /// Enable or disable measuring the loudness removed by the **Compressor** for
+    /// `loudness_reduction_lu`.
+    ///
+    /// The meter K-weights both the input and the output, which costs four biquad filters per
+    /// frame, and so is disabled by default. Enabling it restarts the measurement.
+    #[cfg(feature = "metering")]
+    pub fn set_loudness_meter(&mut self, enabled: bool, sample_hz: f64) {
+        assert_valid_sample_hz(sample_hz, "set_loudness_meter");
+        self.loudness_reduction = if enabled {
+            Some(LoudnessReductionMeter::new(sample_hz))
+        } else {
+            None
+        };
+    }
+
+    /// Whether the loudness removed by the **Compressor** is being measured.
+    #[cfg(feature = "metering")]
+    pub fn loudness_meter(&self) -> bool {
+        self.loudness_reduction.is_some()
+    }
+
+    /// How much loudness the **Compressor** is removing, in loudness units (LU), as measured
+    /// following ITU-R BS.1770.
+    ///
+    /// The input and output are K-weighted and measured over 400ms blocks overlapping by 75%.
+    /// Blocks in which the input is quieter than `LOUDNESS_ABSOLUTE_GATE_LUFS` (-70 LUFS), or more
+    /// than `LOUDNESS_RELATIVE_GATE_LU` (10 LU) below the loudness measured so far, are gated
+    /// out. The power of the remaining blocks is averaged over roughly the last
+    /// `LOUDNESS_REDUCTION_WINDOW_MS`. For a steady tone this matches the gain
+    /// reduction in decibels, while for program material it reflects the loudness actually lost
+    /// rather than the peaks of the gain reduction.
+    ///
+    /// Yields `0.0` while the meter is disabled (see `set_loudness_meter`) and until its first
+    /// block has passed the gates.
+    #[cfg(feature = "metering")]
+    pub fn loudness_reduction_lu(&self) -> f32 {
+        self.loudness_reduction.as_ref().map(|meter| meter.reduction_lu()).unwrap_or(0.0)
+    }
+
+    /// The peak absolute sample of the most recently produced frame as a linear amplitude, e.g.
+    /// for an output level meter.
+    ///
+    /// Yields `0.0` until a frame has been produced.
+    pub fn last_output_peak(&self) -> f32 {
+        self.last_output_peak
+    }
+
+    /// Set the level in decibels relative to full scale at or beyond which an input sample is
+    /// counted as clipped by `input_clip_count`.
+    ///
+    /// Full scale is the largest positive value of the `Frame`'s sample format, so that e.g. an
+    /// `i16` sample of `32767` counts as clipped at the default of `0.0`. A slightly negative
+    /// level such as `-0.1` also catches material that was clipped and then attenuated.
+    pub fn set_input_clip_level_db(&mut self, level_db: f32) {
+        self.input_clip_level = Self::full_scale() * db::db_to_amp(level_db);
+    }
+
+    /// The level in decibels relative to full scale at which an input sample counts as clipped.
+    pub fn input_clip_level_db(&self) -> f32 {
+        db::amp_to_db(self.input_clip_level / Self::full_scale())
+    }
+
+    /// Whether any input sample has reached the clip level since the meters were last reset.
+    ///
+    /// A clipped input slams the detected envelope and so is worth flagging when gain staging.
+    /// The input is checked on its way to the detectors, before the input gain. Cleared by
+    /// `reset_meters`.
+    pub fn input_clipped(&self) -> bool {
+        self.input_clip_count > 0
+    }
+
+    /// The number of input frames in which any sample has reached the clip level since the
+    /// meters were last reset.
+    pub fn input_clip_count(&self) -> u64 {
+        self.input_clip_count
+    }
+
+    /// Capture the current state of every meter as a single `Copy` **MeterSnapshot**.
+    ///
+    /// Producing a snapshot is cheap and never allocates, so it may be taken on the audio thread
+    /// after each processed block. The intended pattern for a GUI is for the audio thread to
+    /// publish each snapshot through a lock-free channel that it owns, such as a triple buffer or
+    /// a single-slot atomic exchange, and for the UI thread to read only the latest one. The
+    /// **CompressorParams** are likewise `Copy` and may be published the same way via `params`.
+    #[cfg(feature = "metering")]
+    pub fn meter_snapshot(&self) -> MeterSnapshot {
+        MeterSnapshot {
+            gain_reduction_db: -db::amp_to_db(self.meter_gain),
+            envelope: self.loudest_envelope(),
+            output_peak: self.last_output_peak,
+            dynamic_range_reduction_db: self.dynamic_range_reduction_db(),
+            loudness_reduction_lu: self.loudness_reduction_lu(),
+            frames_processed: self.frames_processed,
+        }
+    }
+
+    /// Reset the range meter, the loudness reduction meter, the output peak and the input clip
+    /// count, without affecting the signal.
+    pub fn reset_meters(&mut self) {
+        #[cfg(feature = "metering")]
+        {
+            self.range.reset();
+            if let Some(ref mut meter) = self.loudness_reduction {
+                meter.reset();
+            }
+        }
+        self.meter_gain = self.last_gain;
+        self.last_output_peak = 0.0;
+        self.input_clip_count = 0;
+    }
+
+    /// Set how the channels of each frame are presented to the envelope detector.
+    ///
+    /// Defaults to `DetectionMode::PerChannel`.
+    pub fn set_detection_mode(&mut self, mode: DetectionMode) {
+        self.sidechain.set_mode(mode);
+    }
+
+    /// How the channels of each frame are presented to the envelope detector.
+    pub fn detection_mode(&self) -> DetectionMode {
+        self.sidechain.mode()
+    }
+
+    /// Set the weight of each channel in the sum detected by the `MonoSum` and `SumAbs`
+    /// **DetectionMode**s, e.g. `0.0` to exclude an LFE channel from detection.
+    ///
+    /// Each channel is scaled by its weight before being summed, and the `MonoSum` is scaled by
+    /// the reciprocal of the total weight rather than of the channel count, so that a signal
+    /// common to every weighted channel is still detected at its per-channel level. Only the
+    /// detection is weighted: every channel still receives the applied gain. The weights have no
+    /// effect in the `PerChannel` mode. Defaults to `1.0` for every channel.
+    ///
+    /// **Panics** if the number of weights differs from the `Frame`'s number of channels.
+    pub fn set_sum_weights(&mut self, weights: &[f32]) {
+        assert_eq!(weights.len(), F::n_channels(), "`weights` must have one weight per channel");
+        self.sidechain.set_sum_weights(weights);
+    }
+
+    /// The weight of each channel in the sum detected by the `MonoSum` and `SumAbs`
+    /// **DetectionMode**s.
+    pub fn sum_weights(&self) -> &[f32] {
+        self.sidechain.sum_weights()
+    }
+
+    /// Enable or disable detecting only the first channel, e.g. for a mono source duplicated
+    /// across a stereo **Compressor**.
+    ///
+    /// The first channel is duplicated across every channel on its way to the detector, so that
+    /// every channel is guaranteed to be detected identically and so to yield bit-identical gains.
+    /// The gain is applied to every channel as usual. This is only valid when the channels are
+    /// known to be identical, as the content of every other channel is ignored by detection.
+    /// The duplicated channel is then presented according to the `DetectionMode`. Defaults to
+    /// `false`.
+    ///
+    /// This is equivalent to `set_key_channel(Some(0))` when enabled and `set_key_channel(None)`
+    /// when disabled.
+    pub fn set_mono_detect(&mut self, enabled: bool) {
+        self.set_key_channel(if enabled { Some(0) } else { None });
+    }
+
+    /// Whether only the first channel is detected.
+    pub fn mono_detect(&self) -> bool {
+        self.sidechain.key_channel() == Some(0)
+    }
+
+    /// Set the channel from which the envelope is detected for every channel, or `None` to detect
+    /// every channel as usual.
+    ///
+    /// When `Some`, the given channel is duplicated across every channel on its way to the
+    /// detector, so that every channel receives the gain derived from that channel alone, e.g. to
+    /// compress a whole surround mix according to its centre (dialogue) channel. The content of
+    /// every other channel is ignored by detection. Defaults to `None`.
+    ///
+    /// **Panics** if the index is not less than the `Frame`'s number of channels.
+    pub fn set_key_channel(&mut self, index: Option<usize>) {
+        if let Some(i) = index {
+            assert!(i < F::n_channels(), "key channel {} out of range for {} channels",
+                    i, F::n_channels());
+        }
+        self.sidechain.set_key_channel(index);
+    }
+
+    /// The channel from which the envelope is detected for every channel, if any.
+    pub fn key_channel(&self) -> Option<usize> {
+        self.sidechain.key_channel()
+    }
+
+    /// Set the amount by which the detectors of the channels are linked, from `0.0` to `1.0`.
+    ///
+    /// Before the gain is computed, each channel's detected envelope is blended toward the
+    /// envelope of the loudest channel by this amount. At `1.0` every channel shares the loudest
+    /// envelope, so every channel yields the same gain even when only one of them is loud. At
+    /// `0.0` each channel is detected independently. This links detection itself, whereas the
+    /// **EvenGainFunction** combines the resulting per-channel gains. Values outside of `0.0` to
+    /// `1.0` are clamped. Defaults to `0.0`.
+    pub fn set_detector_link(&mut self, amount: f32) {
+        self.detector_link = if amount < 0.0 { 0.0 } else if amount > 1.0 { 1.0 } else { amount };
+    }
+
+    /// The amount by which the detectors of the channels are linked.
+    pub fn detector_link(&self) -> f32 {
+        self.detector_link
+    }
+
+    /// Enable or disable an ITU-R BS.1770 K-weighting filter on the signal on its way to the
+    /// envelope detector.
+    ///
+    /// K-weighting is a two-stage biquad pre-filter (a high shelf followed by a high-pass) that
+    /// approximates the ear's sensitivity, so that the **Compressor** responds to perceived
+    /// loudness rather than raw level. High frequency content drives more gain reduction than
+    /// low frequency content of the same level. It only affects detection. Defaults to `false`.
+    ///
+    /// See **BiquadCoefficients::k_weighting_shelf** and
+    /// **BiquadCoefficients::k_weighting_highpass** for the filter coefficients.
+    #[cfg(feature = "sidechain-filter")]
+    pub fn set_k_weighting(&mut self, enabled: bool, sample_hz: f64) {
+        assert_valid_sample_hz(sample_hz, "set_k_weighting");
+        self.sidechain.set_k_weighting(enabled, sample_hz);
+    }
+
+    /// Whether the K-weighting filter is applied to the signal on its way to the envelope
+    /// detector.
+    #[cfg(feature = "sidechain-filter")]
+    pub fn k_weighting(&self) -> bool {
+        self.sidechain.k_weighting()
+    }
+
+    /// Emphasise the band between `low_hz` and `high_hz` by `gain_db` on the signal's way to the
+    /// envelope detector, biasing detection toward that band.
+    ///
+    /// The emphasis is a second-order peaking filter applied after any K-weighting and before the
+    /// sidechain high-pass filter. Like the other sidechain filters it only affects detection: the
+    /// signal to which the gain is applied is never filtered. Boosting e.g. 5 to 8 kHz makes
+    /// sibilant content drive more gain reduction than other content of the same level, giving
+    /// de-essing-like behaviour without splitting the signal into bands.
+    #[cfg(feature = "sidechain-filter")]
+    pub fn set_detection_emphasis(&mut self,
+                                  low_hz: f64,
+                                  high_hz: f64,
+                                  gain_db: f32,
+                                  sample_hz: f64)
+    {
+        assert_valid_sample_hz(sample_hz, "set_detection_emphasis");
+        self.sidechain.set_emphasis(Some((low_hz, high_hz, gain_db)), sample_hz);
+    }
+
+    /// Remove the emphasis set via `set_detection_emphasis`.
+    #[cfg(feature = "sidechain-filter")]
+    pub fn clear_detection_emphasis(&mut self, sample_hz: f64) {
+        assert_valid_sample_hz(sample_hz, "clear_detection_emphasis");
+        self.sidechain.set_emphasis(None, sample_hz);
+    }
+
+    /// The emphasised band and its gain as `(low_hz, high_hz, gain_db)`, if enabled.
+    #[cfg(feature = "sidechain-filter")]
+    pub fn detection_emphasis(&self) -> Option<(f64, f64, f32)> {
+        self.sidechain.emphasis()
+    }
+
+    /// Set the cutoff of a high-pass filter applied to the signal on its way to the envelope
+    /// detector, or `None` to disable it.
+    ///
+    /// The filter is a second-order Butterworth applied after the channels are combined according
+    /// to the `DetectionMode` and after any K-weighting. It only affects detection, making the
+    /// **Compressor** less sensitive to low-frequency energy without filtering the signal itself.
+    #[cfg(feature = "sidechain-filter")]
+    pub fn set_sidechain_hpf(&mut self, cutoff_hz: Option<f64>, sample_hz: f64) {
+        assert_valid_sample_hz(sample_hz, "set_sidechain_hpf");
+        self.sidechain.set_hpf_hz(cutoff_hz, sample_hz);
+    }
+
+    /// The cutoff of the sidechain high-pass filter, if enabled with the same cutoff for every
+    /// channel.
+    #[cfg(feature = "sidechain-filter")]
+    pub fn sidechain_hpf_hz(&self) -> Option<f64> {
+        self.sidechain.hpf_hz()
+    }
+
+    /// Set the cutoff of the sidechain high-pass filter for the detection of the channel at the
+    /// given index alone, or `None` to disable it for that channel.
+    ///
+    /// Each channel's filter has its own state, so e.g. the left and right detection paths of a
+    /// stereo sidechain may be treated differently. Only that channel's contribution to the
+    /// detected envelope is filtered, before the channels are linked via the `detector_link` and
+    /// the **EvenGainFunction**. As with `set_sidechain_hpf`, the filter follows the
+    /// `DetectionMode`, so the channels only differ in what they detect under `PerChannel`
+    /// detection.
+    ///
+    /// **Panics** if `channel` is not less than the `Frame`'s number of channels.
+    #[cfg(feature = "sidechain-filter")]
+    pub fn set_sidechain_hpf_channel(&mut self,
+                                     channel: usize,
+                                     cutoff_hz: Option<f64>,
+                                     sample_hz: f64)
+    {
+        assert!(channel < F::n_channels(), "sidechain channel {} out of range for {} channels",
+                channel, F::n_channels());
+        assert_valid_sample_hz(sample_hz, "set_sidechain_hpf_channel");
+        self.sidechain.set_hpf_channel_hz(channel, cutoff_hz, sample_hz);
+    }
+
+    /// The cutoff of the sidechain high-pass filter for the channel at the given index, if
+    /// enabled.
+    ///
+    /// **Panics** if `channel` is not less than the `Frame`'s number of channels.
+    #[cfg(feature = "sidechain-filter")]
+    pub fn sidechain_hpf_channel_hz(&self, channel: usize) -> Option<f64> {
+        self.sidechain.hpf_channel_hz(channel)
+    }
+
+    /// Set the number of frames by which the signal is delayed before the gain is applied to it.
+    ///
+    /// Steep sidechain filters add phase delay to the detection path, causing the gain to arrive
+    /// slightly late. Setting this to the group delay of the detection path re-aligns the gain
+    /// with the signal it was detected from.
+    ///
+    /// Delaying the signal relative to the detector is the same mechanism that a lookahead relies
+    /// upon: any delay beyond that of the detection path lets the gain begin moving before the
+    /// signal arrives, and every frame of delay adds a frame of latency to the output.
+    ///
+    /// If the delay is shortened, the oldest delayed frames are dropped. If it is lengthened, the
+    /// delay line is padded with frames at equilibrium.
+    pub fn set_sidechain_delay_frames(&mut self, frames: usize) {
+        let len = self.signal_delay.len();
+        if len > frames {
+            for _ in 0..len - frames {
+                self.signal_delay.pop_front();
+            }
+        } else {
+            for _ in 0..frames - len {
+                self.signal_delay.push_front(F::equilibrium());
+            }
+        }
+    }
+
+    /// The number of frames by which the signal is delayed before the gain is applied to it.
+    pub fn sidechain_delay_frames(&self) -> usize {
+        self.signal_delay.len()
+    }
+
+    /// The latency introduced by the **Compressor** in frames, i.e. the number of frames by which
+    /// the output lags the input.
+    ///
+    /// This is currently the signal delay set via `set_sidechain_delay_frames`.
+    pub fn latency_frames(&self) -> usize {
+        self.signal_delay.len()
+    }
+
+    /// The latency introduced by each stage of the **Compressor** in frames.
+    ///
+    /// The signal delay (the `lookahead`) and the lag of the detector's RMS window and decimation
+    /// (the `detection`) contribute to the `total`. See **LatencyBreakdown** for details.
+    pub fn latency_breakdown(&self) -> LatencyBreakdown {
+        let lookahead = self.signal_delay.len();
+        let oversampling = 0;
+        let detector = &self.envelope_detector;
+        let detection = detector.window_frames() / 2 + (detector.decimation() - 1);
+        LatencyBreakdown {
+            lookahead: lookahead,
+            oversampling: oversampling,
+            detection: detection,
+            total: lookahead + oversampling + detection,
+        }
+    }
+
+    /// A rough estimate of the processing cost of the current configuration.
+    ///
+    /// The estimate is relative rather than absolute (see **CostEstimate**), e.g. a **Hann** RMS
+    /// window or the loudness meter raise it, while a shorter **Hann** window or disabling the
+    /// sidechain filters lower it.
+    pub fn estimated_cost(&self) -> CostEstimate {
+        let mut metering = 0.0;
+        #[cfg(feature = "metering")]
+        {
+            if self.loudness_reduction.is_some() {
+                metering += 4.0;
+            }
+        }
+        if self.auto_gain_db.is_some() {
+            metering += 0.5;
+        }
+        if self.adaptive_threshold_db.is_some() {
+            metering += 0.5;
+        }
+        CostEstimate {
+            detection: self.envelope_detector.relative_cost(),
+            sidechain: self.sidechain.n_biquads() as f32,
+            gain: 1.0,
+            metering: metering,
+            delay_frames: self.signal_delay.len(),
+        }
+    }
+
+    /// The set of optional stages currently engaged, e.g. for a UI to display the active signal
+    /// chain.
+    pub fn enabled_stages(&self) -> StageFlags {
+        let mut stages = StageFlags::empty();
+        #[cfg(feature = "sidechain-filter")]
+        {
+            stages.set(StageFlags::SIDECHAIN_HPF, self.sidechain.hpf_enabled());
+            stages.set(StageFlags::SIDECHAIN_EMPHASIS, self.sidechain.emphasis().is_some());
+            stages.set(StageFlags::K_WEIGHTING, self.sidechain.k_weighting());
+        }
+        stages.set(StageFlags::LOOKAHEAD, !self.signal_delay.is_empty());
+        stages.set(StageFlags::INPUT_GAIN, self.input_gain != 1.0);
+        stages.set(StageFlags::MAKEUP_GAIN, self.auto_makeup || self.makeup_gain != 1.0);
+        stages.set(StageFlags::RELEASE_STAGES, self.release_stages.is_some());
+        stages.set(StageFlags::PUNCH, self.punch_frames > 0);
+        stages.set(StageFlags::RANGE, self.max_reduction_db.is_some() || self.min_gain > 0.0);
+        stages.set(StageFlags::ADAPTIVE_THRESHOLD, self.adaptive_threshold_db.is_some());
+        stages.set(StageFlags::AUTO_GAIN, self.auto_gain_db.is_some());
+        stages.set(StageFlags::OVERSHOOT_CEILING, self.overshoot_ceiling != std::f32::INFINITY);
+        stages.set(StageFlags::DITHER, self.quantizer.dither() != DitherKind::None);
+        #[cfg(feature = "metering")]
+        stages.set(StageFlags::LOUDNESS_METER, self.loudness_reduction.is_some());
+        stages.set(StageFlags::BYPASS, self.bypass);
+        stages
+    }
+
+    /// A multi-line, human-readable summary of every parameter and which of the optional stages
+    /// are engaged, e.g. for logging or attaching to a bug report.
+    ///
+    /// Each line is of the form `name: value`. Levels are given in decibels relative to the
+    /// `db_reference` and durations in milliseconds. The exact wording is intended for people
+    /// rather than parsing and may change.
+    pub fn describe(&self) -> String {
+        use std::fmt::Write;
+
+        fn on_off(enabled: bool) -> &'static str {
+            if enabled { "on" } else { "off" }
+        }
+
+        let params = self.params();
+        let mut s = String::new();
+        let detector = self.envelope_detector.description();
+        writeln!(s, "detector: {}", detector).unwrap();
+        writeln!(s, "threshold: {} dB ({:?})", self.threshold_db(), self.threshold_domain).unwrap();
+        writeln!(s, "ratio: {}:1", params.ratio).unwrap();
+        writeln!(s, "knee: {} dB", self.knee_db).unwrap();
+        writeln!(s, "direction: {:?}", self.direction).unwrap();
+        writeln!(s, "attack: {} ms ({:?})", params.attack_ms, self.attack_mode()).unwrap();
+        writeln!(s, "release: {} ms ({:?})", params.release_ms, self.release_mode()).unwrap();
+        if let Some((fast, slow, crossover_db)) = self.release_stages {
+            writeln!(s, "release stages: {} ms beyond {} dB of reduction, then {} ms",
+                     fast.ms(), crossover_db, slow.ms()).unwrap();
+        }
+        if let Some(window_ms) = params.window_ms {
+            writeln!(s, "window: {} ms", window_ms).unwrap();
+        }
+        match self.max_reduction_db {
+            Some(db) => writeln!(s, "range: {} dB (knee {} dB)", db, self.range_knee_db).unwrap(),
+            None => writeln!(s, "range: unlimited").unwrap(),
+        }
+        if self.min_gain > 0.0 {
+            writeln!(s, "min gain: {} dB", db::amp_to_db(self.min_gain)).unwrap();
+        }
+        if self.overshoot_ceiling != std::f32::INFINITY {
+            writeln!(s, "overshoot ceiling: {}", self.overshoot_ceiling).unwrap();
+        }
+        writeln!(s, "input gain: {} dB", self.input_gain_db()).unwrap();
+        let makeup_db = db::amp_to_db(self.effective_makeup_gain());
+        let makeup_kind = if self.auto_makeup { "auto" } else { "manual" };
+        writeln!(s, "makeup gain: {} dB ({})", makeup_db, makeup_kind).unwrap();
+        match self.auto_gain_db {
+            Some(db) => writeln!(s, "auto gain: {} dB over {} ms", db, self.auto_gain_ms.ms())
+                .unwrap(),
+            None => writeln!(s, "auto gain: off").unwrap(),
+        }
+        match self.adaptive_threshold_db {
+            Some(db) => writeln!(s, "adaptive threshold: {} dB over {} ms",
+                                 db, self.adaptive_threshold_ms.ms()).unwrap(),
+            None => writeln!(s, "adaptive threshold: off").unwrap(),
+        }
+        writeln!(s, "lookahead: {} frames", self.sidechain_delay_frames()).unwrap();
+        writeln!(s, "latency: {} frames", self.latency_frames()).unwrap();
+        writeln!(s, "detection mode: {:?}", self.detection_mode()).unwrap();
+        if let Some(index) = self.key_channel() {
+            writeln!(s, "key channel: {}", index).unwrap();
+        }
+        writeln!(s, "detector link: {}", self.detector_link).unwrap();
+        writeln!(s, "sum weights: {:?}", self.sum_weights()).unwrap();
+        #[cfg(feature = "sidechain-filter")]
+        {
+            writeln!(s, "k-weighting: {}", on_off(self.k_weighting())).unwrap();
+            if let Some((low_hz, high_hz, gain_db)) = self.detection_emphasis() {
+                writeln!(s, "detection emphasis: {} dB from {} to {} Hz",
+                         gain_db, low_hz, high_hz).unwrap();
+            }
+            if let Some(hz) = self.sidechain_hpf_hz() {
+                writeln!(s, "sidechain hpf: {} Hz", hz).unwrap();
+            }
+        }
+        writeln!(s, "punch: {} ms", self.punch_ms.ms()).unwrap();
+        writeln!(s, "parameter smoothing: {} ms", self.parameter_smoothing_ms.ms()).unwrap();
+        writeln!(s, "meter smoothing: {} ms", self.meter_smoothing_ms.ms()).unwrap();
+        #[cfg(feature = "metering")]
+        writeln!(s, "loudness meter: {}", on_off(self.loudness_meter())).unwrap();
+        writeln!(s, "silence optimization: {}", on_off(self.silence_optimization)).unwrap();
+        writeln!(s, "dither: {:?}", self.dither()).unwrap();
+        writeln!(s, "frozen: {}", on_off(self.frozen)).unwrap();
+        writeln!(s, "bypass: {}", on_off(self.bypass)).unwrap();
+        s
+    }
+
+    /// The latency introduced by the **Compressor** in milliseconds at the given sample_hz.
+    pub fn latency_ms(&self, sample_hz: f64) -> f64 {
+        time::Samples(self.latency_frames() as i64).ms(sample_hz)
+    }
+
+    /// Allocate all of the **Compressor**'s internal buffers to the given maximum sizes up front.
+    ///
+    /// This covers the envelope detector's window (if it has one) and the signal delay line.
+    /// Once reserved, changing the window duration (i.e. via `set_window_ms` or a change in sample
+    /// rate) or the sidechain delay within these sizes never allocates, making such changes safe
+    /// to perform on a real-time audio thread.
+    ///
+    /// There is no capacity to reserve for a gain reduction history, as the **Compressor** keeps
+    /// none.
+    pub fn reserve(&mut self, max_window_frames: usize, max_delay_frames: usize, sample_hz: f64) {
+        assert_valid_sample_hz(sample_hz, "reserve");
+        self.envelope_detector.reserve_window_frames(max_window_frames, sample_hz);
+        let len = self.signal_delay.len();
+        if max_delay_frames > len {
+            self.signal_delay.reserve(max_delay_frames - len);
+        }
+    }
+
+    /// Consumes the **Compressor** and returns it with all internal buffers allocated to the
+    /// given maximum sizes.
+    ///
+    /// See `reserve` for details.
+    pub fn with_reserved(mut self,
+                         max_window_frames: usize,
+                         max_delay_frames: usize,
+                         sample_hz: f64) -> Self
+    {
+        self.reserve(max_window_frames, max_delay_frames, sample_hz);
+        self
+    }
+
+    /// Set the kind of dither added when converting the compressed result back to an integer
+    /// sample format.
+    ///
+    /// Dither avoids the quantization distortion that gain changes can expose at low bit depths.
+    /// It has no effect when compressing floating point frames. Defaults to `DitherKind::None`.
+    pub fn set_dither(&mut self, dither: DitherKind) {
+        self.quantizer.set_dither(dither);
+    }
+
+    /// The kind of dither added when converting the compressed result back to an integer sample
+    /// format.
+    pub fn dither(&self) -> DitherKind {
+        self.quantizer.dither()
+    }
+
+    /// Clear the state derived from the detected level while keeping the level itself, e.g. to
+    /// recover from a glitch in the gain stage without a jump in the level reading.
+    ///
+    /// The smoothed threshold and ratio snap to their targets, the punch and release stage
+    /// restart, the last gain returns to unity and the auto gain's trim restarts at unity. The
+    /// envelope detector, including the contents of any RMS window, is left untouched, so the
+    /// detected `envelope` continues exactly where it was and the gain is recomputed from it on
+    /// the next frame. To also clear the detected level, seed it with silence via `warm_start`.
+    pub fn soft_reset(&mut self) {
+        self.smoothed_threshold = self.target_threshold();
+        self.smoothed_slope = self.slope;
+        self.over_threshold_frames = 0;
+        self.last_gain = 1.0;
+        if let Some(db) = self.auto_gain_db {
+            let target_rms = self.db_to_amp(db);
+            self.auto_gain.set_target(target_rms);
+        }
+    }
+
+    /// Clear all of the state accumulated from previous frames, e.g. before reusing the
+    /// **Compressor** for an unrelated stream, while keeping every setting.
+    ///
+    /// The envelope detector (including any RMS window and held release), the sidechain filters
+    /// and the signal delay line return to silence, with the delay keeping its length. Everything
+    /// cleared by `soft_reset` and `reset_meters` is cleared, as are the loudness match, the
+    /// `frames_processed` and the dither noise. The adaptive threshold's measurement restarts
+    /// from the `threshold`, as it does when enabled. Any scheduled parameter changes and ratio
+    /// ramp are discarded. Afterwards the **Compressor** yields the same output as a freshly
+    /// constructed one with the same settings.
+    pub fn reset(&mut self) {
+        self.envelope_detector.reset();
+        self.sidechain.reset();
+        for frame in self.signal_delay.iter_mut() {
+            *frame = F::equilibrium();
+        }
+        let adaptive_level = match self.adaptive_threshold_db {
+            Some(offset_db) => {
+                self.threshold_domain.to_amplitude(self.threshold) * db::db_to_amp(offset_db)
+            },
+            None => 0.0,
+        };
+        self.adaptive_level.set_rms(adaptive_level);
+        #[cfg(feature = "automation")]
+        self.automation.clear();
+        self.ratio_ramp = None;
+        self.soft_reset();
+        self.select_release_stage();
+        self.reset_meters();
+        #[cfg(feature = "metering")]
+        self.loudness_match.reset();
+        self.quantizer.reset();
+        self.frames_processed = 0;
+    }
+
+    /// Seed the envelope detector as though it had settled on the given per-channel envelope
+    /// `level`, e.g. the expected level of the program material.
+    ///
+    /// This avoids the gain ramp that otherwise occurs while the envelope rises from silence, so
+    /// that the first frames are already compressed by the correct amount. `level` is given in
+    /// the same terms as the detected envelope, i.e. the peak amplitude for peak detection or the
+    /// RMS for RMS detection.
+    pub fn warm_start(&mut self, level: F::Float) {
+        self.envelope_detector.set_envelope(level);
+    }
+
+    /// Prepare to compress the given loop seamlessly, by running the **Compressor** over the tail
+    /// of the loop and discarding the output.
+    ///
+    /// Without priming, the state at the start of the loop is that of whatever came before
+    /// (typically silence), while on every later pass it is that left by the loop's end, so the
+    /// gain jumps at each loop point. Priming leaves the detector, lookahead and smoothing state
+    /// as though the loop had just played, so compressing `frames` after priming yields the same
+    /// gain across the loop point as within the loop. The tail spans at least the whole loop and
+    /// long enough for the slower of the attack and release to settle, plus the lookahead,
+    /// wrapping around the loop as many times as necessary. For a steady loop, priming again
+    /// before each pass is unnecessary, as each pass leaves the state for the next.
+    ///
+    /// The primed frames pass through the meters like any others.
+    pub fn prime_for_loop(&mut self, frames: &[F]) {
+        let len = frames.len();
+        if len == 0 {
+            return;
+        }
+        let settle = self.ballistics_settling_frames();
+        let tail = if settle > len { settle } else { len } + self.latency_frames();
+        let start = (len - tail % len) % len;
+        for &frame in frames.iter().cycle().skip(start).take(tail) {
+            self.next_frame(frame);
+        }
+    }
+
+    /// The most recently detected per-channel envelope.
+    pub fn envelope(&self) -> F::Float {
+        self.envelope_detector.last_envelope()
+    }
+
+    /// The loudest channel of the most recently detected envelope.
+    fn loudest_envelope(&self) -> f32 {
+        self.envelope().channels().fold(0.0, |max, sample| {
+            let s: f32 = sample.to_sample();
+            if s > max { s } else { max }
+        })
+    }
+
+    /// Steps forward only the detectors by each of the given `input` frames, writing the largest
+    /// channel of the detected envelope for each frame to the corresponding element of `env_out`.
+    ///
+    /// No gain is produced and no signal passes through the **Compressor**, making this suitable
+    /// for drawing the level curve the **Compressor** reacts to, e.g. across a whole file. The
+    /// envelope is in the same terms as `envelope`.
+    ///
+    /// **Panics** if `input` and `env_out` differ in length.
+    pub fn detect_envelope_slice(&mut self, input: &[F], env_out: &mut [f32]) {
+        assert_eq!(input.len(), env_out.len(), "`input` and `env_out` must be the same length");
+        for (frame, env) in input.iter().zip(env_out.iter_mut()) {
+            self.meter_input(*frame);
+            let key_frame = self.sidechain.next(self.trim_input(*frame));
+            let env_frame = self.envelope_detector.next_envelope(key_frame);
+            let env_frame = self.link_detectors(env_frame);
+            *env = env_frame.channels().fold(0.0, |max, sample| {
+                let s: f32 = sample.to_sample();
+                if s > max { s } else { max }
+            });
+        }
+    }
+
+    /// Steps forward the detectors using the given frame and determines the gain per-channel,
+    /// yielding the result as a `Frame`.
+    pub fn next_gain_per_channel(&mut self, next_frame: F) -> F::Float {
+        let (input, threshold, slope) = self.step_parameters(next_frame);
+        self.select_release_stage();
+        let key_frame = self.sidechain.next(input);
+        let env_frame = self.envelope_detector.next_envelope(key_frame);
+        let env_frame = self.link_detectors(env_frame);
+        let knee = self.knee().to_sample();
+        env_frame.map(|s| self.transfer_gain(s, threshold, slope, knee))
+    }
+
+    /// The gain that the transfer curve in the current `direction` applies to the given envelope.
+    #[inline]
+    fn transfer_gain<S>(&self, envelope: S, threshold: S, slope: S, knee: S) -> S
+        where S: FloatSample,
+    {
+        match self.direction {
+            Direction::Compress => calc_knee_gain(envelope, threshold, slope, knee),
+            Direction::Expand => calc_expand_gain(envelope, threshold, slope),
+        }
+    }
+
+    /// The ratio between the threshold and either edge of the soft knee.
+    fn knee(&self) -> f32 {
+        db::db_to_amp(self.knee_db / 2.0)
+    }
+
+    /// Convert a level in decibels relative to the `db_reference` to a linear amplitude.
+    fn db_to_amp(&self, level_db: f32) -> f32 {
+        self.db_reference * db::db_to_amp(level_db)
+    }
+
+    /// Convert a linear amplitude to a level in decibels relative to the `db_reference`.
+    fn amp_to_db(&self, amp: f32) -> f32 {
+        db::amp_to_db(amp / self.db_reference)
+    }
+
+    /// The gain that the **Compressor**'s static transfer curve applies to an envelope at the
+    /// given linear amplitude.
+    fn static_gain(&self, level: f32) -> f32 {
+        let threshold = self.threshold_domain.to_amplitude(self.threshold);
+        match self.direction {
+            Direction::Compress => knee_gain(level, threshold, self.slope, self.knee_db),
+            Direction::Expand => calc_expand_gain(level, threshold, self.slope),
+        }
+    }
+
+    /// The gain to which the **Compressor** converges for a sustained input whose detected
+    /// envelope is at the given linear amplitude on every channel.
+    ///
+    /// This is the static transfer curve alone, ignoring the attack and release of the detector,
+    /// the punch and any parameter smoothing in progress. `input_level` is given in the same terms
+    /// as the detected envelope, i.e. the peak amplitude for peak detection or the RMS for RMS
+    /// detection.
+    pub fn steady_state_gain(&self, input_level: f32) -> f32 {
+        self.static_gain(input_level.abs())
+    }
+
+    /// The effective ratio of the compression being applied right now, i.e. how many decibels the
+    /// input must rise for the output to rise by one decibel at the current envelope.
+    ///
+    /// The ratio is measured from the static transfer curve at the loudest channel's detected
+    /// envelope, using the threshold and ratio currently in effect and the `max_reduction_db`.
+    /// Yields `1.0` while the envelope is below the threshold and any soft knee. As the gain
+    /// falls linearly with the envelope above the threshold, the effective ratio grows beyond the
+    /// nominal ratio as the envelope rises, and yields infinity where the output no longer rises
+    /// with the input at all. Once the reduction is held at the `max_reduction_db`, the gain no
+    /// longer changes and the ratio returns to `1.0`.
+    ///
+    /// When expanding, this yields `1.0` while the envelope is at or above the threshold, and
+    /// below it the inverse of the expansion ratio, e.g. `0.25` for 4:1, as the output falls by
+    /// four decibels for each decibel of input.
+    pub fn instantaneous_ratio(&self) -> f32 {
+        const DELTA_DB: f32 = 0.01;
+        let envelope = self.loudest_envelope();
+        let threshold = self.threshold_domain.to_amplitude(self.smoothed_threshold);
+        let knee = self.knee();
+        let inactive = match self.direction {
+            Direction::Compress => !(envelope > threshold / knee),
+            Direction::Expand => !(envelope < threshold),
+        };
+        if inactive {
+            return 1.0;
+        }
+        let slope = self.smoothed_slope;
+        let output_db = |level: f32| {
+            let gain = self.transfer_gain(level, threshold, slope, knee);
+            let gain: <F::Sample as Sample>::Float = gain.to_sample();
+            let gain: f32 = self.limit_reduction(gain).to_sample();
+            db::amp_to_db(level * gain)
+        };
+        let output_delta_db = output_db(envelope * db::db_to_amp(DELTA_DB)) - output_db(envelope);
+        if output_delta_db > 0.0 {
+            DELTA_DB / output_delta_db
+        } else if self.direction == Direction::Expand {
+            // A gate removes the signal below the threshold altogether.
+            0.0
+        } else {
+            ::std::f32::INFINITY
+        }
+    }
+
+    /// Writes the `steady_state_gain` for each of the given `levels` to the corresponding element
+    /// of `out`, e.g. for drawing the transfer curve.
+    ///
+    /// **Panics** if `levels` and `out` differ in length.
+    pub fn gains_for_levels(&self, levels: &[f32], out: &mut [f32]) {
+        assert_eq!(levels.len(), out.len(), "`levels` and `out` must be the same length");
+        for (&level, gain) in levels.iter().zip(out.iter_mut()) {
+            *gain = self.steady_state_gain(level);
+        }
+    }
+
+    /// The output level in decibels produced by the static transfer curve for an input
+    /// level in decibels.
+    ///
+    /// The static curve describes the gain applied once the detected envelope has settled on the
+    /// input level, ignoring the attack and release of the detector. Both levels are relative to
+    /// the `db_reference`.
+    pub fn output_db_for_input_db(&self, input_db: f32) -> f32 {
+        let gain = self.static_gain(self.db_to_amp(input_db));
+        input_db + db::amp_to_db(gain)
+    }
+
+    /// Whether an input peaking at the given linear amplitude would produce output beyond full
+    /// scale (an amplitude of `1.0`) once passed through the input gain, the static transfer curve
+    /// and the `effective_makeup_gain`.
+    ///
+    /// This is a worst-case prediction that assumes the detected envelope has settled on the
+    /// input peak. It ignores the attack of the detector, so transients that outrun the attack may
+    /// still exceed the predicted output peak, as well as the auto gain, whose trim depends on
+    /// the material.
+    pub fn would_clip(&self, input_peak: f32) -> bool {
+        let input_peak = input_peak.abs() * self.input_gain;
+        input_peak * self.static_gain(input_peak) * self.effective_makeup_gain() > 1.0
+    }
+
+    /// Sample `n` `(input_db, output_db)` points along the static transfer curve, evenly spaced
+    /// from `TRANSFER_CURVE_MIN_DB` to `TRANSFER_CURVE_MAX_DB` relative to the `db_reference`.
+    ///
+    /// This is the set of points that a GUI needs to draw the **Compressor**'s transfer curve.
+    pub fn sample_transfer_curve(&self, n: usize) -> Vec<(f32, f32)> {
+        let range_db = TRANSFER_CURVE_MAX_DB - TRANSFER_CURVE_MIN_DB;
+        let step_db = if n > 1 { range_db / (n - 1) as f32 } else { 0.0 };
+        (0..n).map(|i| {
+            let input_db = TRANSFER_CURVE_MIN_DB + step_db * i as f32;
+            (input_db, self.output_db_for_input_db(input_db))
+        }).collect()
+    }
+
+    /// Produce the gain to be applied evenly across all channels for the next frame.
+    ///
+    /// The gain is yielded in the floating point format of the `Frame`'s samples and is applied to
+    /// every channel as is, without any per-channel conversion.
+    #[inline]
+    pub fn next_gain(&mut self, next_frame: F) -> <F::Sample as Sample>::Float {
+        self.next_gain_by(next_frame, EGF::next_gain)
+    }
+
+    /// Produce the gain for the next frame, using `even_gain` in place of the
+    /// **EvenGainFunction** to step the detectors and reduce the channel gains to one.
+    #[inline]
+    fn next_gain_by<G>(&mut self, next_frame: F, even_gain: G) -> <F::Sample as Sample>::Float
+        where G: FnOnce(&mut Self, F) -> <F::Sample as Sample>::Float,
+    {
+        self.frames_processed += 1;
+        if self.frozen {
+            #[cfg(feature = "metering")]
+            self.range.next(self.last_gain);
+            self.follow_meter_gain(1);
+            return self.last_gain.to_sample();
+        }
+        let gain = even_gain(self, next_frame);
+        self.shape_gain(gain)
+    }
+
+    /// Steps forward the `Compressor` by the given frame and returns the compressed result.
+    ///
+    /// When compressing integer frames, any result beyond full scale saturates at the sample
+    /// format's extremes rather than wrapping around.
+    ///
+    /// Whenever the gain applied is exactly unity, e.g. while the signal stays below the
+    /// threshold, the delayed input is returned bit-for-bit: no sign, denormal or NaN payload is
+    /// altered and no dither is added, so that null tests against the dry signal cancel exactly.
+    /// A gain of exactly zero yields equilibrium rather than negative zeros.
+    ///
+    /// This never allocates: the detection window and signal delay line only ever exchange a
+    /// frame for a frame.
+    #[inline]
+    pub fn next_frame(&mut self, next_frame: F) -> F {
+        self.next_frame_keyed(next_frame, next_frame)
+    }
+
+    /// Steps forward the detectors by the given `key` frame and returns the `signal` frame
+    /// compressed by the resulting gain, e.g. to duck a music bus beneath a separate voiceover.
+    ///
+    /// The `key` passes through the input gain, the sidechain filters and the detectors, and is
+    /// counted by the `input_clip_count`, while the `signal` passes through the signal delay line
+    /// and receives the gain. `next_frame` is equivalent to passing the same frame as both.
+    #[inline]
+    pub fn next_frame_keyed(&mut self, key: F, signal: F) -> F {
+        self.next_frame_and_gain(key, signal).0
+    }
+
+    /// Compresses the given `frames` in place using the given precomputed detection `envelope`
+    /// rather than the **Compressor**'s own detectors, e.g. where the envelope of a whole file has
+    /// been detected elsewhere.
+    ///
+    /// Each element of `envelope` is the level of the corresponding frame in the same terms as
+    /// `envelope`, and is applied to every channel, e.g. as written by `detect_envelope_slice`.
+    /// The gain is derived via the static transfer curve and then passes through every later
+    /// stage exactly as in `compress_slice`, i.e. the parameter smoothing, range, punch, signal
+    /// delay, input gain and auto gain. The detectors and sidechain are neither stepped nor
+    /// consulted. Feeding the envelope detected by an identical **Compressor** reproduces the
+    /// output of `compress_slice` exactly for mono frames, or for any frames with the **Minimum**
+    /// even gain, which follows the loudest channel just as `detect_envelope_slice` does.
+    ///
+    /// **Panics** if `frames` and `envelope` differ in length.
+    pub fn apply_precomputed_gain_reduction(&mut self, frames: &mut [F], envelope: &[f32]) {
+        assert_eq!(frames.len(), envelope.len(), "`frames` and `envelope` must be the same length");
+        for (i, (frame, &level)) in frames.iter_mut().zip(envelope.iter()).enumerate() {
+            self.apply_scheduled(i);
+            self.frames_processed += 1;
+            let gain = if self.frozen {
+                #[cfg(feature = "metering")]
+                self.range.next(self.last_gain);
+                self.follow_meter_gain(1);
+                self.last_gain.to_sample()
+            } else {
+                let (_, threshold, slope) = self.step_parameters(*frame);
+                let knee = self.knee().to_sample();
+                let gain = self.transfer_gain(level.to_sample(), threshold, slope, knee);
+                self.shape_gain(gain)
+            };
+            let delayed_frame = self.delay_signal(*frame);
+            if self.bypass {
+                *frame = self.meter_output(delayed_frame);
+                continue;
+            }
+            let gain = self.finish_gain(delayed_frame, gain);
+            let output = self.quantizer.scale_amp(delayed_frame, gain);
+            *frame = self.meter_output(output);
+        }
+        self.advance_scheduled(frames.len());
+    }
+
+    /// Steps forward the `Compressor` by the given frame and returns both the compressed (wet)
+    /// and the uncompressed (dry) result as `(wet, dry)`, e.g. for parallel compression.
+    ///
+    /// The dry frame is taken from the same signal delay line as the wet frame, so it is delayed
+    /// by exactly the `latency_frames` of the wet path and the two may be blended without comb
+    /// filtering. The dry frame is otherwise untouched.
+    #[inline]
+    pub fn next_frame_wet_dry(&mut self, next_frame: F) -> (F, F) {
+        let (wet, _, dry) = self.next_frame_and_gain(next_frame, next_frame);
+        (wet, dry)
+    }
+
+    /// Steps forward the detectors by the given `key` frame and the signal delay line by the
+    /// `signal` frame, yielding the compressed result along with the linear gain applied to it
+    /// and the delayed, uncompressed frame.
+    #[inline]
+    fn next_frame_and_gain(&mut self, key: F, signal: F) -> (F, f32, F) {
+        self.next_frame_and_gain_by(key, signal, EGF::next_gain)
+    }
+
+    /// Steps forward the `Compressor` as in `next_frame_and_gain`, producing the gain via
+    /// `even_gain` as in `next_gain_by`.
+    #[inline]
+    fn next_frame_and_gain_by<G>(&mut self, key: F, signal: F, even_gain: G) -> (F, f32, F)
+        where G: FnOnce(&mut Self, F) -> <F::Sample as Sample>::Float,
+    {
+        let gain = self.next_gain_by(key, even_gain);
+        let delayed_frame = self.delay_signal(signal);
+        if self.bypass {
+            return (self.meter_output(delayed_frame), 1.0, delayed_frame);
+        }
+        let gain = self.finish_gain(delayed_frame, gain);
+        let output = self.quantizer.scale_amp(delayed_frame, gain);
+        (self.meter_output(output), gain.to_sample(), delayed_frame)
+    }
+
+    /// Consume the **Compressor**, yielding an iterator that compresses each of the given
+    /// `frames` in turn.
+    ///
+    /// Each item pairs the compressed frame with the linear gain applied to it, including any
+    /// auto gain, so that the output may be analysed alongside the gain without a separate
+    /// metering pass.
+    pub fn compress_with_gain_iter<I>(self, frames: I) -> CompressWithGain<I::IntoIter, F, D, EGF>
+        where I: IntoIterator<Item=F>,
+    {
+        CompressWithGain {
+            frames: frames.into_iter(),
+            compressor: self,
+        }
+    }
+
+    /// Consume the **Compressor**, yielding a **Signal** that lazily compresses each frame of the
+    /// given `signal` at the given `sample_hz`.
+    ///
+    /// The attack, release and all other durations are updated to the `sample_hz`. Parameters may
+    /// be changed mid-stream from any thread via the **SharedParams** handle returned by
+    /// `CompressedSignal::params_handle`: each change is applied via `set_all` before the next
+    /// frame, without locking. See **CompressedSignal**.
+    pub fn compress_signal<S>(self, signal: S, sample_hz: f64)
+        -> CompressedSignal<S::IntoIter, F, D, EGF>
+        where S: IntoIterator<Item=F>,
+    {
+        CompressedSignal::new(signal.into_iter(), self, sample_hz)
+    }
+
+    /// Consume the **Compressor**, yielding a `dasp_signal::Signal` that lazily compresses each
+    /// frame of the given `dasp` `signal` at the given `sample_hz`.
+    ///
+    /// This is `compress_signal` for the `dasp` ecosystem, with the same **SharedParams** handle
+    /// for changing parameters mid-stream.
+    #[cfg(feature = "dasp")]
+    pub fn compress_dasp_signal<S>(self, signal: S, sample_hz: f64)
+        -> CompressedSignal<S, F, D, EGF>
+        where S: dasp_signal::Signal<Frame=F>,
+    {
+        CompressedSignal::new(signal, self, sample_hz)
+    }
+
+    /// Compresses the given `frames` in place using an even gain across all channels.
+    ///
+    /// Any parameter changes scheduled via `schedule_threshold` or `schedule_ratio` are applied at
+    /// their frame offset from the start of `frames`.
+    ///
+    /// Like `next_frame`, this never allocates, making it safe to call on a real-time audio
+    /// thread. Only reconfiguration may allocate: growing the detection window or signal delay
+    /// beyond the sizes set via `reserve`, and scheduling more parameter changes than were set
+    /// via `reserve_scheduled`.
+    #[inline]
+    pub fn compress_slice(&mut self, frames: &mut [F]) {
+        if self.silence_optimization && self.is_silent(frames) {
+            self.skip_silence(frames);
+        } else {
+            for (i, frame) in frames.iter_mut().enumerate() {
+                self.apply_scheduled(i);
+                *frame = self.next_frame(*frame);
+            }
+        }
+        self.advance_scheduled(frames.len());
+    }
+
+    /// Compresses the given `frames` in place as though time ran backward, i.e. the last frame is
+    /// compressed first, leaving the `frames` in their original order.
+    ///
+    /// This is only meaningful offline, e.g. when mastering a whole file. Run backward, the
+    /// detector meets each transient from its end, so the release in reversed time becomes an
+    /// attack that begins *before* the transient in forward time: gain reduction anticipates
+    /// every peak as it would with lookahead, yet without any latency. The attack in reversed
+    /// time becomes the release after each transient. A forward pass may follow to add the usual
+    /// attack and release behaviour on top.
+    ///
+    /// The **Compressor** otherwise behaves exactly as for `compress_slice` on the reversed
+    /// frames. Any signal delay therefore shifts the output earlier rather than later, so a
+    /// lookahead should usually be disabled for the reverse pass. Scheduled parameter changes are
+    /// applied at their offset from the *end* of `frames`.
+    pub fn compress_slice_reverse(&mut self, frames: &mut [F]) {
+        frames.reverse();
+        self.compress_slice(frames);
+        frames.reverse();
+    }
+
+    /// Compresses the given `frames` in place just as `compress_slice` does, first copying the
+    /// uncompressed frames into `dry_out`, e.g. so that a host may offer instant A/B comparison
+    /// of a captured passage.
+    ///
+    /// The dry copy is taken before any detection or gain, so `dry_out` holds exactly the input
+    /// while `frames` holds exactly what `compress_slice` would have produced. This saves the
+    /// caller cloning whole buffers up front when only a capture window is of interest, and like
+    /// `compress_slice` it never allocates.
+    ///
+    /// **Panics** if `frames` and `dry_out` differ in length.
+    #[inline]
+    pub fn compress_slice_capturing(&mut self, frames: &mut [F], dry_out: &mut [F]) {
+        assert_eq!(frames.len(), dry_out.len(), "`frames` and `dry_out` must be the same length");
+        dry_out.copy_from_slice(frames);
+        self.compress_slice(frames);
+    }
+
+    /// Compresses the given `signal` in place by the gain detected from the corresponding frames
+    /// of the `key`, as `next_frame_keyed` does for each frame.
+    ///
+    /// Only as many frames as the shorter of the two slices are processed. Any remaining frames
+    /// of the `signal` are left untouched. Scheduled parameter changes and the silence
+    /// optimization behave as in `compress_slice`, with silence detected on the `key`.
+    #[inline]
+    pub fn compress_slice_keyed(&mut self, key: &[F], signal: &mut [F]) {
+        let n_frames = if key.len() < signal.len() { key.len() } else { signal.len() };
+        let (key, signal) = (&key[..n_frames], &mut signal[..n_frames]);
+        if self.silence_optimization && self.is_silent(key) {
+            self.skip_silence(signal);
+        } else {
+            for (i, (&key, frame)) in key.iter().zip(signal.iter_mut()).enumerate() {
+                self.apply_scheduled(i);
+                *frame = self.next_frame_keyed(key, *frame);
+            }
+        }
+        self.advance_scheduled(n_frames);
+    }
+
+    /// Compresses the given `frames` in place just as `compress_slice` does, calling `f` with the
+    /// index of each frame, the linear gain applied to it and the input frame before compression.
+    ///
+    /// This is a hook for instrumentation, e.g. logging, drawing or recording the gain reduction
+    /// without allocating an output buffer. `f` is called on the thread doing the processing,
+    /// once per frame as it is compressed, so on a real-time audio thread it should neither block
+    /// nor allocate. The gain is that applied to the delayed frame leaving the **Compressor**,
+    /// including the input, makeup and auto gain, or `1.0` while bypassed. Every frame is
+    /// detected, even if the silence optimization is enabled. Prefer `compress_slice` where no
+    /// hook is needed.
+    #[inline]
+    pub fn compress_slice_with<G>(&mut self, frames: &mut [F], mut f: G)
+        where G: FnMut(usize, f32, F),
+    {
+        for (i, frame) in frames.iter_mut().enumerate() {
+            self.apply_scheduled(i);
+            let input = *frame;
+            let (output, gain, _) = self.next_frame_and_gain(input, input);
+            f(i, gain, input);
+            *frame = output;
+        }
+        self.advance_scheduled(frames.len());
+    }
+
+    /// Compress the given `material` with the current settings and yield the gain in decibels that
+    /// would bring the compressed output to the `target_db`, e.g. for loudness-targeted
+    /// mastering as the first of two passes.
+    ///
+    /// Loudness is measured as the integrated loudness in LUFS following ITU-R BS.1770: the
+    /// output is K-weighted, measured over 400ms blocks and gated by both
+    /// `LOUDNESS_ABSOLUTE_GATE_LUFS` and `LOUDNESS_RELATIVE_GATE_LU`, so the `target_db` is in
+    /// LUFS. The output is measured after the signal delay line is drained via `flush`, aligned
+    /// with the `material`. Yields `0.0` if the material is too short (under 400ms) or too quiet
+    /// to be measured.
+    ///
+    /// The gain is relative to the output as it stands, including any makeup gain already
+    /// applied, so it is applied on top of the current makeup gain, e.g. via
+    /// `set_makeup_gain(makeup_gain * db::db_to_amp(gain_db))`.
+    ///
+    /// The **Compressor** is stepped through the whole `material`, just as by `compress_slice`.
+    /// Before the second pass, clear the state it leaves behind, e.g. via `warm_start` with the
+    /// start of the material followed by `soft_reset`.
+    #[cfg(feature = "metering")]
+    pub fn makeup_for_target_loudness(&mut self,
+                                      material: &[F],
+                                      target_db: f32,
+                                      sample_hz: f64) -> f32
+    {
+        let mut output = Vec::with_capacity(material.len() + self.signal_delay.len());
+        for &frame in material {
+            let frame = self.next_frame(frame);
+            output.push(frame);
+        }
+        self.flush(&mut output);
+        let latency = output.len() - material.len();
+        let output: Vec<F::Float> = output[latency..].iter().map(|f| f.to_float_frame()).collect();
+        match meter::integrated_loudness(&output, sample_hz) {
+            Some(loudness) => target_db - loudness,
+            None => 0.0,
+        }
+    }
+
+    /// Measure the total harmonic distortion the **Compressor** adds to a sine of the given
+    /// `freq_hz` at its steady state, as a ratio, e.g. `0.01` for 1%.
+    ///
+    /// A sine peaking at `analysis::THD_TEST_LEVEL_DB` (-6 dB) relative to full scale is fed to
+    /// every channel, first until the detector and any lookahead have settled and then for a
+    /// further `analysis::THD_ANALYSIS_MS`, whose output from the first channel is measured via
+    /// `harmonic_distortion`. Distortion arises wherever the gain moves within each period of the
+    /// sine, e.g. from fast ballistics relative to the period or from the overshoot ceiling, so
+    /// a low `freq_hz` is the most revealing. As a steady sine is detected at a steady level,
+    /// settings that don't compress the sine at the test level add no distortion.
+    ///
+    /// The **Compressor** is stepped through the whole test signal, just as by `compress_slice`,
+    /// so its state afterwards is that left by the sine.
+    #[cfg(feature = "analysis")]
+    pub fn measure_thd(&mut self, freq_hz: f64, sample_hz: f64) -> f32 {
+        assert_valid_sample_hz(sample_hz, "measure_thd");
+        let amp = db::db_to_amp(analysis::THD_TEST_LEVEL_DB) as f64;
+        let w = 2.0 * std::f64::consts::PI * freq_hz / sample_hz;
+        let n_frames = Ms(analysis::THD_ANALYSIS_MS).samples(sample_hz) as usize;
+        let settle = self.ballistics_settling_frames();
+        let warm_up = if settle > n_frames { settle } else { n_frames } + self.latency_frames();
+        let mut output = Vec::with_capacity(n_frames);
+        for i in 0..warm_up + n_frames {
+            let s: <F::Sample as Sample>::Float = (amp * (w * i as f64).sin()).to_sample();
+            let frame = self.next_frame(F::from_fn(|_| s.to_sample()));
+            if i >= warm_up {
+                let s = frame.channel(0).map(|s| s.to_float_sample().to_sample()).unwrap_or(0.0);
+                output.push(s);
+            }
+        }
+        harmonic_distortion(&output, freq_hz, sample_hz)
+    }
+
+    /// Drain the signal delay line at the end of a stream, pushing the frames still held within
+    /// it onto `out`, e.g. so that an offline render doesn't lose its tail.
+    ///
+    /// Silence is fed in to push out exactly `latency_frames` frames, i.e. the last frames given
+    /// to the **Compressor**, compressed as usual. Nothing is pushed without a lookahead delay.
+    ///
+    /// Afterwards the delay line holds silence and the detectors have followed the silence fed
+    /// in. Before reusing the **Compressor** for an unrelated stream, clear the remaining state
+    /// via `reset`.
+    pub fn flush(&mut self, out: &mut Vec<F>) {
+        let n_frames = self.signal_delay.len();
+        out.reserve(n_frames);
+        for _ in 0..n_frames {
+            let frame = self.next_frame(F::equilibrium());
+            out.push(frame);
+        }
+    }
+
+    /// Steps forward the detectors by each of the given `frames`, writing the gain that would be
+    /// applied to each frame to the corresponding element of `gains`.
+    ///
+    /// The `frames` themselves are left untouched. The `gains` may then be applied to the same or
+    /// another signal via `apply_gain_slice`, e.g. to compress one signal using the dynamics of
+    /// another.
+    ///
+    /// **Panics** if `frames` and `gains` differ in length.
+    pub fn compute_gain_slice(&mut self, frames: &[F], gains: &mut [f32]) {
+        assert_eq!(frames.len(), gains.len(), "`frames` and `gains` must be the same length");
+        for (frame, gain) in frames.iter().zip(gains.iter_mut()) {
+            *gain = self.next_gain(*frame).to_sample();
+        }
+    }
+
+    /// Scales each of the given `frames` in place by the corresponding precomputed gain.
+    ///
+    /// The signal passes through the **Compressor**'s signal delay line and is converted back to
+    /// the `Frame`'s sample format exactly as it is by `next_frame`, so `compute_gain_slice`
+    /// followed by `apply_gain_slice` on the same signal is equivalent to `compress_slice`.
+    /// The detectors are not stepped.
+    ///
+    /// **Panics** if `frames` and `gains` differ in length.
+    pub fn apply_gain_slice(&mut self, frames: &mut [F], gains: &[f32]) {
+        assert_eq!(frames.len(), gains.len(), "`frames` and `gains` must be the same length");
+        for (frame, gain) in frames.iter_mut().zip(gains.iter()) {
+            let delayed_frame = self.delay_signal(*frame);
+            if self.bypass {
+                *frame = self.meter_output(delayed_frame);
+                continue;
+            }
+            let gain = self.finish_gain(delayed_frame, gain.to_sample());
+            let output = self.quantizer.scale_amp(delayed_frame, gain);
+            *frame = self.meter_output(output);
+        }
+    }
+
+    /// Steps forward the `Compressor` by the given frame, but returns the *uncompressed* frame
+    /// scaled so that its recent loudness matches that of the compressed output.
+    ///
+    /// Use this in place of `next_frame` while auditioning the bypassed signal, so that the
+    /// compressed version isn't judged as "better" simply for being louder. The loudness of both
+    /// signals is followed as their mean square over roughly the last `loudness_match_ms`.
+    ///
+    /// The compressed output is derived exactly as for `next_frame`, including the overshoot
+    /// ceiling and the loudness reduction meter. While the **Compressor** is bypassed via
+    /// `set_bypass`, the compressed output is the uncompressed frame, which is returned untouched.
+    #[cfg(feature = "metering")]
+    pub fn loudness_matched_bypass(&mut self, next_frame: F) -> F {
+        let gain = self.next_gain(next_frame);
+        let dry_frame = self.delay_signal(next_frame);
+        if self.bypass {
+            return self.meter_output(dry_frame);
+        }
+        let gain = self.finish_gain(dry_frame, gain);
+        let wet_frame = self.quantizer.scale_amp(dry_frame, gain);
+        let dry_power = meter::mean_square(dry_frame);
+        let wet_power = meter::mean_square(wet_frame);
+        self.loudness_match.next(dry_power, wet_power);
+        let matching_gain = self.loudness_match.matching_gain().to_sample();
+        let output = self.quantizer.scale_amp(dry_frame, matching_gain);
+        self.meter_output(output)
+    }
+
+    /// Whether the given block may be skipped by the silence optimization.
+    fn is_silent(&self, frames: &[F]) -> bool {
+        let silence = SILENCE_AMPLITUDE;
+        let domain = self.threshold_domain;
+        let threshold = domain.to_amplitude(self.target_threshold());
+        let smoothed_threshold = domain.to_amplitude(self.smoothed_threshold);
+        if self.frozen || threshold < silence || smoothed_threshold < silence {
+            return false;
+        }
+        // Silence is attenuated rather than passed at unity when expanding.
+        if self.direction == Direction::Expand {
+            return false;
+        }
+        let below = |s: f32| s < silence && s > -silence;
+        let envelope = self.envelope_detector.last_envelope();
+        envelope.channels().all(|s| below(s.to_sample()))
+            && frames.iter().all(|&f| self.trim_input(f).channels().all(|s| below(s.to_sample())))
+    }
+
+    /// Pass the given silent block through at unity gain, advancing the detectors and smoothed
+    /// parameters as though each frame had been processed.
+    fn skip_silence(&mut self, frames: &mut [F]) {
+        let identity = <<F::Sample as Sample>::Float as FloatSample>::identity();
+        for (i, frame) in frames.iter_mut().enumerate() {
+            self.apply_scheduled(i);
+            let delayed_frame = self.delay_signal(*frame);
+            if self.bypass {
+                *frame = self.meter_output(delayed_frame);
+                continue;
+            }
+            let gain = self.finish_gain(delayed_frame, identity);
+            let output = self.quantizer.scale_amp(delayed_frame, gain);
+            *frame = self.meter_output(output);
+        }
+        let n_frames = frames.len();
+        self.frames_processed += n_frames as u64;
+        if self.adaptive_threshold_db.is_some() {
+            self.adaptive_level.next_silence(n_frames);
+        }
+        self.advance_ratio_ramp(n_frames as u64);
+        let decay = self.parameter_smoothing_gain.powf(n_frames as f32);
+        let (threshold, slope) = (self.target_threshold(), self.slope);
+        self.smoothed_threshold = threshold + (self.smoothed_threshold - threshold) * decay;
+        self.smoothed_slope = slope + (self.smoothed_slope - slope) * decay;
+        self.envelope_detector.advance_silence(n_frames);
+        self.over_threshold_frames = 0;
+        self.last_gain = 1.0;
+        #[cfg(feature = "metering")]
+        self.range.next_n(self.last_gain, n_frames);
+        self.follow_meter_gain(n_frames);
+    }
+
+    /// Advance the ratio ramp in progress, if any, by the given number of frames.
+    #[inline]
+    fn advance_ratio_ramp(&mut self, n_frames: u64) {
+        if let Some((target, step, remaining)) = self.ratio_ramp {
+            if n_frames >= remaining {
+                self.slope = target;
+                self.ratio_ramp = None;
+            } else {
+                self.slope += step * n_frames as f32;
+                self.ratio_ramp = Some((target, step, remaining - n_frames));
+            }
+        }
+    }
+
+    /// Apply all scheduled parameter changes that are due at the given frame offset.
+    #[cfg(feature = "automation")]
+    #[inline]
+    fn apply_scheduled(&mut self, frame_offset: usize) {
+        while let Some(change) = self.automation.pop_due(frame_offset) {
+            match change {
+                Change::Threshold(threshold) => self.threshold = threshold,
+                Change::Ratio(ratio) => self.slope = ratio::slope_from_ratio(ratio),
+            }
+        }
+    }
+
+    /// Without the `automation` feature no changes may be scheduled.
+    #[cfg(not(feature = "automation"))]
+    #[inline]
+    fn apply_scheduled(&mut self, _frame_offset: usize) {}
+
+    /// Shift the scheduled parameter changes back by the given number of processed frames.
+    #[cfg(feature = "automation")]
+    #[inline]
+    fn advance_scheduled(&mut self, n_frames: usize) {
+        self.automation.advance(n_frames);
+    }
+
+    /// Without the `automation` feature no changes may be scheduled.
+    #[cfg(not(feature = "automation"))]
+    #[inline]
+    fn advance_scheduled(&mut self, _n_frames: usize) {}
+
+    /// Switch the detector to the release stage appropriate to the most recent gain reduction.
+    #[inline]
+    fn select_release_stage(&mut self) {
+        if self.release_stages.is_none() {
+            return;
+        }
+        let fast = self.last_gain < self.release_crossover_gain;
+        if fast != self.fast_release {
+            self.fast_release = fast;
+            let gain = if fast { self.fast_release_gain } else { self.slow_release_gain };
+            self.envelope_detector.detector().set_release_gain(gain);
+        }
+    }
+
+    /// Blend each channel of the given envelope toward the loudest channel by the
+    /// `detector_link`.
+    #[inline]
+    fn link_detectors(&self, env_frame: F::Float) -> F::Float {
+        if self.detector_link <= 0.0 {
+            return env_frame;
+        }
+        let zero = <<F::Sample as Sample>::Float as Sample>::equilibrium();
+        let max = env_frame.channels().fold(zero, |max, s| if s > max { s } else { max });
+        let link = self.detector_link.to_sample();
+        env_frame.map(|s| s + (max - s) * link)
+    }
+
+    /// Step the smoothed parameters toward their targets by a single frame.
+    #[inline]
+    fn smooth_parameters(&mut self) {
+        let gain = self.parameter_smoothing_gain;
+        let (threshold, slope) = (self.target_threshold(), self.slope);
+        self.smoothed_threshold = threshold + (self.smoothed_threshold - threshold) * gain;
+        self.smoothed_slope = slope + (self.smoothed_slope - slope) * gain;
+    }
+
+    /// Step the input meters, the adaptive threshold and the smoothed parameters forward by the
+    /// given frame, yielding the trimmed input along with the threshold and slope in effect.
+    #[inline]
+    fn step_parameters(&mut self, next_frame: F)
+        -> (F::Float, <F::Sample as Sample>::Float, <F::Sample as Sample>::Float)
+    {
+        self.advance_ratio_ramp(1);
+        self.meter_input(next_frame);
+        let input = self.trim_input(next_frame);
+        if self.adaptive_threshold_db.is_some() {
+            self.adaptive_level.next(meter::mean_square(input));
+        }
+        self.smooth_parameters();
+        let threshold = self.threshold_domain.to_amplitude(self.smoothed_threshold).to_sample();
+        let slope = self.smoothed_slope.to_sample();
+        (input, threshold, slope)
+    }
+
+    /// The number of frames until the detector settles on a steady input, as governed by the
+    /// slower of the attack and release.
+    fn ballistics_settling_frames(&self) -> usize {
+        let detector = self.envelope_detector.detector_ref();
+        let (attack_gain, release_gain) = (detector.attack_gain(), detector.release_gain());
+        frames_to_settle(if attack_gain > release_gain { attack_gain } else { release_gain })
+    }
+
+    /// Apply the range, punch and minimum gain to the given even gain, recording it as the
+    /// `last_gain`.
+    #[inline]
+    fn shape_gain(&mut self, gain: <F::Sample as Sample>::Float) -> <F::Sample as Sample>::Float {
+        let gain = self.limit_reduction(gain);
+        let gain = self.punch(gain);
+        let min_gain = self.min_gain.to_sample();
+        let gain = if gain < min_gain { min_gain } else { gain };
+        self.last_gain = gain.to_sample();
+        #[cfg(feature = "metering")]
+        self.range.next(self.last_gain);
+        self.follow_meter_gain(1);
+        gain
+    }
+
+    /// Step the `meter_gain` toward the `last_gain` by the given number of frames.
+    #[inline]
+    fn follow_meter_gain(&mut self, n_frames: usize) {
+        let decay = self.meter_smoothing_gain.powf(n_frames as f32);
+        self.meter_gain = self.last_gain + (self.meter_gain - self.last_gain) * decay;
+    }
+
+    /// Limit the gain reduction of the given gain to the `max_reduction_db`, approaching it along
+    /// the `range_knee_db`.
+    #[inline]
+    fn limit_reduction(&self, gain: <F::Sample as Sample>::Float) -> <F::Sample as Sample>::Float {
+        let floor_db = match self.max_reduction_db {
+            Some(db) => db,
+            None => return gain,
+        };
+        let linear: f32 = gain.to_sample();
+        let reduction_db = -db::amp_to_db(linear);
+        let half_knee = self.range_knee_db / 2.0;
+        if reduction_db <= floor_db - half_knee {
+            return gain;
+        }
+        let limited_db = if reduction_db >= floor_db + half_knee {
+            floor_db
+        } else {
+            let over = reduction_db - floor_db + half_knee;
+            reduction_db - over * over / (2.0 * self.range_knee_db)
+        };
+        db::db_to_amp(-limited_db).to_sample()
+    }
+
+    /// Hold the given gain at unity for the first `punch_frames` that the envelope exceeds the
+    /// threshold.
+    #[inline]
+    fn punch(&mut self, gain: <F::Sample as Sample>::Float) -> <F::Sample as Sample>::Float {
+        let identity = <<F::Sample as Sample>::Float as FloatSample>::identity();
+        if gain >= identity {
+            self.over_threshold_frames = 0;
+            return gain;
+        }
+        if self.over_threshold_frames < self.punch_frames {
+            self.over_threshold_frames += 1;
+            return identity;
+        }
+        gain
+    }
+
+    /// The floating point representation of the given input frame scaled by the input gain, as
+    /// seen by the detectors.
+    #[inline]
+    fn trim_input(&self, frame: F) -> F::Float {
+        frame.to_float_frame().scale_amp(self.input_gain.to_sample())
+    }
+
+    /// Apply the input gain and the makeup gain to the given gain about to be applied to the
+    /// delayed frame.
+    #[inline]
+    fn apply_static_gain(&self,
+                         gain: <F::Sample as Sample>::Float) -> <F::Sample as Sample>::Float
+    {
+        let static_gain: <F::Sample as Sample>::Float =
+            (self.input_gain * self.effective_makeup_gain()).to_sample();
+        gain * static_gain
+    }
+
+    /// Step the auto gain forward with the given frame that is about to be scaled by `gain`,
+    /// yielding `gain` with the auto gain's trim applied.
+    #[inline]
+    fn apply_auto_gain(&mut self,
+                       frame: F,
+                       gain: <F::Sample as Sample>::Float) -> <F::Sample as Sample>::Float
+    {
+        if self.auto_gain_db.is_none() {
+            return gain;
+        }
+        let gain: f32 = gain.to_sample();
+        self.auto_gain.next(meter::mean_square(frame) * gain * gain);
+        (gain * self.auto_gain.trim()).to_sample()
+    }
+
+    /// Reduce the given gain about to be applied to the given frame so that the frame's peak
+    /// doesn't exceed the `overshoot_ceiling`.
+    #[inline]
+    fn clamp_overshoot(&self,
+                       frame: F,
+                       gain: <F::Sample as Sample>::Float) -> <F::Sample as Sample>::Float
+    {
+        if self.overshoot_ceiling == std::f32::INFINITY {
+            return gain;
+        }
+        let peak = meter::peak(frame);
+        let linear: f32 = gain.to_sample();
+        if peak * linear > self.overshoot_ceiling {
+            (self.overshoot_ceiling / peak).to_sample()
+        } else {
+            gain
+        }
+    }
+
+    /// Carry the given gain from the detectors through every later stage of the wet path, i.e.
+    /// the static gain, the auto gain and the overshoot ceiling, metering the loudness reduction
+    /// of the result, yielding the final gain to be applied to the given delayed frame.
+    #[inline]
+    fn finish_gain(&mut self,
+                   delayed_frame: F,
+                   gain: <F::Sample as Sample>::Float) -> <F::Sample as Sample>::Float
+    {
+        let gain = self.apply_static_gain(gain);
+        let gain = self.apply_auto_gain(delayed_frame, gain);
+        let gain = self.clamp_overshoot(delayed_frame, gain);
+        #[cfg(feature = "metering")]
+        self.meter_loudness(delayed_frame, gain);
+        gain
+    }
+
+    /// Step the loudness reduction meter, if enabled, with the given dry frame and the gain about
+    /// to be applied to it.
+    #[cfg(feature = "metering")]
+    #[inline]
+    fn meter_loudness(&mut self, dry_frame: F, gain: <F::Sample as Sample>::Float) {
+        if let Some(ref mut meter) = self.loudness_reduction {
+            let dry = dry_frame.to_float_frame();
+            meter.next(dry, dry.scale_amp(gain));
+        }
+    }
+
+    /// Record the peak of the given output frame for `last_output_peak`, yielding the frame.
+    #[inline]
+    fn meter_output(&mut self, output: F) -> F {
+        self.last_output_peak = meter::peak(output);
+        output
+    }
+
+    /// Count the given input frame toward the `input_clip_count` if any of its samples reach the
+    /// `input_clip_level`.
+    #[inline]
+    fn meter_input(&mut self, input: F) {
+        let level = self.input_clip_level;
+        let clipped = input.channels().any(|sample| {
+            let s: f32 = sample.to_float_sample().to_sample();
+            s >= level || s <= -level
+        });
+        if clipped {
+            self.input_clip_count += 1;
+        }
+    }
+
+    /// The linear amplitude of full scale in the `Frame`'s sample format, e.g. just below `1.0`
+    /// for the positive extreme of integer formats.
+    fn full_scale() -> f32 {
+        let identity = <F::Sample as Sample>::identity();
+        let full_scale: F::Sample = identity.to_sample();
+        full_scale.to_float_sample().to_sample()
+    }
+
+    /// Push the given frame onto the signal delay line and yield the frame that falls out of it.
+    #[inline]
+    fn delay_signal(&mut self, frame: F) -> F {
+        match self.signal_delay.pop_front() {
+            Some(delayed) => {
+                self.signal_delay.push_back(frame);
+                delayed
+            },
+            None => frame,
+        }
+    }
+
+}
+
+impl<F, D, EGF> Compressor<F, D, EGF>
+    where F: Frame<Sample=f32>,
+          D: Detector<F::Float>,
+          EGF: EvenGainFunction,
+{
+
+    /// Compresses the planar `inputs`, writing the result to the planar `outputs`, as expected by
+    /// plugin SDKs such as VST, CLAP and AU.
+    ///
+    /// Each element of `inputs` and `outputs` is a single channel's buffer. The inputs are only
+    /// read and the outputs only written, so the two never need to alias. Any scheduled parameter
+    /// changes are applied exactly as they are by `compress_slice`.
+    ///
+    /// **Panics** if the number of input or output channels differs from the `Frame`'s channel
+    /// count, or if the channel buffers differ in length. See `try_process_replacing` for a
+    /// version that returns the mismatch as an error instead.
+    pub fn process_replacing(&mut self, inputs: &[&[f32]], outputs: &mut [&mut [f32]]) {
+        if let Err(err) = self.try_process_replacing(inputs, outputs) {
+            panic!("process_replacing: {}", err);
+        }
+    }
+
+    /// The same as `process_replacing`, but returns a **CompressorError** rather than panicking
+    /// if the number of channels or the lengths of the buffers don't match.
+    ///
+    /// The buffers are checked before any processing, so on error the **Compressor** and the
+    /// `outputs` are left untouched.
+    pub fn try_process_replacing(&mut self,
+                                 inputs: &[&[f32]],
+                                 outputs: &mut [&mut [f32]]) -> Result<(), CompressorError>
+    {
+        self.try_process_replacing_keyed(inputs, inputs, outputs)
+    }
+
+    /// Compresses the planar `inputs` by the gain detected from the corresponding frames of the
+    /// planar `keys`, writing the result to the planar `outputs`, as `compress_slice_keyed` does
+    /// for frames, e.g. to duck the inputs beneath a voiceover on a host's sidechain bus.
+    ///
+    /// **Panics** if the number of key, input or output channels differs from the `Frame`'s
+    /// channel count, or if the channel buffers differ in length. See
+    /// `try_process_replacing_keyed` for a version that returns the mismatch as an error instead.
+    pub fn process_replacing_keyed(&mut self,
+                                   keys: &[&[f32]],
+                                   inputs: &[&[f32]],
+                                   outputs: &mut [&mut [f32]])
+    {
+        if let Err(err) = self.try_process_replacing_keyed(keys, inputs, outputs) {
+            panic!("process_replacing_keyed: {}", err);
+        }
+    }
+
+    /// The same as `process_replacing_keyed`, but returns a **CompressorError** rather than
+    /// panicking if the number of channels or the lengths of the buffers don't match, e.g. for a
+    /// mono sidechain bus given to a stereo **Compressor**.
+    ///
+    /// The buffers are checked before any processing, so on error the **Compressor** and the
+    /// `outputs` are left untouched.
+    pub fn try_process_replacing_keyed(&mut self,
+                                       keys: &[&[f32]],
+                                       inputs: &[&[f32]],
+                                       outputs: &mut [&mut [f32]]) -> Result<(), CompressorError>
+    {
+        let n_channels = F::n_channels();
+        for &found in &[keys.len(), inputs.len(), outputs.len()] {
+            if found != n_channels {
+                return Err(CompressorError::ChannelMismatch { expected: n_channels, found: found });
+            }
+        }
+        let n_frames = inputs.first().map(|input| input.len()).unwrap_or(0);
+        let lens = keys.iter().chain(inputs).map(|buffer| buffer.len())
+            .chain(outputs.iter().map(|output| output.len()));
+        for found in lens {
+            if found != n_frames {
+                return Err(CompressorError::LengthMismatch { expected: n_frames, found: found });
+            }
+        }
+        for i in 0..n_frames {
+            self.apply_scheduled(i);
+            let key = F::from_fn(|ch| keys[ch][i]);
+            let frame = F::from_fn(|ch| inputs[ch][i]);
+            let compressed = self.next_frame_keyed(key, frame);
+            for (ch, sample) in compressed.channels().enumerate() {
+                outputs[ch][i] = sample;
+            }
+        }
+        self.advance_scheduled(n_frames);
+        Ok(())
+    }
+
+    /// Compresses the interleaved `signal` in place by the gain detected from the corresponding
+    /// frames of the interleaved `key`, whose frames are `key_channels` wide, as
+    /// `compress_slice_keyed` does for frames.
+    ///
+    /// Any trailing samples of the `signal` that don't fill a whole frame are left untouched.
+    ///
+    /// **Panics** if the `key_channels` differs from the `Frame`'s channel count, or if the `key`
+    /// and `signal` differ in length. See `try_compress_interleaved_keyed` for a version that
+    /// returns the mismatch as an error instead.
+    pub fn compress_interleaved_keyed(&mut self,
+                                      key: &[f32],
+                                      key_channels: usize,
+                                      signal: &mut [f32])
+    {
+        if let Err(err) = self.try_compress_interleaved_keyed(key, key_channels, signal) {
+            panic!("compress_interleaved_keyed: {}", err);
+        }
+    }
+
+    /// The same as `compress_interleaved_keyed`, but returns a **CompressorError** rather than
+    /// panicking if the `key_channels` or the lengths of the buffers don't match, e.g. for a mono
+    /// `key` given to a stereo **Compressor**.
+    ///
+    /// The buffers are checked before any processing, so on error the **Compressor** and the
+    /// `signal` are left untouched. A **LengthMismatch** is given in frames of the `signal`.
+    pub fn try_compress_interleaved_keyed(&mut self,
+                                          key: &[f32],
+                                          key_channels: usize,
+                                          signal: &mut [f32]) -> Result<(), CompressorError>
+    {
+        let n_channels = F::n_channels();
+        if key_channels != n_channels {
+            let found = key_channels;
+            return Err(CompressorError::ChannelMismatch { expected: n_channels, found: found });
+        }
+        let n_frames = signal.len() / n_channels;
+        if key.len() != signal.len() {
+            let found = key.len() / n_channels;
+            return Err(CompressorError::LengthMismatch { expected: n_frames, found: found });
+        }
+        let frames = key.chunks_exact(n_channels).zip(signal.chunks_exact_mut(n_channels));
+        for (i, (key, samples)) in frames.enumerate() {
+            self.apply_scheduled(i);
+            let key = F::from_fn(|ch| key[ch]);
+            let frame = F::from_fn(|ch| samples[ch]);
+            let compressed = self.next_frame_keyed(key, frame);
+            for (sample, compressed) in samples.iter_mut().zip(compressed.channels()) {
+                *sample = compressed;
+            }
+        }
+        self.advance_scheduled(n_frames);
+        Ok(())
+    }
+
+}
+
+impl<F, D> Compressor<F, D, EvenGainKind>
+    where F: Frame,
+          D: Detector<F::Float>,
+{
+
+    /// The kind of **EvenGainFunction** currently used to determine the gain applied evenly to
+    /// all channels.
+    pub fn even_gain(&self) -> EvenGainKind {
+        self.even_gain_fn
+    }
+
+    /// Change the kind of **EvenGainFunction** used to determine the gain applied evenly to all
+    /// channels.
+    pub fn set_even_gain(&mut self, kind: EvenGainKind) {
+        self.even_gain_fn = kind;
+    }
+
+}
+
+impl<F> DynamicCompressor<F>
+    where F: Frame,
+{
+
+    /// The kind of envelope detection currently performed.
+    pub fn detector_kind(&self) -> DetectorKind {
+        self.envelope_detector.kind()
+    }
+
+    /// Switch to the given kind of envelope detection without rebuilding the **Compressor**.
+    ///
+    /// The current envelope carries over to the new detector, so the gain continues smoothly. See
+    /// **DynamicDetector::set_kind**.
+    pub fn set_detector_kind(&mut self, kind: DetectorKind, sample_hz: f64) {
+        assert_valid_sample_hz(sample_hz, "set_detector_kind");
+        self.envelope_detector.set_kind(kind, sample_hz);
+    }
+
+    /// Apply the detector kind, **EvenGainFunction** and parameters of the given `config` in one
+    /// step, e.g. when a host switches presets while streaming.
+    ///
+    /// This is the in-place equivalent of `config.build(sample_hz)`, except that the
+    /// **Compressor**'s state survives wherever it remains meaningful:
+    ///
+    /// - The envelope carries over to the new detector kind. Switching to **Rms** detection fills
+    ///   the new window with the current envelope, while any previous window's contents are
+    ///   discarded.
+    /// - The meters (range, loudness reduction and output peak), the auto gain, the loudness
+    ///   match and the `frames_processed` are untouched.
+    /// - The signal delay line, the sidechain filters and every setting not described by a
+    ///   **CompressorBuilder** are untouched, so the latency doesn't change.
+    /// - Scheduled parameter changes remain queued, and any parameter smoothing glides toward the
+    ///   new threshold and ratio as usual.
+    ///
+    /// A `window_ms` of `None` leaves the current window duration untouched, as with `set_all`.
+    pub fn reconfigure(&mut self, config: &CompressorBuilder, sample_hz: f64) {
+        self.set_detector_kind(config.detector, sample_hz);
+        self.set_even_gain(config.even_gain);
+        self.set_all(&config.params, sample_hz);
+    }
+
+}
+
+impl<F, EGF> PeakCompressor<F, EGF>
+    where F: Frame,
+          EGF: EvenGainFunction + Default,
+{
+
+    /// Construct a **Compressor** that uses a **Peak** **EnvelopeDetector**.
+    pub fn peak<A, R>(attack_ms: A,
+                      release_ms: R,
+                      sample_hz: f64,
+                      threshold: f32,
+                      ratio: f32) -> Self
+        where A: Into<Ms>,
+              R: Into<Ms>,
+    {
+        let attack_ms: Ms = attack_ms.into();
+        let release_ms: Ms = release_ms.into();
+        let attack_frames = attack_ms.samples(sample_hz) as f32;
+        let release_frames = release_ms.samples(sample_hz) as f32;
+        let envelope_detector = EnvelopeDetector::peak(attack_frames, release_frames);
+        Compressor::new(envelope_detector, attack_ms, release_ms, sample_hz, threshold, ratio,
+                        EGF::default())
+    }
+
+    /// Construct a **Compressor** that uses a **Peak** **EnvelopeDetector** whose attack and
+    /// release are given directly as a number of frames.
+    ///
+    /// The detector's coefficients are calculated from the frames as given, bypassing the
+    /// conversion from milliseconds and its truncation to whole frames. The equivalent durations
+    /// at the given `sample_hz` are stored and reported by `params`, and a later
+    /// `update_to_sample_hz` recalculates the coefficients from those durations as usual.
+    pub fn peak_from_frames(attack_frames: f32,
+                            release_frames: f32,
+                            sample_hz: f64,
+                            threshold: f32,
+                            ratio: f32) -> Self
+    {
+        let attack_ms = ms_for_frames(attack_frames, sample_hz);
+        let release_ms = ms_for_frames(release_frames, sample_hz);
+        let envelope_detector = EnvelopeDetector::peak(attack_frames, release_frames);
+        Compressor::new(envelope_detector, attack_ms, release_ms, sample_hz, threshold, ratio,
+                        EGF::default())
+    }
+
+}
+
+impl<F> PeakAvgCompressor<F>
+    where F: Frame,
+{
+
+    /// Construct a **Compressor** that uses the **Average** across all channels yielded by a
+    /// **Peak** **EnvelopeDetector**
+    pub fn peak_avg<A, R>(attack_ms: A,
+                          release_ms: R,
+                          sample_hz: f64,
+                          threshold: f32,
+                          ratio: f32) -> Self
+        where A: Into<Ms>,
+              R: Into<Ms>,
+    {
+        Self::peak(attack_ms, release_ms, sample_hz, threshold, ratio)
+    }
+
+}
+
+impl<F> PeakMinCompressor<F>
+    where F: Frame,
+{
+
+    /// Construct a **Compressor** that uses the **Minimum** across all channels yielded by a
+    /// **Peak** **EnvelopeDetector**
+    pub fn peak_min<A, R>(attack_ms: A,
+                          release_ms: R,
+                          sample_hz: f64,
+                          threshold: f32,
+                          ratio: f32) -> Self
+        where A: Into<Ms>,
+              R: Into<Ms>,
+    {
+        Self::peak(attack_ms, release_ms, sample_hz, threshold, ratio)
+    }
+
+}
+
+impl<F, EGF> RmsCompressor<F, EGF>
+    where F: Frame,
+          EGF: EvenGainFunction + Default,
+{
+
+    /// Construct a **Compressor** that uses an **Rms** **EnvelopeDetector**.
+    ///
+    /// The RMS window always spans at least one frame, however short the given `window_ms`.
+    pub fn rms<W, A, R>(window_ms: W,
+                        attack_ms: A,
+                        release_ms: R,
+                        sample_hz: f64,
+                        threshold: f32,
+                        ratio: f32) -> Self
+        where W: Into<Ms>,
+              A: Into<Ms>,
+              R: Into<Ms>,
+    {
+        Self::rms_windowed(RmsWindow::Rectangular,
+                           window_ms,
+                           attack_ms,
+                           release_ms,
+                           sample_hz,
+                           threshold,
+                           ratio)
+    }
+
+    /// Construct a **Compressor** that uses an **Rms** **EnvelopeDetector** whose window has the
+    /// given shape.
+    ///
+    /// A **Hann** window gives a smoother envelope than the default **Rectangular** window at the
+    /// cost of work proportional to the window length for every frame.
+    pub fn rms_windowed<W, A, R>(shape: RmsWindow,
+                                 window_ms: W,
+                                 attack_ms: A,
+                                 release_ms: R,
+                                 sample_hz: f64,
+                                 threshold: f32,
+                                 ratio: f32) -> Self
+        where W: Into<Ms>,
+              A: Into<Ms>,
+              R: Into<Ms>,
+    {
+        let window_ms: Ms = window_ms.into();
+        let attack_ms: Ms = attack_ms.into();
+        let release_ms: Ms = release_ms.into();
+        let window_frames = detector::rms_window_frames(window_ms, sample_hz);
+        let attack_frames = attack_ms.samples(sample_hz) as f32;
+        let release_frames = release_ms.samples(sample_hz) as f32;
+        let rms = Rms::with_shape(window_frames, shape);
+        let envelope_detector = EnvelopeDetector::new(rms, attack_frames, release_frames);
+        let rms_envelope_detector = RmsEnvelopeDetector {
+            rms: envelope_detector,
+            window_ms: window_ms,
+        };
+        Compressor::new(rms_envelope_detector, attack_ms, release_ms, sample_hz, threshold, ratio,
+                        EGF::default())
+    }
+
+    /// Construct a **Compressor** that uses an **Rms** **EnvelopeDetector** whose window, attack
+    /// and release are given directly as a number of frames.
+    ///
+    /// As with `peak_from_frames`, the detector is configured from the frames as given and the
+    /// equivalent durations at the given `sample_hz` are stored and reported by `params`. The
+    /// window always spans at least one frame.
+    pub fn rms_from_frames(window_frames: usize,
+                           attack_frames: f32,
+                           release_frames: f32,
+                           sample_hz: f64,
+                           threshold: f32,
+                           ratio: f32) -> Self
+    {
+        let window_frames = if window_frames < 1 { 1 } else { window_frames };
+        let window_ms = ms_for_frames(window_frames as f32, sample_hz);
+        let attack_ms = ms_for_frames(attack_frames, sample_hz);
+        let release_ms = ms_for_frames(release_frames, sample_hz);
+        let rms = Rms::with_shape(window_frames, RmsWindow::Rectangular);
+        let envelope_detector = EnvelopeDetector::new(rms, attack_frames, release_frames);
+        let rms_envelope_detector = RmsEnvelopeDetector {
+            rms: envelope_detector,
+            window_ms: window_ms,
+        };
+        Compressor::new(rms_envelope_detector, attack_ms, release_ms, sample_hz, threshold, ratio,
+                        EGF::default())
+    }
+
+}
+
+impl<F, EGF> RmsCompressor<F, EGF>
+    where F: Frame,
+          EGF: EvenGainFunction,
+{
+
+    /// Set the duration of the envelope's RMS window in milliseconds.
+    ///
+    /// The window always spans at least one frame, however short the given duration.
+    pub fn set_window_ms<M: Into<Ms>>(&mut self, ms: M, sample_hz: f64) {
+        let ms: Ms = ms.into();
+        self.envelope_detector.window_ms = ms;
+        self.update_window_to_sample_hz(sample_hz);
+    }
+
+    /// Updates the **Compressor**'s window size in frames via the given sample_hz.
+    pub fn update_window_to_sample_hz(&mut self, sample_hz: f64) {
+        assert_valid_sample_hz(sample_hz, "update_window_to_sample_hz");
+        self.envelope_detector.update_to_sample_hz(sample_hz);
+    }
+
+}
+
+impl<F, EGF> BlendCompressor<F, EGF>
+    where F: Frame,
+          EGF: EvenGainFunction + Default,
+{
+
+    /// Construct a **Compressor** whose detector blends between **Peak** and **Rms** detection,
+    /// starting from an even blend of `detector::DEFAULT_DETECTION_MIX`.
+    ///
+    /// Both detections run for every frame, so this costs roughly the sum of a peak and an RMS
+    /// **Compressor**'s detection, i.e. little more than RMS detection alone for a
+    /// **Rectangular** window.
+    pub fn blend<W, A, R>(window_ms: W,
+                          attack_ms: A,
+                          release_ms: R,
+                          sample_hz: f64,
+                          threshold: f32,
+                          ratio: f32) -> Self
+        where W: Into<Ms>,
+              A: Into<Ms>,
+              R: Into<Ms>,
+    {
+        let window_ms: Ms = window_ms.into();
+        let attack_ms: Ms = attack_ms.into();
+        let release_ms: Ms = release_ms.into();
+        let attack_frames = attack_ms.samples(sample_hz) as f32;
+        let release_frames = release_ms.samples(sample_hz) as f32;
+        let detector = BlendEnvelopeDetector::new(RmsWindow::Rectangular,
+                                                  window_ms,
+                                                  detector::DEFAULT_DETECTION_MIX,
+                                                  attack_frames,
+                                                  release_frames,
+                                                  sample_hz);
+        Compressor::new(detector, attack_ms, release_ms, sample_hz, threshold, ratio,
+                        EGF::default())
+    }
+
+}
+
+impl<F, EGF> BlendCompressor<F, EGF>
+    where F: Frame,
+          EGF: EvenGainFunction,
+{
+
+    /// Set the blend between detections, where `0.0` is pure **Rms** detection and `1.0` is pure
+    /// **Peak** detection. Values outside of this range are clamped.
+    ///
+    /// The blended level is `(1 - mix) * rms + mix * peak` for each channel and always lies
+    /// between the pure peak and pure RMS levels.
+    pub fn set_detection_mix(&mut self, mix: f32) {
+        self.envelope_detector.envelope.mode_mut().set_mix(mix);
+    }
+
+    /// The blend between detections, where `0.0` is pure **Rms** and `1.0` is pure **Peak**.
+    pub fn detection_mix(&self) -> f32 {
+        self.envelope_detector.envelope.mode().mix()
+    }
+
+    /// Set the duration of the detector's RMS window in milliseconds.
+    ///
+    /// The window always spans at least one frame, however short the given duration.
+    pub fn set_window_ms<M: Into<Ms>>(&mut self, ms: M, sample_hz: f64) {
+        self.envelope_detector.window_ms = ms.into();
+        self.envelope_detector.update_to_sample_hz(sample_hz);
+    }
+
+}
+
+impl<F, EGF> DualCompressor<F, EGF>
+    where F: Frame,
+          EGF: EvenGainFunction + Default,
+{
+
+    /// Construct a **Compressor** whose detector runs a fast and a slow **Peak** follower.
+    ///
+    /// The `attack_ms` and `release_ms` are those of the slow follower, while the fast follower
+    /// starts with the `detector::DEFAULT_FAST_ATTACK_MS` and `detector::DEFAULT_FAST_RELEASE_MS`.
+    /// See **DualEnvelopeDetector** for how the two are combined.
+    pub fn dual<A, R>(attack_ms: A,
+                      release_ms: R,
+                      sample_hz: f64,
+                      threshold: f32,
+                      ratio: f32) -> Self
+        where A: Into<Ms>,
+              R: Into<Ms>,
+    {
+        let attack_ms: Ms = attack_ms.into();
+        let release_ms: Ms = release_ms.into();
+        let attack_frames = attack_ms.samples(sample_hz) as f32;
+        let release_frames = release_ms.samples(sample_hz) as f32;
+        let detector = DualEnvelopeDetector::new(attack_frames, release_frames, sample_hz);
+        Compressor::new(detector, attack_ms, release_ms, sample_hz, threshold, ratio,
+                        EGF::default())
+    }
+
+}
+
+impl<F, EGF> DualCompressor<F, EGF>
+    where F: Frame,
+          EGF: EvenGainFunction,
+{
+
+    /// Set how far the envelope is pulled toward the fast follower while it leads the slow one,
+    /// from `0.0` (the slow follower alone) to `1.0` (the greater of the two). Values outside of
+    /// this range are clamped.
+    ///
+    /// This only affects the attack: whenever the signal falls, the fast follower drops below the
+    /// slow one and the envelope releases with the slow follower's `release_ms` regardless of the
+    /// weight. A weight of `1.0` therefore attacks as quickly as the fast follower while
+    /// releasing as slowly as the slow one. Defaults to `1.0`.
+    pub fn set_envelope_blend(&mut self, fast_weight: f32) {
+        self.envelope_detector.set_fast_weight(fast_weight);
+    }
+
+    /// How far the envelope is pulled toward the fast follower while it leads the slow one.
+    pub fn envelope_blend(&self) -> f32 {
+        self.envelope_detector.fast_weight()
+    }
+
+    /// Set the attack and release durations of the fast follower in milliseconds.
+    pub fn set_fast_ms<A, R>(&mut self, attack_ms: A, release_ms: R, sample_hz: f64)
+        where A: Into<Ms>,
+              R: Into<Ms>,
+    {
+        self.envelope_detector.fast_attack_ms = attack_ms.into();
+        self.envelope_detector.fast_release_ms = release_ms.into();
+        self.envelope_detector.update_to_sample_hz(sample_hz);
+    }
+
+}
+
+impl<F> RmsAvgCompressor<F>
+    where F: Frame,
+{
+
+    /// Construct a **Compressor** that uses the **Average** across all channels yielded by a
+    /// **Rms** **EnvelopeDetector**
+    pub fn rms_avg<W, A, R>(window_ms: W,
+                            attack_ms: A,
+                            release_ms: R,
+                            sample_hz: f64,
+                            threshold: f32,
+                            ratio: f32) -> Self
+        where W: Into<Ms>,
+              A: Into<Ms>,
+              R: Into<Ms>,
+    {
+        Self::rms(window_ms, attack_ms, release_ms, sample_hz, threshold, ratio)
+    }
+
+}
+
+impl<F> RmsAvgCompressor<F>
+    where F: Frame,
+{
+
+    /// Construct a bus "glue" **Compressor**.
+    ///
+    /// The signal flow is:
+    ///
+    /// 1. All channels are summed to mono (see `DetectionMode::MonoSum`).
+    /// 2. The sum is high-passed at `BUS_GLUE_SIDECHAIN_HPF_HZ` so that the low end doesn't
+    ///    dominate detection.
+    /// 3. The **Rms** envelope of the filtered sum is detected.
+    /// 4. The resulting gain is applied identically to every channel of the signal.
+    #[cfg(feature = "sidechain-filter")]
+    pub fn bus_glue<W, A, R>(window_ms: W,
+                             attack_ms: A,
+                             release_ms: R,
+                             sample_hz: f64,
+                             threshold: f32,
+                             ratio: f32) -> Self
+        where W: Into<Ms>,
+              A: Into<Ms>,
+              R: Into<Ms>,
+    {
+        let mut compressor =
+            Self::rms(window_ms, attack_ms, release_ms, sample_hz, threshold, ratio);
+        compressor.set_detection_mode(DetectionMode::MonoSum);
+        compressor.set_sidechain_hpf(Some(BUS_GLUE_SIDECHAIN_HPF_HZ), sample_hz);
+        compressor
+    }
+
+}
+
+impl<F> RmsMinCompressor<F>
+    where F: Frame,
+{
+
+    /// Construct a **Compressor** that uses the **Minimum** across all channels yielded by a
+    /// **Rms** **EnvelopeDetector**
+    pub fn rms_min<W, A, R>(window_ms: W,
+                            attack_ms: A,
+                            release_ms: R,
+                            sample_hz: f64,
+                            threshold: f32,
+                            ratio: f32) -> Self
+        where W: Into<Ms>,
+              A: Into<Ms>,
+              R: Into<Ms>,
+    {
+        Self::rms(window_ms, attack_ms, release_ms, sample_hz, threshold, ratio)
+    }
+
+}
+
+impl<I, F, D, EGF> Iterator for CompressWithGain<I, F, D, EGF>
+    where I: Iterator<Item=F>,
+          F: Frame,
+          D: Detector<F::Float>,
+          EGF: EvenGainFunction,
+{
+    type Item = (F, f32);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.frames.next().map(|frame| {
+            let (output, gain, _) = self.compressor.next_frame_and_gain(frame, frame);
+            (output, gain)
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.frames.size_hint()
+    }
+}
+
+impl<I, F, D, EGF> std::fmt::Debug for CompressWithGain<I, F, D, EGF>
+    where I: std::fmt::Debug,
+          F: Frame,
+          Compressor<F, D, EGF>: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "CompressWithGain {{ frames: {:?}, compressor: {:?} }}",
+               &self.frames, &self.compressor)
+    }
+}
+
+impl<F, EGF> TryFrom<DynamicCompressor<F>> for PeakCompressor<F, EGF>
+    where F: Frame,
+          EGF: EvenGainFunction + TryFrom<EvenGainKind>,
+{
+    type Error = DynamicCompressor<F>;
+
+    /// Convert a **DynamicCompressor** performing **Peak** detection, retaining all state.
+    ///
+    /// Returns the **DynamicCompressor** unchanged as the `Err` if it performs **Rms** detection
+    /// or if its **EvenGainKind** differs from `EGF`.
+    fn try_from(compressor: DynamicCompressor<F>) -> Result<Self, Self::Error> {
+        if compressor.envelope_detector.kind() != DetectorKind::Peak {
+            return Err(compressor);
+        }
+        let even_gain_fn = match EGF::try_from(compressor.even_gain_fn) {
+            Ok(even_gain_fn) => even_gain_fn,
+            Err(_) => return Err(compressor),
+        };
+        Ok(compressor.map_parts(|detector, _| match detector.into_peak() {
+            Some(peak) => (peak, even_gain_fn),
+            None => unreachable!(),
+        }))
+    }
+}
+
+impl<F, EGF> TryFrom<DynamicCompressor<F>> for RmsCompressor<F, EGF>
+    where F: Frame,
+          EGF: EvenGainFunction + TryFrom<EvenGainKind>,
+{
+    type Error = DynamicCompressor<F>;
+
+    /// Convert a **DynamicCompressor** performing **Rms** detection, retaining all state, e.g. in
+    /// order to reach RMS-only methods such as `set_window_ms`.
+    ///
+    /// Returns the **DynamicCompressor** unchanged as the `Err` if it performs **Peak** detection
+    /// or if its **EvenGainKind** differs from `EGF`.
+    fn try_from(compressor: DynamicCompressor<F>) -> Result<Self, Self::Error> {
+        if compressor.envelope_detector.kind() == DetectorKind::Peak {
+            return Err(compressor);
+        }
+        let even_gain_fn = match EGF::try_from(compressor.even_gain_fn) {
+            Ok(even_gain_fn) => even_gain_fn,
+            Err(_) => return Err(compressor),
+        };
+        Ok(compressor.map_parts(|detector, _| match detector.into_rms() {
+            Some(rms) => (rms, even_gain_fn),
+            None => unreachable!(),
+        }))
+    }
+}
+
+impl<F, EGF> From<PeakCompressor<F, EGF>> for DynamicCompressor<F>
+    where F: Frame,
+          EGF: EvenGainFunction,
+          EvenGainKind: From<EGF>,
+{
+    fn from(compressor: PeakCompressor<F, EGF>) -> Self {
+        compressor.map_parts(|peak, even_gain_fn| (peak.into(), even_gain_fn.into()))
+    }
+}
+
+impl<F, EGF> From<RmsCompressor<F, EGF>> for DynamicCompressor<F>
+    where F: Frame,
+          EGF: EvenGainFunction,
+          EvenGainKind: From<EGF>,
+{
+    fn from(compressor: RmsCompressor<F, EGF>) -> Self {
+        compressor.map_parts(|rms, even_gain_fn| (rms.into(), even_gain_fn.into()))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_HZ: f64 = 44_100.0;
+
+    fn peak_compressor() -> PeakAvgCompressor<[f32; 2]> {
+        Compressor::peak_avg(1.0, 100.0, SAMPLE_HZ, 0.5, 4.0)
+    }
+
+    #[cfg(feature = "metering")]
+    #[test]
+    fn dynamic_range_reduction_reads_spread_of_gain_reduction() {
+        let mut compressor = peak_compressor();
+        compressor.set_range_window_ms(60_000.0, SAMPLE_HZ);
+        let loud_db = db::amp_to_db(compressor.steady_state_gain(1.0));
+        assert!(loud_db < -1.0);
+        assert_eq!(compressor.steady_state_gain(0.1), 1.0);
+        // A signal alternating 20 dB between a loud and a quiet passage, compressed only while
+        // loud, reads the gain reduction of the loud passage.
+        for _ in 0..4 {
+            let mut loud = [[1.0, 1.0]; 4_410];
+            compressor.compress_slice(&mut loud);
+            let mut quiet = [[0.1, 0.1]; 4_410];
+            compressor.compress_slice(&mut quiet);
+        }
+        let range = compressor.dynamic_range_reduction_db();
+        assert!((range + loud_db).abs() < 0.1, "{} vs {}", range, -loud_db);
+    }
+
+    #[cfg(feature = "metering")]
+    #[test]
+    fn dynamic_range_reduction_of_steady_signal_is_zero() {
+        let mut compressor = peak_compressor();
+        compressor.set_range_window_ms(10.0, SAMPLE_HZ);
+        for _ in 0..10 {
+            let mut frames = [[1.0, 1.0]; 4_410];
+            compressor.compress_slice(&mut frames);
+        }
+        assert!(compressor.current_gain_reduction_db() < -1.0);
+        assert!(compressor.dynamic_range_reduction_db().abs() < 1e-3);
+    }
+
+    #[test]
+    fn sidechain_delay_shifts_signal_by_its_length() {
+        let mut compressor = peak_compressor();
+        compressor.set_sidechain_delay_frames(16);
+        assert_eq!(compressor.sidechain_delay_frames(), 16);
+        let input: Vec<[f32; 2]> = (0..256).map(|i| [i as f32 / 1_000.0, 0.0]).collect();
+        let output: Vec<[f32; 2]> = input.iter().map(|&f| compressor.next_frame(f)).collect();
+        assert!(output[..16].iter().all(|f| *f == [0.0; 2]));
+        assert_eq!(&output[16..], &input[..240]);
+    }
+
+    #[test]
+    fn sidechain_delay_lets_gain_lead_the_signal() {
+        let mut aligned = peak_compressor();
+        let mut delayed = peak_compressor();
+        delayed.set_sidechain_delay_frames(128);
+        let mut step = vec![[0.0; 2]; 128];
+        step.extend(vec![[1.0, 1.0]; 256]);
+        let first_loud = |compressor: &mut PeakAvgCompressor<[f32; 2]>, latency: usize| {
+            let output: Vec<_> = step.iter().map(|&f| compressor.next_frame(f)).collect();
+            output[128 + latency][0]
+        };
+        assert_eq!(first_loud(&mut aligned, 0), 1.0);
+        assert!(first_loud(&mut delayed, 128) < 0.9);
+    }
+
+    #[test]
+    fn shortening_sidechain_delay_drops_oldest_frames() {
+        let mut compressor = peak_compressor();
+        compressor.set_sidechain_delay_frames(4);
+        for i in 0..4 {
+            compressor.next_frame([i as f32 / 10.0; 2]);
+        }
+        compressor.set_sidechain_delay_frames(2);
+        assert_eq!(compressor.next_frame([0.0; 2]), [0.2; 2]);
+        assert_eq!(compressor.next_frame([0.0; 2]), [0.3; 2]);
+    }
+
+    #[test]
+    fn cloned_compressor_tracks_its_own_signal() {
+        let mut original: RmsAvgCompressor<[f32; 2]> =
+            Compressor::rms_avg(10.0, 1.0, 100.0, SAMPLE_HZ, 0.5, 4.0);
+        original.set_sidechain_delay_frames(8);
+        let mut loud = [[1.0, 1.0]; 4_410];
+        original.compress_slice(&mut loud);
+        let mut clone = original.clone();
+        let mut original_frames = [[1.0, 1.0]; 4_410];
+        let mut clone_frames = [[0.01, 0.01]; 4_410];
+        original.compress_slice(&mut original_frames);
+        clone.compress_slice(&mut clone_frames);
+        assert!(original.current_gain_reduction_db() < -1.0);
+        assert_eq!(clone.current_gain_reduction_db(), 0.0);
+        assert_eq!(clone_frames[4_409], [0.01, 0.01]);
+    }
+
+    #[test]
+    fn transfer_curve_spans_the_drawn_range() {
+        let curve = peak_compressor().sample_transfer_curve(61);
+        assert_eq!(curve.len(), 61);
+        assert_eq!(curve[0].0, TRANSFER_CURVE_MIN_DB);
+        assert!((curve[60].0 - TRANSFER_CURVE_MAX_DB).abs() < 1e-4);
+        assert!((curve[1].0 - curve[0].0 - 1.0).abs() < 1e-4);
+        let single = peak_compressor().sample_transfer_curve(1);
+        assert_eq!(single, vec![(TRANSFER_CURVE_MIN_DB, TRANSFER_CURVE_MIN_DB)]);
+        assert!(peak_compressor().sample_transfer_curve(0).is_empty());
+    }
+
+    #[test]
+    fn transfer_curve_is_unity_below_threshold_and_compresses_above() {
+        let compressor = peak_compressor();
+        let threshold_db = db::amp_to_db(0.5);
+        for (input_db, output_db) in compressor.sample_transfer_curve(61) {
+            if input_db < threshold_db {
+                assert!((output_db - input_db).abs() < 1e-4);
+            } else if input_db > threshold_db + 1.0 {
+                assert!(output_db < input_db);
+            }
+        }
+    }
+
+    #[test]
+    fn integer_frames_below_threshold_pass_untouched() {
+        let mut compressor: PeakAvgCompressor<[i16; 2]> =
+            Compressor::peak_avg(1.0, 100.0, SAMPLE_HZ, 0.5, 4.0);
+        let mut frames: Vec<[i16; 2]> = (0..512i16).map(|i| [i * 31, -i * 17]).collect();
+        let original = frames.clone();
+        compressor.compress_slice(&mut frames);
+        assert_eq!(frames, original);
+    }
+
+    #[test]
+    fn integer_frames_compress_like_float_frames() {
+        let mut int_compressor: PeakAvgCompressor<[i32; 1]> =
+            Compressor::peak_avg(1.0, 100.0, SAMPLE_HZ, 0.25, 4.0);
+        let mut float_compressor: PeakAvgCompressor<[f32; 1]> =
+            Compressor::peak_avg(1.0, 100.0, SAMPLE_HZ, 0.25, 4.0);
+        for _ in 0..4_410 {
+            let int = int_compressor.next_frame([1 << 30]);
+            let float = float_compressor.next_frame([0.5]);
+            assert!((int[0] as f32 / 2_147_483_648.0 - float[0]).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn dither_defaults_to_none_and_is_configurable() {
+        let mut compressor: PeakAvgCompressor<[i16; 2]> =
+            Compressor::peak_avg(1.0, 100.0, SAMPLE_HZ, 0.5, 4.0);
+        assert_eq!(compressor.dither(), DitherKind::None);
+        compressor.set_dither(DitherKind::Rectangular);
+        assert_eq!(compressor.dither(), DitherKind::Rectangular);
+    }
+
+    fn params() -> CompressorParams {
+        CompressorParams {
+            threshold: 0.25,
+            ratio: 8.0,
+            attack_ms: 3.0,
+            release_ms: 250.0,
+            window_ms: Some(20.0),
+            knee_db: 6.0,
+            makeup_gain: 2.0,
+            auto_makeup: false,
+        }
+    }
+
+    #[test]
+    fn set_all_matches_a_freshly_constructed_compressor() {
+        let mut compressor: RmsAvgCompressor<[f32; 2]> =
+            Compressor::rms_avg(5.0, 1.0, 100.0, SAMPLE_HZ, 0.5, 4.0);
+        compressor.set_all(&params(), SAMPLE_HZ);
+        let fresh: RmsAvgCompressor<[f32; 2]> =
+            Compressor::rms_avg(20.0, 3.0, 250.0, SAMPLE_HZ, 0.25, 8.0);
+        assert_eq!(compressor.threshold, fresh.threshold);
+        assert_eq!(compressor.slope, fresh.slope);
+        assert_eq!(compressor.attack_ms, fresh.attack_ms);
+        assert_eq!(compressor.release_ms, fresh.release_ms);
+        assert_eq!(compressor.envelope_detector.window_ms, fresh.envelope_detector.window_ms);
+    }
+
+    #[test]
+    fn set_all_ignores_the_window_of_peak_detectors() {
+        let mut compressor = peak_compressor();
+        compressor.set_all(&params(), SAMPLE_HZ);
+        assert_eq!(compressor.threshold, 0.25);
+        assert_eq!(compressor.slope, ratio::slope_from_ratio(8.0));
+        assert_eq!(compressor.attack_ms, Ms(3.0));
+        assert_eq!(compressor.release_ms, Ms(250.0));
+    }
+
+    #[test]
+    fn set_all_matches_the_individual_setters() {
+        for &auto_makeup in &[false, true] {
+            let params = CompressorParams { auto_makeup: auto_makeup, ..params() };
+            let mut all: RmsAvgCompressor<[f32; 2]> =
+                Compressor::rms_avg(5.0, 1.0, 100.0, SAMPLE_HZ, 0.5, 4.0);
+            let mut each = all.clone();
+            all.set_all(&params, SAMPLE_HZ);
+            each.threshold = params.threshold;
+            each.slope = ratio::slope_from_ratio(params.ratio);
+            each.set_attack_ms(params.attack_ms, SAMPLE_HZ);
+            each.set_release_ms(params.release_ms, SAMPLE_HZ);
+            each.set_window_ms(params.window_ms.unwrap(), SAMPLE_HZ);
+            each.set_knee_db(params.knee_db);
+            each.set_makeup_gain(params.makeup_gain);
+            each.set_auto_makeup(params.auto_makeup);
+            assert_eq!(all.params(), params);
+            assert_eq!(each.params(), params);
+            assert_eq!(all.knee_db(), each.knee_db());
+            assert_eq!(all.makeup_gain(), each.makeup_gain());
+            assert_eq!(all.auto_makeup(), each.auto_makeup());
+            for i in 0..4_410 {
+                let s = 0.9 * (i as f32 * 0.01).sin();
+                assert_eq!(all.next_frame([s, -s]), each.next_frame([s, -s]));
+            }
+        }
+    }
+
+    #[cfg(feature = "metering")]
+    #[test]
+    fn loudness_matched_bypass_converges_to_the_compressed_level() {
+        let mut compressor = peak_compressor();
+        let mut twin = peak_compressor();
+        let mut bypassed = [0.0; 2];
+        let mut compressed = [0.0; 2];
+        for _ in 0..SAMPLE_HZ as usize * 2 {
+            bypassed = compressor.loudness_matched_bypass([0.9, -0.9]);
+            compressed = twin.next_frame([0.9, -0.9]);
+        }
+        assert!(compressed[0] < 0.8);
+        assert!((bypassed[0] - compressed[0]).abs() < 1e-3, "{} {}", bypassed[0], compressed[0]);
+        assert_eq!(bypassed[1], -bypassed[0]);
+    }
+
+    #[test]
+    fn reserving_leaves_the_output_unchanged() {
+        let mut plain: RmsAvgCompressor<[f32; 2]> =
+            Compressor::rms_avg(5.0, 1.0, 100.0, SAMPLE_HZ, 0.25, 4.0);
+        let mut reserved = plain.clone().with_reserved(44_100, 4_410, SAMPLE_HZ);
+        plain.set_sidechain_delay_frames(64);
+        reserved.set_sidechain_delay_frames(64);
+        assert_eq!(reserved.envelope_detector.window_ms, plain.envelope_detector.window_ms);
+        assert!(reserved.signal_delay.capacity() >= 4_410);
+        for i in 0..4_410 {
+            let s = (i as f32 * 0.05).sin();
+            assert_eq!(reserved.next_frame([s, -s]), plain.next_frame([s, -s]));
+        }
+        plain.set_window_ms(500.0, SAMPLE_HZ);
+        reserved.set_window_ms(500.0, SAMPLE_HZ);
+        for i in 0..4_410 {
+            let s = (i as f32 * 0.05).sin();
+            assert_eq!(reserved.next_frame([s, -s]), plain.next_frame([s, -s]));
+        }
+    }
+
+    #[test]
+    fn copied_coefficients_produce_identical_gain() {
+        let mut source = peak_compressor();
+        let mut copy: PeakAvgCompressor<[f32; 2]> =
+            Compressor::peak_avg(7.0, 300.0, 48_000.0, 0.5, 2.0);
+        copy.set_coefficients(source.coefficients());
+        assert_eq!(copy.coefficients(), source.coefficients());
+        for i in 0..4_410 {
+            let s = (i as f32 * 0.01).sin();
+            assert_eq!(copy.next_gain([s, s]), source.next_gain([s, s]));
+        }
+    }
+
+    #[test]
+    fn hann_windowed_compressor_settles_like_a_rectangular_one() {
+        let mut hann: RmsAvgCompressor<[f32; 2]> =
+            Compressor::rms_windowed(RmsWindow::Hann, 10.0, 1.0, 100.0, SAMPLE_HZ, 0.25, 4.0);
+        let mut rectangular: RmsAvgCompressor<[f32; 2]> =
+            Compressor::rms_avg(10.0, 1.0, 100.0, SAMPLE_HZ, 0.25, 4.0);
+        let (mut hann_gain, mut rectangular_gain) = (0.0, 0.0);
+        for _ in 0..4_410 {
+            hann_gain = hann.next_gain([0.8, -0.8]);
+            rectangular_gain = rectangular.next_gain([0.8, -0.8]);
+        }
+        assert!(hann_gain < 0.9);
+        assert!((hann_gain - rectangular_gain).abs() < 1e-4);
+    }
+
+    #[cfg(feature = "sidechain-filter")]
+    #[test]
+    fn bus_glue_ignores_sub_bass_and_links_every_channel() {
+        let mut compressor: RmsAvgCompressor<[f32; 2]> =
+            Compressor::bus_glue(10.0, 1.0, 100.0, SAMPLE_HZ, 0.1, 4.0);
+        assert_eq!(compressor.detection_mode(), DetectionMode::MonoSum);
+        assert_eq!(compressor.sidechain_hpf_hz(), Some(BUS_GLUE_SIDECHAIN_HPF_HZ));
+        let mut output = [0.0; 2];
+        let step = 2.0 * std::f32::consts::PI * 20.0 / SAMPLE_HZ as f32;
+        for i in 0..SAMPLE_HZ as usize {
+            let bass = 0.8 * (i as f32 * step).sin();
+            output = compressor.next_frame([bass, bass]);
+        }
+        assert!(compressor.current_gain_reduction_db() > -0.5);
+        assert_eq!(output[0], output[1]);
+    }
+
+    #[test]
+    fn integer_results_beyond_full_scale_saturate() {
+        // A ratio below 1:1 raises the gain above unity beyond the threshold.
+        let mut compressor: PeakAvgCompressor<[i16; 2]> =
+            Compressor::peak_avg(1.0, 100.0, SAMPLE_HZ, 0.0, 0.25);
+        let mut frame = [0; 2];
+        for _ in 0..4_410 {
+            frame = compressor.next_frame([20_000, -20_000]);
+        }
+        assert_eq!(frame, [i16::MAX, i16::MIN]);
+    }
+
+    #[test]
+    fn every_channel_is_scaled_by_the_same_gain() {
+        let mut compressor: PeakAvgCompressor<[f32; 3]> =
+            Compressor::peak_avg(1.0, 50.0, SAMPLE_HZ, 0.1, 10.0);
+        for i in 0..4_410 {
+            let s = (i as f32 * 0.03).sin();
+            let input = [s, -0.5 * s, 0.25 * s];
+            let output = compressor.next_frame(input);
+            assert_eq!(output[1], output[0] * -0.5);
+            assert_eq!(output[2], output[0] * 0.25);
+        }
+        assert!(compressor.current_gain_reduction_db() < -1.0);
+    }
+
+    #[test]
+    fn warm_start_compresses_the_first_frames_by_the_settled_amount() {
+        let mut settled: RmsAvgCompressor<[f32; 2]> =
+            Compressor::rms_avg(10.0, 10.0, 100.0, SAMPLE_HZ, 0.25, 4.0);
+        let mut cold = settled.clone();
+        let mut warm = settled.clone();
+        let mut settled_gain = 0.0;
+        for _ in 0..SAMPLE_HZ as usize {
+            settled_gain = settled.next_gain([0.8, -0.8]);
+        }
+        warm.warm_start([0.8, 0.8]);
+        let warm_gain = warm.next_gain([0.8, -0.8]);
+        let cold_gain = cold.next_gain([0.8, -0.8]);
+        assert!((warm_gain - settled_gain).abs() < 1e-3, "{} {}", warm_gain, settled_gain);
+        assert!(cold_gain > 0.99);
+    }
+
+    #[test]
+    fn slope_percent_maps_to_the_ratio() {
+        let mut compressor = peak_compressor();
+        assert!((compressor.slope_percent() - 75.0).abs() < 1e-4);
+        compressor.set_slope_percent(50.0);
+        assert!((1.0 / (1.0 - compressor.slope) - 2.0).abs() < 1e-4);
+        compressor.set_slope_percent(100.0);
+        assert_eq!(1.0 / (1.0 - compressor.slope), std::f32::INFINITY);
+        compressor.set_slope_percent(150.0);
+        assert_eq!(compressor.slope_percent(), 100.0);
+        compressor.set_slope_percent(-10.0);
+        assert_eq!(compressor.slope_percent(), 0.0);
+        assert_eq!(1.0 / (1.0 - compressor.slope), 1.0);
+    }
+
+    #[test]
+    fn punch_delays_the_onset_of_gain_reduction() {
+        let mut plain = peak_compressor();
+        let mut punchy = peak_compressor();
+        punchy.set_punch_ms(1.0, SAMPLE_HZ);
+        let punch_frames = 44;
+        for _ in 0..2 {
+            let onset = (0..1_000).position(|_| plain.next_gain([1.0, 1.0]) < 1.0).unwrap();
+            for _ in 0..onset + punch_frames {
+                assert_eq!(punchy.next_gain([1.0, 1.0]), 1.0);
+            }
+            assert!(punchy.next_gain([1.0, 1.0]) < 1.0);
+            // Once the envelope falls back below the threshold, the punch is re-armed.
+            for _ in 0..SAMPLE_HZ as usize {
+                plain.next_gain([0.0, 0.0]);
+                punchy.next_gain([0.0, 0.0]);
+            }
+        }
+    }
+
+    #[test]
+    fn parameter_smoothing_glides_to_a_new_threshold() {
+        let mut compressor = peak_compressor();
+        compressor.set_parameter_smoothing_ms(10.0, SAMPLE_HZ);
+        let mut target: PeakAvgCompressor<[f32; 2]> =
+            Compressor::peak_avg(1.0, 100.0, SAMPLE_HZ, 0.25, 4.0);
+        let mut gain = 0.0;
+        for _ in 0..4_410 {
+            gain = compressor.next_gain([0.8, 0.8]);
+            target.next_gain([0.8, 0.8]);
+        }
+        compressor.nudge_threshold_db(db::amp_to_db(0.5));
+        let first = compressor.next_gain([0.8, 0.8]);
+        assert!(first < gain && gain - first < 0.01);
+        let mut target_gain = 0.0;
+        for _ in 0..4_410 {
+            gain = compressor.next_gain([0.8, 0.8]);
+            target_gain = target.next_gain([0.8, 0.8]);
+        }
+        assert!((gain - target_gain).abs() < 0.01 * (1.0 - target_gain));
+    }
+
+    #[test]
+    fn nudges_are_relative_to_the_current_parameters() {
+        let mut compressor = peak_compressor();
+        compressor.nudge_threshold_db(-6.0);
+        assert!((compressor.threshold - 0.5 * db::db_to_amp(-6.0)).abs() < 1e-6);
+        compressor.nudge_ratio(0.5);
+        assert!((1.0 / (1.0 - compressor.slope) - 4.5).abs() < 1e-4);
+        compressor.nudge_ratio(-10.0);
+        assert_eq!(compressor.slope, 0.0);
+    }
+
+    fn test_signal(n_frames: usize) -> Vec<[f32; 2]> {
+        (0..n_frames).map(|i| {
+            let s = (i as f32 * 0.02).sin() * if i % 2_000 < 1_000 { 1.0 } else { 0.1 };
+            [s, -0.5 * s]
+        }).collect()
+    }
+
+    #[test]
+    fn computing_then_applying_gain_matches_compress_slice() {
+        let mut split = peak_compressor();
+        split.set_sidechain_delay_frames(16);
+        let mut whole = split.clone();
+        let mut frames = test_signal(8_000);
+        let mut expected = frames.clone();
+        let mut gains = vec![0.0; frames.len()];
+        split.compute_gain_slice(&frames, &mut gains);
+        split.apply_gain_slice(&mut frames, &gains);
+        whole.compress_slice(&mut expected);
+        assert_eq!(frames, expected);
+        assert!(gains.iter().any(|&g| g < 0.9));
+    }
+
+    #[test]
+    fn gain_computed_from_one_signal_applies_to_another() {
+        let mut compressor = peak_compressor();
+        let key = vec![[1.0, 1.0]; 4_410];
+        let mut gains = vec![0.0; key.len()];
+        compressor.compute_gain_slice(&key, &mut gains);
+        let mut frames = vec![[0.1, -0.1]; 4_410];
+        compressor.apply_gain_slice(&mut frames, &gains);
+        for (frame, &gain) in frames.iter().zip(&gains) {
+            assert_eq!(*frame, [0.1 * gain, -0.1 * gain]);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn gain_slices_must_match_the_frames_in_length() {
+        peak_compressor().compute_gain_slice(&[[0.0; 2]; 4], &mut [0.0; 3]);
+    }
+
+    #[test]
+    fn threshold_db_is_relative_to_the_db_reference() {
+        let mut compressor = peak_compressor();
+        assert_eq!(compressor.db_reference(), DEFAULT_DB_REFERENCE);
+        assert!((compressor.threshold_db() - db::amp_to_db(0.5)).abs() < 1e-4);
+        compressor.set_db_reference(db::db_to_amp(-18.0));
+        compressor.set_threshold_db(0.0);
+        assert!((compressor.threshold - db::db_to_amp(-18.0)).abs() < 1e-6);
+        assert!(compressor.threshold_db().abs() < 1e-4);
+        compressor.nudge_threshold_db(-6.0);
+        assert!((compressor.threshold_db() + 6.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn transfer_curve_follows_the_db_reference() {
+        let mut compressor = peak_compressor();
+        let full_scale = compressor.output_db_for_input_db(0.0);
+        compressor.set_db_reference(0.5);
+        assert!((compressor.output_db_for_input_db(-20.0) + 20.0).abs() < 1e-4);
+        assert!(compressor.output_db_for_input_db(0.0) > full_scale);
+    }
+
+    #[test]
+    fn zero_length_rms_window_still_detects() {
+        let mut compressor: RmsAvgCompressor<[f32; 2]> =
+            Compressor::rms_avg(0.0, 1.0, 100.0, SAMPLE_HZ, 0.25, 4.0);
+        for _ in 0..4_410 {
+            compressor.next_frame([0.8, -0.8]);
+        }
+        assert!(compressor.current_gain_reduction_db() < -1.0);
+        compressor.set_window_ms(0.0, SAMPLE_HZ);
+        assert_eq!(compressor.envelope_detector.window_frames(), 1);
+    }
+
+    #[test]
+    fn would_clip_follows_the_static_transfer_curve() {
+        let mut compressor = peak_compressor();
+        assert!(!compressor.would_clip(0.9));
+        assert!(!compressor.would_clip(-1.0));
+        assert!(!compressor.would_clip(1.2));
+        compressor.set_slope_percent(0.0);
+        assert!(!compressor.would_clip(1.0));
+        assert!(compressor.would_clip(1.2));
+        assert!(compressor.would_clip(-1.2));
+    }
+
+    #[test]
+    fn smoothing_modes_default_to_exponential() {
+        let mut compressor = peak_compressor();
+        assert_eq!(compressor.attack_mode(), SmoothingMode::Exponential);
+        assert_eq!(compressor.release_mode(), SmoothingMode::Exponential);
+        compressor.set_attack_mode(SmoothingMode::Linear);
+        compressor.set_release_mode(SmoothingMode::Hold);
+        assert_eq!(compressor.attack_mode(), SmoothingMode::Linear);
+        assert_eq!(compressor.release_mode(), SmoothingMode::Hold);
+    }
+
+    #[test]
+    fn frozen_gain_holds_until_unfrozen() {
+        let mut compressor = peak_compressor();
+        let mut gain = 1.0;
+        for _ in 0..4_410 {
+            gain = compressor.next_gain([0.9, 0.9]);
+        }
+        assert!(gain < 0.9);
+        compressor.set_freeze(true);
+        assert!(compressor.is_frozen());
+        for _ in 0..4_410 {
+            assert_eq!(compressor.next_gain([0.0, 0.0]), gain);
+        }
+        assert_eq!(compressor.next_frame([0.2, 0.2]), [0.2 * gain, 0.2 * gain]);
+        compressor.set_freeze(false);
+        let released = compressor.next_gain([0.0, 0.0]);
+        assert!(released > gain && released < 1.0);
+    }
+
+    #[test]
+    fn latency_is_the_signal_delay() {
+        let mut compressor = peak_compressor();
+        assert_eq!(compressor.latency_frames(), 0);
+        assert_eq!(compressor.latency_ms(SAMPLE_HZ), 0.0);
+        compressor.set_sidechain_delay_frames(441);
+        assert_eq!(compressor.latency_frames(), 441);
+        assert!((compressor.latency_ms(SAMPLE_HZ) - 10.0).abs() < 1e-9);
+        let mut frames = [[0.0, 0.0]; 442];
+        frames[0] = [0.1, 0.1];
+        compressor.compress_slice(&mut frames);
+        let first = frames.iter().position(|f| f[0] != 0.0);
+        assert_eq!(first, Some(compressor.latency_frames()));
+    }
+
+    #[cfg(feature = "automation")]
+    #[test]
+    fn scheduled_threshold_takes_effect_at_its_frame() {
+        let mut compressor: PeakAvgCompressor<[f32; 2]> =
+            Compressor::peak_avg(1.0, 100.0, SAMPLE_HZ, 1.0, 4.0);
+        let mut warm_up = [[0.8, 0.8]; 4_410];
+        compressor.compress_slice(&mut warm_up);
+        compressor.schedule_threshold(100, 0.25);
+        compressor.schedule_threshold(164, 1.0);
+        let mut frames = [[0.8, 0.8]; 128];
+        compressor.compress_slice(&mut frames);
+        assert!(frames[..100].iter().all(|f| *f == [0.8, 0.8]));
+        assert!(frames[100..].iter().all(|f| f[0] < 0.8));
+        // The change beyond the end of the block lands on the 36th frame of the next.
+        let mut frames = [[0.8, 0.8]; 128];
+        compressor.compress_slice(&mut frames);
+        assert!(frames[..36].iter().all(|f| f[0] < 0.8));
+        assert_eq!(frames[36], [0.8, 0.8]);
+    }
+
+    #[test]
+    fn auto_gain_levels_the_output_toward_its_target() {
+        let mut compressor = peak_compressor();
+        compressor.set_auto_gain_ms(100.0, SAMPLE_HZ);
+        compressor.set_auto_gain(Some(-12.0));
+        assert_eq!(compressor.auto_gain_db(), Some(-12.0));
+        let mut output = [0.0; 2];
+        for _ in 0..SAMPLE_HZ as usize * 2 {
+            output = compressor.next_frame([0.1, -0.1]);
+        }
+        assert!((db::amp_to_db(output[0]) + 12.0).abs() < 0.1, "{}", db::amp_to_db(output[0]));
+        compressor.set_auto_gain(None);
+        assert_eq!(compressor.next_frame([0.1, -0.1]), [0.1, -0.1]);
+    }
+
+    #[test]
+    fn auto_attack_is_disabled_by_default() {
+        let mut compressor = peak_compressor();
+        assert!(!compressor.auto_attack());
+        compressor.set_auto_attack(true);
+        assert!(compressor.auto_attack());
+    }
+
+    #[cfg(feature = "sidechain-filter")]
+    #[test]
+    fn detection_emphasis_biases_gain_reduction_toward_its_band() {
+        let sine = |hz: f32| {
+            let step = 2.0 * std::f32::consts::PI * hz / SAMPLE_HZ as f32;
+            move |i: usize| 0.2 * (i as f32 * step).sin()
+        };
+        let reduction = |compressor: &mut PeakAvgCompressor<[f32; 2]>, hz: f32| {
+            let signal = sine(hz);
+            for i in 0..SAMPLE_HZ as usize / 2 {
+                compressor.next_frame([signal(i), signal(i)]);
+            }
+            compressor.current_gain_reduction_db()
+        };
+        let mut compressor: PeakAvgCompressor<[f32; 2]> =
+            Compressor::peak_avg(1.0, 100.0, SAMPLE_HZ, 0.25, 4.0);
+        compressor.set_detection_emphasis(5_000.0, 8_000.0, 12.0, SAMPLE_HZ);
+        assert_eq!(compressor.detection_emphasis(), Some((5_000.0, 8_000.0, 12.0)));
+        assert!(reduction(&mut compressor.clone(), 200.0) > -0.1);
+        assert!(reduction(&mut compressor.clone(), 6_300.0) < -1.0);
+        compressor.clear_detection_emphasis(SAMPLE_HZ);
+        assert_eq!(compressor.detection_emphasis(), None);
+        assert!(reduction(&mut compressor, 6_300.0) > -0.1);
+    }
+
+    #[test]
+    fn process_replacing_matches_compress_slice() {
+        let mut planar = peak_compressor();
+        let mut interleaved = planar.clone();
+        let mut frames = test_signal(8_000);
+        let left: Vec<f32> = frames.iter().map(|f| f[0]).collect();
+        let right: Vec<f32> = frames.iter().map(|f| f[1]).collect();
+        let (mut out_left, mut out_right) = (vec![0.0; 8_000], vec![0.0; 8_000]);
+        planar.process_replacing(&[&left, &right], &mut [&mut out_left, &mut out_right]);
+        interleaved.compress_slice(&mut frames);
+        for (i, frame) in frames.iter().enumerate() {
+            assert_eq!([out_left[i], out_right[i]], *frame);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn process_replacing_requires_a_buffer_per_channel() {
+        let input = [0.0; 16];
+        let mut output = [0.0; 16];
+        peak_compressor().process_replacing(&[&input], &mut [&mut output]);
+    }
+
+    #[test]
+    fn settling_frames_is_when_the_smoothed_threshold_has_settled() {
+        let mut compressor = peak_compressor();
+        assert_eq!(compressor.settling_frames(), 0);
+        compressor.set_parameter_smoothing_ms(5.0, SAMPLE_HZ);
+        let settling_frames = compressor.settling_frames();
+        assert!(settling_frames > 0);
+        compressor.set_threshold_db(db::amp_to_db(0.25));
+        let covered = |compressor: &PeakAvgCompressor<[f32; 2]>| {
+            (0.5 - compressor.smoothed_threshold) / (0.5 - 0.25)
+        };
+        for _ in 0..settling_frames - 1 {
+            compressor.next_gain([0.0, 0.0]);
+        }
+        assert!(covered(&compressor) < SETTLED_FRACTION);
+        compressor.next_gain([0.0, 0.0]);
+        assert!(covered(&compressor) >= SETTLED_FRACTION);
+    }
+
+    #[test]
+    fn skipping_silence_matches_processing_it() {
+        let mut plain: RmsAvgCompressor<[f32; 2]> =
+            Compressor::rms_avg(5.0, 1.0, 20.0, SAMPLE_HZ, 0.25, 4.0);
+        plain.set_sidechain_delay_frames(32);
+        let mut skipping = plain.clone();
+        skipping.set_silence_optimization(true);
+        assert!(skipping.silence_optimization());
+        for block in 0..64 {
+            let loud = block % 32 == 0;
+            let mut frames = if loud { test_signal(512) } else { vec![[0.0, 0.0]; 512] };
+            let mut expected = frames.clone();
+            skipping.compress_slice(&mut frames);
+            plain.compress_slice(&mut expected);
+            for (frame, expected) in frames.iter().zip(&expected) {
+                assert!((frame[0] - expected[0]).abs() < 1e-6);
+                assert!((frame[1] - expected[1]).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn silence_optimization_is_off_by_default() {
+        assert!(!peak_compressor().silence_optimization());
+    }
+
+    #[test]
+    fn converting_to_and_from_dynamic_retains_state() {
+        let mut peak = peak_compressor();
+        let mut frames = test_signal(1024);
+        peak.compress_slice(&mut frames);
+        let mut dynamic = DynamicCompressor::from(peak.clone());
+        assert!(RmsAvgCompressor::try_from(dynamic.clone()).is_err());
+        let mut back = PeakAvgCompressor::try_from(dynamic.clone()).unwrap();
+        for frame in test_signal(1024) {
+            let expected = peak.next_frame(frame);
+            assert_eq!(dynamic.next_frame(frame), expected);
+            assert_eq!(back.next_frame(frame), expected);
+        }
+    }
+
+    #[test]
+    fn converting_from_dynamic_requires_a_matching_even_gain_kind() {
+        let dynamic: DynamicCompressor<[f32; 2]> = CompressorBuilder::new().build(SAMPLE_HZ);
+        let dynamic = PeakMinCompressor::try_from(dynamic).unwrap_err();
+        assert!(PeakAvgCompressor::try_from(dynamic).is_ok());
+    }
+
+    /// The frames taken for the gain reduction to fall below each of `thresholds_db` once the
+    /// loud signal driving the compressor stops.
+    fn recovery_frames(compressor: &mut PeakAvgCompressor<[f32; 2]>, thresholds_db: &[f32])
+        -> Vec<usize>
+    {
+        for _ in 0..4410 {
+            compressor.next_frame([1.0, 1.0]);
+        }
+        let mut frames = vec![0; thresholds_db.len()];
+        for n in 1..441_000 {
+            compressor.next_frame([0.0, 0.0]);
+            let reduction = -compressor.current_gain_reduction_db();
+            for (frames, &threshold) in frames.iter_mut().zip(thresholds_db) {
+                if *frames == 0 && reduction < threshold {
+                    *frames = n;
+                }
+            }
+        }
+        frames
+    }
+
+    #[test]
+    fn two_stage_release_is_fast_then_slow() {
+        let mut fast = Compressor::peak_avg(1.0, 10.0, SAMPLE_HZ, 0.1, 10.0);
+        let mut slow = Compressor::peak_avg(1.0, 1000.0, SAMPLE_HZ, 0.1, 10.0);
+        let mut staged = fast.clone();
+        staged.set_release_stages(10.0, 1000.0, 3.0, SAMPLE_HZ);
+        assert_eq!(staged.release_stages(), Some((Ms(10.0), Ms(1000.0), 3.0)));
+        let thresholds = [6.0, 1.0];
+        let fast = recovery_frames(&mut fast, &thresholds);
+        let slow = recovery_frames(&mut slow, &thresholds);
+        let staged = recovery_frames(&mut staged, &thresholds);
+        // Deep reduction recovers at the fast pace, the final decibels at the slow one.
+        assert!(staged[0] < slow[0] / 10);
+        assert!(staged[1] - staged[0] > (fast[1] - fast[0]) * 10);
+    }
+
+    #[test]
+    fn clearing_release_stages_restores_the_single_release() {
+        let mut compressor = peak_compressor();
+        let mut expected = compressor.clone();
+        compressor.set_release_stages(10.0, 1000.0, 3.0, SAMPLE_HZ);
+        compressor.clear_release_stages(SAMPLE_HZ);
+        assert_eq!(compressor.release_stages(), None);
+        for frame in test_signal(4096) {
+            assert_eq!(compressor.next_frame(frame), expected.next_frame(frame));
+        }
+    }
+
+    #[test]
+    fn latency_breakdown_of_peak_is_lookahead() {
+        let mut compressor = peak_compressor();
+        compressor.set_sidechain_delay_frames(64);
+        let latency = compressor.latency_breakdown();
+        assert_eq!(latency, LatencyBreakdown {
+            lookahead: 64,
+            oversampling: 0,
+            detection: 0,
+            total: 64,
+        });
+        assert_eq!(latency.total, compressor.latency_frames());
+    }
+
+    #[test]
+    fn latency_breakdown_includes_rms_window_and_decimation() {
+        let mut compressor: RmsAvgCompressor<[f32; 2]> =
+            Compressor::rms_avg(10.0, 1.0, 100.0, SAMPLE_HZ, 0.5, 4.0);
+        compressor.set_sidechain_delay_frames(32);
+        let window_frames = compressor.envelope_detector.window_frames();
+        assert_eq!(window_frames, 441);
+        let latency = compressor.latency_breakdown();
+        assert_eq!(latency.detection, window_frames / 2);
+        assert_eq!(latency.total, 32 + window_frames / 2);
+        assert_eq!(compressor.latency_frames(), 32);
+
+        compressor.set_detection_decimation(8);
+        let window_frames = compressor.envelope_detector.window_frames();
+        let latency = compressor.latency_breakdown();
+        assert_eq!(latency.detection, window_frames / 2 + 7);
+        assert_eq!(latency.total, latency.lookahead + latency.oversampling + latency.detection);
+    }
+
+    #[test]
+    fn detection_mix_extremes_match_pure_detection() {
+        let blend: BlendCompressor<[f32; 2], Average> =
+            Compressor::blend(5.0, 3.0, 100.0, SAMPLE_HZ, 0.25, 4.0);
+        assert_eq!(blend.detection_mix(), detector::DEFAULT_DETECTION_MIX);
+        let mut pure_rms = blend.clone();
+        pure_rms.set_detection_mix(0.0);
+        let mut pure_peak = blend.clone();
+        pure_peak.set_detection_mix(1.0);
+        let mut rms = Compressor::rms_avg(5.0, 3.0, 100.0, SAMPLE_HZ, 0.25, 4.0);
+        let mut peak = Compressor::peak_avg(3.0, 100.0, SAMPLE_HZ, 0.25, 4.0);
+        for frame in test_signal(4096) {
+            let (r, p) = (rms.next_frame(frame), peak.next_frame(frame));
+            let (br, bp) = (pure_rms.next_frame(frame), pure_peak.next_frame(frame));
+            assert!((br[0] - r[0]).abs() < 1e-5);
+            assert!((bp[0] - p[0]).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn morphing_by_zero_keeps_and_by_one_reaches_the_target() {
+        let mut compressor: RmsAvgCompressor<[f32; 2]> =
+            Compressor::rms_avg(10.0, 1.0, 100.0, SAMPLE_HZ, 0.5, 2.0);
+        let initial = compressor.params();
+        let target = params();
+        compressor.morph(&target, 0.0, SAMPLE_HZ);
+        assert_eq!(compressor.params(), initial);
+        compressor.morph(&target, 1.0, SAMPLE_HZ);
+        let reached = compressor.params();
+        assert!((reached.threshold - target.threshold).abs() < 1e-6);
+        assert!((reached.ratio - target.ratio).abs() < 1e-3);
+        assert!((reached.attack_ms - target.attack_ms).abs() < 1e-9);
+        assert!((reached.release_ms - target.release_ms).abs() < 1e-9);
+        assert_eq!(reached.window_ms, target.window_ms);
+    }
+
+    #[test]
+    fn mono_buffers_for_a_stereo_compressor_are_a_channel_mismatch() {
+        let mut compressor = peak_compressor();
+        let input = [0.5f32; 64];
+        let mut output = [7.0f32; 64];
+        let result = compressor.try_process_replacing(&[&input], &mut [&mut output]);
+        assert_eq!(result, Err(CompressorError::ChannelMismatch { expected: 2, found: 1 }));
+        assert!(output.iter().all(|&s| s == 7.0));
+    }
+
+    #[test]
+    fn buffers_of_differing_lengths_are_a_length_mismatch() {
+        let mut compressor = peak_compressor();
+        let (left, right) = ([0.5f32; 64], [0.5f32; 32]);
+        let (mut out_left, mut out_right) = ([0.0f32; 64], [0.0f32; 64]);
+        let result = compressor.try_process_replacing(&[&left, &right],
+                                                      &mut [&mut out_left, &mut out_right]);
+        assert_eq!(result, Err(CompressorError::LengthMismatch { expected: 64, found: 32 }));
+    }
+
+    #[test]
+    fn keyed_buffers_match_compress_slice_keyed() {
+        let mut planar = peak_compressor();
+        let mut interleaved = planar.clone();
+        let mut framed = planar.clone();
+        let key = test_signal(8_000);
+        let mut frames: Vec<[f32; 2]> = (0..8_000).map(|i| [0.5, (i as f32 * 0.1).sin()]).collect();
+        let channel = |frames: &[[f32; 2]], ch: usize| -> Vec<f32> {
+            frames.iter().map(|f| f[ch]).collect()
+        };
+        let planar_key = [channel(&key, 0), channel(&key, 1)];
+        let planar_in = [channel(&frames, 0), channel(&frames, 1)];
+        let (mut out_left, mut out_right) = (vec![0.0; 8_000], vec![0.0; 8_000]);
+        planar.process_replacing_keyed(&[&planar_key[0], &planar_key[1]],
+                                       &[&planar_in[0], &planar_in[1]],
+                                       &mut [&mut out_left, &mut out_right]);
+        let interleaved_key: Vec<f32> = key.iter().flat_map(|f| f.iter().cloned()).collect();
+        let mut samples: Vec<f32> = frames.iter().flat_map(|f| f.iter().cloned()).collect();
+        interleaved.compress_interleaved_keyed(&interleaved_key, 2, &mut samples);
+        framed.compress_slice_keyed(&key, &mut frames);
+        for (i, frame) in frames.iter().enumerate() {
+            assert_eq!([out_left[i], out_right[i]], *frame);
+            assert_eq!([samples[i * 2], samples[i * 2 + 1]], *frame);
+        }
+    }
+
+    #[test]
+    fn a_mono_key_for_a_stereo_compressor_is_a_channel_mismatch() {
+        let mut compressor = peak_compressor();
+        let key = [0.9f32; 64];
+        let (left, right) = ([0.5f32; 64], [0.5f32; 64]);
+        let (mut out_left, mut out_right) = ([7.0f32; 64], [7.0f32; 64]);
+        let result = compressor.try_process_replacing_keyed(&[&key], &[&left, &right],
+                                                            &mut [&mut out_left, &mut out_right]);
+        assert_eq!(result, Err(CompressorError::ChannelMismatch { expected: 2, found: 1 }));
+        assert!(out_left.iter().chain(&out_right).all(|&s| s == 7.0));
+        let mut signal = [0.5f32; 128];
+        let result = compressor.try_compress_interleaved_keyed(&key, 1, &mut signal);
+        assert_eq!(result, Err(CompressorError::ChannelMismatch { expected: 2, found: 1 }));
+        assert!(signal.iter().all(|&s| s == 0.5));
+        let result = compressor.try_compress_interleaved_keyed(&key, 2, &mut signal);
+        assert_eq!(result, Err(CompressorError::LengthMismatch { expected: 64, found: 32 }));
+        assert_eq!(compressor.frames_processed(), 0);
+    }
+
+    #[cfg(feature = "metering")]
+    #[test]
+    fn loudness_reduction_of_a_steady_tone_matches_the_gain_reduction() {
+        let mut compressor: RmsAvgCompressor<[f32; 2]> =
+            Compressor::rms_avg(50.0, 5.0, 500.0, SAMPLE_HZ, 0.02, 4.0);
+        assert!(!compressor.loudness_meter());
+        compressor.set_loudness_meter(true, SAMPLE_HZ);
+        for i in 0..4 * SAMPLE_HZ as usize {
+            let s = (i as f32 * 2.0 * std::f32::consts::PI * 1_000.0 / SAMPLE_HZ as f32).sin();
+            compressor.next_frame([s * 0.5, s * 0.5]);
+        }
+        let gain_reduction_db = -compressor.current_gain_reduction_db();
+        assert!(gain_reduction_db > 2.0);
+        assert!((compressor.loudness_reduction_lu() - gain_reduction_db).abs() < 0.15);
+    }
+
+    #[test]
+    fn fully_linked_detectors_share_the_loudest_envelope() {
+        let mut unlinked = peak_compressor();
+        let mut linked = peak_compressor();
+        linked.set_detector_link(1.0);
+        let mut both_loud = peak_compressor();
+        for frame in test_signal(4096) {
+            let one_loud = [frame[0], 0.0];
+            let unlinked_gain = unlinked.next_gain(one_loud);
+            let linked_gain = linked.next_gain(one_loud);
+            assert_eq!(linked_gain, both_loud.next_gain([frame[0], frame[0]]));
+            assert!(linked_gain <= unlinked_gain);
+        }
+        assert!(linked.next_gain([1.0, 0.0]) < unlinked.next_gain([1.0, 0.0]));
+    }
+
+    #[test]
+    fn detector_link_is_clamped() {
+        let mut compressor = peak_compressor();
+        assert_eq!(compressor.detector_link(), 0.0);
+        compressor.set_detector_link(1.5);
+        assert_eq!(compressor.detector_link(), 1.0);
+        compressor.set_detector_link(-0.5);
+        assert_eq!(compressor.detector_link(), 0.0);
+    }
+
+    #[test]
+    fn frames_processed_counts_every_method() {
+        let mut compressor = peak_compressor();
+        assert_eq!(compressor.frames_processed(), 0);
+        compressor.next_frame([0.5, 0.5]);
+        compressor.next_gain([0.5, 0.5]);
+        compressor.compress_slice(&mut test_signal(100));
+        let input = [0.5f32; 10];
+        let (mut left, mut right) = ([0.0f32; 10], [0.0f32; 10]);
+        compressor.process_replacing(&[&input, &input], &mut [&mut left, &mut right]);
+        assert_eq!(compressor.frames_processed(), 112);
+    }
+
+    #[test]
+    fn hold_release_ends_on_exactly_its_final_frame() {
+        // At 1kHz a 10ms hold spans exactly 10 frames.
+        let mut compressor: PeakAvgCompressor<[f32; 1]> =
+            Compressor::peak_avg(1.0, 10.0, 1_000.0, 0.5, 4.0);
+        compressor.set_attack_mode(SmoothingMode::Hold);
+        compressor.set_release_mode(SmoothingMode::Hold);
+        assert!(compressor.next_gain([1.0]) < 1.0);
+        let loud_frame = compressor.frames_processed();
+        while compressor.next_gain([0.0]) < 1.0 {}
+        assert_eq!(compressor.frames_processed() - loud_frame, 10);
+    }
+
+    // The core path must compress with every optional feature disabled.
+    #[test]
+    fn core_passes_signal_below_threshold_untouched() {
+        let mut compressor = peak_compressor();
+        for i in 0..1_000 {
+            let frame = [0.25 * (i as f32 * 0.01).sin(), -0.1];
+            assert_eq!(compressor.next_frame(frame), frame);
+        }
+        assert_eq!(compressor.current_gain_reduction_db(), 0.0);
+    }
+
+    #[test]
+    fn core_compresses_signal_above_threshold() {
+        let mut compressor = peak_compressor();
+        let mut frames = [[1.0, -1.0]; 4_410];
+        compressor.compress_slice(&mut frames);
+        let last = frames[frames.len() - 1];
+        assert!(last[0] < 1.0 && last[0] > 0.5, "{}", last[0]);
+        assert_eq!(last[0], -last[1]);
+        assert!(compressor.current_gain_reduction_db() < 0.0);
+    }
+
+    #[test]
+    fn last_output_peak_is_the_loudest_output_channel() {
+        let mut compressor = peak_compressor();
+        assert_eq!(compressor.last_output_peak(), 0.0);
+        for frame in test_signal(2048) {
+            let output = compressor.next_frame(frame);
+            assert_eq!(compressor.last_output_peak(), output[0].abs().max(output[1].abs()));
+        }
+        let mut frames = [[0.2f32, -0.9]];
+        compressor.compress_slice(&mut frames);
+        assert_eq!(compressor.last_output_peak(), frames[0][1].abs());
+        compressor.reset_meters();
+        assert_eq!(compressor.last_output_peak(), 0.0);
+    }
+
+    #[test]
+    fn detected_envelope_rises_and_falls_with_a_ramp() {
+        let mut compressor = peak_compressor();
+        let rising = (0..4_410).map(|i| i as f32 / 4_410.0);
+        let falling = (0..44_100).map(|i| if i < 4_410 { 1.0 - i as f32 / 4_410.0 } else { 0.0 });
+        let input: Vec<[f32; 2]> = rising.chain(falling).map(|s| [s, -0.5 * s]).collect();
+        let mut env = vec![0.0; input.len()];
+        compressor.detect_envelope_slice(&input, &mut env);
+        // The 1ms attack keeps up with the rise, while the 100ms release lags the fall.
+        assert!((env[4_409] - 1.0).abs() < 0.05);
+        assert!(env[..4_410].windows(2).all(|w| w[1] >= w[0]));
+        assert!(env[4_500..].windows(2).all(|w| w[1] <= w[0]));
+        assert!(env[8_820] > 0.5);
+        assert!(env[env.len() - 1] < 0.01);
+        assert_eq!(compressor.frames_processed(), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn detecting_into_a_shorter_buffer_panics() {
+        peak_compressor().detect_envelope_slice(&test_signal(4), &mut [0.0; 3]);
+    }
+
+    /// The gain reduction in decibels and the threshold reached after a second of a sine of the
+    /// given amplitude.
+    fn settle_on_sine(compressor: &mut RmsAvgCompressor<[f32; 2]>, amp: f32) -> (f32, f32) {
+        for i in 0..SAMPLE_HZ as usize {
+            let s = (i as f32 * 0.05).sin() * amp;
+            compressor.next_frame([s, s]);
+        }
+        (-compressor.current_gain_reduction_db(), compressor.smoothed_threshold)
+    }
+
+    #[test]
+    fn adaptive_threshold_follows_the_input_level() {
+        let mut adaptive: RmsAvgCompressor<[f32; 2]> =
+            Compressor::rms_avg(20.0, 5.0, 100.0, SAMPLE_HZ, 0.05, 4.0);
+        let mut fixed = adaptive.clone();
+        adaptive.set_adaptive_threshold(6.0);
+        adaptive.set_adaptive_threshold_ms(100.0, SAMPLE_HZ);
+        assert_eq!(adaptive.adaptive_threshold_db(), Some(6.0));
+        let (quiet_db, quiet_threshold) = settle_on_sine(&mut adaptive, 0.1);
+        let (loud_db, loud_threshold) = settle_on_sine(&mut adaptive, 0.4);
+        // The threshold sits 6dB below the RMS of each sine, i.e. at half of it.
+        let rms = 0.1 / 2.0f32.sqrt();
+        assert!((quiet_threshold - rms * 0.5).abs() < rms * 0.05);
+        assert!((loud_threshold - 4.0 * rms * 0.5).abs() < rms * 0.2);
+        // Quadrupling the level grows the reduction far less than with a fixed threshold.
+        let (fixed_quiet_db, _) = settle_on_sine(&mut fixed, 0.1);
+        let (fixed_loud_db, _) = settle_on_sine(&mut fixed, 0.4);
+        assert!(quiet_db > 0.0);
+        assert!(loud_db / quiet_db < fixed_loud_db / fixed_quiet_db);
+        adaptive.clear_adaptive_threshold();
+        assert_eq!(adaptive.adaptive_threshold_db(), None);
+    }
+
+    #[test]
+    fn ratio_ramp_reaches_target_slope() {
+        let mut compressor = peak_compressor();
+        compressor.ramp_ratio_over_beats(std::f32::INFINITY, 1.0, 120.0, SAMPLE_HZ);
+        assert!(compressor.is_ramping_ratio());
+        for _ in 0..(SAMPLE_HZ / 2.0) as usize {
+            compressor.next_frame([0.0; 2]);
+        }
+        assert!(!compressor.is_ramping_ratio());
+        assert_eq!(compressor.coefficients().slope, 1.0);
+    }
+
+    #[test]
+    fn ratio_ramp_over_zero_beats_is_immediate() {
+        let mut compressor = peak_compressor();
+        compressor.ramp_ratio_over_beats(2.0, 0.0, 120.0, SAMPLE_HZ);
+        assert!(!compressor.is_ramping_ratio());
+        assert_eq!(compressor.coefficients().slope, 0.5);
+    }
+
+    #[test]
+    #[should_panic]
+    fn ratio_ramp_rejects_zero_bpm() {
+        peak_compressor().ramp_ratio_over_beats(2.0, 4.0, 0.0, SAMPLE_HZ);
+    }
+
+    #[test]
+    #[should_panic]
+    fn ratio_ramp_rejects_nan_beats() {
+        peak_compressor().ramp_ratio_over_beats(2.0, std::f64::NAN, 120.0, SAMPLE_HZ);
+    }
+
+    #[test]
+    #[should_panic]
+    fn ratio_ramp_rejects_invalid_sample_hz() {
+        peak_compressor().ramp_ratio_over_beats(2.0, 4.0, 120.0, 0.0);
+    }
+
+    #[test]
+    fn steady_state_gain_matches_the_converged_gain() {
+        let mut peak = peak_compressor();
+        peak.set_knee_db(6.0);
+        let mut rms: RmsAvgCompressor<[f32; 2]> =
+            Compressor::rms_avg(10.0, 1.0, 100.0, SAMPLE_HZ, 0.5, 4.0);
+        for &level in &[0.2f32, 0.5, 0.8, 1.0] {
+            let (mut peak_gain, mut rms_gain) = (1.0, 1.0);
+            for _ in 0..SAMPLE_HZ as usize {
+                peak_gain = peak.next_gain([level, -level]);
+                rms_gain = rms.next_gain([level, -level]);
+            }
+            assert!((peak_gain - peak.steady_state_gain(level)).abs() < 1e-4);
+            assert!((rms_gain - rms.steady_state_gain(level)).abs() < 1e-4);
+        }
+        assert_eq!(peak.steady_state_gain(0.2), 1.0);
+        assert_eq!(peak.steady_state_gain(-0.8), peak.steady_state_gain(0.8));
+    }
+
+    #[test]
+    fn mono_detect_matches_full_detection_of_duplicated_mono() {
+        let mut full = peak_compressor();
+        let mut mono = peak_compressor();
+        assert!(!mono.mono_detect());
+        mono.set_mono_detect(true);
+        assert!(mono.mono_detect());
+        for frame in test_signal(8192) {
+            let frame = [frame[0], frame[0]];
+            let output = mono.next_frame(frame);
+            assert_eq!(output[0], output[1]);
+            assert_eq!(output, full.next_frame(frame));
+        }
+    }
+
+    #[test]
+    fn gains_for_levels_match_steady_state_gains() {
+        let compressor = peak_compressor();
+        let levels = [0.0, 0.1, 0.4999, 0.5, 0.6, 0.9, 1.0, 1.5];
+        let mut gains = [0.0; 8];
+        compressor.gains_for_levels(&levels, &mut gains);
+        for (&level, &gain) in levels.iter().zip(&gains) {
+            assert_eq!(gain, compressor.steady_state_gain(level));
+        }
+        assert_eq!(gains[0], 1.0);
+        assert!(gains[7] < 1.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn gains_for_levels_requires_equal_lengths() {
+        peak_compressor().gains_for_levels(&[0.5; 4], &mut [0.0; 2]);
+    }
+
+    #[test]
+    fn power_threshold_is_the_square_of_the_amplitude_threshold() {
+        let rms = |threshold| -> RmsAvgCompressor<[f32; 2]> {
+            Compressor::rms_avg(10.0, 1.0, 100.0, SAMPLE_HZ, threshold, 4.0)
+        };
+        let mut amplitude = rms(0.25);
+        let mut power = rms(0.25);
+        power.set_threshold_domain(ThresholdDomain::Power);
+        assert_eq!(power.threshold_domain(), ThresholdDomain::Power);
+        let mut equivalent = rms(0.5);
+        // A sine of amplitude 0.8 has an RMS of about 0.57, above 0.5 and well above 0.25.
+        let (mut amplitude_gain, mut power_gain) = (1.0, 1.0);
+        for i in 0..SAMPLE_HZ as usize {
+            let s = (i as f32 * 0.05).sin() * 0.8;
+            amplitude_gain = amplitude.next_gain([s, s]);
+            power_gain = power.next_gain([s, s]);
+            assert!((power_gain - equivalent.next_gain([s, s])).abs() < 1e-6);
+        }
+        assert!(power_gain < 1.0);
+        assert!(amplitude_gain < power_gain);
+    }
+
+    #[test]
+    fn bypass_nulls_with_every_stage_configured() {
+        let mut compressor = peak_compressor();
+        compressor.set_knee_db(6.0);
+        compressor.set_parameter_smoothing_ms(20.0, SAMPLE_HZ);
+        compressor.set_input_gain_db(6.0);
+        compressor.set_makeup_gain(2.0);
+        compressor.set_auto_gain(Some(-12.0));
+        compressor.set_dither(DitherKind::Triangular);
+        compressor.set_sidechain_delay_frames(64);
+        #[cfg(feature = "sidechain-filter")]
+        compressor.set_sidechain_hpf(Some(100.0), SAMPLE_HZ);
+        compressor.set_bypass(true);
+        assert!(compressor.is_bypassed());
+        assert_eq!(compressor.null_test(&test_signal(8192)), 0.0);
+        assert!(compressor.is_bypassed());
+    }
+
+    #[test]
+    fn null_test_restores_the_bypass() {
+        let mut compressor = peak_compressor();
+        assert_eq!(compressor.null_test(&test_signal(4096)), 0.0);
+        assert!(!compressor.is_bypassed());
+        let mut frames = test_signal(4096);
+        compressor.compress_slice(&mut frames);
+        assert!(frames.iter().zip(test_signal(4096)).any(|(out, dry)| out != &dry));
+    }
+
+    #[test]
+    fn compress_with_gain_iter_pairs_each_frame_with_its_gain() {
+        let mut expected = peak_compressor();
+        let items: Vec<_> = peak_compressor().compress_with_gain_iter(test_signal(4096)).collect();
+        assert_eq!(items.len(), 4096);
+        for ((output, gain), frame) in items.into_iter().zip(test_signal(4096)) {
+            assert!(gain <= 1.0);
+            assert_eq!(output, expected.next_frame(frame));
+            assert_eq!(output, [frame[0] * gain, frame[1] * gain]);
+        }
+        assert!(expected.last_gain < 1.0);
+    }
+
+    /// The limited reduction in decibels for reductions from 0dB to 20dB in 0.01dB steps.
+    fn limited_reductions(compressor: &PeakAvgCompressor<[f32; 2]>) -> Vec<f32> {
+        (0..2_000)
+            .map(|i| {
+                let gain = db::db_to_amp(-(i as f32 * 0.01));
+                -db::amp_to_db(compressor.limit_reduction(gain))
+            })
+            .collect()
+    }
+
+    /// The largest change in slope between consecutive steps of the given curve.
+    fn sharpest_corner(curve: &[f32]) -> f32 {
+        let slopes: Vec<f32> = curve.windows(2).map(|w| (w[1] - w[0]) / 0.01).collect();
+        slopes.windows(2).fold(0.0, |max, w| max.max((w[1] - w[0]).abs()))
+    }
+
+    #[test]
+    fn range_knee_approaches_the_floor_without_a_corner() {
+        let mut compressor = peak_compressor();
+        compressor.set_max_reduction_db(Some(10.0));
+        assert_eq!(compressor.max_reduction_db(), Some(10.0));
+        let hard = limited_reductions(&compressor);
+        assert!(sharpest_corner(&hard) > 0.9);
+        compressor.set_range_knee_db(4.0);
+        assert_eq!(compressor.range_knee_db(), 4.0);
+        let soft = limited_reductions(&compressor);
+        assert!(sharpest_corner(&soft) < 0.01);
+        assert!(soft.windows(2).all(|w| w[1] >= w[0]));
+        assert!((soft[700] - 7.0).abs() < 1e-3);
+        assert!(soft.iter().all(|&db| db <= 10.0 + 1e-3));
+        assert!((soft[1_500] - 10.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn instantaneous_ratio_follows_the_static_curve() {
+        let mut compressor = peak_compressor();
+        for _ in 0..4_410 {
+            compressor.next_gain([0.3, 0.3]);
+        }
+        assert_eq!(compressor.instantaneous_ratio(), 1.0);
+        // The gain falls linearly, so d(out dB)/d(in dB) is 1 - slope * e / (1 - (e - t) * slope).
+        let (threshold, slope) = (0.5, 0.75);
+        let mut last_ratio = 1.0;
+        for &level in &[0.6f32, 0.7, 0.8] {
+            for _ in 0..SAMPLE_HZ as usize {
+                compressor.next_gain([level, level]);
+            }
+            let expected = 1.0 / (1.0 - slope * level / (1.0 - (level - threshold) * slope));
+            let ratio = compressor.instantaneous_ratio();
+            assert!((ratio - expected).abs() < expected * 0.01);
+            assert!(ratio > last_ratio);
+            last_ratio = ratio;
+        }
+        compressor.set_max_reduction_db(Some(1.0));
+        assert!((compressor.instantaneous_ratio() - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn loud_key_channel_ducks_every_channel() {
+        let mut compressor: PeakMinCompressor<[f32; 5]> =
+            Compressor::peak_min(1.0, 100.0, SAMPLE_HZ, 0.25, 8.0);
+        compressor.set_key_channel(Some(2));
+        assert_eq!(compressor.key_channel(), Some(2));
+        let mut output = [0.0; 5];
+        for _ in 0..4_410 {
+            output = compressor.next_frame([0.1, 0.1, 0.9, 0.1, 0.1]);
+        }
+        let gain = output[0] / 0.1;
+        assert!(gain < 0.5);
+        assert!((output[2] / 0.9 - gain).abs() < 1e-6);
+        // Loud signal in any other channel is ignored.
+        for _ in 0..SAMPLE_HZ as usize {
+            output = compressor.next_frame([0.9, 0.9, 0.1, 0.9, 0.9]);
+        }
+        assert_eq!(output, [0.9, 0.9, 0.1, 0.9, 0.9]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn key_channel_must_be_in_range() {
+        peak_compressor().set_key_channel(Some(2));
+    }
+
+    #[test]
+    fn wet_and_dry_impulses_align() {
+        let mut compressor = peak_compressor();
+        compressor.set_sidechain_delay_frames(48);
+        let latency = compressor.latency_frames();
+        assert_eq!(latency, 48);
+        let (mut wet_at, mut dry_at) = (None, None);
+        for i in 0..256 {
+            let frame = if i == 10 { [1.0, 1.0] } else { [0.0, 0.0] };
+            let (wet, dry) = compressor.next_frame_wet_dry(frame);
+            if wet[0] != 0.0 {
+                assert_eq!(wet_at, None);
+                wet_at = Some(i);
+            }
+            if dry[0] != 0.0 {
+                assert_eq!(dry, [1.0, 1.0]);
+                dry_at = Some(i);
+            }
+        }
+        assert_eq!(wet_at, Some(10 + latency));
+        assert_eq!(dry_at, wet_at);
+    }
+
+    #[test]
+    fn expensive_stages_raise_the_estimated_cost() {
+        let mut compressor = peak_compressor();
+        let baseline = compressor.estimated_cost();
+        assert_eq!(baseline.total(), 2.0);
+        assert_eq!(baseline.delay_frames, 0);
+        compressor.set_auto_gain(Some(-12.0));
+        compressor.set_sidechain_delay_frames(64);
+        #[cfg(feature = "metering")]
+        compressor.set_loudness_meter(true, SAMPLE_HZ);
+        let cost = compressor.estimated_cost();
+        assert!(cost.total() > baseline.total());
+        assert_eq!(cost.delay_frames, 64);
+
+        let hann = |decimation| {
+            let mut compressor = CompressorBuilder::new()
+                .detector(DetectorKind::Rms(RmsWindow::Hann))
+                .window_ms(10.0)
+                .build::<[f32; 2]>(SAMPLE_HZ);
+            compressor.set_detection_decimation(decimation);
+            compressor.estimated_cost().detection
+        };
+        let rectangular: RmsAvgCompressor<[f32; 2]> =
+            Compressor::rms_avg(10.0, 1.0, 100.0, SAMPLE_HZ, 0.5, 4.0);
+        assert!(hann(1) > rectangular.estimated_cost().detection);
+        assert!(hann(4) < hann(1));
+    }
+
+    #[test]
+    fn input_gain_drives_the_compressor_harder() {
+        let gain_reduction_db = |input_gain_db| {
+            let mut compressor = peak_compressor();
+            compressor.set_input_gain_db(input_gain_db);
+            assert!((compressor.input_gain_db() - input_gain_db).abs() < 1e-4);
+            for _ in 0..4_410 {
+                compressor.next_frame([0.6, 0.6]);
+            }
+            -compressor.current_gain_reduction_db()
+        };
+        let (unity, driven) = (gain_reduction_db(0.0), gain_reduction_db(3.0));
+        assert!(unity > 0.0);
+        assert!(driven > unity);
+        assert_eq!(gain_reduction_db(-6.0), 0.0);
+    }
+
+    #[test]
+    fn input_gain_applies_to_the_output_path() {
+        let mut compressor = peak_compressor();
+        compressor.set_input_gain_db(-6.0);
+        let output = compressor.next_frame([0.4, -0.4]);
+        let trim = db::db_to_amp(-6.0);
+        assert!((output[0] - 0.4 * trim).abs() < 1e-6);
+        assert!((output[1] + 0.4 * trim).abs() < 1e-6);
+    }
+
+    #[test]
+    fn soft_reset_keeps_the_envelope_and_clears_the_smoothing() {
+        let mut compressor: RmsAvgCompressor<[f32; 2]> =
+            Compressor::rms_avg(10.0, 1.0, 100.0, SAMPLE_HZ, 0.5, 4.0);
+        compressor.set_parameter_smoothing_ms(200.0, SAMPLE_HZ);
+        compressor.compress_slice(&mut test_signal(2048));
+        compressor.threshold = 0.1;
+        compressor.next_frame([0.8, 0.8]);
+        assert!(compressor.smoothed_threshold > 0.4);
+        let envelope = compressor.envelope();
+        let mut twin = compressor.clone();
+        compressor.soft_reset();
+        assert_eq!(compressor.envelope(), envelope);
+        assert_eq!(compressor.smoothed_threshold, 0.1);
+        assert_eq!(compressor.last_gain, 1.0);
+        // The detector continues exactly as though no reset had occurred.
+        for frame in test_signal(1024) {
+            compressor.next_frame(frame);
+            twin.next_frame(frame);
+            assert_eq!(compressor.envelope(), twin.envelope());
+        }
+    }
+
+    #[test]
+    fn description_lists_the_configured_rms_compressor() {
+        let mut compressor: RmsAvgCompressor<[f32; 2]> =
+            Compressor::rms_avg(20.0, 5.0, 150.0, SAMPLE_HZ, 0.5, 4.0);
+        compressor.set_sidechain_delay_frames(32);
+        compressor.set_silence_optimization(true);
+        let description = compressor.describe();
+        for line in &["detector: RMS (Rectangular window)",
+                      "ratio: 4:1",
+                      "attack: 5 ms",
+                      "release: 150 ms",
+                      "window: 20 ms",
+                      "lookahead: 32 frames",
+                      "latency: 32 frames",
+                      "silence optimization: on",
+                      "bypass: off"] {
+            assert!(description.lines().any(|l| l.starts_with(line)), "missing {:?}", line);
+        }
+        assert!(description.contains("threshold: -6.02"));
+    }
+
+    #[cfg(feature = "sidechain-filter")]
+    #[test]
+    fn filtering_one_channel_leaves_the_other_detection_untouched() {
+        let mut unfiltered = peak_compressor();
+        let mut filtered = peak_compressor();
+        filtered.set_sidechain_hpf_channel(0, Some(200.0), SAMPLE_HZ);
+        assert_eq!(filtered.sidechain_hpf_channel_hz(0), Some(200.0));
+        assert_eq!(filtered.sidechain_hpf_channel_hz(1), None);
+        assert_eq!(filtered.sidechain_hpf_hz(), None);
+        // A 20Hz rumble on both channels.
+        for i in 0..SAMPLE_HZ as usize {
+            let s = (i as f32 * 2.0 * std::f32::consts::PI * 20.0 / SAMPLE_HZ as f32).sin();
+            unfiltered.next_frame([s * 0.8, s * 0.8]);
+            filtered.next_frame([s * 0.8, s * 0.8]);
+            assert_eq!(filtered.envelope()[1], unfiltered.envelope()[1]);
+        }
+        assert!(filtered.envelope()[0] < unfiltered.envelope()[0] * 0.2);
+        assert!(filtered.last_gain > unfiltered.last_gain);
+    }
+
+    #[cfg(feature = "sidechain-filter")]
+    #[test]
+    #[should_panic]
+    fn sidechain_hpf_channel_must_be_in_range() {
+        peak_compressor().set_sidechain_hpf_channel(2, Some(100.0), SAMPLE_HZ);
+    }
+
+    #[test]
+    fn reconfiguring_from_peak_to_rms_preserves_the_meters() {
+        let mut compressor: DynamicCompressor<[f32; 2]> = CompressorBuilder::new()
+            .params(params())
+            .window_ms(20.0)
+            .detector(DetectorKind::Rms(RmsWindow::Rectangular))
+            .build(SAMPLE_HZ);
+        compressor.set_detector_kind(DetectorKind::Peak, SAMPLE_HZ);
+        compressor.compress_slice(&mut test_signal(4096));
+        #[cfg(feature = "metering")]
+        let range_db = compressor.dynamic_range_reduction_db();
+        let (peak, envelope) = (compressor.last_output_peak(), compressor.envelope());
+        let config = CompressorBuilder::new()
+            .detector(DetectorKind::Rms(RmsWindow::Hann))
+            .even_gain(EvenGainKind::Minimum)
+            .threshold(0.1)
+            .ratio(2.0)
+            .window_ms(5.0);
+        compressor.reconfigure(&config, SAMPLE_HZ);
+        assert_eq!(compressor.detector_kind(), DetectorKind::Rms(RmsWindow::Hann));
+        assert_eq!(compressor.even_gain(), EvenGainKind::Minimum);
+        assert_eq!(compressor.threshold, 0.1);
+        assert_eq!(compressor.params().window_ms, Some(5.0));
+        #[cfg(feature = "metering")]
+        assert_eq!(compressor.dynamic_range_reduction_db(), range_db);
+        assert_eq!(compressor.last_output_peak(), peak);
+        assert_eq!(compressor.frames_processed(), 4096);
+        for (&a, &b) in compressor.envelope().iter().zip(&envelope) {
+            assert!((a - b).abs() < 1e-6);
+        }
+    }
+
+    #[cfg(feature = "metering")]
+    #[test]
+    fn meter_snapshot_reflects_the_latest_frame() {
+        let mut compressor = peak_compressor();
+        assert_eq!(compressor.meter_snapshot().frames_processed, 0);
+        for frame in test_signal(3000) {
+            compressor.next_frame(frame);
+            let snapshot = compressor.meter_snapshot();
+            let copy = snapshot;
+            assert_eq!(copy, snapshot);
+            assert_eq!(snapshot.gain_reduction_db, -compressor.current_gain_reduction_db());
+            assert_eq!(snapshot.output_peak, compressor.last_output_peak());
+            assert_eq!(snapshot.envelope, compressor.envelope()[0].max(compressor.envelope()[1]));
+            assert_eq!(snapshot.dynamic_range_reduction_db,
+                       compressor.dynamic_range_reduction_db());
+            assert_eq!(snapshot.frames_processed, compressor.frames_processed());
+        }
+        assert!(compressor.meter_snapshot().gain_reduction_db > 0.0);
+    }
+
+    #[test]
+    fn full_scale_input_is_counted_as_clipped() {
+        let mut compressor = peak_compressor();
+        assert_eq!(compressor.input_clip_level_db(), 0.0);
+        compressor.compress_slice(&mut vec![[0.5, -0.5]; 512]);
+        assert!(!compressor.input_clipped());
+        compressor.next_frame([0.2, -1.0]);
+        compressor.next_frame([1.0, 1.0]);
+        assert!(compressor.input_clipped());
+        assert_eq!(compressor.input_clip_count(), 2);
+        compressor.reset_meters();
+        assert!(!compressor.input_clipped());
+    }
+
+    #[test]
+    fn integer_input_clips_at_its_own_full_scale() {
+        let mut compressor: PeakAvgCompressor<[i16; 1]> =
+            Compressor::peak_avg(1.0, 100.0, SAMPLE_HZ, 0.5, 4.0);
+        compressor.next_frame([32_000]);
+        assert!(!compressor.input_clipped());
+        compressor.next_frame([32_767]);
+        assert!(compressor.input_clipped());
+        compressor.set_input_clip_level_db(-6.0);
+        assert!((compressor.input_clip_level_db() + 6.0).abs() < 1e-4);
+        compressor.next_frame([-20_000]);
+        assert_eq!(compressor.input_clip_count(), 2);
+    }
+
+    #[test]
+    fn flushing_recovers_the_frames_held_by_the_lookahead() {
+        let mut compressor = peak_compressor();
+        compressor.set_sidechain_delay_frames(32);
+        let input = test_signal(1_000);
+        let mut rendered = input.clone();
+        compressor.compress_slice(&mut rendered);
+        assert!(rendered[..32].iter().all(|&frame| frame == [0.0, 0.0]));
+        compressor.flush(&mut rendered);
+        assert_eq!(rendered.len(), 1_032);
+        for (output, dry) in rendered[1_000..].iter().zip(&input[968..]) {
+            let gain = output[0] / dry[0];
+            assert!(gain > 0.0 && gain <= 1.0);
+            assert!((output[1] - dry[1] * gain).abs() < 1e-6);
+        }
+        let mut nothing = Vec::new();
+        peak_compressor().flush(&mut nothing);
+        assert!(nothing.is_empty());
+        // Once reset, the flushed compressor renders the stream again exactly as it did at first.
+        compressor.reset();
+        let mut again = input.clone();
+        compressor.compress_slice(&mut again);
+        assert_eq!(again[..], rendered[..1_000]);
+    }
+
+    #[test]
+    fn reset_matches_a_freshly_configured_compressor() {
+        let mut compressor: RmsAvgCompressor<[f32; 2]> =
+            Compressor::rms_avg(5.0, 1.0, 100.0, SAMPLE_HZ, 0.25, 4.0);
+        compressor.set_detection_decimation(4);
+        compressor.set_sidechain_delay_frames(32);
+        compressor.set_punch_ms(2.0, SAMPLE_HZ);
+        compressor.set_parameter_smoothing_ms(10.0, SAMPLE_HZ);
+        compressor.set_release_stages(20.0, 500.0, 3.0, SAMPLE_HZ);
+        compressor.set_adaptive_threshold(-6.0);
+        #[cfg(feature = "sidechain-filter")]
+        compressor.set_sidechain_hpf(Some(80.0), SAMPLE_HZ);
+        #[cfg(feature = "metering")]
+        compressor.set_loudness_meter(true, SAMPLE_HZ);
+        let mut fresh = compressor.clone();
+        let mut loud = test_signal(10_001);
+        compressor.compress_slice(&mut loud);
+        #[cfg(feature = "automation")]
+        compressor.schedule_ratio(100, 8.0);
+        compressor.reset();
+        assert_eq!(compressor.frames_processed(), 0);
+        for i in 0..SAMPLE_HZ as usize {
+            let s = 0.9 * (i as f32 * 0.01).sin();
+            assert_eq!(compressor.next_frame([s, -s]), fresh.next_frame([s, -s]));
+        }
+        assert_eq!(compressor.current_gain_reduction_db(), fresh.current_gain_reduction_db());
+        #[cfg(feature = "metering")]
+        assert_eq!(compressor.meter_snapshot(), fresh.meter_snapshot());
+    }
+
+    #[test]
+    fn decimated_long_window_levels_like_the_full_window() {
+        let mut full: RmsAvgCompressor<[f32; 2]> =
+            Compressor::rms_avg(1_000.0, 50.0, 500.0, SAMPLE_HZ, 0.1, 4.0);
+        let mut decimated = full.clone();
+        decimated.set_detection_decimation(64);
+        assert_eq!(decimated.detection_decimation(), 64);
+        let (mut full_gain, mut decimated_gain) = (1.0, 1.0);
+        for i in 0..2 * SAMPLE_HZ as usize {
+            let s = (i as f32 * 0.05).sin() * 0.5;
+            full_gain = full.next_gain([s, s]);
+            decimated_gain = decimated.next_gain([s, s]);
+        }
+        assert!(full_gain < 1.0);
+        assert!((full_gain - decimated_gain).abs() < 1e-3);
+        let mut peak = peak_compressor();
+        peak.set_detection_decimation(64);
+        assert_eq!(peak.detection_decimation(), 1);
+    }
+
+    fn scaled_reduction_db(peak_db: f32, scale: bool) -> f32 {
+        let profile = CompressorParams {
+            threshold: db::db_to_amp(-6.0),
+            ratio: 4.0,
+            attack_ms: 1.0,
+            release_ms: 100.0,
+            window_ms: None,
+            knee_db: 0.0,
+            makeup_gain: 1.0,
+            auto_makeup: false,
+        };
+        let mut compressor = peak_compressor();
+        if scale {
+            compressor.apply_profile_scaled(&profile, SAMPLE_HZ, peak_db);
+            let expected = db::db_to_amp(peak_db - 6.0);
+            assert!((compressor.params().threshold - expected).abs() < 1e-6);
+        } else {
+            compressor.set_all(&profile, SAMPLE_HZ);
+        }
+        let amp = db::db_to_amp(peak_db);
+        for i in 0..SAMPLE_HZ as usize {
+            let s = (i as f32 * 0.05).sin() * amp;
+            compressor.next_frame([s, s]);
+        }
+        -compressor.current_gain_reduction_db()
+    }
+
+    #[test]
+    fn scaled_profile_compresses_differently_leveled_sources() {
+        let loud = scaled_reduction_db(-6.0, true);
+        let quiet = scaled_reduction_db(-20.0, true);
+        // Unscaled, the profile leaves the quieter source untouched.
+        assert_eq!(scaled_reduction_db(-20.0, false), 0.0);
+        // The linear gain law reduces the quieter source less, but both are compressed.
+        assert!(loud > 1.0);
+        assert!(quiet > 0.1 * loud && quiet < loud);
+    }
+
+    #[test]
+    fn release_tail_estimate_counts_down_to_recovery() {
+        let mut compressor = peak_compressor();
+        assert_eq!(compressor.frames_until_release_complete(), 0);
+        for i in 0..2_000 {
+            let s = (i as f32 * 0.05).sin();
+            compressor.next_frame([s, s]);
+        }
+        let frames = compressor.frames_until_release_complete();
+        assert!(frames > 0);
+        let mut remaining = frames;
+        for _ in 0..frames / 2 {
+            compressor.next_frame([0.0; 2]);
+            let next = compressor.frames_until_release_complete();
+            assert!(next < remaining);
+            remaining = next;
+        }
+        for _ in 0..remaining {
+            compressor.next_frame([0.0; 2]);
+        }
+        assert_eq!(compressor.frames_until_release_complete(), 0);
+        assert!(compressor.current_gain_reduction_db() > -RELEASE_COMPLETE_DB);
+    }
+
+    #[test]
+    fn unity_gain_returns_the_input_bit_for_bit() {
+        let mut compressor = peak_compressor();
+        let frames = [[-0.0f32, 1.0e-40], [-1.0e-40, 0.25], [0.1, -0.0]];
+        for &frame in frames.iter().cycle().take(300) {
+            let output = compressor.next_frame(frame);
+            assert_eq!(output[0].to_bits(), frame[0].to_bits());
+            assert_eq!(output[1].to_bits(), frame[1].to_bits());
+        }
+        let mut compressor: PeakAvgCompressor<[i16; 2]> =
+            Compressor::peak_avg(1.0, 100.0, SAMPLE_HZ, 0.5, 4.0);
+        compressor.set_dither(DitherKind::Triangular);
+        for i in -1_000i16..1_000 {
+            assert_eq!(compressor.next_frame([i, -i]), [i, -i]);
+        }
+    }
+
+    #[test]
+    fn overshoot_ceiling_is_honoured_by_every_gain_path() {
+        let mut compressor = peak_compressor();
+        compressor.set_makeup_gain(4.0);
+        compressor.set_overshoot_ceiling(0.5);
+        for _ in 0..1_000 {
+            let output = compressor.next_frame([0.3, -0.3]);
+            assert!(output[0] <= 0.5 && output[1] >= -0.5);
+        }
+        let mut frames = [[0.3, -0.3]; 64];
+        compressor.apply_gain_slice(&mut frames, &[1.0; 64]);
+        assert!(frames.iter().all(|f| f[0] <= 0.5 && f[1] >= -0.5));
+        let mut frames = [[0.3, -0.3]; 64];
+        compressor.apply_precomputed_gain_reduction(&mut frames, &[0.0; 64]);
+        assert!(frames.iter().all(|f| f[0] <= 0.5 && f[1] >= -0.5));
+    }
+
+    #[cfg(feature = "metering")]
+    #[test]
+    fn loudness_matched_bypass_matches_clamped_output() {
+        let mut compressor = peak_compressor();
+        compressor.set_threshold_db(0.0);
+        compressor.set_makeup_gain(4.0);
+        compressor.set_overshoot_ceiling(0.5);
+        let mut output = [0.0; 2];
+        for _ in 0..SAMPLE_HZ as usize * 2 {
+            output = compressor.loudness_matched_bypass([0.25, 0.25]);
+        }
+        assert!((output[0] - 0.5).abs() < 1e-3, "{}", output[0]);
+    }
+
+    #[cfg(feature = "metering")]
+    #[test]
+    fn loudness_matched_bypass_passes_input_while_bypassed() {
+        let mut compressor = peak_compressor();
+        compressor.set_makeup_gain(4.0);
+        compressor.set_bypass(true);
+        for i in 0..1_000 {
+            let frame = [i as f32 / 1_000.0, -0.5];
+            assert_eq!(compressor.loudness_matched_bypass(frame), frame);
+        }
+    }
+
+    #[cfg(feature = "metering")]
+    #[test]
+    fn makeup_for_target_loudness_reaches_the_target() {
+        let material = test_signal(SAMPLE_HZ as usize * 2);
+        let mut measure = peak_compressor();
+        let mut compressor = measure.clone();
+        let makeup_db = measure.makeup_for_target_loudness(&material, -4.0, SAMPLE_HZ);
+        assert!(makeup_db > 1.0);
+        compressor.set_makeup_gain(db::db_to_amp(makeup_db));
+        let mut output: Vec<_> = material.iter().map(|&f| compressor.next_frame(f)).collect();
+        compressor.flush(&mut output);
+        let loudness = meter::integrated_loudness(&output, SAMPLE_HZ).unwrap();
+        assert!((loudness + 4.0).abs() < 0.1, "{}", loudness);
+        let short = &material[..100];
+        assert_eq!(peak_compressor().makeup_for_target_loudness(short, -4.0, SAMPLE_HZ), 0.0);
+    }
+
+    #[test]
+    fn enabled_stages_reflect_the_lookahead_and_ceiling() {
+        let mut compressor = peak_compressor();
+        assert!(compressor.enabled_stages().is_empty());
+        compressor.set_sidechain_delay_frames(64);
+        compressor.set_overshoot_ceiling(1.0);
+        let expected = StageFlags::LOOKAHEAD | StageFlags::OVERSHOOT_CEILING;
+        assert_eq!(compressor.enabled_stages(), expected);
+        compressor.set_sidechain_delay_frames(0);
+        assert_eq!(compressor.enabled_stages(), StageFlags::OVERSHOOT_CEILING);
+    }
+
+    #[test]
+    fn precomputed_envelope_reproduces_compress_slice() {
+        let mut detector: PeakAvgCompressor<[f32; 1]> =
+            Compressor::peak_avg(1.0, 100.0, SAMPLE_HZ, 0.25, 8.0);
+        detector.set_sidechain_delay_frames(32);
+        let mut compressor = detector.clone();
+        let mut applied = detector.clone();
+        let input: Vec<[f32; 1]> = test_signal(8_000).iter().map(|f| [f[0]]).collect();
+        let mut envelope = vec![0.0; input.len()];
+        detector.detect_envelope_slice(&input, &mut envelope);
+        let mut expected = input.clone();
+        compressor.compress_slice(&mut expected);
+        let mut frames = input.clone();
+        applied.apply_precomputed_gain_reduction(&mut frames, &envelope);
+        assert_ne!(frames, input);
+        assert_eq!(frames, expected);
+        assert_eq!(applied.last_gain, compressor.last_gain);
+    }
+
+    #[test]
+    #[should_panic]
+    fn precomputed_envelope_must_match_the_frames() {
+        let mut frames = [[0.5f32; 2]; 4];
+        peak_compressor().apply_precomputed_gain_reduction(&mut frames, &[0.5; 3]);
+    }
+
+    #[test]
+    fn threshold_is_set_below_the_peak_of_the_buffer() {
+        let mut compressor = peak_compressor();
+        let peak = db::db_to_amp(-3.0);
+        let frames: Vec<[f32; 2]> = (0..4_410).map(|i| {
+            let s = (i as f32 * 0.05).sin() * peak;
+            [0.5 * s, -s]
+        }).collect();
+        compressor.auto_set_threshold_below_peak(&frames, 6.0);
+        assert!((compressor.threshold_db() + 9.0).abs() < 0.01);
+        assert_eq!(compressor.frames_processed(), 0);
+        compressor.auto_set_threshold_below_peak(&[[0.0; 2]; 64], 6.0);
+        assert!((compressor.threshold_db() + 9.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn auto_makeup_restores_a_steady_tone_above_the_threshold() {
+        let mut compressor = peak_compressor();
+        compressor.set_auto_makeup(true);
+        assert!(compressor.effective_makeup_gain() > 1.0);
+        let mut output_peak = 0.0f32;
+        for i in 0..SAMPLE_HZ as usize {
+            let s = (i as f32 * 0.05).sin() * 0.9;
+            let output = compressor.next_frame([s, -s]);
+            if i > SAMPLE_HZ as usize / 2 {
+                output_peak = output_peak.max(output[0].abs());
+            }
+        }
+        assert!(compressor.current_gain_reduction_db() < -1.0);
+        assert!((db::amp_to_db(output_peak) - db::amp_to_db(0.9)).abs() < 1.5);
+    }
+
+    #[test]
+    fn manual_makeup_applies_unless_auto_makeup_is_enabled() {
+        let mut compressor = peak_compressor();
+        assert_eq!(compressor.effective_makeup_gain(), 1.0);
+        compressor.set_makeup_gain(2.0);
+        assert_eq!(compressor.makeup_gain(), 2.0);
+        assert_eq!(compressor.next_frame([0.1, -0.2]), [0.2, -0.4]);
+        compressor.set_auto_makeup(true);
+        assert!(compressor.auto_makeup());
+        assert_eq!(compressor.effective_makeup_gain(), 1.0 / compressor.steady_state_gain(1.0));
+    }
+
+    #[test]
+    fn would_clip_includes_the_input_and_makeup_gain() {
+        let mut compressor = peak_compressor();
+        compressor.set_slope_percent(0.0);
+        compressor.set_makeup_gain(2.0);
+        assert!(!compressor.would_clip(0.45));
+        assert!(compressor.would_clip(0.55));
+        assert!(compressor.would_clip(-0.55));
+        compressor.set_makeup_gain(1.0);
+        compressor.set_input_gain_db(6.0);
+        assert!(!compressor.would_clip(0.45));
+        assert!(compressor.would_clip(0.55));
+    }
+
+    #[test]
+    fn soft_knee_is_continuous_in_value_and_slope_at_both_edges() {
+        let (threshold, slope, knee_db) = (0.5f64, 0.75, 12.0);
+        let knee = db::db_to_amp(knee_db / 2.0) as f64;
+        let gain = |envelope: f64| knee_gain(envelope, threshold, slope, knee_db);
+        let h = 1e-6;
+        for &(edge, expected_slope) in &[(threshold / knee, 0.0), (threshold * knee, -slope)] {
+            let (below, at, above) = (gain(edge - h), gain(edge), gain(edge + h));
+            assert!((below - at).abs() < 1e-5 && (above - at).abs() < 1e-5);
+            let (left, right) = ((at - below) / h, (above - at) / h);
+            assert!((left - expected_slope).abs() < 1e-4, "{} vs {}", left, expected_slope);
+            assert!((right - expected_slope).abs() < 1e-4, "{} vs {}", right, expected_slope);
+        }
+        // At the threshold the knee has begun to reduce the gain, though by less than the chord
+        // joining its edges.
+        let (lower, upper) = (threshold / knee, threshold * knee);
+        let chord = 1.0 + (gain(upper) - 1.0) * (threshold - lower) / (upper - lower);
+        assert!(gain(threshold) < 1.0 && gain(threshold) > chord);
+    }
+
+    #[test]
+    fn zero_knee_is_the_hard_knee() {
+        for &envelope in &[0.1f32, 0.25, 0.5, 0.75, 1.0] {
+            assert_eq!(knee_gain(envelope, 0.5, 0.75, 0.0), calc_gain(envelope, 0.5, 0.75));
+            assert_eq!(knee_gain(envelope, 0.5, 0.75, -3.0), calc_gain(envelope, 0.5, 0.75));
+        }
+    }
+
+    #[test]
+    fn gain_reduction_reading_follows_the_last_gain_of_every_path() {
+        let mut stepped = peak_compressor();
+        let mut sliced = peak_compressor();
+        assert_eq!(stepped.current_gain_reduction_db(), 0.0);
+        let mut frames = test_signal(2_500);
+        for &frame in &frames {
+            stepped.next_frame(frame);
+        }
+        sliced.compress_slice(&mut frames);
+        let reduction_db = stepped.current_gain_reduction_db();
+        assert!(reduction_db < -1.0);
+        assert_eq!(reduction_db, db::amp_to_db(stepped.last_gain));
+        assert_eq!(sliced.current_gain_reduction_db(), reduction_db);
+        // Makeup gain is not part of the reading.
+        sliced.set_makeup_gain(2.0);
+        sliced.next_frame(frames[0]);
+        stepped.next_frame(frames[0]);
+        assert_eq!(sliced.current_gain_reduction_db(), stepped.current_gain_reduction_db());
+    }
+
+    #[test]
+    fn reverse_pass_attenuates_ahead_of_a_transient() {
+        let mut frames = vec![[0.2f32, -0.2]; 24_000];
+        for frame in &mut frames[20_000..20_100] {
+            *frame = [0.9, -0.9];
+        }
+        let mut forward = frames.clone();
+        peak_compressor().compress_slice(&mut forward);
+        peak_compressor().compress_slice_reverse(&mut frames);
+        // Forward, nothing happens until the transient arrives.
+        assert!(forward[..20_000].iter().all(|f| *f == [0.2, -0.2]));
+        // Reversed, the gain is already reduced in the frames leading up to it, recovering
+        // further back in time.
+        assert!(frames[19_990][0] < 0.19);
+        assert!(frames[15_000][0] > frames[19_990][0]);
+        assert!((frames[0][0] - 0.2).abs() < 1e-4);
+        assert!(frames[20_050][0] < 0.9);
+    }
+
+    #[test]
+    fn frame_constructors_match_the_requested_frame_counts() {
+        let peak: PeakAvgCompressor<[f32; 2]> =
+            Compressor::peak_from_frames(44.5, 4_410.0, SAMPLE_HZ, 0.5, 4.0);
+        let coefficients = peak.coefficients();
+        assert_eq!(coefficients.attack_gain, envelope::calc_gain(44.5));
+        assert_eq!(coefficients.release_gain, envelope::calc_gain(4_410.0));
+        assert!((peak.params().attack_ms - 44.5 * 1_000.0 / SAMPLE_HZ).abs() < 1e-9);
+        assert!((peak.params().release_ms - 100.0).abs() < 1e-9);
+        let rms: RmsAvgCompressor<[f32; 2]> =
+            Compressor::rms_from_frames(441, 132.0, 4_410.0, SAMPLE_HZ, 0.5, 4.0);
+        assert_eq!(rms.envelope_detector.window_frames(), 441);
+        assert_eq!(rms.coefficients().attack_gain, envelope::calc_gain(132.0));
+        assert_eq!(rms.params().window_ms, Some(10.0));
+        let empty: RmsAvgCompressor<[f32; 2]> =
+            Compressor::rms_from_frames(0, 132.0, 4_410.0, SAMPLE_HZ, 0.5, 4.0);
+        assert_eq!(empty.envelope_detector.window_frames(), 1);
+    }
+
+    #[test]
+    fn meter_smoothing_steadies_the_reading_but_not_the_audio() {
+        let mut raw = peak_compressor();
+        let mut smoothed = peak_compressor();
+        smoothed.set_meter_smoothing_ms(50.0, SAMPLE_HZ);
+        let (mut raw_jitter, mut smoothed_jitter) = (0.0, 0.0);
+        let (mut raw_db, mut smoothed_db) = (0.0, 0.0);
+        for frame in test_signal(8_000) {
+            assert_eq!(smoothed.next_frame(frame), raw.next_frame(frame));
+            raw_jitter += (raw.current_gain_reduction_db() - raw_db).abs();
+            smoothed_jitter += (smoothed.current_gain_reduction_db() - smoothed_db).abs();
+            raw_db = raw.current_gain_reduction_db();
+            smoothed_db = smoothed.current_gain_reduction_db();
+        }
+        assert!(smoothed_jitter < 0.5 * raw_jitter);
+        assert_eq!(smoothed.last_gain, raw.last_gain);
+        smoothed.reset_meters();
+        assert_eq!(smoothed.current_gain_reduction_db(), raw.current_gain_reduction_db());
+    }
+
+    #[test]
+    fn loop_primed_output_is_seamless() {
+        // Ten whole cycles of 100 Hz, so that the loop repeats perfectly.
+        let frames: Vec<[f32; 2]> = (0..4_410).map(|i| {
+            let s = (i as f32 * 2.0 * std::f32::consts::PI / 441.0).sin() * 0.9;
+            [s, -s]
+        }).collect();
+        let mut primed = peak_compressor();
+        primed.set_sidechain_delay_frames(32);
+        let mut unprimed = primed.clone();
+        primed.prime_for_loop(&frames);
+        let (mut first, mut second) = (frames.clone(), frames.clone());
+        primed.compress_slice(&mut first);
+        primed.compress_slice(&mut second);
+        assert!(primed.current_gain_reduction_db() < -1.0);
+        for (a, b) in first.iter().zip(second.iter()) {
+            assert!((a[0] - b[0]).abs() < 1e-4);
+        }
+        // Without priming, the first pass starts out uncompressed.
+        let (mut first, mut second) = (frames.clone(), frames);
+        unprimed.compress_slice(&mut first);
+        unprimed.compress_slice(&mut second);
+        assert!(first.iter().zip(second.iter()).any(|(a, b)| (a[0] - b[0]).abs() > 0.01));
+    }
+
+    fn surround_compressor(mode: DetectionMode) -> PeakAvgCompressor<[f32; 6]> {
+        let mut compressor = Compressor::peak_avg(1.0, 100.0, SAMPLE_HZ, 0.25, 4.0);
+        compressor.set_detection_mode(mode);
+        compressor.set_sum_weights(&[1.0, 1.0, 1.0, 0.0, 1.0, 1.0]);
+        compressor
+    }
+
+    #[test]
+    fn zero_weighted_lfe_is_excluded_from_the_detected_sum() {
+        for &mode in &[DetectionMode::MonoSum, DetectionMode::SumAbs] {
+            let mut compressor = surround_compressor(mode);
+            for _ in 0..4_410 {
+                compressor.next_frame([0.0, 0.0, 0.0, 0.9, 0.0, 0.0]);
+            }
+            assert_eq!(compressor.envelope()[0], 0.0);
+            assert_eq!(compressor.current_gain_reduction_db(), 0.0);
+        }
+        // A signal common to the weighted channels is detected at its per-channel level, and
+        // the gain still reaches the LFE.
+        let mut compressor = surround_compressor(DetectionMode::MonoSum);
+        let mut output = [0.0; 6];
+        for _ in 0..4_410 {
+            output = compressor.next_frame([0.5, 0.5, 0.5, 0.9, 0.5, 0.5]);
+        }
+        assert!((compressor.envelope()[0] - 0.5).abs() < 1e-3);
+        assert!(output[3] < 0.9);
+        assert_eq!(compressor.sum_weights(), &[1.0, 1.0, 1.0, 0.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn sum_weights_must_match_the_channel_count() {
+        peak_compressor().set_sum_weights(&[1.0, 1.0, 0.0]);
+    }
+
+    #[cfg(feature = "analysis")]
+    #[test]
+    fn fast_heavy_limiting_adds_more_distortion_than_slow_gentle_compression() {
+        let mut gentle: RmsAvgCompressor<[f32; 2]> =
+            Compressor::rms_avg(50.0, 50.0, 500.0, SAMPLE_HZ, 0.15, 2.0);
+        let mut heavy: PeakAvgCompressor<[f32; 2]> =
+            Compressor::peak_avg(0.1, 5.0, SAMPLE_HZ, 0.1, 20.0);
+        let gentle_thd = gentle.measure_thd(100.0, SAMPLE_HZ);
+        let heavy_thd = heavy.measure_thd(100.0, SAMPLE_HZ);
+        assert!(gentle.current_gain_reduction_db() < -0.5);
+        assert!(gentle_thd < 0.01, "{}", gentle_thd);
+        assert!(heavy_thd > 5.0 * gentle_thd, "{} vs {}", heavy_thd, gentle_thd);
+        // Below the threshold the compressor is transparent.
+        assert!(peak_compressor().measure_thd(100.0, SAMPLE_HZ) < 1e-4);
+    }
+
+    #[test]
+    fn high_precision_rms_applies_only_to_windowed_detectors() {
+        let mut compressor: RmsAvgCompressor<[f32; 2]> =
+            Compressor::rms_avg(20.0, 1.0, 100.0, SAMPLE_HZ, 0.5, 4.0);
+        assert!(!compressor.high_precision_rms());
+        compressor.set_high_precision_rms(true);
+        assert!(compressor.high_precision_rms());
+        let mut peak = peak_compressor();
+        peak.set_high_precision_rms(true);
+        assert!(!peak.high_precision_rms());
+    }
+
+    #[test]
+    fn invalid_sample_rates_are_rejected_without_touching_the_compressor() {
+        let mut compressor = peak_compressor();
+        let coefficients = compressor.coefficients();
+        for &sample_hz in &[0.0, -44_100.0, std::f64::INFINITY] {
+            let result = compressor.try_update_to_sample_hz(sample_hz);
+            assert_eq!(result, Err(CompressorError::InvalidSampleRate(sample_hz)));
+            assert_eq!(compressor.coefficients(), coefficients);
+        }
+        match compressor.try_update_to_sample_hz(std::f64::NAN) {
+            Err(CompressorError::InvalidSampleRate(hz)) => assert!(hz.is_nan()),
+            result => panic!("expected InvalidSampleRate, got {:?}", result),
+        }
+        assert_eq!(compressor.coefficients(), coefficients);
+        assert_eq!(compressor.try_update_to_sample_hz(96_000.0), Ok(()));
+        assert!(compressor.coefficients().attack_gain > coefficients.attack_gain);
+    }
+
+    #[test]
+    #[should_panic]
+    fn constructing_at_a_zero_sample_rate_panics() {
+        let _: PeakAvgCompressor<[f32; 2]> = Compressor::peak_avg(1.0, 100.0, 0.0, 0.5, 4.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn setting_a_duration_at_a_nan_sample_rate_panics() {
+        peak_compressor().set_attack_ms(5.0, std::f64::NAN);
+    }
+
+    #[test]
+    fn every_method_taking_a_sample_rate_rejects_invalid_ones() {
+        fn panics<T: FnOnce()>(f: T) -> bool {
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).is_err()
+        }
+        for &hz in &[0.0, -44_100.0, std::f64::NAN, std::f64::INFINITY] {
+            let mut compressor = peak_compressor();
+            #[cfg(feature = "sidechain-filter")]
+            {
+                assert!(panics(|| compressor.set_sidechain_hpf(Some(100.0), hz)));
+                assert!(panics(|| compressor.set_sidechain_hpf_channel(0, Some(100.0), hz)));
+                assert!(panics(|| compressor.set_k_weighting(true, hz)));
+                assert!(panics(|| compressor.set_detection_emphasis(5e3, 8e3, 6.0, hz)));
+                assert!(panics(|| compressor.clear_detection_emphasis(hz)));
+                assert_eq!(compressor.sidechain_hpf_channel_hz(0), None);
+                assert!(!compressor.k_weighting());
+                assert_eq!(compressor.detection_emphasis(), None);
+            }
+            #[cfg(feature = "metering")]
+            {
+                assert!(panics(|| compressor.set_loudness_meter(true, hz)));
+                assert!(!compressor.loudness_meter());
+            }
+            #[cfg(feature = "analysis")]
+            assert!(panics(|| { compressor.measure_thd(100.0, hz); }));
+            assert!(panics(|| compressor.reserve(4_410, 4_410, hz)));
+            assert_eq!(compressor.frames_processed(), 0);
+
+            let mut dynamic = DynamicCompressor::<[f32; 2]>::from(peak_compressor());
+            assert!(panics(|| dynamic.set_detector_kind(DetectorKind::Rms(RmsWindow::Hann), hz)));
+            assert_eq!(dynamic.detector_kind(), DetectorKind::Peak);
+        }
+    }
+
+    #[test]
+    fn capturing_records_the_dry_input_and_compresses_as_usual() {
+        let mut capturing = peak_compressor();
+        let mut plain = peak_compressor();
+        let input = test_signal(4_000);
+        let mut frames = input.clone();
+        let mut dry = vec![[0.0; 2]; input.len()];
+        capturing.compress_slice_capturing(&mut frames, &mut dry);
+        let mut expected = input.clone();
+        plain.compress_slice(&mut expected);
+        assert_eq!(dry, input);
+        assert_eq!(frames, expected);
+        assert_ne!(frames, input);
+    }
+
+    #[test]
+    #[should_panic]
+    fn capturing_requires_a_dry_buffer_of_the_same_length() {
+        let mut frames = [[0.5f32; 2]; 4];
+        peak_compressor().compress_slice_capturing(&mut frames, &mut [[0.0; 2]; 3]);
+    }
 }
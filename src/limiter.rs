@@ -0,0 +1,131 @@
+//! A purpose-built stereo limiter that never alters the balance between its channels.
+//!
+//! The primary type of interest is the [**StereoLimiter**](./struct.StereoLimiter).
+
+use PeakMinCompressor;
+use envelope_detector::Sample;
+use std;
+use time::Ms;
+
+
+/// A peak limiter for stereo frames that always applies one gain to both channels.
+///
+/// The limiter tracks the peak envelope of each channel and derives its gain from whichever
+/// channel is loudest (the **Minimum** of the per-channel gains), limiting at the threshold with
+/// an infinite ratio. The single gain then multiplies both channels, so the ratio between the left
+/// and right channels of every frame is exactly that of the input, however hard the signal is
+/// limited. No per-channel gain, detection mode or function that could cause the channels to
+/// diverge is exposed.
+///
+/// As with any **Compressor**, integer sample formats are rounded after the gain is applied, which
+/// may alter the ratio by at most one quantization step per channel.
+#[derive(Clone)]
+pub struct StereoLimiter<S>
+    where S: Sample,
+{
+    compressor: PeakMinCompressor<[S; 2]>,
+}
+
+impl<S> std::fmt::Debug for StereoLimiter<S>
+    where S: Sample + std::fmt::Debug,
+          S::Float: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "StereoLimiter {{ compressor: {:?} }}", &self.compressor)
+    }
+}
+
+
+impl<S> StereoLimiter<S>
+    where S: Sample,
+{
+
+    /// Construct a new **StereoLimiter** that holds the peak envelope of the loudest channel at
+    /// or below the given threshold.
+    pub fn new<A, R>(attack_ms: A, release_ms: R, sample_hz: f64, threshold: f32) -> Self
+        where A: Into<Ms>,
+              R: Into<Ms>,
+    {
+        let ratio = ::std::f32::INFINITY;
+        let compressor = PeakMinCompressor::peak_min(attack_ms, release_ms, sample_hz, threshold,
+                                                     ratio);
+        StereoLimiter { compressor: compressor }
+    }
+
+    /// The linear amplitude above which the loudest channel's envelope is limited.
+    pub fn threshold(&self) -> f32 {
+        self.compressor.threshold
+    }
+
+    /// Set the linear amplitude above which the loudest channel's envelope is limited.
+    pub fn set_threshold(&mut self, threshold: f32) {
+        self.compressor.threshold = threshold;
+    }
+
+    /// Set the duration of the envelope's attack in milliseconds.
+    pub fn set_attack_ms<M: Into<Ms>>(&mut self, ms: M, sample_hz: f64) {
+        self.compressor.set_attack_ms(ms, sample_hz);
+    }
+
+    /// Set the duration of the envelope's release in milliseconds.
+    pub fn set_release_ms<M: Into<Ms>>(&mut self, ms: M, sample_hz: f64) {
+        self.compressor.set_release_ms(ms, sample_hz);
+    }
+
+    /// Updates all of the limiter's duration-based state in accordance with the given sample_hz.
+    pub fn update_to_sample_hz(&mut self, sample_hz: f64) {
+        self.compressor.update_to_sample_hz(sample_hz);
+    }
+
+    /// Borrow the underlying **Compressor**, e.g. for metering.
+    pub fn compressor(&self) -> &PeakMinCompressor<[S; 2]> {
+        &self.compressor
+    }
+
+    /// Steps forward the limiter by the given frame and returns the limited result.
+    #[inline]
+    pub fn next_frame(&mut self, frame: [S; 2]) -> [S; 2] {
+        self.compressor.next_frame(frame)
+    }
+
+    /// Limits the given `frames` in place.
+    #[inline]
+    pub fn limit_slice(&mut self, frames: &mut [[S; 2]]) {
+        self.compressor.compress_slice(frames);
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limiting_preserves_the_stereo_balance() {
+        let mut limiter = StereoLimiter::<f32>::new(1.0, 100.0, 44_100.0, 0.25);
+        let mut limited = false;
+        for i in 0..8_820 {
+            let s = (i as f32 * 0.02).sin();
+            let output = limiter.next_frame([s, 0.25 * s]);
+            assert_eq!(output[1], output[0] * 0.25);
+            assert!(output[0].abs() <= s.abs());
+            limited |= output[0].abs() < s.abs();
+        }
+        assert!(limited);
+    }
+
+    #[test]
+    fn signal_below_the_threshold_passes_untouched() {
+        let mut limiter = StereoLimiter::<f32>::new(1.0, 100.0, 44_100.0, 0.5);
+        let mut frames: Vec<[f32; 2]> =
+            (0..4_410).map(|i| [(i as f32 * 0.02).sin() * 0.4, 0.1]).collect();
+        let expected = frames.clone();
+        limiter.limit_slice(&mut frames);
+        assert_eq!(frames, expected);
+        limiter.set_threshold(0.05);
+        assert_eq!(limiter.threshold(), 0.05);
+        limiter.limit_slice(&mut frames);
+        assert!(frames[4_409][1] < 0.1);
+    }
+}
@@ -0,0 +1,558 @@
+//! Meters for observing the gain applied by the **Compressor** without affecting its output.
+
+#[cfg(feature = "metering")]
+use assert_valid_sample_hz;
+#[cfg(feature = "metering")]
+use db;
+use envelope_detector::{Frame, Sample};
+#[cfg(feature = "metering")]
+use filter::{Biquad, BiquadCoefficients};
+#[cfg(feature = "metering")]
+use sample::FloatSample;
+#[cfg(feature = "metering")]
+use time::Ms;
+
+
+/// A snapshot of a **Compressor**'s meters at a single moment, as produced by `meter_snapshot`.
+///
+/// The snapshot is plain `Copy` data, so it may be published by the audio thread and read by a
+/// UI thread without locks.
+#[cfg(feature = "metering")]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct MeterSnapshot {
+    /// The gain reduction applied to the most recent frame in decibels.
+    pub gain_reduction_db: f32,
+    /// The loudest channel of the most recently detected envelope.
+    pub envelope: f32,
+    /// The peak absolute sample of the most recently produced frame.
+    pub output_peak: f32,
+    /// The spread of gain reduction applied over the range window in decibels.
+    pub dynamic_range_reduction_db: f32,
+    /// The loudness removed by the **Compressor** in LU, or `0.0` if not measured.
+    pub loudness_reduction_lu: f32,
+    /// The number of frames processed when the snapshot was taken.
+    pub frames_processed: u64,
+}
+
+/// Follows the highest and lowest gain applied over a sliding period.
+///
+/// Each follower jumps immediately to a new extreme and otherwise relaxes back toward the current
+/// gain over the window, so the spread between the two describes how much the applied gain has
+/// been varying recently.
+#[cfg(feature = "metering")]
+#[derive(Copy, Clone, Debug)]
+pub struct RangeFollower {
+    /// The amount of the previous extreme retained each frame while relaxing.
+    release_gain: f32,
+    /// The highest recently applied gain.
+    max_gain: f32,
+    /// The lowest recently applied gain.
+    min_gain: f32,
+}
+
+/// The lowest gain considered by the range readout, avoiding an infinite range when the gain
+/// reaches zero.
+#[cfg(feature = "metering")]
+const MIN_GAIN: f32 = 1.0e-6;
+
+
+fn calc_gain(n_frames: f32) -> f32 {
+    ::std::f32::consts::E.powf(-1.0 / n_frames)
+}
+
+
+#[cfg(feature = "metering")]
+impl RangeFollower {
+
+    /// Construct a new **RangeFollower** relaxing over the given number of frames.
+    pub fn new(window_frames: f32) -> Self {
+        RangeFollower {
+            release_gain: calc_gain(window_frames),
+            max_gain: 1.0,
+            min_gain: 1.0,
+        }
+    }
+
+    /// Set the period over which the followers relax as a number of frames.
+    pub fn set_window_frames(&mut self, frames: f32) {
+        self.release_gain = calc_gain(frames);
+    }
+
+    /// Step the followers forward with the gain applied to the next frame.
+    #[inline]
+    pub fn next(&mut self, gain: f32) {
+        let gain = if gain < MIN_GAIN { MIN_GAIN } else { gain };
+        let release_gain = self.release_gain;
+        self.max_gain = if gain > self.max_gain {
+            gain
+        } else {
+            gain + (self.max_gain - gain) * release_gain
+        };
+        self.min_gain = if gain < self.min_gain {
+            gain
+        } else {
+            gain + (self.min_gain - gain) * release_gain
+        };
+    }
+
+    /// Step the followers forward by `n_frames` during which the same gain was applied.
+    pub fn next_n(&mut self, gain: f32, n_frames: usize) {
+        if n_frames == 0 {
+            return;
+        }
+        let gain = if gain < MIN_GAIN { MIN_GAIN } else { gain };
+        let release_gain = self.release_gain.powf((n_frames - 1) as f32);
+        self.next(gain);
+        self.max_gain = gain + (self.max_gain - gain) * release_gain;
+        self.min_gain = gain + (self.min_gain - gain) * release_gain;
+    }
+
+    /// Forget all previously applied gains.
+    pub fn reset(&mut self) {
+        self.max_gain = 1.0;
+        self.min_gain = 1.0;
+    }
+
+    /// The spread between the highest and lowest recently applied gain in decibels.
+    pub fn range_db(&self) -> f32 {
+        db::amp_to_db(self.max_gain / self.min_gain)
+    }
+
+}
+
+
+/// Follows the recent power of both the dry and the compressed signal in order to find the gain
+/// that makes the dry signal as loud as the compressed one.
+#[cfg(feature = "metering")]
+#[derive(Copy, Clone, Debug)]
+pub struct LoudnessMatcher {
+    /// The amount of the previous power retained each frame.
+    gain: f32,
+    /// The recent mean square of the dry signal.
+    dry_power: f32,
+    /// The recent mean square of the compressed signal.
+    wet_power: f32,
+}
+
+/// The power below which the dry signal is considered silent and left unscaled.
+#[cfg(feature = "metering")]
+const MIN_POWER: f32 = 1.0e-12;
+
+
+/// The mean square across all channels of the given frame.
+#[inline]
+pub fn mean_square<F>(frame: F) -> f32
+    where F: Frame,
+{
+    let sum = frame.channels().fold(0.0, |sum, s| {
+        let s: f32 = s.to_float_sample().to_sample();
+        sum + s * s
+    });
+    sum / F::n_channels() as f32
+}
+
+/// The peak absolute sample across all channels of the given frame.
+#[inline]
+pub fn peak<F>(frame: F) -> f32
+    where F: Frame,
+{
+    frame.channels().fold(0.0, |peak, sample| {
+        let s: f32 = sample.to_float_sample().to_sample();
+        let s = s.abs();
+        if s > peak { s } else { peak }
+    })
+}
+
+
+#[cfg(feature = "metering")]
+impl LoudnessMatcher {
+
+    /// Construct a new **LoudnessMatcher** averaging power over the given number of frames.
+    pub fn new(window_frames: f32) -> Self {
+        LoudnessMatcher {
+            gain: calc_gain(window_frames),
+            dry_power: 0.0,
+            wet_power: 0.0,
+        }
+    }
+
+    /// Set the period over which power is averaged as a number of frames.
+    pub fn set_window_frames(&mut self, frames: f32) {
+        self.gain = calc_gain(frames);
+    }
+
+    /// Step the followers forward with the power of the next dry and compressed frames.
+    #[inline]
+    pub fn next(&mut self, dry_power: f32, wet_power: f32) {
+        let gain = self.gain;
+        self.dry_power = dry_power + (self.dry_power - dry_power) * gain;
+        self.wet_power = wet_power + (self.wet_power - wet_power) * gain;
+    }
+
+    /// Forget the power of all previous frames.
+    pub fn reset(&mut self) {
+        self.dry_power = 0.0;
+        self.wet_power = 0.0;
+    }
+
+    /// The gain that brings the dry signal's recent power to that of the compressed signal.
+    pub fn matching_gain(&self) -> f32 {
+        if self.dry_power < MIN_POWER {
+            1.0
+        } else {
+            (self.wet_power / self.dry_power).sqrt()
+        }
+    }
+
+}
+
+
+/// Follows the long-term RMS level of a signal as a one-pole average of its mean square.
+#[derive(Copy, Clone, Debug)]
+pub struct PowerFollower {
+    /// The amount of the previous power retained each frame.
+    gain: f32,
+    /// The long-term mean square of the signal.
+    power: f32,
+}
+
+impl PowerFollower {
+
+    /// Construct a new **PowerFollower** averaging power over the given number of frames.
+    pub fn new(window_frames: f32) -> Self {
+        PowerFollower {
+            gain: calc_gain(window_frames),
+            power: 0.0,
+        }
+    }
+
+    /// Set the period over which power is averaged as a number of frames.
+    pub fn set_window_frames(&mut self, frames: f32) {
+        self.gain = calc_gain(frames);
+    }
+
+    /// Restart the measurement from the given RMS level.
+    pub fn set_rms(&mut self, rms: f32) {
+        self.power = rms * rms;
+    }
+
+    /// Step the follower forward with the power of the next frame.
+    #[inline]
+    pub fn next(&mut self, power: f32) {
+        self.power = power + (self.power - power) * self.gain;
+    }
+
+    /// Step the follower forward by `n_frames` of silence.
+    pub fn next_silence(&mut self, n_frames: usize) {
+        self.power *= self.gain.powf(n_frames as f32);
+    }
+
+    /// The long-term RMS level of the signal.
+    pub fn rms(&self) -> f32 {
+        self.power.sqrt()
+    }
+
+}
+
+
+/// Measures how much loudness the **Compressor** removes, following ITU-R BS.1770.
+///
+/// Both the dry and the compressed signal are K-weighted and their power is measured over 400ms
+/// blocks overlapping by 75%. Blocks are gated by the dry signal's loudness: a block quieter than
+/// `LOUDNESS_ABSOLUTE_GATE_LUFS`, or more than `LOUDNESS_RELATIVE_GATE_LU` below the loudness
+/// measured so far, is ignored. The power of the remaining blocks is averaged over roughly the
+/// last `LOUDNESS_REDUCTION_WINDOW_MS`, so that the reading follows changes in the program.
+#[cfg(feature = "metering")]
+#[derive(Copy, Clone, Debug)]
+pub struct LoudnessReductionMeter<F> {
+    /// The high shelf stage of the dry signal's K-weighting filter.
+    dry_shelf: Biquad<F>,
+    /// The high-pass stage of the dry signal's K-weighting filter.
+    dry_highpass: Biquad<F>,
+    /// The high shelf stage of the compressed signal's K-weighting filter.
+    wet_shelf: Biquad<F>,
+    /// The high-pass stage of the compressed signal's K-weighting filter.
+    wet_highpass: Biquad<F>,
+    /// The number of frames in each 100ms sub-block.
+    sub_block_frames: usize,
+    /// The number of frames accumulated into the current sub-block.
+    frame_count: usize,
+    /// The summed power of the dry and compressed signal over the current sub-block.
+    sub_block_sum: (f32, f32),
+    /// The mean power of the dry and compressed signal over the last four sub-blocks.
+    sub_blocks: [(f32, f32); 4],
+    /// The index of the oldest of the `sub_blocks`.
+    oldest: usize,
+    /// The amount of the previous average power retained for each gated block.
+    gain: f32,
+    /// The average power of the gated blocks of the dry signal.
+    dry_power: f32,
+    /// The average power of the gated blocks of the compressed signal.
+    wet_power: f32,
+}
+
+/// The period over which `LoudnessReductionMeter` averages the loudness of gated blocks.
+#[cfg(feature = "metering")]
+pub const LOUDNESS_REDUCTION_WINDOW_MS: f64 = 3_000.0;
+/// The loudness below which blocks are ignored by the `LoudnessReductionMeter`.
+#[cfg(feature = "metering")]
+pub const LOUDNESS_ABSOLUTE_GATE_LUFS: f32 = -70.0;
+/// The amount by which a block may fall below the measured loudness before it is ignored by the
+/// `LoudnessReductionMeter`.
+#[cfg(feature = "metering")]
+pub const LOUDNESS_RELATIVE_GATE_LU: f32 = 10.0;
+/// The duration of each of the four sub-blocks that make up a 400ms block.
+#[cfg(feature = "metering")]
+const SUB_BLOCK_MS: f64 = 100.0;
+
+
+/// The loudness in LUFS of a block with the given summed channel power.
+#[cfg(feature = "metering")]
+fn lufs(power: f32) -> f32 {
+    -0.691 + 10.0 * power.log10()
+}
+
+/// The integrated loudness in LUFS of the given frames, following ITU-R BS.1770.
+///
+/// The frames are K-weighted and their power measured over 400ms blocks overlapping by 75%. Blocks
+/// quieter than `LOUDNESS_ABSOLUTE_GATE_LUFS` are ignored, as are blocks more than
+/// `LOUDNESS_RELATIVE_GATE_LU` below the loudness of the remaining blocks. Yields `None` if no
+/// block passes the gates, e.g. for frames spanning less than 400ms.
+#[cfg(feature = "metering")]
+pub fn integrated_loudness<F>(frames: &[F], sample_hz: f64) -> Option<f32>
+    where F: Frame,
+          F::Sample: FloatSample,
+{
+    let mut shelf = Biquad::new(BiquadCoefficients::k_weighting_shelf(sample_hz));
+    let mut highpass = Biquad::new(BiquadCoefficients::k_weighting_highpass(sample_hz));
+    let sub_block_frames = Ms(SUB_BLOCK_MS).samples(sample_hz);
+    let sub_block_frames = if sub_block_frames < 1 { 1 } else { sub_block_frames as usize };
+    let n_channels = F::n_channels() as f32;
+
+    // The mean power of each complete sub-block.
+    let mut sub_blocks = Vec::with_capacity(frames.len() / sub_block_frames);
+    let mut sum = 0.0;
+    for (i, &frame) in frames.iter().enumerate() {
+        let weighted = highpass.next(shelf.next(frame));
+        sum += mean_square(weighted) * n_channels;
+        if (i + 1) % sub_block_frames == 0 {
+            sub_blocks.push(sum / sub_block_frames as f32);
+            sum = 0.0;
+        }
+    }
+
+    let blocks: Vec<f32> = sub_blocks.windows(4)
+        .map(|window| window.iter().fold(0.0, |sum, &power| sum + power) / 4.0)
+        .filter(|&power| lufs(power) > LOUDNESS_ABSOLUTE_GATE_LUFS)
+        .collect();
+    if blocks.is_empty() {
+        return None;
+    }
+    let mean = blocks.iter().fold(0.0, |sum, &power| sum + power) / blocks.len() as f32;
+    let gate = lufs(mean) - LOUDNESS_RELATIVE_GATE_LU;
+    let (sum, count) = blocks.iter()
+        .filter(|&&power| lufs(power) >= gate)
+        .fold((0.0, 0), |(sum, count), &power| (sum + power, count + 1));
+    Some(lufs(sum / count as f32))
+}
+
+
+#[cfg(feature = "metering")]
+impl<F> LoudnessReductionMeter<F>
+    where F: Frame,
+          F::Sample: FloatSample,
+{
+
+    /// Construct a new **LoudnessReductionMeter** for the given sample_hz.
+    pub fn new(sample_hz: f64) -> Self {
+        let identity = Biquad::new(BiquadCoefficients::identity());
+        let mut meter = LoudnessReductionMeter {
+            dry_shelf: identity,
+            dry_highpass: identity,
+            wet_shelf: identity,
+            wet_highpass: identity,
+            sub_block_frames: 1,
+            frame_count: 0,
+            sub_block_sum: (0.0, 0.0),
+            sub_blocks: [(0.0, 0.0); 4],
+            oldest: 0,
+            gain: 0.0,
+            dry_power: 0.0,
+            wet_power: 0.0,
+        };
+        meter.update_to_sample_hz(sample_hz);
+        meter
+    }
+
+    /// Update the filters and block length in accordance with the given sample_hz.
+    pub fn update_to_sample_hz(&mut self, sample_hz: f64) {
+        assert_valid_sample_hz(sample_hz, "LoudnessReductionMeter::update_to_sample_hz");
+        let shelf = BiquadCoefficients::k_weighting_shelf(sample_hz);
+        let highpass = BiquadCoefficients::k_weighting_highpass(sample_hz);
+        self.dry_shelf.set_coefficients(shelf);
+        self.wet_shelf.set_coefficients(shelf);
+        self.dry_highpass.set_coefficients(highpass);
+        self.wet_highpass.set_coefficients(highpass);
+        let sub_block_frames = Ms(SUB_BLOCK_MS).samples(sample_hz);
+        self.sub_block_frames = if sub_block_frames < 1 { 1 } else { sub_block_frames as usize };
+        let window_blocks = (LOUDNESS_REDUCTION_WINDOW_MS / SUB_BLOCK_MS) as f32;
+        self.gain = calc_gain(window_blocks);
+    }
+
+    /// Forget the signal measured so far, keeping the filter coefficients and block length.
+    pub fn reset(&mut self) {
+        self.dry_shelf.reset();
+        self.dry_highpass.reset();
+        self.wet_shelf.reset();
+        self.wet_highpass.reset();
+        self.frame_count = 0;
+        self.sub_block_sum = (0.0, 0.0);
+        self.sub_blocks = [(0.0, 0.0); 4];
+        self.oldest = 0;
+        self.dry_power = 0.0;
+        self.wet_power = 0.0;
+    }
+
+    /// Step the meter forward with the next dry and compressed frames.
+    #[inline]
+    pub fn next(&mut self, dry: F, wet: F) {
+        let dry = self.dry_highpass.next(self.dry_shelf.next(dry));
+        let wet = self.wet_highpass.next(self.wet_shelf.next(wet));
+        let n_channels = F::n_channels() as f32;
+        self.sub_block_sum.0 += mean_square(dry) * n_channels;
+        self.sub_block_sum.1 += mean_square(wet) * n_channels;
+        self.frame_count += 1;
+        if self.frame_count >= self.sub_block_frames {
+            self.end_sub_block();
+        }
+    }
+
+    /// Complete the current sub-block and measure the 400ms block that it completes.
+    fn end_sub_block(&mut self) {
+        let n_frames = self.frame_count as f32;
+        let (dry_sum, wet_sum) = self.sub_block_sum;
+        self.sub_blocks[self.oldest] = (dry_sum / n_frames, wet_sum / n_frames);
+        self.oldest = (self.oldest + 1) % self.sub_blocks.len();
+        self.sub_block_sum = (0.0, 0.0);
+        self.frame_count = 0;
+
+        let (dry_sum, wet_sum) = self.sub_blocks.iter()
+            .fold((0.0, 0.0), |(d, w), &(dry, wet)| (d + dry, w + wet));
+        let n_sub_blocks = self.sub_blocks.len() as f32;
+        let (dry_block, wet_block) = (dry_sum / n_sub_blocks, wet_sum / n_sub_blocks);
+        let loudness = lufs(dry_block);
+        if !(loudness > LOUDNESS_ABSOLUTE_GATE_LUFS) {
+            return;
+        }
+        if self.dry_power > 0.0 {
+            if loudness < lufs(self.dry_power) - LOUDNESS_RELATIVE_GATE_LU {
+                return;
+            }
+            let gain = self.gain;
+            self.dry_power = dry_block + (self.dry_power - dry_block) * gain;
+            self.wet_power = wet_block + (self.wet_power - wet_block) * gain;
+        } else {
+            self.dry_power = dry_block;
+            self.wet_power = wet_block;
+        }
+    }
+
+    /// The loudness removed from the signal in loudness units, or `0.0` until a block has passed
+    /// the gates.
+    pub fn reduction_lu(&self) -> f32 {
+        if self.dry_power > 0.0 && self.wet_power > 0.0 {
+            10.0 * (self.dry_power / self.wet_power).log10()
+        } else {
+            0.0
+        }
+    }
+
+}
+
+
+#[cfg(all(test, feature = "metering"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loudness_matcher_converges_to_the_power_ratio() {
+        let mut matcher = LoudnessMatcher::new(100.0);
+        assert_eq!(matcher.matching_gain(), 1.0);
+        for _ in 0..10_000 {
+            matcher.next(mean_square([0.8f32, -0.8]), mean_square([0.2f32, -0.2]));
+        }
+        assert!((matcher.matching_gain() - 0.25).abs() < 1e-4);
+    }
+
+    #[test]
+    fn loudness_matcher_leaves_silence_unscaled() {
+        let mut matcher = LoudnessMatcher::new(100.0);
+        for _ in 0..10_000 {
+            matcher.next(0.0, mean_square([0.2f32]));
+        }
+        assert_eq!(matcher.matching_gain(), 1.0);
+    }
+
+    #[test]
+    fn range_follower_next_n_matches_repeated_next() {
+        let mut stepped = RangeFollower::new(100.0);
+        let mut skipped = stepped;
+        stepped.next(0.25);
+        skipped.next(0.25);
+        for _ in 0..50 {
+            stepped.next(0.5);
+        }
+        skipped.next_n(0.5, 50);
+        assert!((stepped.range_db() - skipped.range_db()).abs() < 1e-4);
+    }
+
+    fn tone(i: usize) -> [f32; 2] {
+        let s = (i as f32 * 2.0 * std::f32::consts::PI * 1_000.0 / 48_000.0).sin() * 0.5;
+        [s, s]
+    }
+
+    #[test]
+    fn halving_the_level_reduces_loudness_by_six_lu() {
+        let mut meter = LoudnessReductionMeter::new(48_000.0);
+        for i in 0..48_000 {
+            let dry = tone(i);
+            meter.next(dry, [dry[0] * 0.5, dry[1] * 0.5]);
+        }
+        assert!((meter.reduction_lu() - 6.02).abs() < 0.01);
+    }
+
+    #[test]
+    fn silence_is_gated() {
+        let mut meter = LoudnessReductionMeter::new(48_000.0);
+        for _ in 0..48_000 {
+            meter.next([0.0f32, 0.0], [0.0, 0.0]);
+        }
+        assert_eq!(meter.reduction_lu(), 0.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn constructing_at_a_zero_sample_rate_panics() {
+        LoudnessReductionMeter::<[f32; 2]>::new(0.0);
+    }
+
+    #[cfg(feature = "metering")]
+    #[test]
+    fn integrated_loudness_of_a_full_scale_sine_is_near_zero_lufs() {
+        // A 1 kHz sine of amplitude 1.0 in both channels reads close to 0 LUFS.
+        let frames: Vec<[f32; 2]> = (0..48_000).map(|i| [tone(i)[0] * 2.0; 2]).collect();
+        let loudness = integrated_loudness(&frames, 48_000.0).unwrap();
+        assert!(loudness.abs() < 0.1, "{}", loudness);
+        let halved: Vec<[f32; 2]> = (0..48_000).map(tone).collect();
+        let halved = integrated_loudness(&halved, 48_000.0).unwrap();
+        assert!((loudness - halved - 6.02).abs() < 0.01);
+    }
+
+    #[cfg(feature = "metering")]
+    #[test]
+    fn integrated_loudness_of_short_or_silent_frames_is_unmeasured() {
+        let short: Vec<[f32; 2]> = (0..9_600).map(tone).collect();
+        assert_eq!(integrated_loudness(&short, 48_000.0), None);
+        assert_eq!(integrated_loudness(&[[0.0f32; 2]; 48_000][..], 48_000.0), None);
+    }
+}
@@ -0,0 +1,298 @@
+//! A multiband compressor that splits the signal into frequency bands and compresses each band
+//! with its own **Compressor**.
+//!
+//! The primary type of interest is the [**MultibandCompressor**](./struct.MultibandCompressor).
+
+use {CompressorParams, EvenGainFunction, PeakCompressor};
+use envelope_detector::Frame;
+use filter::{BUTTERWORTH_Q, Biquad, BiquadCoefficients};
+use sample::FloatSample;
+use std;
+
+
+/// The parameters of a single band of a **MultibandCompressor**.
+///
+/// Bands use peak detection, so the `window_ms` is ignored.
+pub type BandParams = CompressorParams;
+
+/// A fourth-order Linkwitz-Riley crossover, splitting the signal into a low and a high band that
+/// sum back to an all-pass response.
+#[derive(Copy, Clone, Debug)]
+struct Crossover<F> {
+    lowpass: [Biquad<F>; 2],
+    highpass: [Biquad<F>; 2],
+}
+
+/// Splits the signal into bands at a series of crossover frequencies and compresses each band
+/// with its own peak **Compressor**, before summing the bands back together.
+///
+/// Each band has its own attack, release, threshold and ratio, e.g. slow ballistics for the lows
+/// and fast ballistics for the highs when mastering. Each crossover is a fourth-order
+/// Linkwitz-Riley filter, and every band below a crossover passes through the all-pass response
+/// of that crossover, so that the bands remain phase-coherent and sum back to a flat magnitude
+/// response while no gain reduction is applied.
+///
+/// The band **Compressor**s work on the filtered signal, so the frames must be of floating point
+/// samples.
+#[derive(Clone)]
+pub struct MultibandCompressor<F, EGF>
+    where F: Frame,
+{
+    bands: Vec<PeakCompressor<F, EGF>>,
+    crossovers_hz: Vec<f64>,
+    crossovers: Vec<Crossover<F>>,
+    /// For each band, the all-passes matching the phase of each crossover above the band.
+    allpasses: Vec<Vec<Biquad<F>>>,
+}
+
+impl<F, EGF> std::fmt::Debug for MultibandCompressor<F, EGF>
+    where F: Frame + std::fmt::Debug,
+          PeakCompressor<F, EGF>: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "MultibandCompressor {{ bands: {:?}, crossovers_hz: {:?} }}",
+               &self.bands, &self.crossovers_hz)
+    }
+}
+
+
+impl<F> Crossover<F>
+    where F: Frame,
+          F::Sample: FloatSample,
+{
+
+    fn new(cutoff_hz: f64, sample_hz: f64) -> Self {
+        let lowpass = Biquad::new(BiquadCoefficients::lowpass(cutoff_hz, BUTTERWORTH_Q, sample_hz));
+        let highpass =
+            Biquad::new(BiquadCoefficients::highpass(cutoff_hz, BUTTERWORTH_Q, sample_hz));
+        Crossover {
+            lowpass: [lowpass; 2],
+            highpass: [highpass; 2],
+        }
+    }
+
+    fn set_cutoff_hz(&mut self, cutoff_hz: f64, sample_hz: f64) {
+        let lowpass = BiquadCoefficients::lowpass(cutoff_hz, BUTTERWORTH_Q, sample_hz);
+        let highpass = BiquadCoefficients::highpass(cutoff_hz, BUTTERWORTH_Q, sample_hz);
+        for biquad in &mut self.lowpass {
+            biquad.set_coefficients(lowpass);
+        }
+        for biquad in &mut self.highpass {
+            biquad.set_coefficients(highpass);
+        }
+    }
+
+    /// Split the given frame into its low and high bands.
+    #[inline]
+    fn next(&mut self, frame: F) -> (F, F) {
+        let low = self.lowpass.iter_mut().fold(frame, |f, biquad| biquad.next(f));
+        let high = self.highpass.iter_mut().fold(frame, |f, biquad| biquad.next(f));
+        (low, high)
+    }
+
+}
+
+
+impl<F, EGF> MultibandCompressor<F, EGF>
+    where F: Frame,
+          F::Sample: FloatSample,
+          EGF: EvenGainFunction + Default,
+{
+
+    /// Construct a **MultibandCompressor** with a band for each of the given `bands`, from the
+    /// lowest band to the highest, split at the given ascending `crossovers_hz`.
+    ///
+    /// **Panics** if `bands` is empty or if there isn't exactly one fewer crossover than there
+    /// are bands.
+    pub fn from_bands(bands: &[BandParams], crossovers_hz: &[f64], sample_hz: f64) -> Self {
+        let n_bands = bands.len();
+        assert!(n_bands > 0, "a `MultibandCompressor` requires at least one band");
+        assert_eq!(crossovers_hz.len(), n_bands - 1,
+                   "there must be one fewer crossover than bands");
+        let bands = bands.iter().map(|params| {
+            PeakCompressor::peak(params.attack_ms, params.release_ms, sample_hz, params.threshold,
+                                 params.ratio)
+        }).collect();
+        let crossovers = crossovers_hz.iter().map(|&hz| Crossover::new(hz, sample_hz)).collect();
+        let allpasses = (0..n_bands).map(|band| {
+            crossovers_hz.iter().skip(band + 1).map(|&hz| {
+                Biquad::new(BiquadCoefficients::allpass(hz, BUTTERWORTH_Q, sample_hz))
+            }).collect()
+        }).collect();
+        MultibandCompressor {
+            bands: bands,
+            crossovers_hz: crossovers_hz.to_vec(),
+            crossovers: crossovers,
+            allpasses: allpasses,
+        }
+    }
+
+}
+
+impl<F, EGF> MultibandCompressor<F, EGF>
+    where F: Frame,
+          F::Sample: FloatSample,
+          EGF: EvenGainFunction,
+{
+
+    /// The number of bands.
+    pub fn n_bands(&self) -> usize {
+        self.bands.len()
+    }
+
+    /// Borrow the **Compressor** of the band at the given index, counting from the lowest band.
+    pub fn band(&self, index: usize) -> &PeakCompressor<F, EGF> {
+        &self.bands[index]
+    }
+
+    /// Mutably borrow the **Compressor** of the band at the given index, counting from the lowest
+    /// band.
+    pub fn band_mut(&mut self, index: usize) -> &mut PeakCompressor<F, EGF> {
+        &mut self.bands[index]
+    }
+
+    /// Apply the given parameters to the band at the given index.
+    pub fn set_band_params(&mut self, index: usize, params: &BandParams, sample_hz: f64) {
+        self.bands[index].set_all(params, sample_hz);
+    }
+
+    /// The frequencies at which the bands are split, from lowest to highest.
+    pub fn crossovers_hz(&self) -> &[f64] {
+        &self.crossovers_hz
+    }
+
+    /// Set the frequency of the crossover at the given index.
+    ///
+    /// The crossovers should remain in ascending order.
+    pub fn set_crossover_hz(&mut self, index: usize, hz: f64, sample_hz: f64) {
+        self.crossovers_hz[index] = hz;
+        self.update_filters_to_sample_hz(sample_hz);
+    }
+
+    /// Updates every band's duration-based state and the crossovers in accordance with the given
+    /// sample_hz.
+    pub fn update_to_sample_hz(&mut self, sample_hz: f64) {
+        for band in &mut self.bands {
+            band.update_to_sample_hz(sample_hz);
+        }
+        self.update_filters_to_sample_hz(sample_hz);
+    }
+
+    fn update_filters_to_sample_hz(&mut self, sample_hz: f64) {
+        for (crossover, &hz) in self.crossovers.iter_mut().zip(self.crossovers_hz.iter()) {
+            crossover.set_cutoff_hz(hz, sample_hz);
+        }
+        for (band, allpasses) in self.allpasses.iter_mut().enumerate() {
+            let crossovers_hz = self.crossovers_hz.iter().skip(band + 1);
+            for (allpass, &hz) in allpasses.iter_mut().zip(crossovers_hz) {
+                allpass.set_coefficients(BiquadCoefficients::allpass(hz, BUTTERWORTH_Q, sample_hz));
+            }
+        }
+    }
+
+    /// Steps forward every band by the given frame and returns the sum of the compressed bands.
+    #[inline]
+    pub fn next_frame(&mut self, frame: F) -> F {
+        let mut remaining = frame;
+        let mut output = F::equilibrium();
+        for band in 0..self.bands.len() {
+            let band_frame = match self.crossovers.get_mut(band) {
+                Some(crossover) => {
+                    let (low, high) = crossover.next(remaining);
+                    remaining = high;
+                    self.allpasses[band].iter_mut().fold(low, |f, allpass| allpass.next(f))
+                },
+                None => remaining,
+            };
+            let compressed = self.bands[band].next_frame(band_frame);
+            output = output.zip_map(compressed, |a, b| a + b);
+        }
+        output
+    }
+
+    /// Compresses the given `frames` in place.
+    #[inline]
+    pub fn compress_slice(&mut self, frames: &mut [F]) {
+        for frame in frames.iter_mut() {
+            *frame = self.next_frame(*frame);
+        }
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use CompressorBuilder;
+    use even_gain_fn::Average;
+
+    const SAMPLE_HZ: f64 = 44_100.0;
+
+    fn band(threshold: f32, ratio: f32) -> BandParams {
+        BandParams {
+            threshold: threshold,
+            ratio: ratio,
+            attack_ms: 1.0,
+            release_ms: 100.0,
+            ..CompressorBuilder::new().params
+        }
+    }
+
+    fn sine(hz: f64, amp: f32, i: usize) -> f32 {
+        (2.0 * std::f64::consts::PI * hz * i as f64 / SAMPLE_HZ).sin() as f32 * amp
+    }
+
+    #[test]
+    fn bands_sum_to_a_flat_response_without_gain_reduction() {
+        let bands = [band(1.0, 1.0), band(1.0, 1.0), band(1.0, 1.0)];
+        for &hz in &[60.0, 500.0, 4_000.0, 12_000.0] {
+            let mut multiband: MultibandCompressor<[f32; 1], Average> =
+                MultibandCompressor::from_bands(&bands, &[250.0, 2_500.0], SAMPLE_HZ);
+            let n = SAMPLE_HZ as usize / 2;
+            let (mut input, mut output) = (0.0, 0.0);
+            for i in 0..n {
+                let s = sine(hz, 0.5, i);
+                let out = multiband.next_frame([s])[0];
+                if i >= n / 2 {
+                    input += s * s;
+                    output += out * out;
+                }
+            }
+            assert!((output / input - 1.0).abs() < 1e-2, "{} Hz: {}", hz, output / input);
+        }
+    }
+
+    #[test]
+    fn transient_only_triggers_gain_reduction_in_its_own_band() {
+        let bands = [band(0.1, 4.0), band(0.1, 4.0)];
+        let mut multiband: MultibandCompressor<[f32; 1], Average> =
+            MultibandCompressor::from_bands(&bands, &[1_000.0], SAMPLE_HZ);
+        // A Hann-windowed 100Hz burst, so that its onset doesn't spill into the high band.
+        let n = SAMPLE_HZ as usize / 10;
+        let (mut low_gain, mut high_gain) = (1.0f32, 1.0f32);
+        for i in 0..n * 2 {
+            let window = if i < n {
+                0.5 - 0.5 * (2.0 * std::f64::consts::PI * i as f64 / n as f64).cos()
+            } else {
+                0.0
+            };
+            multiband.next_frame([sine(100.0, 0.8, i) * window as f32]);
+            let gain = |band: &PeakCompressor<[f32; 1], Average>| {
+                band.steady_state_gain(band.envelope()[0])
+            };
+            low_gain = low_gain.min(gain(multiband.band(0)));
+            high_gain = high_gain.min(gain(multiband.band(1)));
+        }
+        assert!(low_gain < 0.5);
+        assert_eq!(high_gain, 1.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn from_bands_requires_one_fewer_crossover_than_bands() {
+        let bands = [band(0.5, 4.0), band(0.5, 4.0)];
+        let _: MultibandCompressor<[f32; 1], Average> =
+            MultibandCompressor::from_bands(&bands, &[], SAMPLE_HZ);
+    }
+}
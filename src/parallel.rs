@@ -0,0 +1,152 @@
+//! A program limiter that runs a slow RMS **Compressor** and a fast peak **Compressor** side by
+//! side, applying whichever gain reduces the signal the most.
+//!
+//! The primary type of interest is the [**PeakRmsParallel**](./struct.PeakRmsParallel).
+
+use {EvenGainFunction, PeakCompressor, RmsCompressor};
+use envelope_detector::{Frame, Sample};
+use std;
+
+
+/// Steps a peak **Compressor** and an RMS **Compressor** with the same signal and applies the
+/// lesser of their two gains to each frame.
+///
+/// The RMS path typically has a long window and slow ballistics, levelling sustained material
+/// smoothly, while the peak path has a short attack and a high threshold, catching any transients
+/// that the RMS path lets through. Taking `min(peak_gain, rms_gain)` means that each path governs
+/// the signal only while it calls for more reduction than the other.
+///
+/// Both **Compressor**s are configured as usual via `peak_mut` and `rms_mut`. Only their gains are
+/// used, so neither applies its signal delay or auto gain to the output.
+#[derive(Clone)]
+pub struct PeakRmsParallel<F, EGF>
+    where F: Frame,
+{
+    peak: PeakCompressor<F, EGF>,
+    rms: RmsCompressor<F, EGF>,
+}
+
+impl<F, EGF> std::fmt::Debug for PeakRmsParallel<F, EGF>
+    where F: Frame,
+          PeakCompressor<F, EGF>: std::fmt::Debug,
+          RmsCompressor<F, EGF>: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "PeakRmsParallel {{ peak: {:?}, rms: {:?} }}", &self.peak, &self.rms)
+    }
+}
+
+
+impl<F, EGF> PeakRmsParallel<F, EGF>
+    where F: Frame,
+          EGF: EvenGainFunction,
+{
+
+    /// Construct a new **PeakRmsParallel** from the given peak and RMS **Compressor**s.
+    pub fn new(peak: PeakCompressor<F, EGF>, rms: RmsCompressor<F, EGF>) -> Self {
+        PeakRmsParallel {
+            peak: peak,
+            rms: rms,
+        }
+    }
+
+    /// Borrow the peak **Compressor**.
+    pub fn peak(&self) -> &PeakCompressor<F, EGF> {
+        &self.peak
+    }
+
+    /// Mutably borrow the peak **Compressor**.
+    pub fn peak_mut(&mut self) -> &mut PeakCompressor<F, EGF> {
+        &mut self.peak
+    }
+
+    /// Borrow the RMS **Compressor**.
+    pub fn rms(&self) -> &RmsCompressor<F, EGF> {
+        &self.rms
+    }
+
+    /// Mutably borrow the RMS **Compressor**.
+    pub fn rms_mut(&mut self) -> &mut RmsCompressor<F, EGF> {
+        &mut self.rms
+    }
+
+    /// Consume the **PeakRmsParallel**, yielding the peak and RMS **Compressor**s.
+    pub fn into_parts(self) -> (PeakCompressor<F, EGF>, RmsCompressor<F, EGF>) {
+        (self.peak, self.rms)
+    }
+
+    /// Updates both **Compressor**s' duration-based state in accordance with the given sample_hz.
+    pub fn update_to_sample_hz(&mut self, sample_hz: f64) {
+        self.peak.update_to_sample_hz(sample_hz);
+        self.rms.update_to_sample_hz(sample_hz);
+    }
+
+    /// Produce the lesser of the peak and RMS gains for the next frame.
+    #[inline]
+    pub fn next_gain(&mut self, next_frame: F) -> <F::Sample as Sample>::Float {
+        let peak_gain = self.peak.next_gain(next_frame);
+        let rms_gain = self.rms.next_gain(next_frame);
+        if peak_gain < rms_gain { peak_gain } else { rms_gain }
+    }
+
+    /// Steps forward both **Compressor**s by the given frame and returns the compressed result.
+    #[inline]
+    pub fn next_frame(&mut self, next_frame: F) -> F {
+        let gain = self.next_gain(next_frame);
+        self.peak.quantizer.scale_amp(next_frame, gain)
+    }
+
+    /// Compresses the given `frames` in place.
+    #[inline]
+    pub fn compress_slice(&mut self, frames: &mut [F]) {
+        for frame in frames.iter_mut() {
+            *frame = self.next_frame(*frame);
+        }
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use {Average, Compressor};
+
+    const SAMPLE_HZ: f64 = 44_100.0;
+
+    fn limiter() -> PeakRmsParallel<[f32; 2], Average> {
+        let peak = Compressor::peak_avg(0.1, 50.0, SAMPLE_HZ, 0.8, std::f32::INFINITY);
+        let rms = Compressor::rms_avg(50.0, 20.0, 500.0, SAMPLE_HZ, 0.2, 4.0);
+        PeakRmsParallel::new(peak, rms)
+    }
+
+    #[test]
+    fn sustained_material_is_governed_by_the_rms_path() {
+        let mut limiter = limiter();
+        for i in 0..SAMPLE_HZ as usize {
+            let s = (i as f32 * 0.05).sin() * 0.5;
+            let gain = limiter.next_gain([s, s]);
+            if i > 4_410 {
+                assert!(gain < 1.0);
+                assert_eq!(limiter.peak().last_gain, 1.0);
+                assert_eq!(gain, limiter.rms().last_gain);
+            }
+        }
+    }
+
+    #[test]
+    fn transients_are_caught_by_the_peak_path() {
+        let mut limiter = limiter();
+        for _ in 0..4_410 {
+            limiter.next_gain([0.05, 0.05]);
+        }
+        // A 2ms burst is long enough for the peak path's attack but not the RMS path's.
+        for _ in 0..88 {
+            limiter.next_gain([1.0, 1.0]);
+        }
+        let gain = limiter.next_gain([1.0, 1.0]);
+        assert!(gain < limiter.rms().last_gain);
+        assert_eq!(gain, limiter.peak().last_gain);
+    }
+}
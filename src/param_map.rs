@@ -0,0 +1,87 @@
+//! Mappings between durations and normalised `0.0` to `1.0` values, e.g. for UI knobs.
+//!
+//! Each mapping is exponential, so that equal movements of a knob change the duration by equal
+//! ratios: halfway along the attack range lies the geometric mean of `ATTACK_MIN_MS` and
+//! `ATTACK_MAX_MS`, i.e. 10ms. Normalised values outside of `0.0` to `1.0`, and durations outside
+//! of the range, are clamped to the ends of the range.
+
+use time::Ms;
+
+
+/// The attack duration at a normalised value of `0.0`.
+pub const ATTACK_MIN_MS: f64 = 0.1;
+/// The attack duration at a normalised value of `1.0`.
+pub const ATTACK_MAX_MS: f64 = 1_000.0;
+/// The release duration at a normalised value of `0.0`.
+pub const RELEASE_MIN_MS: f64 = 1.0;
+/// The release duration at a normalised value of `1.0`.
+pub const RELEASE_MAX_MS: f64 = 5_000.0;
+
+
+/// Map a normalised value onto the exponential range between `min_ms` and `max_ms`.
+fn ms_from_norm(norm: f32, min_ms: f64, max_ms: f64) -> Ms {
+    let norm = if norm < 0.0 { 0.0 } else if norm > 1.0 { 1.0 } else { norm as f64 };
+    Ms(min_ms * (max_ms / min_ms).powf(norm))
+}
+
+/// Map a duration within the exponential range between `min_ms` and `max_ms` to a normalised
+/// value.
+fn norm_from_ms(ms: Ms, min_ms: f64, max_ms: f64) -> f32 {
+    let Ms(ms) = ms;
+    let ms = if !(ms > min_ms) { min_ms } else if ms > max_ms { max_ms } else { ms };
+    ((ms / min_ms).ln() / (max_ms / min_ms).ln()) as f32
+}
+
+/// The attack duration for the given normalised value, from `ATTACK_MIN_MS` at `0.0` to
+/// `ATTACK_MAX_MS` at `1.0`.
+pub fn attack_from_norm(norm: f32) -> Ms {
+    ms_from_norm(norm, ATTACK_MIN_MS, ATTACK_MAX_MS)
+}
+
+/// The normalised value for the given attack duration, the inverse of `attack_from_norm`.
+pub fn norm_from_attack(ms: Ms) -> f32 {
+    norm_from_ms(ms, ATTACK_MIN_MS, ATTACK_MAX_MS)
+}
+
+/// The release duration for the given normalised value, from `RELEASE_MIN_MS` at `0.0` to
+/// `RELEASE_MAX_MS` at `1.0`.
+pub fn release_from_norm(norm: f32) -> Ms {
+    ms_from_norm(norm, RELEASE_MIN_MS, RELEASE_MAX_MS)
+}
+
+/// The normalised value for the given release duration, the inverse of `release_from_norm`.
+pub fn norm_from_release(ms: Ms) -> f32 {
+    norm_from_ms(ms, RELEASE_MIN_MS, RELEASE_MAX_MS)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ends_map_to_the_range_limits() {
+        assert_eq!(attack_from_norm(0.0), Ms(ATTACK_MIN_MS));
+        assert_eq!(attack_from_norm(1.0), Ms(ATTACK_MAX_MS));
+        assert_eq!(release_from_norm(0.0), Ms(RELEASE_MIN_MS));
+        assert_eq!(release_from_norm(1.0), Ms(RELEASE_MAX_MS));
+        assert!((attack_from_norm(0.5).ms() - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn out_of_range_values_are_clamped() {
+        assert_eq!(attack_from_norm(-1.0), Ms(ATTACK_MIN_MS));
+        assert_eq!(release_from_norm(2.0), Ms(RELEASE_MAX_MS));
+        assert_eq!(norm_from_attack(Ms(0.0)), 0.0);
+        assert_eq!(norm_from_release(Ms(60_000.0)), 1.0);
+    }
+
+    #[test]
+    fn mappings_round_trip() {
+        for i in 0..=100 {
+            let norm = i as f32 / 100.0;
+            assert!((norm_from_attack(attack_from_norm(norm)) - norm).abs() < 1e-6);
+            assert!((norm_from_release(release_from_norm(norm)) - norm).abs() < 1e-6);
+        }
+    }
+}
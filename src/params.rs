@@ -0,0 +1,386 @@
+//! Plain-data descriptions of a **Compressor**'s tunable parameters, the coefficients derived
+//! from them and the latency they introduce.
+
+use ratio;
+use std;
+
+
+/// The terms in which a **Compressor**'s threshold is given.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ThresholdDomain {
+    /// The threshold is a linear amplitude, compared directly against the detected envelope,
+    /// i.e. the peak amplitude for peak detection or the RMS for RMS detection.
+    Amplitude,
+    /// The threshold is a mean-square power, i.e. the square of the equivalent amplitude, as is
+    /// common for RMS compressors that compare the mean square without taking its root. A
+    /// threshold of `0.25` in this domain is equivalent to `0.5` in the `Amplitude` domain.
+    Power,
+}
+
+impl ThresholdDomain {
+
+    /// Convert a threshold given in this domain to a linear amplitude.
+    #[inline]
+    pub fn to_amplitude(self, threshold: f32) -> f32 {
+        match self {
+            ThresholdDomain::Amplitude => threshold,
+            ThresholdDomain::Power => threshold.sqrt(),
+        }
+    }
+
+    /// Convert a linear amplitude to a threshold given in this domain.
+    #[inline]
+    pub fn from_amplitude(self, amp: f32) -> f32 {
+        match self {
+            ThresholdDomain::Amplitude => amp,
+            ThresholdDomain::Power => amp * amp,
+        }
+    }
+
+}
+
+
+/// Which side of the threshold a **Compressor** attenuates.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// Attenuate the signal while its envelope is above the threshold, i.e. compression.
+    Compress,
+    /// Attenuate the signal while its envelope is below the threshold, i.e. downward expansion,
+    /// leaving the signal above the threshold untouched. An infinite ratio is a hard noise gate.
+    Expand,
+}
+
+
+/// The tunable parameters of a **Compressor**.
+///
+/// Durations are plain milliseconds so that the parameters are independent of any sample rate.
+///
+/// With the `serde` feature enabled the parameters may be serialized, e.g. to persist a preset,
+/// and later applied via `Compressor::set_all` or `CompressorBuilder::params`. The ratio is
+/// stored rather than the **Compressor**'s internal slope, and any ratio applied via the
+/// **Compressor**'s setters is reported by `Compressor::params` such that it converts back to the
+/// same slope. Note that an infinite ratio (i.e. a limiter) cannot be represented by formats
+/// lacking infinities, such as JSON. The `knee_db`, `makeup_gain` and `auto_makeup` may be
+/// missing from serialized parameters, e.g. those saved before they were added, in which case
+/// they take their defaults of a hard knee and no makeup.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CompressorParams {
+    /// The linear amplitude above which the detected envelope is compressed.
+    pub threshold: f32,
+    /// The compression ratio, i.e. `4.0` for 4:1.
+    pub ratio: f32,
+    /// The envelope attack duration in milliseconds.
+    pub attack_ms: f64,
+    /// The envelope release duration in milliseconds.
+    pub release_ms: f64,
+    /// The duration of the envelope detector's window in milliseconds.
+    ///
+    /// This is ignored by detectors that have no window, i.e. **Peak** detectors.
+    pub window_ms: Option<f64>,
+    /// The width in decibels of the soft knee centred on the threshold, or `0.0` for a hard knee.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub knee_db: f32,
+    /// The linear makeup gain, ignored while `auto_makeup` is enabled.
+    #[cfg_attr(feature = "serde", serde(default = "unity_gain"))]
+    pub makeup_gain: f32,
+    /// Whether the makeup gain is derived from the threshold and ratio.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub auto_makeup: bool,
+}
+
+/// The default `makeup_gain` of deserialized **CompressorParams**, i.e. no makeup.
+#[cfg(feature = "serde")]
+fn unity_gain() -> f32 {
+    1.0
+}
+
+impl CompressorParams {
+
+    /// Interpolate each parameter between `self` (at an `amount` of `0.0`) and `other` (at an
+    /// `amount` of `1.0`).
+    ///
+    /// The ratio is interpolated via its slope (i.e. `0.75` for 4:1), so that an infinite ratio
+    /// morphs smoothly rather than dominating. The threshold is interpolated in decibels (i.e.
+    /// geometrically), unless either threshold is zero, and so is the makeup gain. The attack,
+    /// release and window durations and the knee width are interpolated linearly. If only one of
+    /// the two has a `window_ms`, the `window_ms` of whichever is nearer is used, as is the
+    /// `auto_makeup` of whichever is nearer.
+    pub fn interpolate(&self, other: &CompressorParams, amount: f32) -> CompressorParams {
+        let lerp = |a: f32, b: f32| a + (b - a) * amount;
+        let lerp_f64 = |a: f64, b: f64| a + (b - a) * amount as f64;
+        let a_slope = ratio::slope_from_ratio(self.ratio);
+        let b_slope = ratio::slope_from_ratio(other.ratio);
+        let lerp_db = |a: f32, b: f32| if a > 0.0 && b > 0.0 {
+            a * (b / a).powf(amount)
+        } else {
+            lerp(a, b)
+        };
+        let window_ms = match (self.window_ms, other.window_ms) {
+            (Some(a), Some(b)) => Some(lerp_f64(a, b)),
+            (a, b) => if amount < 0.5 { a } else { b },
+        };
+        CompressorParams {
+            threshold: lerp_db(self.threshold, other.threshold),
+            ratio: ratio::ratio_from_slope(lerp(a_slope, b_slope)),
+            attack_ms: lerp_f64(self.attack_ms, other.attack_ms),
+            release_ms: lerp_f64(self.release_ms, other.release_ms),
+            window_ms: window_ms,
+            knee_db: lerp(self.knee_db, other.knee_db),
+            makeup_gain: lerp_db(self.makeup_gain, other.makeup_gain),
+            auto_makeup: if amount < 0.5 { self.auto_makeup } else { other.auto_makeup },
+        }
+    }
+
+}
+
+
+/// The raw coefficients in effect within a **Compressor**.
+///
+/// Where **CompressorParams** describe durations in milliseconds, these describe the values the
+/// **Compressor** actually uses after those durations have been rounded to a number of frames.
+/// Copying them from one **Compressor** to another makes the two behave identically.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Coefficients {
+    /// The amount of the previous envelope retained each frame while the envelope rises.
+    pub attack_gain: f32,
+    /// The amount of the previous envelope retained each frame while the envelope falls.
+    pub release_gain: f32,
+    /// The slope of the compression ratio, i.e. `0.75` for 4:1.
+    pub slope: f32,
+}
+
+
+/// A rough estimate of the processing cost of a **Compressor**'s current configuration.
+///
+/// Every cost is relative rather than absolute, in units of roughly the work of detecting one
+/// channel of one frame with **Peak** detection, and is given per channel per frame. The
+/// estimate is a heuristic over the configuration rather than a measurement, suited to comparing
+/// configurations, e.g. to decide which stages to disable under load.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct CostEstimate {
+    /// The cost of envelope detection, including the RMS window.
+    pub detection: f32,
+    /// The cost of the sidechain filters, one unit per biquad.
+    pub sidechain: f32,
+    /// The cost of applying the gain and of the always-on stages such as the punch and range
+    /// meter.
+    pub gain: f32,
+    /// The cost of the optional meters and levellers, i.e. the loudness meter, auto gain and
+    /// adaptive threshold.
+    pub metering: f32,
+    /// The number of frames held by the signal delay line, which costs memory rather than time.
+    pub delay_frames: usize,
+}
+
+impl CostEstimate {
+
+    /// The total cost per channel per frame.
+    pub fn total(&self) -> f32 {
+        self.detection + self.sidechain + self.gain + self.metering
+    }
+
+}
+
+
+/// The latency introduced by each stage of a **Compressor**, in frames.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct LatencyBreakdown {
+    /// The delay of the signal relative to the detector, as set via
+    /// `set_sidechain_delay_frames`.
+    pub lookahead: usize,
+    /// The latency of any oversampling filters. There is currently no oversampling stage, so this
+    /// is always `0`.
+    pub oversampling: usize,
+    /// The lag of the detection path: half the length of any RMS window, the delay of its
+    /// centre, plus the frames by which detection decimation may hold a change in level.
+    ///
+    /// Detection runs alongside the signal rather than in its path, so this delays the arrival of
+    /// the gain rather than the output. It is `0` for **Peak** detection without a window.
+    pub detection: usize,
+    /// The sum of all stages.
+    ///
+    /// This includes the `detection` lag, so it may exceed `latency_frames`, which counts only the
+    /// delay imposed on the output.
+    pub total: usize,
+}
+
+
+/// The set of a **Compressor**'s optional stages that are currently engaged, as produced by
+/// `enabled_stages`.
+///
+/// Each stage is a single bit, so that sets of stages may be combined via `|` and compared via
+/// `contains`, e.g. `stages.contains(StageFlags::LOOKAHEAD | StageFlags::OVERSHOOT_CEILING)`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct StageFlags(u32);
+
+impl StageFlags {
+
+    /// A high-pass filter on the sidechain of at least one channel.
+    pub const SIDECHAIN_HPF: StageFlags = StageFlags(1 << 0);
+    /// The sidechain's peaking emphasis filter.
+    pub const SIDECHAIN_EMPHASIS: StageFlags = StageFlags(1 << 1);
+    /// K-weighting of the sidechain.
+    pub const K_WEIGHTING: StageFlags = StageFlags(1 << 2);
+    /// A signal delay, so that the detector looks ahead of the signal.
+    pub const LOOKAHEAD: StageFlags = StageFlags(1 << 3);
+    /// A gain other than unity applied to the input.
+    pub const INPUT_GAIN: StageFlags = StageFlags(1 << 4);
+    /// The two-stage release.
+    pub const RELEASE_STAGES: StageFlags = StageFlags(1 << 5);
+    /// Holding the gain at unity as the envelope first exceeds the threshold.
+    pub const PUNCH: StageFlags = StageFlags(1 << 6);
+    /// A limit on the gain reduction, via the maximum reduction or the minimum gain.
+    pub const RANGE: StageFlags = StageFlags(1 << 7);
+    /// The threshold following the long-term input level.
+    pub const ADAPTIVE_THRESHOLD: StageFlags = StageFlags(1 << 8);
+    /// The auto gain trimming the output toward a long-term level.
+    pub const AUTO_GAIN: StageFlags = StageFlags(1 << 9);
+    /// The clamp on transients exceeding the overshoot ceiling.
+    pub const OVERSHOOT_CEILING: StageFlags = StageFlags(1 << 10);
+    /// Dither added to integer samples.
+    pub const DITHER: StageFlags = StageFlags(1 << 11);
+    /// The loudness reduction meter.
+    pub const LOUDNESS_METER: StageFlags = StageFlags(1 << 12);
+    /// The bypass, passing the signal through without any change in gain.
+    pub const BYPASS: StageFlags = StageFlags(1 << 13);
+    /// A makeup gain other than unity, or the auto makeup.
+    pub const MAKEUP_GAIN: StageFlags = StageFlags(1 << 14);
+
+    /// The empty set, with no stages engaged.
+    pub fn empty() -> Self {
+        StageFlags(0)
+    }
+
+    /// The set of stages with the given bits.
+    pub fn from_bits(bits: u32) -> Self {
+        StageFlags(bits)
+    }
+
+    /// The bits of the set of stages.
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// Whether no stages are engaged.
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether every stage in `other` is engaged.
+    pub fn contains(&self, other: StageFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Add the stages in `other` to the set.
+    pub fn insert(&mut self, other: StageFlags) {
+        self.0 |= other.0;
+    }
+
+    /// Add the stages in `other` to the set if `enabled`, or remove them otherwise.
+    pub fn set(&mut self, other: StageFlags, enabled: bool) {
+        if enabled {
+            self.0 |= other.0;
+        } else {
+            self.0 &= !other.0;
+        }
+    }
+
+}
+
+impl std::ops::BitOr for StageFlags {
+    type Output = StageFlags;
+    fn bitor(self, other: StageFlags) -> StageFlags {
+        StageFlags(self.0 | other.0)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preset(threshold: f32, ratio: f32, window_ms: Option<f64>) -> CompressorParams {
+        CompressorParams {
+            threshold, ratio, attack_ms: 2.0, release_ms: 200.0, window_ms,
+            knee_db: 0.0, makeup_gain: 1.0, auto_makeup: false,
+        }
+    }
+
+    #[test]
+    fn interpolating_reaches_each_end() {
+        let a = preset(0.5, 2.0, Some(10.0));
+        let b = preset(0.125, 8.0, Some(30.0));
+        assert_eq!(a.interpolate(&b, 0.0), a);
+        let end = a.interpolate(&b, 1.0);
+        assert!((end.threshold - b.threshold).abs() < 1e-6);
+        assert!((end.ratio - b.ratio).abs() < 1e-4);
+        assert_eq!(end.window_ms, b.window_ms);
+    }
+
+    #[test]
+    fn interpolating_halfway_uses_the_slope_and_decibels() {
+        let a = preset(0.5, 2.0, Some(10.0));
+        let b = preset(0.125, std::f32::INFINITY, None);
+        let half = a.interpolate(&b, 0.5);
+        // Slopes of 0.5 and 1.0 meet at 0.75, i.e. 4:1, and -6 dB and -18 dB meet at -12 dB.
+        assert!((half.ratio - 4.0).abs() < 1e-4);
+        assert!((half.threshold - 0.25).abs() < 1e-6);
+        assert_eq!(half.window_ms, None);
+        assert_eq!(a.interpolate(&b, 0.25).window_ms, Some(10.0));
+    }
+
+    #[test]
+    fn interpolating_the_knee_and_makeup() {
+        let a = CompressorParams { knee_db: 0.0, makeup_gain: 1.0, ..preset(0.5, 2.0, None) };
+        let b = CompressorParams {
+            knee_db: 12.0, makeup_gain: 4.0, auto_makeup: true, ..preset(0.5, 2.0, None)
+        };
+        let half = a.interpolate(&b, 0.5);
+        // 0 dB and +12 dB of makeup meet at +6 dB.
+        assert_eq!(half.knee_db, 6.0);
+        assert!((half.makeup_gain - 2.0).abs() < 1e-6);
+        assert!(!a.interpolate(&b, 0.25).auto_makeup);
+        assert!(a.interpolate(&b, 0.75).auto_makeup);
+    }
+
+    #[test]
+    fn threshold_domains_convert_to_and_from_amplitude() {
+        assert_eq!(ThresholdDomain::Amplitude.to_amplitude(0.25), 0.25);
+        assert_eq!(ThresholdDomain::Power.to_amplitude(0.25), 0.5);
+        assert_eq!(ThresholdDomain::Power.from_amplitude(0.5), 0.25);
+        assert_eq!(ThresholdDomain::Amplitude.from_amplitude(0.5), 0.5);
+    }
+
+    #[test]
+    fn stage_flags_combine_and_compare_bitwise() {
+        let mut stages = StageFlags::empty();
+        assert!(stages.is_empty());
+        stages.insert(StageFlags::LOOKAHEAD);
+        stages.set(StageFlags::DITHER, true);
+        assert!(stages.contains(StageFlags::LOOKAHEAD | StageFlags::DITHER));
+        assert!(!stages.contains(StageFlags::LOOKAHEAD | StageFlags::BYPASS));
+        stages.set(StageFlags::LOOKAHEAD, false);
+        assert_eq!(stages, StageFlags::DITHER);
+        assert_eq!(StageFlags::from_bits(stages.bits()), stages);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn params_round_trip_through_json() {
+        extern crate serde_json;
+        let params = preset(0.25, 4.0, Some(10.0));
+        let json = serde_json::to_string(&params).unwrap();
+        assert_eq!(serde_json::from_str::<CompressorParams>(&json).unwrap(), params);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn params_missing_the_knee_and_makeup_take_their_defaults() {
+        extern crate serde_json;
+        let json = r#"{"threshold":0.25,"ratio":4.0,"attack_ms":2.0,"release_ms":200.0,
+                       "window_ms":10.0}"#;
+        let params = serde_json::from_str::<CompressorParams>(json).unwrap();
+        assert_eq!(params, preset(0.25, 4.0, Some(10.0)));
+    }
+}
@@ -0,0 +1,171 @@
+//! A named collection of **CompressorParams** for managing presets.
+//!
+//! The primary type of interest is the [**PresetBank**](./struct.PresetBank).
+
+use {Compressor, CompressorParams, Detector, EvenGainFunction};
+use envelope_detector::Frame;
+use std::collections::{btree_map, BTreeMap};
+
+
+/// A collection of **CompressorParams**, each stored under a unique name.
+///
+/// Presets are kept in order of their names.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PresetBank {
+    presets: BTreeMap<String, CompressorParams>,
+}
+
+
+impl PresetBank {
+
+    /// Construct an empty **PresetBank**.
+    pub fn new() -> Self {
+        PresetBank { presets: BTreeMap::new() }
+    }
+
+    /// The number of presets in the bank.
+    pub fn len(&self) -> usize {
+        self.presets.len()
+    }
+
+    /// Whether the bank holds no presets.
+    pub fn is_empty(&self) -> bool {
+        self.presets.is_empty()
+    }
+
+    /// The preset stored under the given name, if there is one.
+    pub fn get(&self, name: &str) -> Option<&CompressorParams> {
+        self.presets.get(name)
+    }
+
+    /// Store the given preset under the given name, yielding the preset it replaces, if any.
+    pub fn insert<S>(&mut self, name: S, params: CompressorParams) -> Option<CompressorParams>
+        where S: Into<String>,
+    {
+        self.presets.insert(name.into(), params)
+    }
+
+    /// Remove the preset stored under the given name, yielding it if there was one.
+    pub fn remove(&mut self, name: &str) -> Option<CompressorParams> {
+        self.presets.remove(name)
+    }
+
+    /// The names of the presets in the bank, in order.
+    pub fn names<'a>(&'a self) -> btree_map::Keys<'a, String, CompressorParams> {
+        self.presets.keys()
+    }
+
+    /// Apply the preset stored under the given name to the given **Compressor** via `set_all`.
+    ///
+    /// Yields `false`, leaving the **Compressor** untouched, if there is no such preset.
+    pub fn apply<F, D, EGF>(&self,
+                            name: &str,
+                            compressor: &mut Compressor<F, D, EGF>,
+                            sample_hz: f64) -> bool
+        where F: Frame,
+              D: Detector<F::Float>,
+              EGF: EvenGainFunction,
+    {
+        match self.presets.get(name) {
+            Some(params) => {
+                compressor.set_all(params, sample_hz);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Apply the preset stored under the given name to the given **Compressor** via
+    /// `apply_profile_scaled`, offsetting its threshold to suit material peaking at
+    /// `reference_peak_db`.
+    ///
+    /// Yields `false`, leaving the **Compressor** untouched, if there is no such preset.
+    pub fn apply_scaled<F, D, EGF>(&self,
+                                   name: &str,
+                                   compressor: &mut Compressor<F, D, EGF>,
+                                   sample_hz: f64,
+                                   reference_peak_db: f32) -> bool
+        where F: Frame,
+              D: Detector<F::Float>,
+              EGF: EvenGainFunction,
+    {
+        match self.presets.get(name) {
+            Some(params) => {
+                compressor.apply_profile_scaled(params, sample_hz, reference_peak_db);
+                true
+            },
+            None => false,
+        }
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use {Compressor, PeakAvgCompressor};
+
+    fn bank() -> PresetBank {
+        let mut bank = PresetBank::new();
+        let vocal = CompressorParams {
+            threshold: 0.25, ratio: 3.0, attack_ms: 5.0, release_ms: 120.0, window_ms: Some(15.0),
+            knee_db: 6.0, makeup_gain: 1.0, auto_makeup: true,
+        };
+        let limit = CompressorParams {
+            threshold: 0.9, ratio: 20.0, attack_ms: 0.5, release_ms: 50.0, window_ms: None,
+            knee_db: 0.0, makeup_gain: 1.0, auto_makeup: false,
+        };
+        assert_eq!(bank.insert("vocal", vocal), None);
+        assert_eq!(bank.insert("limit", limit), None);
+        bank
+    }
+
+    #[test]
+    fn presets_are_kept_in_name_order() {
+        let mut bank = bank();
+        assert_eq!(bank.len(), 2);
+        assert_eq!(bank.names().collect::<Vec<_>>(), ["limit", "vocal"]);
+        assert!(bank.remove("limit").is_some());
+        assert!(bank.get("limit").is_none());
+        assert!(!bank.is_empty());
+    }
+
+    #[test]
+    fn applying_a_named_preset_configures_the_compressor() {
+        let bank = bank();
+        let mut compressor: PeakAvgCompressor<[f32; 2]> =
+            Compressor::peak_avg(1.0, 100.0, 44_100.0, 0.5, 4.0);
+        let untouched = compressor.params();
+        assert!(!bank.apply("missing", &mut compressor, 44_100.0));
+        assert_eq!(compressor.params(), untouched);
+        assert!(bank.apply("limit", &mut compressor, 44_100.0));
+        let limit = bank.get("limit").unwrap();
+        let params = compressor.params();
+        assert_eq!(params.threshold, limit.threshold);
+        assert!((params.ratio - limit.ratio).abs() < 1e-3);
+        assert_eq!((params.attack_ms, params.release_ms), (limit.attack_ms, limit.release_ms));
+    }
+
+    #[test]
+    fn applying_a_scaled_preset_offsets_its_threshold() {
+        let bank = bank();
+        let mut compressor: PeakAvgCompressor<[f32; 2]> =
+            Compressor::peak_avg(1.0, 100.0, 44_100.0, 0.5, 4.0);
+        assert!(!bank.apply_scaled("missing", &mut compressor, 44_100.0, -20.0));
+        assert_eq!(compressor.params().threshold, 0.5);
+        assert!(bank.apply_scaled("vocal", &mut compressor, 44_100.0, -20.0));
+        assert!((compressor.params().threshold - 0.025).abs() < 1e-6);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn bank_round_trips_through_json() {
+        extern crate serde_json;
+        let bank = bank();
+        let json = serde_json::to_string(&bank).unwrap();
+        assert_eq!(serde_json::from_str::<PresetBank>(&json).unwrap(), bank);
+    }
+}
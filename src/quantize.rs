@@ -0,0 +1,295 @@
+//! Conversion of the compressed signal from its floating point representation back to the sample
+//! format of the **Compressor**'s `Frame`s.
+
+use envelope_detector::{Frame, Sample};
+use sample::FloatSample;
+use std;
+
+
+/// The kind of dither added to integer samples before they are rounded.
+///
+/// Dither decorrelates the quantization error from the signal, trading the distortion caused by
+/// rounding the gain-scaled signal for a low, constant noise floor. It has no effect on floating
+/// point sample formats.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DitherKind {
+    /// No dither is added.
+    None,
+    /// Rectangular (uniform) dither with a peak amplitude of half a quantization step.
+    Rectangular,
+    /// Triangular (TPDF) dither with a peak amplitude of one quantization step.
+    ///
+    /// This fully decorrelates the first and second moments of the quantization error from the
+    /// signal.
+    Triangular,
+}
+
+
+/// Applies gain to frames of the sample format `S`.
+///
+/// Floating point formats are scaled directly. Integer formats are converted to floating point,
+/// scaled and then rounded to the nearest representable value on the way back, rather than being
+/// truncated toward equilibrium. Integer results beyond full scale saturate at the nearest
+/// representable extreme rather than wrapping around.
+#[derive(Copy, Clone)]
+pub struct Quantizer<S>
+    where S: Sample,
+{
+    /// The difference between two adjacent values of the sample format, or `None` for floating
+    /// point formats.
+    step: Option<S::Float>,
+    /// The kind of dither added to integer samples before rounding.
+    dither: DitherKind,
+    /// The state of the dither noise generator.
+    noise: u32,
+}
+
+/// The initial state of the dither noise generator.
+const NOISE_SEED: u32 = 0x9E37_79B9;
+
+impl<S> std::fmt::Debug for Quantizer<S>
+    where S: Sample,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        let step = self.step.map(|step| step.to_sample::<f64>());
+        write!(f, "Quantizer {{ step: {:?}, dither: {:?}, noise: {:?} }}",
+               step, &self.dither, &self.noise)
+    }
+}
+
+
+/// Determine the difference between two adjacent values of the sample format `S` in its floating
+/// point representation.
+///
+/// Returns `None` if `S` is a floating point format.
+fn quantization_step<S>() -> Option<S::Float>
+    where S: Sample,
+{
+    // Floating point formats represent a value far smaller than any integer step, whereas integer
+    // formats (even those whose float format is too narrow to hold them exactly) truncate it.
+    let tiny: S::Float = 1.0e-30f32.to_sample();
+    if tiny.to_sample::<S>().to_float_sample() == tiny {
+        return None;
+    }
+
+    // Halve the step until half of it can no longer be represented.
+    let two: S::Float = 2.0f32.to_sample();
+    let mut step = <S::Float as FloatSample>::identity();
+    loop {
+        let half = step / two;
+        if half.to_sample::<S>() == S::equilibrium() {
+            return Some(step);
+        }
+        step = half;
+    }
+}
+
+/// Clamp the given value to the range represented by an integer sample format with the given
+/// quantization step, i.e. `-1.0` to one step below `1.0`.
+///
+/// Converting a value beyond this range to the integer format would otherwise wrap around.
+#[inline]
+fn saturate<S>(value: S, step: S) -> S
+    where S: FloatSample,
+{
+    let max = <S as FloatSample>::identity() - step;
+    let min = -<S as FloatSample>::identity();
+    if value > max {
+        max
+    } else if value < min {
+        min
+    } else {
+        value
+    }
+}
+
+
+impl<S> Quantizer<S>
+    where S: Sample,
+{
+
+    /// Construct a new **Quantizer** for the sample format `S`.
+    pub fn new() -> Self {
+        Quantizer {
+            step: quantization_step::<S>(),
+            dither: DitherKind::None,
+            noise: NOISE_SEED,
+        }
+    }
+
+    /// Restart the dither noise generator from its initial state.
+    pub fn reset(&mut self) {
+        self.noise = NOISE_SEED;
+    }
+
+    /// Set the kind of dither added to integer samples before rounding.
+    pub fn set_dither(&mut self, dither: DitherKind) {
+        self.dither = dither;
+    }
+
+    /// The kind of dither added to integer samples before rounding.
+    pub fn dither(&self) -> DitherKind {
+        self.dither
+    }
+
+    /// The next uniformly distributed noise value between `-0.5` and `0.5`.
+    #[inline]
+    fn next_noise(&mut self) -> f32 {
+        // Xorshift32.
+        let mut x = self.noise;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.noise = x;
+        (x >> 8) as f32 / (1 << 24) as f32 - 0.5
+    }
+
+    /// The next dither value as a fraction of a quantization step.
+    #[inline]
+    fn next_dither(&mut self) -> f32 {
+        match self.dither {
+            DitherKind::None => 0.0,
+            DitherKind::Rectangular => self.next_noise(),
+            DitherKind::Triangular => self.next_noise() + self.next_noise(),
+        }
+    }
+
+    /// Scale each channel of the given frame by `gain`.
+    ///
+    /// Every channel is multiplied by the same `gain` in its floating point representation, so no
+    /// channel is scaled by a different amount than another.
+    ///
+    /// A unity `gain` returns the frame bit-for-bit, without dither, and a `gain` of zero returns
+    /// equilibrium, so that e.g. a negative float sample never becomes a negative zero.
+    #[inline]
+    pub fn scale_amp<F>(&mut self, frame: F, gain: S::Float) -> F
+        where F: Frame<Sample=S>,
+    {
+        if gain == <S::Float as FloatSample>::identity() {
+            return frame;
+        }
+        if gain == S::Float::equilibrium() {
+            return F::equilibrium();
+        }
+        match self.step {
+            None => frame.scale_amp(gain),
+            Some(step) => {
+                let half_step = step / 2.0f32.to_sample();
+                frame.map(|s| {
+                    let dither = step * self.next_dither().to_sample();
+                    let scaled = s.to_float_sample() * gain + dither;
+                    // Conversion truncates toward equilibrium, so offset by half a step to round.
+                    let rounded = if scaled < S::Float::equilibrium() {
+                        scaled - half_step
+                    } else {
+                        scaled + half_step
+                    };
+                    saturate(rounded, step).to_sample()
+                })
+            },
+        }
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn float_formats_have_no_quantization_step() {
+        assert!(quantization_step::<f32>().is_none());
+        assert!(quantization_step::<f64>().is_none());
+        assert_eq!(quantization_step::<i16>(), Some(1.0 / 32_768.0));
+        assert_eq!(quantization_step::<i32>(), Some(1.0 / 2_147_483_648.0));
+    }
+
+    #[test]
+    fn integer_results_round_to_nearest() {
+        let mut quantizer = Quantizer::<i16>::new();
+        assert_eq!(quantizer.scale_amp([3i16, -3], 0.5), [2, -2]);
+        assert_eq!(quantizer.scale_amp([1_000i16, -1_000], 0.4999), [500, -500]);
+        assert_eq!(quantizer.scale_amp([1i16, -1], 0.4), [0, 0]);
+        let mut quantizer = Quantizer::<i32>::new();
+        assert_eq!(quantizer.scale_amp([3i32, -3], 0.5), [2, -2]);
+    }
+
+    #[test]
+    fn integer_results_saturate_beyond_full_scale() {
+        let mut quantizer = Quantizer::<i16>::new();
+        assert_eq!(quantizer.scale_amp([i16::MAX, i16::MIN], 2.0), [i16::MAX, i16::MIN]);
+        let mut quantizer = Quantizer::<i32>::new();
+        assert_eq!(quantizer.scale_amp([i32::MAX, i32::MIN], 2.0), [i32::MAX, i32::MIN]);
+    }
+
+    #[test]
+    fn dither_stays_within_one_step() {
+        for &dither in &[DitherKind::Rectangular, DitherKind::Triangular] {
+            let mut plain = Quantizer::<i16>::new();
+            let mut dithered = Quantizer::<i16>::new();
+            dithered.set_dither(dither);
+            for i in -2_000i16..2_000 {
+                let s = i.wrapping_mul(16);
+                let expected = plain.scale_amp([s], 0.37)[0] as i32;
+                let actual = dithered.scale_amp([s], 0.37)[0] as i32;
+                assert!((actual - expected).abs() <= 1, "{:?}: {} vs {}", dither, actual, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn triangular_dither_preserves_sub_step_levels_on_average() {
+        let mut plain = Quantizer::<i16>::new();
+        let mut dithered = Quantizer::<i16>::new();
+        dithered.set_dither(DitherKind::Triangular);
+        let n = 100_000;
+        let sum: i64 = (0..n).map(|_| dithered.scale_amp([1i16], 0.25)[0] as i64).sum();
+        let mean = sum as f64 / n as f64;
+        assert!((mean - 0.25).abs() < 0.02, "mean {}", mean);
+        assert_eq!(plain.scale_amp([1i16], 0.25), [0]);
+    }
+
+    #[test]
+    fn unity_gain_returns_float_frames_bit_for_bit() {
+        let mut quantizer = Quantizer::<f32>::new();
+        let nan = f32::from_bits(0x7fc0_1234);
+        let frame = [-0.0f32, 1.0e-40, -1.0e-40, nan];
+        let scaled = quantizer.scale_amp(frame, 1.0);
+        for (a, b) in scaled.iter().zip(frame.iter()) {
+            assert_eq!(a.to_bits(), b.to_bits());
+        }
+    }
+
+    #[test]
+    fn unity_gain_round_trips_integer_frames() {
+        let mut quantizer = Quantizer::<i16>::new();
+        for &s in &[i16::MIN, -12_345, -1, 0, 1, 12_345, i16::MAX] {
+            let frame = [s, -s.saturating_add(1)];
+            assert_eq!(quantizer.scale_amp(frame, 1.0), frame);
+        }
+        let mut quantizer = Quantizer::<i32>::new();
+        for &s in &[i32::MIN, -123_456_789, -1, 0, 1, 123_456_789, i32::MAX] {
+            assert_eq!(quantizer.scale_amp([s], 1.0), [s]);
+        }
+    }
+
+    #[test]
+    fn zero_gain_yields_equilibrium() {
+        let mut quantizer = Quantizer::<f32>::new();
+        let silent = quantizer.scale_amp([-0.5f32], 0.0);
+        assert!(silent[0] == 0.0 && silent[0].is_sign_positive());
+    }
+
+    #[test]
+    fn dither_leaves_floats_and_unity_gain_untouched() {
+        let mut quantizer = Quantizer::<f32>::new();
+        quantizer.set_dither(DitherKind::Triangular);
+        assert_eq!(quantizer.scale_amp([0.5f32, -0.25], 0.5), [0.25, -0.125]);
+        let mut quantizer = Quantizer::<i16>::new();
+        quantizer.set_dither(DitherKind::Triangular);
+        for s in -100i16..100 {
+            assert_eq!(quantizer.scale_amp([s], 1.0), [s]);
+        }
+    }
+}
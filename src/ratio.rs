@@ -0,0 +1,47 @@
+//! Conversions between a compression ratio and the slope of the gain reduction above the
+//! threshold.
+//!
+//! The **Compressor** stores its ratio as a slope, i.e. `0.75` for 4:1, which is the amount of
+//! gain removed for each unit that the envelope exceeds the threshold.
+
+
+/// Convert a compression ratio, i.e. `4.0` for 4:1, to its slope.
+///
+/// A ratio of `1.0` yields a slope of `0.0` (no compression), while an infinite ratio yields a
+/// slope of `1.0` (limiting).
+#[inline]
+pub fn slope_from_ratio(ratio: f32) -> f32 {
+    1.0 - (1.0 / ratio)
+}
+
+/// Convert a slope back to its compression ratio.
+///
+/// A slope of `0.0` yields a ratio of `1.0`, while a slope of `1.0` yields an infinite ratio.
+#[inline]
+pub fn ratio_from_slope(slope: f32) -> f32 {
+    1.0 / (1.0 - slope)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn endpoints_convert_as_documented() {
+        assert_eq!(slope_from_ratio(1.0), 0.0);
+        assert_eq!(slope_from_ratio(std::f32::INFINITY), 1.0);
+        assert_eq!(ratio_from_slope(0.0), 1.0);
+        assert_eq!(ratio_from_slope(1.0), std::f32::INFINITY);
+        assert_eq!(slope_from_ratio(4.0), 0.75);
+    }
+
+    #[test]
+    fn ratios_round_trip_through_their_slopes() {
+        for i in 0..200 {
+            let ratio = 1.0 + i as f32 * 0.25;
+            let round_trip = ratio_from_slope(slope_from_ratio(ratio));
+            assert!((round_trip - ratio).abs() <= ratio * ratio * 1e-6);
+        }
+    }
+}
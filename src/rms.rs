@@ -0,0 +1,536 @@
+//! Root mean square calculation over a signal.
+//!
+//! The primary type of interest in this module is the [**Rms**](./struct.Rms).
+
+use envelope_detector::{Frame, Mode, Sample};
+use sample::FloatSample;
+use std;
+
+
+/// The shape of the window over which the **Rms** is calculated.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RmsWindow {
+    /// Every frame within the window is weighted equally.
+    ///
+    /// The RMS is updated from a running sum in constant time, but "steps" as transients enter
+    /// and leave the window.
+    Rectangular,
+    /// Frames are weighted by a Hann window, de-emphasising those near either edge.
+    ///
+    /// Transients fade in and out of the RMS smoothly, however the weighted sum must be
+    /// recalculated over the whole window for every frame, so the cost grows linearly with the
+    /// window length.
+    Hann,
+}
+
+
+/// Iteratively extracts the RMS (root mean square) envelope from a window over a signal of
+/// sample `Frame`s.
+#[derive(Clone)]
+pub struct Rms<F>
+    where F: Frame,
+{
+    /// The ringbuffer of frame sample squares (i.e. `sample * sample`) used to calculate the RMS
+    /// per sample.
+    ///
+    /// When a new sample is received, the **Rms** pops the front sample_square and adds the new
+    /// sample_square to the back.
+    ///
+    /// When decimating, each element instead holds the mean square of `decimation` frames.
+    window: std::collections::VecDeque<F::Float>,
+    /// The length of the window in frames, as requested via `set_window_frames`.
+    n_window_frames: usize,
+    /// The number of frames whose mean square is held by each element of the `window`.
+    decimation: usize,
+    /// The number of frames accumulated toward the next element of the `window`.
+    phase: usize,
+    /// The sum of the squares of the frames accumulated toward the next element of the `window`.
+    pending: F::Float,
+    /// The most recently calculated RMS, yielded between elements while decimating.
+    last_rms: F::Float,
+    /// The sum total of all sample_squares currently within the **Rms**'s `window` ring buffer.
+    sum: F::Float,
+    /// The shape of the window.
+    shape: RmsWindow,
+    /// The weight of each position within the `window` from oldest to newest.
+    ///
+    /// Only used for weighted window shapes.
+    weights: Vec<f32>,
+    /// The sum total of all `weights`.
+    weight_sum: f32,
+    /// Whether the sums of squares are accumulated in `f64`.
+    high_precision: bool,
+    /// The `sum` of each channel accumulated in `f64`, only maintained while `high_precision`.
+    precise_sum: Vec<f64>,
+}
+
+impl<F> std::fmt::Debug for Rms<F>
+    where F: Frame,
+          F::Float: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "Rms {{ window: {:?}, sum: {:?}, shape: {:?}, decimation: {:?} }}",
+               &self.window, &self.sum, &self.shape, &self.decimation)
+    }
+}
+
+
+impl<F> Rms<F>
+    where F: Frame,
+{
+
+    /// Construct a new **Rms** with a **Rectangular** window.
+    pub fn new(n_window_frames: usize) -> Self {
+        Self::with_shape(n_window_frames, RmsWindow::Rectangular)
+    }
+
+    /// Construct a new **Rms** with a window of the given shape.
+    pub fn with_shape(n_window_frames: usize, shape: RmsWindow) -> Self {
+        let mut rms = Rms {
+            window: (0..n_window_frames).map(|_| Frame::equilibrium()).collect(),
+            n_window_frames: n_window_frames,
+            decimation: 1,
+            phase: 0,
+            pending: Frame::equilibrium(),
+            last_rms: Frame::equilibrium(),
+            sum: Frame::equilibrium(),
+            shape: shape,
+            weights: Vec::new(),
+            weight_sum: 0.0,
+            high_precision: false,
+            precise_sum: Vec::new(),
+        };
+        rms.update_weights();
+        rms
+    }
+
+    /// The shape of the window.
+    pub fn shape(&self) -> RmsWindow {
+        self.shape
+    }
+
+    /// Whether the sums of squares are accumulated in `f64`.
+    pub fn high_precision(&self) -> bool {
+        self.high_precision
+    }
+
+    /// Enable or disable accumulating the sums of squares in `f64`, regardless of the `Frame`'s
+    /// sample type.
+    ///
+    /// The **Rectangular** window keeps a running sum, adding each new square and subtracting
+    /// each square that leaves the window. In `f32` every addition rounds to the precision of the
+    /// running sum, so after a loud passage fills a long window the sum retains an error on the
+    /// order of `1e-7` times the loud passage's total, which then swamps the squares of a quiet
+    /// passage that follows. Accumulating in `f64` shrinks that error by a factor of roughly
+    /// `1e9`, and the weighted sum of a **Hann** window is likewise summed in `f64`. The squares
+    /// themselves are still held in the `Frame`'s float format, and only the RMS is converted
+    /// back to it.
+    pub fn set_high_precision(&mut self, enabled: bool) {
+        self.high_precision = enabled;
+        self.update_precise_sum();
+    }
+
+    /// Zeroes the sum and the buffer of the `window`.
+    pub fn reset(&mut self) {
+        for sample_square in &mut self.window {
+            *sample_square = Frame::equilibrium();
+        }
+        self.sum = Frame::equilibrium();
+        self.phase = 0;
+        self.pending = Frame::equilibrium();
+        self.last_rms = Frame::equilibrium();
+        self.update_precise_sum();
+    }
+
+    /// Advance the `window` as though it had been fed `n_frames` of silence.
+    pub fn advance_silence(&mut self, n_frames: usize) {
+        let n_frames = self.phase + n_frames;
+        let n_elements = n_frames / self.decimation;
+        if n_elements == 0 {
+            self.phase = n_frames;
+            return;
+        }
+        if n_elements >= self.window.len() {
+            self.reset();
+            self.phase = n_frames % self.decimation;
+            return;
+        }
+        // Complete the element in progress, then push silence for the rest.
+        let pending = self.take_pending();
+        self.phase = n_frames % self.decimation;
+        self.pop_front();
+        self.push_back(pending);
+        for _ in 1..n_elements {
+            self.pop_front();
+            self.window.push_back(Frame::equilibrium());
+        }
+        self.last_rms = self.calc();
+    }
+
+    /// Fill the `window` as though it had only ever been fed a signal whose RMS is `level`.
+    pub fn fill(&mut self, level: F::Float) {
+        let level_square = level.zip_map(level, |a, b| a * b);
+        for sample_square in &mut self.window {
+            *sample_square = level_square;
+        }
+        let num_frames_f: <F::Sample as Sample>::Float = (self.window.len() as f32).to_sample();
+        self.sum = level_square.map(|s| s * num_frames_f);
+        let phase_f: <F::Sample as Sample>::Float = (self.phase as f32).to_sample();
+        self.pending = level_square.map(|s| s * phase_f);
+        self.last_rms = level;
+        self.update_precise_sum();
+    }
+
+    /// The number of frames whose mean square is held by each element of the window.
+    pub fn decimation(&self) -> usize {
+        self.decimation
+    }
+
+    /// Set the number of frames whose mean square is held by each element of the window, at
+    /// least `1` (no decimation).
+    ///
+    /// The window then holds `1 / factor` as many elements and the RMS is only recalculated
+    /// once every `factor` frames, held in between. Each element is the mean of the squares of
+    /// its frames rather than a single frame's square, so no frame is ignored. The window is
+    /// refilled with the most recent RMS, so the level reading continues unbroken.
+    pub fn set_decimation(&mut self, factor: usize) {
+        let factor = if factor < 1 { 1 } else { factor };
+        if factor == self.decimation {
+            return;
+        }
+        self.decimation = factor;
+        self.phase = 0;
+        let n_window_frames = self.n_window_frames;
+        self.resize_window(Self::n_elements(n_window_frames, factor));
+        let last_rms = self.last_rms;
+        self.fill(last_rms);
+    }
+
+    /// Set the size of the `window` as a number of frames.
+    ///
+    /// If the current window length is longer than the given length, the difference will be popped
+    /// from the front of the `window` while adjusting the `sum` accordingly.
+    ///
+    /// If the current window length is shorter than the given length, the difference will be
+    /// pushed to the front of the `window` using frames at signal equilibrium.
+    ///
+    /// If the length already is already correct, no re-sizing occurs.
+    ///
+    /// While decimating, the window holds enough elements to span at least the given number of
+    /// frames.
+    pub fn set_window_frames(&mut self, n_window_frames: usize) {
+        self.n_window_frames = n_window_frames;
+        let n_elements = Self::n_elements(n_window_frames, self.decimation);
+        self.resize_window(n_elements);
+    }
+
+    /// The number of elements needed to span the given number of frames at the given decimation.
+    fn n_elements(n_window_frames: usize, decimation: usize) -> usize {
+        (n_window_frames + decimation - 1) / decimation
+    }
+
+    /// Resize the `window` to the given number of elements.
+    fn resize_window(&mut self, n_window_frames: usize) {
+        let len = self.window.len();
+        if len == n_window_frames {
+            return;
+
+        // If our window is too long, truncate it from the front (removing the olest frames).
+        } else if len > n_window_frames {
+            let diff = len - n_window_frames;
+            for _ in 0..diff {
+                self.pop_front();
+            }
+
+        // If our window is too short, we'll zero-pad the front of the ringbuffer (this way, the
+        // padded zeroes will be the first to be removed).
+        } else if len < n_window_frames {
+            let diff = n_window_frames - len;
+            for _ in 0..diff {
+                self.window.push_front(Frame::equilibrium());
+            }
+        }
+
+        self.update_weights();
+    }
+
+    /// The rough cost of each call to `next` relative to **Peak** detection.
+    ///
+    /// A **Rectangular** window costs about the same regardless of its length, while a **Hann**
+    /// window weighs every element in the window each time it is recalculated.
+    pub fn relative_cost(&self) -> f32 {
+        match self.shape {
+            RmsWindow::Rectangular => 1.0,
+            RmsWindow::Hann => 1.0 + self.window.len() as f32 / self.decimation as f32,
+        }
+    }
+
+    /// The length of the window as a number of frames.
+    #[inline]
+    pub fn window_frames(&self) -> usize {
+        self.window.len() * self.decimation
+    }
+
+    /// The next RMS given the new frame in the sequence.
+    ///
+    /// The **Rms** pops its front frame and adds the new frame to the back.
+    ///
+    /// The yielded RMS is the RMS of all frame squares in the `window` after the new frame is
+    /// added.
+    ///
+    /// While decimating, the new frame is accumulated and the previous RMS is yielded until
+    /// `decimation` frames have been accumulated, at which point their mean square is added.
+    ///
+    /// Returns `Frame::equilibrium` if the `window` is empty.
+    #[inline]
+    pub fn next(&mut self, new_frame: F) -> F::Float {
+        // If our **Window** has no length, there's nothing to calculate.
+        if self.window.len() == 0 {
+            return Frame::equilibrium();
+        }
+        let new_frame = new_frame.to_float_frame();
+        let new_frame_square = new_frame.zip_map(new_frame, |a, b| a * b);
+        let mean_square = if self.decimation > 1 {
+            self.pending = self.pending.add_amp(new_frame_square);
+            self.phase += 1;
+            if self.phase < self.decimation {
+                return self.last_rms;
+            }
+            self.take_pending()
+        } else {
+            new_frame_square
+        };
+        self.pop_front();
+        self.push_back(mean_square);
+        self.last_rms = self.calc();
+        self.last_rms
+    }
+
+    /// Calculate the RMS of the window using its shape.
+    #[inline]
+    fn calc(&self) -> F::Float {
+        match self.shape {
+            RmsWindow::Rectangular => self.calc_rms(),
+            RmsWindow::Hann => self.calc_weighted_rms(),
+        }
+    }
+
+    /// Take the mean of the squares accumulated toward the next element, restarting the
+    /// accumulation.
+    #[inline]
+    fn take_pending(&mut self) -> F::Float {
+        let decimation: <F::Sample as Sample>::Float = (self.decimation as f32).to_sample();
+        let mean_square = self.pending.map(|s| s / decimation);
+        self.pending = Frame::equilibrium();
+        self.phase = 0;
+        mean_square
+    }
+
+    /// Recalculate the `weights` for the current window length and shape.
+    fn update_weights(&mut self) {
+        self.weights.clear();
+        self.weight_sum = 0.0;
+        if let RmsWindow::Rectangular = self.shape {
+            return;
+        }
+        let len = self.window.len();
+        // Exclude the zero-valued end points so that every frame in the window contributes.
+        let period = (len + 1) as f32;
+        for i in 0..len {
+            let phase = (i + 1) as f32 / period;
+            let weight = 0.5 * (1.0 - (2.0 * std::f32::consts::PI * phase).cos());
+            self.weights.push(weight);
+            self.weight_sum += weight;
+        }
+    }
+
+    /// Recalculate the `precise_sum` from the contents of the `window`, or clear it while not
+    /// `high_precision`.
+    fn update_precise_sum(&mut self) {
+        self.precise_sum.clear();
+        if !self.high_precision {
+            return;
+        }
+        self.precise_sum.resize(F::n_channels(), 0.0);
+        for sample_square in &self.window {
+            for (sum, s) in self.precise_sum.iter_mut().zip(sample_square.channels()) {
+                *sum += s.to_sample::<f64>();
+            }
+        }
+    }
+
+    /// Remove the front frame and subtract it from the `sum` frame.
+    fn pop_front(&mut self) {
+        let removed_sample_square = self.window.pop_front().unwrap();
+        self.sum = self.sum.zip_map(removed_sample_square, |s, r| {
+            let diff = s - r;
+            // Don't let floating point rounding errors put us below 0.0.
+            if diff < Sample::equilibrium() { Sample::equilibrium() } else { diff }
+        });
+        for (sum, r) in self.precise_sum.iter_mut().zip(removed_sample_square.channels()) {
+            let diff = *sum - r.to_sample::<f64>();
+            *sum = if diff < 0.0 { 0.0 } else { diff };
+        }
+    }
+
+    /// Pushes the given frame square back onto our buffer and adds it to the `sum`.
+    fn push_back(&mut self, new_frame_square: F::Float) {
+        self.window.push_back(new_frame_square);
+        self.sum = self.sum.add_amp(new_frame_square);
+        for (sum, s) in self.precise_sum.iter_mut().zip(new_frame_square.channels()) {
+            *sum += s.to_sample::<f64>();
+        }
+    }
+
+    /// Calculate the RMS for the **Window** in its current state and yield the result as the
+    /// `Frame`s associated `Float` type.
+    fn calc_rms(&self) -> F::Float {
+        if self.high_precision {
+            let num_frames_f = self.window.len() as f64;
+            return Frame::from_fn(|ch| (self.precise_sum[ch] / num_frames_f).sqrt().to_sample());
+        }
+        let num_frames_f = Sample::from_sample(self.window.len() as f32);
+        self.sum.map(|s| (s / num_frames_f).sample_sqrt())
+    }
+
+    /// Calculate the weighted RMS for the **Window** in its current state.
+    fn calc_weighted_rms(&self) -> F::Float {
+        if self.high_precision {
+            let weight_sum = self.weight_sum as f64;
+            return Frame::from_fn(|ch| {
+                let weighted_sum = self.window.iter().zip(&self.weights).fold(0.0, |sum, (sq, &w)| {
+                    let sq = sq.channel(ch).map(|s| s.to_sample::<f64>()).unwrap_or(0.0);
+                    sum + sq * w as f64
+                });
+                (weighted_sum / weight_sum).sqrt().to_sample()
+            });
+        }
+        let equilibrium: F::Float = Frame::equilibrium();
+        let weighted_sum = self.window.iter().zip(&self.weights).fold(equilibrium, |sum, (sq, w)| {
+            let w: <F::Sample as Sample>::Float = w.to_sample();
+            sum.zip_map(*sq, |s, sq| s + sq * w)
+        });
+        let weight_sum: <F::Sample as Sample>::Float = self.weight_sum.to_sample();
+        weighted_sum.map(|s| (s / weight_sum).sample_sqrt())
+    }
+
+}
+
+impl<F> Mode<F> for Rms<F>
+    where F: Frame,
+{
+    fn next_frame(&mut self, frame: F) -> F {
+        self.next(frame).map(|s| s.to_sample::<F::Sample>())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHAPES: [RmsWindow; 2] = [RmsWindow::Rectangular, RmsWindow::Hann];
+
+    #[test]
+    fn rms_of_a_constant_is_its_magnitude() {
+        for &shape in &SHAPES {
+            let mut rms = Rms::<[f32; 2]>::with_shape(64, shape);
+            let mut level = [0.0; 2];
+            for _ in 0..64 {
+                level = rms.next([0.5, -0.25]);
+            }
+            assert!((level[0] - 0.5).abs() < 1e-5 && (level[1] - 0.25).abs() < 1e-5, "{:?}", shape);
+        }
+    }
+
+    #[test]
+    fn rms_of_a_sine_is_its_amplitude_over_root_two() {
+        for &shape in &SHAPES {
+            let mut rms = Rms::<[f32; 1]>::with_shape(400, shape);
+            let mut level = [0.0];
+            for i in 0..800 {
+                level = rms.next([(i as f32 * std::f32::consts::PI / 50.0).sin()]);
+            }
+            assert!((level[0] - std::f32::consts::FRAC_1_SQRT_2).abs() < 0.01, "{:?}", shape);
+        }
+    }
+
+    #[test]
+    fn hann_window_fades_transients_in() {
+        let mut rectangular = Rms::<[f32; 1]>::with_shape(64, RmsWindow::Rectangular);
+        let mut hann = Rms::<[f32; 1]>::with_shape(64, RmsWindow::Hann);
+        assert_eq!(hann.shape(), RmsWindow::Hann);
+        assert_eq!(hann.window_frames(), 64);
+        let rectangular_level = rectangular.next([1.0]);
+        let hann_level = hann.next([1.0]);
+        assert!(hann_level[0] < rectangular_level[0] / 4.0);
+        let mut hann_levels = (0..63).map(|_| hann.next([0.0])[0]);
+        let peak = hann_levels.by_ref().take(32).fold(0.0, f32::max);
+        assert!(peak > rectangular_level[0]);
+    }
+
+    #[test]
+    fn decimated_rms_of_a_constant_is_its_magnitude() {
+        for &shape in &SHAPES {
+            let mut rms = Rms::<[f32; 2]>::with_shape(4_096, shape);
+            rms.set_decimation(64);
+            assert_eq!(rms.decimation(), 64);
+            assert_eq!(rms.window_frames(), 4_096);
+            let mut level = [0.0; 2];
+            for _ in 0..8_192 {
+                level = rms.next([0.5, -0.25]);
+            }
+            assert!((level[0] - 0.5).abs() < 1e-5 && (level[1] - 0.25).abs() < 1e-5, "{:?}", shape);
+        }
+    }
+
+    #[test]
+    fn decimated_rms_tracks_a_steady_sine() {
+        for &shape in &SHAPES {
+            let mut rms = Rms::<[f32; 1]>::with_shape(44_100, shape);
+            rms.set_decimation(100);
+            let mut level = [0.0];
+            for i in 0..88_200 {
+                level = rms.next([(i as f32 * 0.05).sin() * 0.5]);
+            }
+            let expected = 0.5 * std::f32::consts::FRAC_1_SQRT_2;
+            assert!((level[0] - expected).abs() < expected * 0.01, "{:?}", shape);
+        }
+    }
+
+    #[test]
+    fn decimation_keeps_the_level_reading_unbroken() {
+        let mut rms = Rms::<[f32; 1]>::new(1_024);
+        for _ in 0..1_024 {
+            rms.next([0.5]);
+        }
+        rms.set_decimation(0);
+        assert_eq!(rms.decimation(), 1);
+        rms.set_decimation(16);
+        assert!((rms.next([0.5])[0] - 0.5).abs() < 1e-5);
+    }
+
+    /// The RMS of a long window of quiet constant following a window of loud sine.
+    fn quiet_after_loud(high_precision: bool) -> f32 {
+        let window = 88_200;
+        let mut rms = Rms::<[f32; 1]>::new(window);
+        rms.set_high_precision(high_precision);
+        assert_eq!(rms.high_precision(), high_precision);
+        for i in 0..window {
+            rms.next([(i as f32 * 0.0731).sin()]);
+        }
+        let mut level = [0.0];
+        for _ in 0..window {
+            level = rms.next([0.001]);
+        }
+        level[0]
+    }
+
+    #[test]
+    fn high_precision_reads_a_quiet_passage_after_a_loud_one_more_accurately() {
+        let plain = (quiet_after_loud(false) - 0.001).abs();
+        let precise = (quiet_after_loud(true) - 0.001).abs();
+        assert!(precise < 1e-6, "{}", precise);
+        assert!(plain > 100.0 * precise, "{} vs {}", plain, precise);
+    }
+}
@@ -0,0 +1,389 @@
+//! Processing of the signal on its way to the envelope detector.
+//!
+//! None of this processing affects the signal to which the gain is applied.
+
+#[cfg(feature = "sidechain-filter")]
+use assert_valid_sample_hz;
+use envelope_detector::{Frame, Sample};
+#[cfg(feature = "sidechain-filter")]
+use filter::{Biquad, BiquadCoefficients, BUTTERWORTH_Q};
+use sample::FloatSample;
+use std::marker::PhantomData;
+
+
+/// How the channels of each frame are presented to the envelope detector.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DetectionMode {
+    /// Each channel is detected independently.
+    PerChannel,
+    /// The channels are summed to mono and scaled by the reciprocal of the channel count, so that
+    /// a signal common to all channels is detected at its per-channel level. Every channel of the
+    /// detector then sees the same signal, yielding the same gain for every channel.
+    MonoSum,
+    /// The absolute values of the channels are summed, i.e. `Σ|ch|`, and every channel of the
+    /// detector sees the sum.
+    ///
+    /// Unlike `MonoSum`, which sums the signed samples, channels can't cancel one another out:
+    /// out-of-phase content is detected as strongly as in-phase content. The sum is not scaled by
+    /// the channel count, so the detector reacts more strongly the more channels are loud at once,
+    /// and a signal common to all channels is detected at the channel count times its level.
+    SumAbs,
+}
+
+/// The chain of processing applied to frames on their way to the envelope detector.
+#[derive(Clone, Debug)]
+pub struct Sidechain<F> {
+    /// The channel which alone is detected, duplicated across every channel, if any.
+    key_channel: Option<usize>,
+    /// How the channels are presented to the detector.
+    mode: DetectionMode,
+    /// The weight of each channel in the `MonoSum` and `SumAbs` sums.
+    sum_weights: Vec<f32>,
+    /// The total of the `sum_weights`, by which the `MonoSum` is scaled.
+    sum_weight_total: f32,
+    /// Whether the ITU-R BS.1770 K-weighting filter is applied.
+    #[cfg(feature = "sidechain-filter")]
+    k_weighting: bool,
+    /// The high shelf stage of the K-weighting filter.
+    #[cfg(feature = "sidechain-filter")]
+    k_shelf: Biquad<F>,
+    /// The high-pass stage of the K-weighting filter.
+    #[cfg(feature = "sidechain-filter")]
+    k_highpass: Biquad<F>,
+    /// The band and gain of the emphasis filter as `(low_hz, high_hz, gain_db)`, if enabled.
+    #[cfg(feature = "sidechain-filter")]
+    emphasis: Option<(f64, f64, f32)>,
+    /// The peaking filter emphasising the band of interest.
+    #[cfg(feature = "sidechain-filter")]
+    emphasis_filter: Biquad<F>,
+    /// The cutoff of the high-pass filter for each channel, if enabled for that channel.
+    #[cfg(feature = "sidechain-filter")]
+    hpf_hz: Vec<Option<f64>>,
+    /// The high-pass filter applied after the channels are combined.
+    ///
+    /// Channels whose filter is disabled use the identity coefficients.
+    #[cfg(feature = "sidechain-filter")]
+    hpf: Biquad<F>,
+    frame: PhantomData<F>,
+}
+
+
+impl<F> Sidechain<F>
+    where F: Frame,
+          F::Sample: FloatSample,
+{
+
+    /// Construct a **Sidechain** that passes each channel to the detector unchanged.
+    pub fn new() -> Self {
+        Sidechain {
+            key_channel: None,
+            mode: DetectionMode::PerChannel,
+            sum_weights: vec![1.0; F::n_channels()],
+            sum_weight_total: F::n_channels() as f32,
+            #[cfg(feature = "sidechain-filter")]
+            k_weighting: false,
+            #[cfg(feature = "sidechain-filter")]
+            k_shelf: Biquad::new(BiquadCoefficients::identity()),
+            #[cfg(feature = "sidechain-filter")]
+            k_highpass: Biquad::new(BiquadCoefficients::identity()),
+            #[cfg(feature = "sidechain-filter")]
+            emphasis: None,
+            #[cfg(feature = "sidechain-filter")]
+            emphasis_filter: Biquad::new(BiquadCoefficients::identity()),
+            #[cfg(feature = "sidechain-filter")]
+            hpf_hz: vec![None; F::n_channels()],
+            #[cfg(feature = "sidechain-filter")]
+            hpf: Biquad::new(BiquadCoefficients::identity()),
+            frame: PhantomData,
+        }
+    }
+
+    /// How the channels are presented to the detector.
+    pub fn mode(&self) -> DetectionMode {
+        self.mode
+    }
+
+    /// Set how the channels are presented to the detector.
+    pub fn set_mode(&mut self, mode: DetectionMode) {
+        self.mode = mode;
+    }
+
+    /// The weight of each channel in the `MonoSum` and `SumAbs` sums.
+    pub fn sum_weights(&self) -> &[f32] {
+        &self.sum_weights
+    }
+
+    /// Set the weight of each channel in the `MonoSum` and `SumAbs` sums.
+    pub fn set_sum_weights(&mut self, weights: &[f32]) {
+        self.sum_weights.clear();
+        self.sum_weights.extend_from_slice(weights);
+        self.sum_weight_total = weights.iter().fold(0.0, |total, &w| total + w);
+    }
+
+    /// The channel which alone is detected, if any.
+    pub fn key_channel(&self) -> Option<usize> {
+        self.key_channel
+    }
+
+    /// Set the channel which alone is detected, or `None` to detect every channel.
+    pub fn set_key_channel(&mut self, index: Option<usize>) {
+        self.key_channel = index;
+    }
+
+    /// Whether the K-weighting filter is applied.
+    #[cfg(feature = "sidechain-filter")]
+    pub fn k_weighting(&self) -> bool {
+        self.k_weighting
+    }
+
+    /// Enable or disable the K-weighting filter.
+    #[cfg(feature = "sidechain-filter")]
+    pub fn set_k_weighting(&mut self, enabled: bool, sample_hz: f64) {
+        if !self.k_weighting {
+            self.k_shelf.reset();
+            self.k_highpass.reset();
+        }
+        self.k_weighting = enabled;
+        self.update_to_sample_hz(sample_hz);
+    }
+
+    /// The band and gain of the emphasis filter as `(low_hz, high_hz, gain_db)`, if enabled.
+    #[cfg(feature = "sidechain-filter")]
+    pub fn emphasis(&self) -> Option<(f64, f64, f32)> {
+        self.emphasis
+    }
+
+    /// Set the band and gain of the emphasis filter, or `None` to disable it.
+    #[cfg(feature = "sidechain-filter")]
+    pub fn set_emphasis(&mut self, emphasis: Option<(f64, f64, f32)>, sample_hz: f64) {
+        if self.emphasis.is_none() {
+            self.emphasis_filter.reset();
+        }
+        self.emphasis = emphasis;
+        self.update_to_sample_hz(sample_hz);
+    }
+
+    /// The cutoff of the high-pass filter, if enabled with the same cutoff for every channel.
+    #[cfg(feature = "sidechain-filter")]
+    pub fn hpf_hz(&self) -> Option<f64> {
+        let first = self.hpf_hz.first().and_then(|hz| *hz);
+        if self.hpf_hz.iter().all(|&hz| hz == first) { first } else { None }
+    }
+
+    /// The cutoff of the high-pass filter for the channel at the given index, if enabled.
+    #[cfg(feature = "sidechain-filter")]
+    pub fn hpf_channel_hz(&self, channel: usize) -> Option<f64> {
+        self.hpf_hz[channel]
+    }
+
+    /// Set the cutoff of the high-pass filter for every channel, or `None` to disable it.
+    #[cfg(feature = "sidechain-filter")]
+    pub fn set_hpf_hz(&mut self, cutoff_hz: Option<f64>, sample_hz: f64) {
+        if !self.hpf_enabled() {
+            self.hpf.reset();
+        }
+        for hz in &mut self.hpf_hz {
+            *hz = cutoff_hz;
+        }
+        self.update_to_sample_hz(sample_hz);
+    }
+
+    /// Set the cutoff of the high-pass filter for the channel at the given index alone, or
+    /// `None` to disable it for that channel.
+    #[cfg(feature = "sidechain-filter")]
+    pub fn set_hpf_channel_hz(&mut self, channel: usize, cutoff_hz: Option<f64>, sample_hz: f64) {
+        if !self.hpf_enabled() {
+            self.hpf.reset();
+        }
+        self.hpf_hz[channel] = cutoff_hz;
+        self.update_to_sample_hz(sample_hz);
+    }
+
+    /// Whether the high-pass filter is enabled for any channel.
+    #[cfg(feature = "sidechain-filter")]
+    pub fn hpf_enabled(&self) -> bool {
+        self.hpf_hz.iter().any(|hz| hz.is_some())
+    }
+
+    /// The number of biquad filters applied to each frame.
+    #[cfg(feature = "sidechain-filter")]
+    pub fn n_biquads(&self) -> usize {
+        let k_weighting = if self.k_weighting { 2 } else { 0 };
+        let emphasis = if self.emphasis.is_some() { 1 } else { 0 };
+        let hpf = if self.hpf_enabled() { 1 } else { 0 };
+        k_weighting + emphasis + hpf
+    }
+
+    /// Without the `sidechain-filter` feature no filters are applied.
+    #[cfg(not(feature = "sidechain-filter"))]
+    pub fn n_biquads(&self) -> usize {
+        0
+    }
+
+    /// Clear the state of the filters, as though they had only ever been fed silence.
+    pub fn reset(&mut self) {
+        #[cfg(feature = "sidechain-filter")]
+        {
+            self.k_shelf.reset();
+            self.k_highpass.reset();
+            self.emphasis_filter.reset();
+            self.hpf.reset();
+        }
+    }
+
+    /// Update the filter coefficients in accordance with the given sample_hz.
+    #[cfg(feature = "sidechain-filter")]
+    pub fn update_to_sample_hz(&mut self, sample_hz: f64) {
+        assert_valid_sample_hz(sample_hz, "Sidechain::update_to_sample_hz");
+        if self.k_weighting {
+            self.k_shelf.set_coefficients(BiquadCoefficients::k_weighting_shelf(sample_hz));
+            self.k_highpass.set_coefficients(BiquadCoefficients::k_weighting_highpass(sample_hz));
+        }
+        if let Some((low_hz, high_hz, gain_db)) = self.emphasis {
+            let coefficients =
+                BiquadCoefficients::peaking_band(low_hz, high_hz, gain_db as f64, sample_hz);
+            self.emphasis_filter.set_coefficients(coefficients);
+        }
+        for (channel, &hz) in self.hpf_hz.iter().enumerate() {
+            let coefficients = match hz {
+                Some(hz) => BiquadCoefficients::highpass(hz, BUTTERWORTH_Q, sample_hz),
+                None => BiquadCoefficients::identity(),
+            };
+            self.hpf.set_channel_coefficients(channel, coefficients);
+        }
+    }
+
+    /// Process the next frame on its way to the detector.
+    #[inline]
+    pub fn next(&mut self, frame: F) -> F {
+        let frame = match self.key_channel {
+            Some(index) => {
+                let key = *frame.channel(index).unwrap_or(&F::Sample::equilibrium());
+                F::from_fn(|_| key)
+            },
+            None => frame,
+        };
+        let frame = match self.mode {
+            DetectionMode::PerChannel => frame,
+            DetectionMode::MonoSum => {
+                let zero = F::Sample::equilibrium();
+                let weights = self.sum_weights.iter();
+                let sum = frame.channels().zip(weights).fold(zero, |sum, (s, &w)| {
+                    sum + s * w.to_sample()
+                });
+                let mono = if self.sum_weight_total == 0.0 {
+                    zero
+                } else {
+                    sum / self.sum_weight_total.to_sample()
+                };
+                F::from_fn(|_| mono)
+            },
+            DetectionMode::SumAbs => {
+                let zero = F::Sample::equilibrium();
+                let weights = self.sum_weights.iter();
+                let sum = frame.channels().zip(weights).fold(zero, |sum, (s, &w)| {
+                    let s = if s < zero { -s } else { s };
+                    sum + s * w.to_sample()
+                });
+                F::from_fn(|_| sum)
+            },
+        };
+        self.filter(frame)
+    }
+
+    /// Apply the enabled filters to the combined frame.
+    #[cfg(feature = "sidechain-filter")]
+    #[inline]
+    fn filter(&mut self, frame: F) -> F {
+        let frame = if self.k_weighting {
+            self.k_highpass.next(self.k_shelf.next(frame))
+        } else {
+            frame
+        };
+        let frame = match self.emphasis {
+            Some(_) => self.emphasis_filter.next(frame),
+            None => frame,
+        };
+        if self.hpf_enabled() { self.hpf.next(frame) } else { frame }
+    }
+
+    /// Without the `sidechain-filter` feature no filters are applied.
+    #[cfg(not(feature = "sidechain-filter"))]
+    #[inline]
+    fn filter(&mut self, frame: F) -> F {
+        frame
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn per_channel_detection_passes_each_channel() {
+        let mut sidechain = Sidechain::<[f32; 2]>::new();
+        assert_eq!(sidechain.next([0.5, -0.25]), [0.5, -0.25]);
+    }
+
+    #[test]
+    fn mono_sum_detects_the_mean_on_every_channel() {
+        let mut sidechain = Sidechain::<[f32; 2]>::new();
+        sidechain.set_mode(DetectionMode::MonoSum);
+        assert_eq!(sidechain.next([0.5, -0.25]), [0.125, 0.125]);
+        assert_eq!(sidechain.next([0.5, 0.5]), [0.5, 0.5]);
+        assert_eq!(sidechain.next([0.5, -0.5]), [0.0, 0.0]);
+    }
+
+    #[cfg(feature = "sidechain-filter")]
+    #[test]
+    fn hpf_removes_low_end_from_detection() {
+        let mut sidechain = Sidechain::<[f32; 2]>::new();
+        sidechain.set_mode(DetectionMode::MonoSum);
+        sidechain.set_hpf_hz(Some(100.0), 48_000.0);
+        assert_eq!(sidechain.hpf_hz(), Some(100.0));
+        assert_eq!(sidechain.n_biquads(), 1);
+        let mut detected = [1.0; 2];
+        for _ in 0..48_000 {
+            detected = sidechain.next([0.5, 0.5]);
+        }
+        assert!(detected[0].abs() < 1e-4 && detected[1] == detected[0]);
+        sidechain.set_hpf_hz(None, 48_000.0);
+        assert_eq!(sidechain.n_biquads(), 0);
+        assert_eq!(sidechain.next([0.5, 0.5]), [0.5, 0.5]);
+    }
+
+    #[cfg(feature = "sidechain-filter")]
+    #[test]
+    fn k_weighting_adds_two_biquads() {
+        let mut sidechain = Sidechain::<[f32; 2]>::new();
+        sidechain.set_k_weighting(true, 48_000.0);
+        assert!(sidechain.k_weighting());
+        assert_eq!(sidechain.n_biquads(), 2);
+        let mut detected = [1.0; 2];
+        for _ in 0..48_000 {
+            detected = sidechain.next([0.5, 0.5]);
+        }
+        assert!(detected[0].abs() < 1e-4);
+        sidechain.set_k_weighting(false, 48_000.0);
+        assert_eq!(sidechain.n_biquads(), 0);
+    }
+
+    #[cfg(feature = "sidechain-filter")]
+    #[test]
+    #[should_panic]
+    fn updating_to_a_nan_sample_rate_panics() {
+        Sidechain::<[f32; 2]>::new().update_to_sample_hz(std::f64::NAN);
+    }
+
+    #[test]
+    fn sum_abs_detects_out_of_phase_channels() {
+        let mut sidechain = Sidechain::<[f32; 2]>::new();
+        sidechain.set_mode(DetectionMode::SumAbs);
+        assert_eq!(sidechain.next([0.5, -0.5]), [1.0, 1.0]);
+        assert_eq!(sidechain.next([0.25, 0.0]), [0.25, 0.25]);
+        sidechain.set_mode(DetectionMode::MonoSum);
+        assert_eq!(sidechain.next([0.5, -0.5]), [0.0, 0.0]);
+    }
+}
@@ -0,0 +1,300 @@
+//! Lazy compression of a `sample::Signal`, with parameters that may be changed mid-stream from
+//! another thread.
+//!
+//! With the `dasp` feature, `dasp_signal::Signal`s may be compressed in the same way.
+//!
+//! The primary type of interest is the [**CompressedSignal**](./struct.CompressedSignal),
+//! created via `Compressor::compress_signal`.
+
+use {Compressor, CompressorParams, Detector, EvenGainFunction};
+use envelope_detector::Frame;
+use std;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+
+
+/// A handle to a set of **CompressorParams** shared between a **CompressedSignal** and any
+/// number of other threads.
+///
+/// Every parameter is stored in an atomic, so that reading and writing never blocks. Each call to
+/// `set` bumps a version number, which the **CompressedSignal** checks before every frame,
+/// applying the latest parameters via `Compressor::set_all` whenever the version changes.
+#[derive(Debug)]
+pub struct SharedParams {
+    threshold: AtomicU32,
+    ratio: AtomicU32,
+    attack_ms: AtomicU64,
+    release_ms: AtomicU64,
+    /// The window duration's bits, or those of NaN for `None`.
+    window_ms: AtomicU64,
+    knee_db: AtomicU32,
+    makeup_gain: AtomicU32,
+    auto_makeup: AtomicBool,
+    version: AtomicUsize,
+}
+
+/// A signal yielding each frame of an underlying signal compressed by a **Compressor**.
+///
+/// As the `sample` crate implements **Signal** for any iterator of frames, this is itself a
+/// **Signal** and may be composed with any of its adaptors. Created by
+/// `Compressor::compress_signal`.
+///
+/// With the `dasp` feature, a **CompressedSignal** wrapping a `dasp_signal::Signal` is itself a
+/// `dasp_signal::Signal`. Created by `Compressor::compress_dasp_signal`.
+#[derive(Clone)]
+pub struct CompressedSignal<S, F, D, EGF>
+    where F: Frame,
+{
+    signal: S,
+    compressor: Compressor<F, D, EGF>,
+    sample_hz: f64,
+    params: Arc<SharedParams>,
+    /// The version of the `params` most recently applied to the `compressor`.
+    version: usize,
+}
+
+impl<S, F, D, EGF> std::fmt::Debug for CompressedSignal<S, F, D, EGF>
+    where S: std::fmt::Debug,
+          F: Frame,
+          Compressor<F, D, EGF>: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "CompressedSignal {{ signal: {:?}, compressor: {:?}, sample_hz: {:?}, \
+                   params: {:?} }}",
+               &self.signal, &self.compressor, &self.sample_hz, &self.params)
+    }
+}
+
+
+impl SharedParams {
+
+    /// Construct a new **SharedParams** holding the given parameters.
+    pub fn new(params: &CompressorParams) -> Self {
+        let shared = SharedParams {
+            threshold: AtomicU32::new(0),
+            ratio: AtomicU32::new(0),
+            attack_ms: AtomicU64::new(0),
+            release_ms: AtomicU64::new(0),
+            window_ms: AtomicU64::new(0),
+            knee_db: AtomicU32::new(0),
+            makeup_gain: AtomicU32::new(0),
+            auto_makeup: AtomicBool::new(false),
+            version: AtomicUsize::new(0),
+        };
+        shared.set(params);
+        shared
+    }
+
+    /// Replace the shared parameters, to be applied before the next frame.
+    pub fn set(&self, params: &CompressorParams) {
+        let window_ms = params.window_ms.unwrap_or(std::f64::NAN);
+        self.threshold.store(params.threshold.to_bits(), Ordering::Relaxed);
+        self.ratio.store(params.ratio.to_bits(), Ordering::Relaxed);
+        self.attack_ms.store(params.attack_ms.to_bits(), Ordering::Relaxed);
+        self.release_ms.store(params.release_ms.to_bits(), Ordering::Relaxed);
+        self.window_ms.store(window_ms.to_bits(), Ordering::Relaxed);
+        self.knee_db.store(params.knee_db.to_bits(), Ordering::Relaxed);
+        self.makeup_gain.store(params.makeup_gain.to_bits(), Ordering::Relaxed);
+        self.auto_makeup.store(params.auto_makeup, Ordering::Relaxed);
+        self.version.fetch_add(1, Ordering::Release);
+    }
+
+    /// The current shared parameters.
+    pub fn get(&self) -> CompressorParams {
+        let window_ms = f64::from_bits(self.window_ms.load(Ordering::Relaxed));
+        CompressorParams {
+            threshold: f32::from_bits(self.threshold.load(Ordering::Relaxed)),
+            ratio: f32::from_bits(self.ratio.load(Ordering::Relaxed)),
+            attack_ms: f64::from_bits(self.attack_ms.load(Ordering::Relaxed)),
+            release_ms: f64::from_bits(self.release_ms.load(Ordering::Relaxed)),
+            window_ms: if window_ms.is_nan() { None } else { Some(window_ms) },
+            knee_db: f32::from_bits(self.knee_db.load(Ordering::Relaxed)),
+            makeup_gain: f32::from_bits(self.makeup_gain.load(Ordering::Relaxed)),
+            auto_makeup: self.auto_makeup.load(Ordering::Relaxed),
+        }
+    }
+
+    /// The number of times the parameters have been set.
+    pub fn version(&self) -> usize {
+        self.version.load(Ordering::Acquire)
+    }
+
+}
+
+
+impl<S, F, D, EGF> CompressedSignal<S, F, D, EGF>
+    where F: Frame,
+          D: Detector<F::Float>,
+          EGF: EvenGainFunction,
+{
+
+    /// Construct a **CompressedSignal** compressing the given `signal` at the given `sample_hz`.
+    ///
+    /// The **Compressor**'s duration-based state is updated to the `sample_hz`.
+    pub fn new(signal: S, mut compressor: Compressor<F, D, EGF>, sample_hz: f64) -> Self {
+        compressor.update_to_sample_hz(sample_hz);
+        let params = Arc::new(SharedParams::new(&compressor.params()));
+        let version = params.version();
+        CompressedSignal {
+            signal: signal,
+            compressor: compressor,
+            sample_hz: sample_hz,
+            params: params,
+            version: version,
+        }
+    }
+
+    /// A handle to the parameters of the **Compressor**, which may be cloned and sent to other
+    /// threads to change the parameters mid-stream.
+    ///
+    /// Parameters set via the handle are applied before the next frame is compressed.
+    pub fn params_handle(&self) -> Arc<SharedParams> {
+        self.params.clone()
+    }
+
+    /// The sample rate at which the signal is compressed.
+    pub fn sample_hz(&self) -> f64 {
+        self.sample_hz
+    }
+
+    /// Borrow the **Compressor**, e.g. to read its meters.
+    pub fn compressor(&self) -> &Compressor<F, D, EGF> {
+        &self.compressor
+    }
+
+    /// Mutably borrow the **Compressor**.
+    ///
+    /// Parameters changed this way are replaced by those of the handle the next time the handle
+    /// is set.
+    pub fn compressor_mut(&mut self) -> &mut Compressor<F, D, EGF> {
+        &mut self.compressor
+    }
+
+    /// Consume the **CompressedSignal**, yielding the underlying signal and the **Compressor**.
+    pub fn into_parts(self) -> (S, Compressor<F, D, EGF>) {
+        (self.signal, self.compressor)
+    }
+
+    /// Apply the latest parameters set via the handle, if they have changed.
+    #[inline]
+    fn apply_params(&mut self) {
+        let version = self.params.version();
+        if version != self.version {
+            self.version = version;
+            let params = self.params.get();
+            self.compressor.set_all(&params, self.sample_hz);
+        }
+    }
+
+}
+
+impl<S, F, D, EGF> Iterator for CompressedSignal<S, F, D, EGF>
+    where S: Iterator<Item=F>,
+          F: Frame,
+          D: Detector<F::Float>,
+          EGF: EvenGainFunction,
+{
+    type Item = F;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.apply_params();
+        self.signal.next().map(|frame| self.compressor.next_frame(frame))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.signal.size_hint()
+    }
+}
+
+#[cfg(feature = "dasp")]
+impl<S, F, D, EGF> ::dasp_signal::Signal for CompressedSignal<S, F, D, EGF>
+    where S: ::dasp_signal::Signal<Frame=F>,
+          F: Frame + ::dasp_frame::Frame,
+          D: Detector<<F as Frame>::Float>,
+          EGF: EvenGainFunction,
+{
+    type Frame = F;
+
+    #[inline]
+    fn next(&mut self) -> Self::Frame {
+        self.apply_params();
+        let frame = self.signal.next();
+        self.compressor.next_frame(frame)
+    }
+
+    fn is_exhausted(&self) -> bool {
+        self.signal.is_exhausted()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use PeakAvgCompressor;
+    use sample::signal;
+
+    const SAMPLE_HZ: f64 = 44_100.0;
+
+    fn compressor() -> PeakAvgCompressor<[f64; 1]> {
+        Compressor::peak_avg(1.0, 100.0, 48_000.0, 0.25, 8.0)
+    }
+
+    fn peak<I: Iterator<Item=[f64; 1]>>(frames: I) -> f64 {
+        frames.fold(0.0, |peak, frame| frame[0].abs().max(peak))
+    }
+
+    #[test]
+    fn compressing_a_sine_signal_lowers_its_peak() {
+        let sine = signal::rate(SAMPLE_HZ).const_hz(440.0).sine();
+        let mut compressed = compressor().compress_signal(sine, SAMPLE_HZ);
+        assert_eq!(compressed.sample_hz(), SAMPLE_HZ);
+        let settled = peak(compressed.by_ref().skip(SAMPLE_HZ as usize / 2).take(4_410));
+        assert!(settled < 0.8);
+        assert!(compressed.compressor().current_gain_reduction_db() < -1.0);
+    }
+
+    #[test]
+    fn parameters_set_via_the_handle_apply_mid_stream() {
+        let sine = signal::rate(SAMPLE_HZ).const_hz(440.0).sine();
+        let mut compressed = compressor().compress_signal(sine, SAMPLE_HZ);
+        let handle = compressed.params_handle();
+        let version = handle.version();
+        let params = CompressorParams { threshold: 1.0, ratio: 1.0, ..handle.get() };
+        ::std::thread::spawn(move || handle.set(&params)).join().unwrap();
+        assert_eq!(compressed.params_handle().version(), version + 1);
+        let settled = peak(compressed.by_ref().skip(SAMPLE_HZ as usize).take(4_410));
+        assert!(settled > 0.999);
+        assert_eq!(compressed.compressor().params(), params);
+    }
+
+    #[test]
+    fn shared_params_round_trip_a_missing_window() {
+        let params = CompressorParams {
+            threshold: 0.5, ratio: 4.0, attack_ms: 1.0, release_ms: 100.0, window_ms: None,
+            knee_db: 6.0, makeup_gain: 2.0, auto_makeup: true,
+        };
+        assert_eq!(SharedParams::new(&params).get(), params);
+        let windowed = CompressorParams { window_ms: Some(20.0), ..params };
+        assert_eq!(SharedParams::new(&windowed).get(), windowed);
+    }
+
+    #[cfg(feature = "dasp")]
+    #[test]
+    fn compressing_a_dasp_sine_signal_lowers_its_peak() {
+        use dasp_signal::{self, Signal};
+        let sine = dasp_signal::rate(SAMPLE_HZ).const_hz(440.0).sine().map(|s| [s]);
+        let mut compressed = compressor().compress_dasp_signal(sine, SAMPLE_HZ);
+        assert!(!compressed.is_exhausted());
+        let settled = peak(compressed.by_ref().until_exhausted()
+                                     .skip(SAMPLE_HZ as usize / 2).take(4_410));
+        assert!(settled < 0.8);
+        let handle = compressed.params_handle();
+        handle.set(&CompressorParams { threshold: 1.0, ratio: 1.0, ..handle.get() });
+        let settled = peak(compressed.by_ref().until_exhausted()
+                                     .skip(SAMPLE_HZ as usize).take(4_410));
+        assert!(settled > 0.999);
+    }
+}
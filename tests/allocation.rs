@@ -0,0 +1,111 @@
+//! Checks that the real-time processing methods never allocate once configured.
+
+extern crate compressor;
+
+use compressor::{Compressor, RmsAvgCompressor, RmsWindow};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+/// Counts the allocations made on the current thread while counting is enabled.
+struct CountingAllocator;
+
+thread_local! {
+    static COUNTING: Cell<bool> = const { Cell::new(false) };
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if COUNTING.try_with(Cell::get).unwrap_or(false) {
+            let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        }
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if COUNTING.try_with(Cell::get).unwrap_or(false) {
+            let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        }
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// The number of allocations made on the current thread while running `f`.
+fn allocations<F: FnOnce()>(f: F) -> usize {
+    ALLOCATIONS.with(|n| n.set(0));
+    COUNTING.with(|c| c.set(true));
+    f();
+    COUNTING.with(|c| c.set(false));
+    ALLOCATIONS.with(Cell::get)
+}
+
+fn signal(n_frames: usize) -> Vec<[f32; 2]> {
+    (0..n_frames)
+        .map(|i| {
+            let s = (i as f32 * 0.05).sin() * if (i / 1000) % 2 == 0 { 1.0 } else { 0.1 };
+            [s, -0.5 * s]
+        })
+        .collect()
+}
+
+#[test]
+fn compressing_never_allocates() {
+    let mut compressor: RmsAvgCompressor<[f32; 2]> =
+        Compressor::rms_avg(5.0, 3.0, 100.0, 44_100.0, 0.25, 4.0);
+    compressor.set_sidechain_delay_frames(64);
+    compressor.set_silence_optimization(true);
+    let mut frames = signal(8192);
+    let mut silence = vec![[0.0f32; 2]; 8192];
+    let n = allocations(|| {
+        compressor.compress_slice(&mut frames);
+        compressor.compress_slice(&mut silence);
+        for &frame in &frames {
+            compressor.next_frame(frame);
+        }
+    });
+    assert_eq!(n, 0);
+}
+
+#[test]
+fn resizing_the_window_within_the_reserved_capacity_never_allocates() {
+    const SAMPLE_HZ: f64 = 44_100.0;
+    for &shape in &[RmsWindow::Rectangular, RmsWindow::Hann] {
+        let compressor: RmsAvgCompressor<[f32; 2]> =
+            Compressor::rms_windowed(shape, 10.0, 3.0, 100.0, SAMPLE_HZ, 0.25, 4.0);
+        let max_window_frames = 50 * SAMPLE_HZ as usize / 1_000;
+        let mut compressor = compressor.with_reserved(max_window_frames, 256, SAMPLE_HZ);
+        let mut frames = signal(4096);
+        let n = allocations(|| {
+            for &ms in &[5.0, 20.0, 1.0, 50.0, 10.0] {
+                compressor.set_window_ms(ms, SAMPLE_HZ);
+                compressor.compress_slice(&mut frames);
+            }
+            compressor.set_sidechain_delay_frames(256);
+            compressor.compress_slice(&mut frames);
+        });
+        assert_eq!(n, 0, "{:?}", shape);
+    }
+}
+
+#[cfg(feature = "automation")]
+#[test]
+fn scheduling_within_the_reserved_capacity_never_allocates() {
+    let mut compressor = Compressor::peak_avg(1.0, 100.0, 44_100.0, 0.5, 4.0);
+    compressor.reserve_scheduled(16);
+    let mut frames = signal(4096);
+    let n = allocations(|| {
+        for i in 0..8 {
+            compressor.schedule_threshold(i * 512, 0.25);
+            compressor.schedule_ratio(i * 512 + 256, 8.0);
+        }
+        compressor.compress_slice(&mut frames);
+    });
+    assert_eq!(n, 0);
+}